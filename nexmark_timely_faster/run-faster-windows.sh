@@ -8,6 +8,7 @@
 
 ### paths configuration ###
 CARGO="cargo run --release --"
+export TIMELY_STATE_CONFIG=backends.toml
 
 ### queries configuration ###
 WINDOW_1_COUNT="window_1_faster_count"
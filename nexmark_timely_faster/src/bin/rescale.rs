@@ -0,0 +1,121 @@
+//! Rescales a NEXMark checkpoint to a different number of workers.
+//!
+//! Reads the checkpoint that `--from-workers` workers wrote of an epoch and
+//! writes one of the same epoch for `--to-workers` workers to `--output`, with
+//! the query's state moved to the workers its keys are exchanged to. A run
+//! started with `--resume` and the new number of workers then continues from it.
+
+extern crate clap;
+extern crate nexmark;
+extern crate timely;
+
+use clap::{App, Arg};
+use std::path::PathBuf;
+
+use timely::state::backends::RocksDBBackend;
+use timely::state::checkpoint::WorkerCheckpoints;
+use timely::state::config::BackendConfig;
+use timely::state::rescale::Rescaler;
+
+use nexmark::tools::{read_generator_position, write_generator_position};
+
+fn main() {
+    let matches = App::new("rescale")
+        .arg(
+            Arg::with_name("query")
+                .long("query")
+                .takes_value(true)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("from-workers")
+                .long("from-workers")
+                .takes_value(true)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("to-workers")
+                .long("to-workers")
+                .takes_value(true)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .takes_value(true)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("epoch")
+                .long("epoch")
+                .takes_value(true)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name("state")
+                .long("state")
+                .takes_value(true)
+                .required(false),
+        )
+        .get_matches();
+
+    let query = matches.value_of("query").unwrap();
+    let from_workers: usize = matches
+        .value_of("from-workers")
+        .unwrap()
+        .parse()
+        .expect("couldn't parse number of workers to rescale from");
+    let to_workers: usize = matches
+        .value_of("to-workers")
+        .unwrap()
+        .parse()
+        .expect("couldn't parse number of workers to rescale to");
+    let output = PathBuf::from(matches.value_of("output").unwrap());
+    // The query's dataflow is built after the one that replays the input
+    let state = matches.value_of("state").unwrap_or("dataflow-1");
+
+    let config = BackendConfig::from_env().expect("Invalid state backend configuration");
+    let directory = config.checkpoint_directory.clone();
+    let epoch = match matches.value_of("epoch") {
+        Some(epoch) => epoch.parse().expect("couldn't parse epoch"),
+        None => WorkerCheckpoints::new(directory.clone(), 0)
+            .latest_epoch(from_workers)
+            .expect("Unable to read checkpoints")
+            .expect("No checkpoint to rescale"),
+    };
+
+    let moved = match query {
+        "q3_rocksdb" => {
+            let rescaler = Rescaler::<RocksDBBackend>::open(
+                &config,
+                &directory,
+                epoch,
+                state,
+                from_workers,
+                to_workers,
+            )
+            .expect("Unable to open checkpoint");
+            let moved = nexmark::queries::q3_rescale(&rescaler).expect("Unable to move state");
+            rescaler.checkpoint(&output).expect("Unable to write checkpoint");
+            moved
+        }
+        _ => panic!("Rescaling {} is not supported", query),
+    };
+
+    // New workers continue generating from the least far any old worker got, so
+    // that no events are skipped that some worker's state does not reflect yet
+    let positions: Vec<_> = (0..from_workers)
+        .map(|worker| read_generator_position(&WorkerCheckpoints::new(directory.clone(), worker), epoch))
+        .collect();
+    let events_so_far = positions.iter().map(|&(events, _)| events).min().expect("No workers to rescale from");
+    let target_ns = positions.iter().map(|&(_, ns)| ns).min().expect("No workers to rescale from");
+    for worker in 0..to_workers {
+        let checkpoints = WorkerCheckpoints::new(output.clone(), worker);
+        write_generator_position(&checkpoints, epoch, events_so_far, target_ns);
+    }
+
+    println!(
+        "Rescaled epoch {} of {} from {} to {} workers, moving {} entries",
+        epoch, query, from_workers, to_workers, moved
+    );
+}
@@ -1,4 +1,4 @@
-use faster_rs::FasterRmw;
+use timely::state::Rmw;
 use rand::rngs::SmallRng;
 use rand::seq::SliceRandom;
 use rand::Rng;
@@ -75,7 +75,7 @@ impl ::std::ops::Sub for Date {
     }
 }
 
-impl FasterRmw for Date {
+impl Rmw for Date {
     fn rmw(&self, modification: Self) -> Self {
         Date(self.0 + modification.0)
     }
@@ -182,12 +182,6 @@ pub struct Person {
     pub date_time: Date,
 }
 
-impl FasterRmw for Person {
-    fn rmw(&self, _modification: Person) -> Person {
-        unimplemented!();
-    }
-}
-
 impl Person {
     pub fn from(event: Event) -> Option<Person> {
         match event {
@@ -31,12 +31,16 @@ use timely::dataflow::operators::Operator;
 use timely::dataflow::Scope;
 use timely::dataflow::Stream;
 use timely::state::backends::{
-    FASTERBackend, InMemoryBackend, RocksDBBackend, RocksDBMergeBackend, RocksDBMergeBackend2
+    CachedBackend, FASTERBackend, FASTERNodeBackend, InMemoryBackend, InMemoryOrderedBackend,
+    RocksDBBackend, RocksDBMergeBackend
 };
+use timely::state::StateHandle;
+use timely::worker::AsWorker;
 use timely::ExchangeData;
 
 use nexmark::event::Event;
 use nexmark::queries::{NexmarkInput, NexmarkTimer};
+use nexmark::tools::{read_generator_position, write_generator_position};
 //use timely::dataflow::operators::inspect::Inspect;
 
 use std::time::Duration;
@@ -150,6 +154,16 @@ fn main() {
                 .takes_value(true)
                 .required(false)
         )
+        .arg(
+            Arg::with_name("checkpoint-interval")
+                .long("checkpoint-interval")
+                .takes_value(true)
+                .required(false)
+        )
+        .arg(
+            Arg::with_name("resume")
+                .long("resume")
+        )
         .arg(Arg::with_name("timely").multiple(true))
         .get_matches();
     let timely_args = matches
@@ -200,6 +214,13 @@ fn main() {
     let timeline_output = matches
         .value_of("timeline-output");
 
+    let checkpoint_interval_ns: Option<usize> = matches
+        .value_of("checkpoint-interval")
+        .map(|interval| interval.parse::<usize>().expect("couldn't parse checkpoint interval") * 1_000_000_000);
+
+    let resume = matches
+        .occurrences_of("resume") > 0;
+
     if enable_metrics {
         // Collect metrics
         env_logger::init();
@@ -217,13 +238,28 @@ fn main() {
         _ => None
     };
 
+    let report_cache = queries.iter().any(|query| query.ends_with("_cached"));
+
     // define a new computational scope, in which to run NEXMark queries
     let timelines: Vec<_> = timely::execute_from_args(
         timely_args.into_iter(),
-        move |worker, _node_state_handle| {
+        move |worker, _node_state_handle: StateHandle<FASTERNodeBackend>| {
             let peers = worker.peers();
             let index = worker.index();
 
+            // Resumes from the latest epoch that every worker checkpointed, before any state is opened
+            let resume_epoch = if resume {
+                let epoch = worker
+                    .checkpoints()
+                    .latest_epoch(peers)
+                    .expect("Unable to read checkpoints")
+                    .expect("No checkpoint to resume from");
+                worker.restore_from(epoch).expect("Unable to restore from checkpoint");
+                Some(epoch)
+            } else {
+                None
+            };
+
             // Declare re-used input, control and probe handles.
             let mut input = InputHandle::new();
             //let mut control_input = InputHandle::new();
@@ -366,6 +402,34 @@ fn main() {
                     });
                 }
 
+                // Q4: Find average selling price per category. FASTER behind a write-back cache.
+                if queries.iter().any(|x| *x == "q4_faster_cached") {
+                    worker.dataflow::<_, _, _, CachedBackend<FASTERBackend>>(|scope, _| {
+                        ::nexmark::queries::q4_q6_common_managed(
+                            &nexmark_input,
+                            nexmark_timer,
+                            scope,
+                        )
+                            .capture_into(nexmark_input.closed_auctions.clone());
+                        ::nexmark::queries::q4_managed(&nexmark_input, nexmark_timer, scope)
+                            .probe_with(&mut probe);
+                    });
+                }
+
+                // Q4: Find average selling price per category. RocksDB behind a write-back cache.
+                if queries.iter().any(|x| *x == "q4_rocksdb_cached") {
+                    worker.dataflow::<_, _, _, CachedBackend<RocksDBBackend>>(|scope, _| {
+                        ::nexmark::queries::q4_q6_common_managed(
+                            &nexmark_input,
+                            nexmark_timer,
+                            scope,
+                        )
+                            .capture_into(nexmark_input.closed_auctions.clone());
+                        ::nexmark::queries::q4_managed(&nexmark_input, nexmark_timer, scope)
+                            .probe_with(&mut probe);
+                    });
+                }
+
                 // Q5. Hot Items. FASTER.
                 if queries.iter().any(|x| *x == "q5_faster") {
                     // 60s windows, ticking in 1s intervals
@@ -860,6 +924,22 @@ fn main() {
                     });
                 }
 
+                // The same implementation kept in order in memory, to check its results
+                if queries.iter().any(|x| *x == "window_2a_in_memory_rank") {
+                    assert!(window_slice_count > 0);
+                    assert!(window_slide_ns > 0);
+                    worker.dataflow::<_, _, _, InMemoryOrderedBackend>(|scope, _| {
+                        ::nexmark::queries::window_2a_rocksdb_rank(
+                            &nexmark_input,
+                            nexmark_timer,
+                            scope,
+                            window_slice_count,
+                            window_slide_ns,
+                        )
+                            .probe_with(&mut probe);
+                    });
+                }
+
                 // 2nd window implementation with RocksDB using merge
                 if queries.iter().any(|x| *x == "window_2b_rocksdb") {
                     assert!(window_slice_count > 0);
@@ -880,7 +960,7 @@ fn main() {
                 if queries.iter().any(|x| *x == "window_2b_rocksdb_count") {
                     assert!(window_slice_count > 0);
                     assert!(window_slide_ns > 0);
-                    worker.dataflow::<_, _, _, RocksDBMergeBackend2>(|scope, _| {
+                    worker.dataflow::<_, _, _, RocksDBMergeBackend>(|scope, _| {
                         ::nexmark::queries::window_2b_rocksdb_count(
                             &nexmark_input,
                             nexmark_timer,
@@ -988,6 +1068,22 @@ fn main() {
                     });
                 }
 
+                // The same implementation kept in order in memory, to check its results
+                if queries.iter().any(|x| *x == "window_3a_in_memory_rank") {
+                    assert!(window_slice_count > 0);
+                    assert!(window_slide_ns > 0);
+                    worker.dataflow::<_, _, _, InMemoryOrderedBackend>(|scope, _| {
+                        ::nexmark::queries::window_3a_rocksdb_rank(
+                            &nexmark_input,
+                            nexmark_timer,
+                            scope,
+                            window_slice_count,
+                            window_slide_ns,
+                        )
+                            .probe_with(&mut probe);
+                    });
+                }
+
                 // 3rd window implementation with RocksDB using merge
                 if queries.iter().any(|x| *x == "window_3b_rocksdb") {
                     assert!(window_slice_count > 0);
@@ -1008,7 +1104,7 @@ fn main() {
                 if queries.iter().any(|x| *x == "window_3b_rocksdb_count") {
                     assert!(window_slice_count > 0);
                     assert!(window_slide_ns > 0);
-                    worker.dataflow::<_, _, _, RocksDBMergeBackend2>(|scope, _| {
+                    worker.dataflow::<_, _, _, RocksDBMergeBackend>(|scope, _| {
                         ::nexmark::queries::window_3b_rocksdb_count(
                             &nexmark_input,
                             nexmark_timer,
@@ -1046,8 +1142,10 @@ fn main() {
             let mut config = nexmark::config::NEXMarkConfig::new(&config1);
 
             let count = 1;
-            input.advance_to(count);
-            while probe.less_than(&count) {
+            // A resumed computation continues after the times its checkpoint covers
+            let start = resume_epoch.map_or(count, |epoch| epoch as usize + 2);
+            input.advance_to(start);
+            while probe.less_than(&start) {
                 worker.step();
             }
 
@@ -1056,6 +1154,12 @@ fn main() {
             // Establish a start of the computation.
             let elapsed_ns = timer.elapsed().to_nanos();
             config.base_time_ns = elapsed_ns as usize;
+            let resume_position = resume_epoch.map(|epoch| read_generator_position(&worker.checkpoints(), epoch));
+            // A resumed computation continues from where the generator was at its checkpoint
+            let timer = match resume_position {
+                Some((_, target_ns)) => timer - Duration::from_nanos(target_ns),
+                None => timer,
+            };
 
             use rand::rngs::SmallRng;
             use rand::SeedableRng;
@@ -1089,9 +1193,21 @@ fn main() {
             let mut input_times_gen =
                 ::streaming_harness::input::SyntheticInputTimeGenerator::new(input_times());
 
+            // Creates the events the checkpointed state already reflects without sending them,
+            // so that the generator continues with the same events as before
+            if let Some((resumed_events, target_ns)) = resume_position {
+                while events_so_far < resumed_events {
+                    Event::create(events_so_far, &mut rng, &mut config);
+                    events_so_far += worker.peers();
+                }
+                if let Some(it) = input_times_gen.iter_until(target_ns) {
+                    it.for_each(drop);
+                }
+            }
+
             let mut input = Some(input);
 
-            let mut last_ns = 0;
+            let mut last_ns = resume_position.map_or(0, |(_, target_ns)| target_ns);
 
             loop {
                 let elapsed_ns = timer.elapsed().to_nanos();
@@ -1115,7 +1231,26 @@ fn main() {
                         events_so_far += worker.peers();
                     }
                     //println!("Epoch: {}", target_ns as usize + count);
-                    input.advance_to(target_ns as usize + count);
+                    let next = target_ns as usize + count;
+                    let checkpoint_epoch = checkpoint_interval_ns
+                        .map(|interval| (next - 1) / interval * interval)
+                        .filter(|epoch| epoch >= input.time());
+                    if let Some(epoch) = checkpoint_epoch {
+                        // Holds the input until the state reflects every worker's events up to
+                        // the epoch, and again until every worker has checkpointed it, so that
+                        // no state reflects the events after it
+                        input.advance_to(epoch + 1);
+                        while probe.less_equal(&epoch) {
+                            worker.step();
+                        }
+                        worker.checkpoint(epoch as u64).expect("Unable to checkpoint state");
+                        write_generator_position(&worker.checkpoints(), epoch as u64, events_so_far, target_ns);
+                        input.advance_to(epoch + 2);
+                        while probe.less_equal(&(epoch + 1)) {
+                            worker.step();
+                        }
+                    }
+                    input.advance_to(::std::cmp::max(next, *input.time()));
                 } else {
                     input.take().unwrap();
                 }
@@ -1150,6 +1285,30 @@ fn main() {
     } = ::streaming_harness::output::combine_all(timelines);
 
     let latency_metrics = latency_metrics.into_inner();
+
+    if report_cache {
+        let stats = ::timely::state::cache::process_stats();
+        println!(
+            "cache_stats\t{}\t{}\t{}\t{}",
+            stats.hits, stats.misses, stats.write_backs, stats.evictions
+        );
+    }
+
+    let compaction = ::timely::state::compaction::process_stats();
+    if compaction.compactions > 0 {
+        println!(
+            "compaction_stats\t{}\t{}",
+            compaction.compactions, compaction.reclaimed_bytes
+        );
+    }
+
+    let sessions = ::timely::state::session::process_stats();
+    if sessions.operations > 0 {
+        println!(
+            "session_stats\t{}\t{}\t{}",
+            sessions.operations, sessions.pending, sessions.retries
+        );
+    }
     /*
     println!(
         "DEBUG_summary\t{}",
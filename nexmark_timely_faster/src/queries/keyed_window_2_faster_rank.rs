@@ -134,6 +134,7 @@ pub fn keyed_window_2_faster_rank<S: Scope<Timestamp = usize>>(
                         state_index.remove(&slice).expect("Slice must exist in index");
                     }
                 });
+                state_handle.advance(&notificator.frontier(0));
             },
         )
 }
@@ -158,6 +158,7 @@ pub fn keyed_window_2b_rocksdb_rank<S: Scope<Timestamp = usize>>(
                     }
                     
                 });
+                state_handle.advance(&notificator.frontier(0));
             },
         )
 }
@@ -142,6 +142,7 @@ pub fn keyed_window_3_faster_count<S: Scope<Timestamp = usize>>(
                         }
                     }
                 });
+                state_handle.advance(&notificator.frontier(0));
             }
         )
 }
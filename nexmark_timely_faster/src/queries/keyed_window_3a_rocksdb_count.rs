@@ -2,10 +2,12 @@ use timely::dataflow::channels::pact::Exchange;
 use timely::dataflow::{Scope, Stream};
 
 use std::collections::HashMap;
+use std::ops::Bound;
 
 use crate::queries::{NexmarkInput, NexmarkTimer};
 use timely::dataflow::operators::generic::operator::Operator;
 use timely::dataflow::operators::map::Map;
+use timely::state::primitives::{IterDirection, KeyRange};
 
 // NOTE: This is a *very* quick-n-dirty implementation of a keyed window on RocksDB
 pub fn keyed_window_3a_rocksdb_count<S: Scope<Timestamp = usize>>(
@@ -35,8 +37,6 @@ pub fn keyed_window_3a_rocksdb_count<S: Scope<Timestamp = usize>>(
                 let mut state_index = state_handle.get_managed_map("state_index1");
                 // (key, pane end timestamp) -> keyed pane contents
                 let mut pane_buckets = state_handle.get_managed_map("pane_buckets");
-                let prefix_key_len_1: usize = state_index.as_ref().get_key_prefix_length();
-                let prefix_key_len_2: usize = pane_buckets.as_ref().get_key_prefix_length();
                 let mut buffer = Vec::new();
                 input.for_each(|time, data| {
                     // The end timestamp of the slide the current epoch corresponds to
@@ -91,28 +91,16 @@ pub fn keyed_window_3a_rocksdb_count<S: Scope<Timestamp = usize>>(
                     let mut all_keys = HashMap::new();  // key -> min slice the key appears in
                     let slice_start = window_start + 1_000_000_000;
                     // println!("Slice start: {:?}", slice_start);
-                    let index_iter = state_index.iter(slice_start.to_be());
-                    for (ser_key, ser_value) in index_iter {
-                        let pref = &ser_key[..prefix_key_len_1];
-                        let p: &str =  bincode::deserialize(unsafe {
-                                                            std::slice::from_raw_parts(pref.as_ptr(), pref.len())
-                                                      }).expect("Cannot deserialize prefix");
-                        if p.find("index").is_none() {
-                            break;
-                        }
-                        let k = &ser_key[prefix_key_len_1..];  // Ignore prefix
-                        let mut timestamp: usize = bincode::deserialize(unsafe {
-                                                            std::slice::from_raw_parts(k.as_ptr(), k.len())
-                                                      }).expect("Cannot deserialize slice");
-                        timestamp = usize::from_be(timestamp);  // The end timestamp of the pane
-                        let keys: Vec<usize> = bincode::deserialize(unsafe {
-                                                            std::slice::from_raw_parts(ser_value.as_ptr(), ser_value.len())
-                                                        }).expect("Cannot deserialize keys");
+                    let index_range = KeyRange::Bounded(
+                        Bound::Included(slice_start.to_be()),
+                        Bound::Included(window_end.to_be()),
+                    );
+                    let index_iter = state_index.iter(index_range, IterDirection::Forward)
+                                                .expect("State index must support iteration");
+                    for (slice, keys) in index_iter {
+                        let timestamp = usize::from_be(*slice);  // The end timestamp of the pane
                         // println!("Found distinct keys: time: {}, keys:{:?}", timestamp, keys);
-                        if timestamp > *window_end {  // Outside keyed window
-                            break;
-                        }
-                        for key in keys {
+                        for &key in keys.iter() {
                             let e = all_keys.entry(key).or_insert(timestamp);
                             if *e > timestamp {
                                 *e = timestamp;
@@ -132,38 +120,21 @@ pub fn keyed_window_3a_rocksdb_count<S: Scope<Timestamp = usize>>(
                             if pane == 0 {
                                 pane = first_pane;
                             }
-                            // TODO (john): Do a prefix scan within the key
                             assert!((pane >= first_pane) && (pane < last_pane));
                             let composite_key = (key.to_be(), pane.to_be());
                             // println!("Composite Key {:?}", (key, pane));
                             let mut auction_id = 0;
-                            let mut last_auction_id_seen = 0;
-                            // Iterate over the panes belonging to the current window
-                            let window_iter = pane_buckets.iter(composite_key);
-                            for (ser_key, ser_value) in window_iter {
-                                let pref = &ser_key[..prefix_key_len_2];
-                                let p: &str =  bincode::deserialize(unsafe {
-                                                        std::slice::from_raw_parts(pref.as_ptr(), pref.len())
-                                                  }).expect("Cannot deserialize prefix");
-                                if p.find("buckets").is_none() {
-                                    break;
-                                }
-                                let k = &ser_key[prefix_key_len_2..];  // Ignore prefix
-                                let (auction, mut timestamp): (usize, usize) = bincode::deserialize(unsafe {
-                                                                    std::slice::from_raw_parts(k.as_ptr(), k.len())
-                                                              }).expect("Cannot deserialize (key, timestamp)");
-                                timestamp = usize::from_be(timestamp);  // The end timestamp of the pane
-                                auction_id = usize::from_be(auction);
-                                let record_count: usize = bincode::deserialize(unsafe {
-                                                                    std::slice::from_raw_parts(ser_value.as_ptr(), ser_value.len())
-                                                                }).expect("Cannot deserialize count");
-                                // println!("Found keyed pane:: auction {} time: {} count:{}", auction_id, timestamp, record_count);
-                                if timestamp > last_pane || (auction_id != last_auction_id_seen && last_auction_id_seen != 0){  // Outside keyed window
-                                    auction_id = last_auction_id_seen;
-                                    break;
-                                }
-                                last_auction_id_seen = auction_id;
-                                count += record_count;
+                            // Iterate over the panes belonging to the current keyed window
+                            let window_range = KeyRange::Bounded(
+                                Bound::Included(composite_key),
+                                Bound::Included((key.to_be(), last_pane.to_be())),
+                            );
+                            let window_iter = pane_buckets.iter(window_range, IterDirection::Forward)
+                                                          .expect("Pane buckets must support iteration");
+                            for (keyed_pane, record_count) in window_iter {
+                                auction_id = usize::from_be(keyed_pane.0);
+                                // println!("Found keyed pane:: auction {} time: {} count:{}", auction_id, usize::from_be(keyed_pane.1), record_count);
+                                count += *record_count;
                             }
                             if auction_id != 0 {
                                 // println!("*** End of window: {:?}, Auction: {} Count: {:?}", cap.time(), auction_id, count);
@@ -186,6 +157,7 @@ pub fn keyed_window_3a_rocksdb_count<S: Scope<Timestamp = usize>>(
                         }
                     }
                 });
+                state_handle.advance(&notificator.frontier(0));
             }
         )
 }
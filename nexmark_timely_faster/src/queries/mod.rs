@@ -47,7 +47,7 @@ mod q7_managed;
 mod q8_managed;
 mod q8_managed_map;
 
-pub use self::q3_managed::q3_managed;
+pub use self::q3_managed::{q3_managed, q3_rescale};
 pub use self::q4::q4;
 pub use self::q4_managed::q4_managed;
 pub use self::q4_q6_common_managed::q4_q6_common_managed;
@@ -89,23 +89,6 @@ pub use self::window_3_faster_count::window_3_faster_count;
 pub use self::keyed_window_3_faster_count::keyed_window_3_faster_count;
 pub use self::window_3_faster_rank::window_3_faster_rank;
 
-use faster_rs::FasterKv;
-
-#[inline(always)]
-fn maybe_refresh_faster(faster: &FasterKv, monotonic_serial_number: &mut u64) {
-    if *monotonic_serial_number % (1 << 4) == 0 {
-        faster.refresh();
-        if *monotonic_serial_number % (1 << 10) == 0 {
-            faster.complete_pending(true);
-        }
-    }
-    if *monotonic_serial_number % (1 << 20) == 0 {
-        println!("Size: {}", faster.size());
-    }
-    *monotonic_serial_number += 1;
-}
-
-
 pub struct NexmarkInput<'a> {
     pub bids: &'a Rc<EventLink<usize, Bid>>,
     pub auctions: &'a Rc<EventLink<usize, Auction>>,
@@ -1,12 +1,23 @@
+use std::collections::HashMap;
 use timely::dataflow::channels::pact::Exchange;
-use timely::dataflow::operators::{Filter, Operator};
+use timely::dataflow::operators::{Capability, Filter, Operator};
 use timely::dataflow::{Scope, Stream};
-use timely::state::primitives::ManagedMap;
+use timely::state::primitives::{IterationError, ManagedMap, PendingReads};
+use timely::state::rescale::{KeyGroups, Rescaler};
+use timely::state::StateBackend;
 
 use crate::event::{Auction, Person};
 
 use crate::queries::{NexmarkInput, NexmarkTimer};
 
+// The join's key groups, which bound the number of workers it can rescale to
+const KEY_GROUPS: usize = 1024;
+
+// Neighbouring ids hash alike, as the ids of people and sellers are close together
+fn hash(id: &usize) -> u64 {
+    *id as u64 / 100
+}
+
 pub fn q3_managed<S: Scope<Timestamp = usize>>(
     input: &NexmarkInput,
     _nt: NexmarkTimer,
@@ -18,47 +29,69 @@ pub fn q3_managed<S: Scope<Timestamp = usize>>(
         .people(scope)
         .filter(|p| p.state == "OR" || p.state == "ID" || p.state == "CA");
 
+    let groups = KeyGroups::new(KEY_GROUPS);
+    let peers = scope.peers();
+
     let mut auctions_buffer = vec![];
     let mut people_buffer = vec![];
 
-    auctions.binary(
+    auctions.binary_frontier_named(
         &people,
-        Exchange::new(|a: &Auction| a.seller as u64 / 100),
-        Exchange::new(|p: &Person| p.id as u64 / 100),
+        Exchange::new(move |a: &Auction| groups.route(hash(&a.seller), peers)),
+        Exchange::new(move |p: &Person| groups.route(hash(&p.id), peers)),
         "Q3 Join",
-        |_capability, _info, state_handle| {
+        "q3-join",
+        |_capability, info, state_handle| {
             let mut state1: Box<ManagedMap<usize, Vec<Auction>>> =
                 state_handle.get_managed_map("state1");
             let mut state2: Box<ManagedMap<usize, Person>> = state_handle.get_managed_map("state2");
 
+            // Each auction's seller is looked up without waiting for the backend to read
+            // it, along with the capability to give the join's result at
+            let mut seller_reads: PendingReads<Person, (Capability<usize>, usize, usize, u64)> =
+                PendingReads::new();
+            seller_reads.set_waker(scope.activator_for(&info.address[..]));
+            let mut submitted = 0;
+            // How many lookups had been submitted when each person was stored while
+            // lookups were pending. The person's own lookup of its auctions already
+            // joined the auctions of those lookups
+            let mut stored_after: HashMap<usize, u64> = HashMap::new();
+
             move |input1, input2, output| {
                 // Process each input auction.
                 input1.for_each(|time, data| {
                     data.swap(&mut auctions_buffer);
-                    let mut session = output.session(&time);
+                    let capability = time.retain();
+                    for auction in auctions_buffer.iter() {
+                        let context = (capability.clone(), auction.seller, auction.id, submitted);
+                        seller_reads.submit(&*state2, &auction.seller, context);
+                        submitted += 1;
+                    }
+                    // Appends each seller's auctions with one lookup and one write per batch
+                    let mut new_auctions: HashMap<usize, Vec<Auction>> = HashMap::new();
                     for auction in auctions_buffer.drain(..) {
-                        if let Some(person) = state2.get(&auction.seller) {
-                            session.give((
-                                person.name.clone(),
-                                person.city.clone(),
-                                person.state.clone(),
-                                auction.id,
-                            ));
-                        }
-                        let seller = auction.seller;
-                        //state1.rmw(auction.seller, vec![auction]);
-                        let mut seller_auctions = state1.remove(&seller).unwrap_or(Vec::new());
-                        seller_auctions.push(auction);
-                        state1.insert(seller, seller_auctions);
+                        new_auctions.entry(auction.seller).or_insert_with(Vec::new).push(auction);
                     }
+                    let sellers: Vec<usize> = new_auctions.keys().cloned().collect();
+                    let seller_auctions = sellers
+                        .iter()
+                        .zip(state1.multi_get(&sellers))
+                        .map(|(seller, stored)| {
+                            let mut auctions = stored.map_or_else(Vec::new, |stored| (*stored).clone());
+                            auctions.extend(new_auctions.remove(seller).unwrap_or_default());
+                            (*seller, auctions)
+                        })
+                        .collect();
+                    state1.insert_batch(seller_auctions);
                 });
 
                 // Process each input person.
                 input2.for_each(|time, data| {
                     data.swap(&mut people_buffer);
                     let mut session = output.session(&time);
-                    for person in people_buffer.drain(..) {
-                        if let Some(auctions) = state1.get(&person.id) {
+                    let ids: Vec<usize> = people_buffer.iter().map(|person| person.id).collect();
+                    for (person, auctions) in people_buffer.iter().zip(state1.multi_get(&ids)) {
+                        if let Some(auctions) = auctions {
                             for auction in auctions.iter() {
                                 session.give((
                                     person.name.clone(),
@@ -68,10 +101,49 @@ pub fn q3_managed<S: Scope<Timestamp = usize>>(
                                 ));
                             }
                         }
-                        state2.insert(person.id, person);
                     }
+                    if !seller_reads.is_empty() {
+                        for person in people_buffer.iter() {
+                            stored_after.insert(person.id, submitted);
+                        }
+                    }
+                    state2.insert_batch(people_buffer.drain(..).map(|person| (person.id, person)).collect());
                 });
+
+                // Join the auctions whose sellers have been read.
+                for ((capability, seller, auction, submission), person) in seller_reads.drain(&*state2) {
+                    let joined = stored_after.get(&seller).map_or(false, |stored| *stored > submission);
+                    if let Some(person) = person.filter(|_| !joined) {
+                        output.session(&capability).give((
+                            person.name.clone(),
+                            person.city.clone(),
+                            person.state.clone(),
+                            auction,
+                        ));
+                    }
+                }
+                if seller_reads.is_empty() {
+                    stored_after.clear();
+                }
+
+                let frontier: Vec<usize> = input1
+                    .frontier()
+                    .frontier()
+                    .iter()
+                    .chain(input2.frontier().frontier().iter())
+                    .cloned()
+                    .collect();
+                state_handle.advance(&frontier);
             }
         },
     )
 }
+
+/// Moves the join state of a checkpoint to the workers that the join's inputs
+/// are exchanged to after rescaling, and returns how many entries moved.
+pub fn q3_rescale<B: StateBackend>(rescaler: &Rescaler<B>) -> Result<usize, IterationError> {
+    let groups = KeyGroups::new(KEY_GROUPS);
+    let auctions = rescaler.map::<usize, Vec<Auction>, _>("q3-join.state1", groups, hash)?;
+    let people = rescaler.map::<usize, Person, _>("q3-join.state2", groups, hash)?;
+    Ok(auctions + people)
+}
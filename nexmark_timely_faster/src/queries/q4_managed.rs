@@ -3,12 +3,12 @@ use timely::dataflow::operators::{Map, Operator};
 use timely::dataflow::{Scope, Stream};
 
 use crate::queries::{NexmarkInput, NexmarkTimer};
-use faster_rs::FasterRmw;
+use timely::state::Rmw;
 
 #[derive(Serialize, Deserialize)]
 struct SumWithCount(usize, usize);
 
-impl FasterRmw for SumWithCount {
+impl Rmw for SumWithCount {
     fn rmw(&self, modification: Self) -> Self {
         SumWithCount(self.0 + modification.0, self.1 + modification.1)
     }
@@ -22,9 +22,10 @@ pub fn q4_managed<S: Scope<Timestamp = usize>>(
     input
         .closed_auctions(scope)
         .map(|(a, b)| (a.category, b.price))
-        .unary(
+        .unary_frontier_named(
             Exchange::new(|x: &(usize, usize)| x.0 as u64),
             "Q4 Average",
+            "q4-average",
             |_cap, _info, state_handle| {
                 // Stores category -> (total, count)
                 let mut state = state_handle.get_managed_map("categories");
@@ -40,7 +41,8 @@ pub fn q4_managed<S: Scope<Timestamp = usize>>(
                             session.give((category, current_sum_count.0 / current_sum_count.1));
                             state.insert(category, current_sum_count);
                         }
-                    })
+                    });
+                    state_handle.advance(&*input.frontier().frontier());
                 }
             },
         )
@@ -7,7 +7,6 @@ use timely::state::primitives::ManagedMap;
 use crate::event::{Auction, Bid};
 
 use crate::queries::{NexmarkInput, NexmarkTimer};
-use faster_rs::FasterRmw;
 
 fn is_valid_bid(bid: &Bid, auction: &Auction) -> bool {
     bid.price >= auction.reserve
@@ -18,13 +17,6 @@ fn is_valid_bid(bid: &Bid, auction: &Auction) -> bool {
 #[derive(Serialize, Deserialize)]
 struct AuctionBids(Option<Auction>, Vec<Bid>);
 
-impl FasterRmw for AuctionBids {
-    fn rmw(&self, _modification: Self) -> Self {
-        unimplemented!()
-    }
-}
-
-
 pub fn q4_q6_common_managed<S: Scope<Timestamp = usize>>(
     input: &NexmarkInput,
     nt: NexmarkTimer,
@@ -33,11 +25,12 @@ pub fn q4_q6_common_managed<S: Scope<Timestamp = usize>>(
     let bids = input.bids(scope);
     let auctions = input.auctions(scope);
 
-    bids.binary_notify(
+    bids.binary_notify_named(
         &auctions,
         Exchange::new(|b: &Bid| b.auction as u64),
         Exchange::new(|a: &Auction| a.id as u64),
         "Q4 Auction close",
+        "q4-auction-close",
         None,
         move |input1, input2, output, notificator, state_handle| {
             let mut state: Box<ManagedMap<usize, AuctionBids>> = state_handle.get_managed_map("state");
@@ -115,6 +108,15 @@ pub fn q4_q6_common_managed<S: Scope<Timestamp = usize>>(
                     }
                 }
             });
+
+            // Lets a caching backend write back the state of the times both inputs have passed
+            let frontier: Vec<usize> = notificator
+                .frontier(0)
+                .iter()
+                .chain(notificator.frontier(1).iter())
+                .cloned()
+                .collect();
+            state_handle.advance(&frontier);
         },
     )
 }
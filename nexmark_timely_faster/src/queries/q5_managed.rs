@@ -3,28 +3,16 @@ use timely::dataflow::channels::pact::Exchange;
 use timely::dataflow::{Scope, Stream};
 
 use crate::queries::{NexmarkInput, NexmarkTimer};
-use faster_rs::FasterRmw;
 use timely::dataflow::operators::generic::operator::Operator;
 use timely::dataflow::operators::map::Map;
+use timely::state::primitives::ManagedMap;
 
 #[derive(Deserialize, Serialize)]
 struct Counts(HashMap<usize, usize>);
 
-impl FasterRmw for Counts {
-    fn rmw(&self, _modification: Self) -> Self {
-        panic!("RMW on Counts not allowed!");
-    }
-}
-
 #[derive(Deserialize, Serialize)]
 struct AuctionBids((usize, usize));
 
-impl FasterRmw for AuctionBids {
-    fn rmw(&self, _modification: Self) -> Self {
-        panic!("RMW on AuctionBids not allowed!");
-    }
-}
-
 pub fn q5_managed<S: Scope<Timestamp = usize>>(
     input: &NexmarkInput,
     _nt: NexmarkTimer,
@@ -42,12 +30,13 @@ pub fn q5_managed<S: Scope<Timestamp = usize>>(
             )
         })
         // TODO: Could pre-aggregate pre-exchange, if there was reason to do so.
-        .unary_notify(
+        .unary_notify_named(
             Exchange::new(|b: &(usize, _)| b.0 as u64),
             "Q5 Accumulate Per Worker",
+            "q5-accumulate-per-worker",
             None,
             move |input, output, notificator, state_handle| {
-                let mut pre_reduce_state = state_handle.get_managed_map("state");
+                let mut pre_reduce_state = state_handle.get_expiring_map("state");
                 let mut buffer = Vec::new();
                 input.for_each(|time, data| {
                     // Notify at end timestamp of the slide the current epoch corresponds to
@@ -72,8 +61,10 @@ pub fn q5_managed<S: Scope<Timestamp = usize>>(
                             .unwrap_or(Counts(HashMap::new()));
                         let count = counts.0.entry(auction).or_insert(0);
                         *count += 1;
-                        // Index auction counts by the end timestamp of the slide they correspond to
-                        pre_reduce_state.insert(a_time, counts);
+                        // Index auction counts by the end timestamp of the slide they correspond to,
+                        // until the end of the last window that contains the slide
+                        let last_window_end = a_time + (window_slice_count - 1) * window_slide_ns;
+                        pre_reduce_state.insert_until(a_time, counts, last_window_end as u64);
                     }
                 });
 
@@ -93,14 +84,16 @@ pub fn q5_managed<S: Scope<Timestamp = usize>>(
                         // Gives the accumulation per worker
                         output.session(&cap).give((ac, co));
                     }
-                    // Remove the last first slide of the expired window
-                    pre_reduce_state.remove(&(cap.time() - window_slice_count * window_slide_ns));
                 });
+                // Expire the slides of windows that have ended
+                pre_reduce_state.advance(&notificator.frontier(0));
+                state_handle.advance(&notificator.frontier(0));
             },
         )
-        .unary_notify(
+        .unary_notify_named(
             Exchange::new(|_| 0),
             "Q5 Accumulate Globally",
+            "q5-accumulate-globally",
             None,
             move |input, output, notificator, state_handle| {
                 let mut all_reduce_state = state_handle.get_managed_map("state");
@@ -128,6 +121,7 @@ pub fn q5_managed<S: Scope<Timestamp = usize>>(
                         .session(&cap)
                         .give((all_reduce_state.remove(cap.time()).expect("Must exist").0).0)
                 });
+                state_handle.advance(&notificator.frontier(0));
             },
         )
 }
\ No newline at end of file
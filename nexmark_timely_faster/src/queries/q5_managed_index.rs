@@ -3,30 +3,18 @@ use timely::dataflow::channels::pact::Exchange;
 use timely::dataflow::{Scope, Stream};
 
 use crate::queries::{NexmarkInput, NexmarkTimer};
-use faster_rs::FasterRmw;
 use timely::dataflow::operators::generic::operator::Operator;
 use timely::dataflow::operators::map::Map;
+use timely::state::primitives::ManagedMap;
 
 use std::str::FromStr;
 
 #[derive(Deserialize, Serialize)]
 struct Counts(HashMap<usize, usize>);
 
-impl FasterRmw for Counts {
-    fn rmw(&self, _modification: Self) -> Self {
-        panic!("RMW on Counts not allowed!");
-    }
-}
-
 #[derive(Deserialize, Serialize)]
 struct AuctionBids((usize, usize));
 
-impl FasterRmw for AuctionBids {
-    fn rmw(&self, _modification: Self) -> Self {
-        panic!("RMW on AuctionBids not allowed!");
-    }
-}
-
 pub fn q5_managed_index<S: Scope<Timestamp = usize>>(
     input: &NexmarkInput,
     _nt: NexmarkTimer,
@@ -44,13 +32,14 @@ pub fn q5_managed_index<S: Scope<Timestamp = usize>>(
             )
         })
         // TODO: Could pre-aggregate pre-exchange, if there was reason to do so.
-        .unary_notify(
+        .unary_notify_named(
             Exchange::new(|b: &(usize, _)| b.0 as u64),
             "Q5 Accumulate Per Worker",
+            "q5-accumulate-per-worker",
             None,
             move |input, output, notificator, state_handle| {
-                let mut state_index = state_handle.get_managed_map("index");
-                let mut pre_reduce_state = state_handle.get_managed_map("state");
+                let mut state_index = state_handle.get_expiring_map("index");
+                let mut pre_reduce_state = state_handle.get_expiring_map("state");
                 let mut buffer = Vec::new();
                 input.for_each(|time, data| {
                     // Notify at end timestamp of the slide the current epoch corresponds to
@@ -60,10 +49,11 @@ pub fn q5_managed_index<S: Scope<Timestamp = usize>>(
                     notificator.notify_at(time.delayed(&window_end));
                     data.swap(&mut buffer);
                     for &(auction, a_time) in buffer.iter() {
+                        // The end of the latest window the record corresponds to, after which its slide expires
+                        let w_end = a_time + (window_slice_count - 1) * window_slide_ns;
                         if a_time != current_slide {
                             // a_time < current_slide
                             // Ask notification for the end of the latest window the record corresponds to
-                            let w_end = a_time + (window_slice_count - 1) * window_slide_ns;
                             notificator.notify_at(time.delayed(&w_end));
                         }
                         let mut exists = false;
@@ -79,14 +69,14 @@ pub fn q5_managed_index<S: Scope<Timestamp = usize>>(
                         if !exists {  // Insert new composite key
                             let mut keys = state_index.remove(&a_time).unwrap_or(Vec::new());
                             keys.push(auction);
-                            state_index.insert(a_time, keys)
+                            state_index.insert_until(a_time, keys, w_end as u64)
                         }
                         let composite_key = (a_time, auction);
                         let mut count = pre_reduce_state.remove(&composite_key).unwrap_or(0);
                         // println!("Composite key {:?} with count {}", composite_key, count);
                         count += 1;
                         // Index auction counts by composite key 'slide_auction'
-                        pre_reduce_state.insert(composite_key, count);
+                        pre_reduce_state.insert_until(composite_key, count, w_end as u64);
                     }
                 });
 
@@ -115,25 +105,17 @@ pub fn q5_managed_index<S: Scope<Timestamp = usize>>(
                         // Gives the accumulation per worker
                         output.session(&cap).give((ac, co));
                     }
-                    // Remove the first slide of the expired window
-                    let slide_to_remove = cap.time() - (window_slice_count - 1) * window_slide_ns;
-                    // println!("Slide to remove: {}",slide_to_remove);
-                    if let Some(auctions_in_slide) = state_index.get(&slide_to_remove) {
-                        // println!("Auctions to remove: {:?}",auctions_in_slide);
-                        state_index.remove(&slide_to_remove);
-                        for auction in auctions_in_slide.iter() {
-                            pre_reduce_state.remove(&(slide_to_remove,*auction));
-                        }
-                    }
-                    else {
-                        println!("End of window {}. Could not find slide {}",cap.time(),slide_to_remove);
-                    }
                 });
+                // Expire the slides of windows that have ended
+                state_index.advance(&notificator.frontier(0));
+                pre_reduce_state.advance(&notificator.frontier(0));
+                state_handle.advance(&notificator.frontier(0));
             },
         )
-        .unary_notify(
+        .unary_notify_named(
             Exchange::new(|_| 0),
             "Q5 Accumulate Globally",
+            "q5-accumulate-globally",
             None,
             move |input, output, notificator, state_handle| {
                 let mut all_reduce_state = state_handle.get_managed_map("state");
@@ -161,6 +143,7 @@ pub fn q5_managed_index<S: Scope<Timestamp = usize>>(
                         .session(&cap)
                         .give((all_reduce_state.remove(cap.time()).expect("Must exist").0).0)
                 });
+                state_handle.advance(&notificator.frontier(0));
             },
         )
 }
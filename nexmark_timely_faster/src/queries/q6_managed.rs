@@ -1,4 +1,3 @@
-use faster_rs::FasterRmw;
 use std::collections::VecDeque;
 use timely::dataflow::channels::pact::Exchange;
 use timely::dataflow::operators::{Map, Operator};
@@ -9,12 +8,6 @@ use crate::queries::{NexmarkInput, NexmarkTimer};
 #[derive(Serialize, Deserialize)]
 struct Prices(VecDeque<usize>);
 
-impl FasterRmw for Prices {
-    fn rmw(&self, _modification: Self) -> Self {
-        panic!("RMW on VecDeque<T> is unsafe");
-    }
-}
-
 pub fn q6_managed<S: Scope<Timestamp = usize>>(
     input: &NexmarkInput,
     _nt: NexmarkTimer,
@@ -23,9 +16,10 @@ pub fn q6_managed<S: Scope<Timestamp = usize>>(
     input
         .closed_auctions(scope)
         .map(|(_a, b)| (b.bidder, b.price))
-        .unary(
+        .unary_frontier_named(
             Exchange::new(|x: &(usize, usize)| x.0 as u64),
             "Q6 Average",
+            "q6-average",
             |_cap, _info, state_handle| {
                 // Store bidder -> [prices; 10]
                 let mut state = state_handle.get_managed_map("state");
@@ -45,6 +39,7 @@ pub fn q6_managed<S: Scope<Timestamp = usize>>(
                             state.insert(bidder, Prices(entry));
                         }
                     });
+                    state_handle.advance(&*input.frontier().frontier());
                 }
             },
         )
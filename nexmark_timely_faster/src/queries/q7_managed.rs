@@ -1,6 +1,7 @@
 use timely::dataflow::channels::pact::{Exchange, Pipeline};
 use timely::dataflow::operators::{Map, Operator};
 use timely::dataflow::{Scope, Stream};
+use timely::state::primitives::ManagedMap;
 
 use crate::event::Date;
 
@@ -20,12 +21,13 @@ pub fn q7_managed<S: Scope<Timestamp = usize>>(
                 b.price,
             )
         })
-        .unary_notify(
+        .unary_notify_named(
             Pipeline,
             "Q7 Pre-Reduce",
+            "q7-pre-reduce",
             None,
             move |input, output, notificator, state_handle| {
-                let mut pre_reduce_state = state_handle.get_managed_map("pre-reduce");
+                let mut pre_reduce_state = state_handle.get_expiring_map("pre-reduce");
                 let mut buffer = Vec::new();
                 input.for_each(|time, data| {
                     // Notify at end of epoch
@@ -37,24 +39,29 @@ pub fn q7_managed<S: Scope<Timestamp = usize>>(
                         let epoch = nt.from_nexmark_time(b_time);
                         let current_highest = pre_reduce_state.get(&epoch).map_or(0, |v| *v);
                         if b_price > current_highest {
-                            pre_reduce_state.insert(epoch, b_price);
+                            // The maximum is read once the frontier reaches its epoch
+                            pre_reduce_state.insert_until(epoch, b_price, epoch as u64);
                         }
                     }
                 });
 
                 notificator.for_each(|cap, _, _| {
-                    if let Some(max_price) = pre_reduce_state.remove(&cap.time()) {
-                        output.session(&cap).give((*cap.time(), max_price));
+                    if let Some(max_price) = pre_reduce_state.get(&cap.time()) {
+                        output.session(&cap).give((*cap.time(), *max_price));
                     }
                 });
+                // Expire the maxima of epochs that have ended
+                pre_reduce_state.advance(&notificator.frontier(0));
+                state_handle.advance(&notificator.frontier(0));
             },
         )
-        .unary_notify(
+        .unary_notify_named(
             Exchange::new(move |x: &(usize, usize)| (x.0 / window_size_ns) as u64),
             "Q7 All-Reduce",
+            "q7-all-reduce",
             None,
             move |input, output, notificator, state_handle| {
-                let mut all_reduce_state = state_handle.get_managed_map("all-reduce");
+                let mut all_reduce_state = state_handle.get_expiring_map("all-reduce");
                 let mut buffer = Vec::new();
                 input.for_each(|time, data| {
                     // Notify at end of epoch
@@ -63,16 +70,18 @@ pub fn q7_managed<S: Scope<Timestamp = usize>>(
                     for &(b_time, b_price) in buffer.iter() {
                         let current_highest = all_reduce_state.get(&b_time).map_or(0, |v| *v);
                         if b_price > current_highest {
-                            all_reduce_state.insert(b_time, b_price);
+                            all_reduce_state.insert_until(b_time, b_price, b_time as u64);
                         }
                     }
                 });
 
                 notificator.for_each(|cap, _, _| {
-                    if let Some(max_price) = all_reduce_state.remove(&cap.time()) {
-                        output.session(&cap).give(max_price);
+                    if let Some(max_price) = all_reduce_state.get(&cap.time()) {
+                        output.session(&cap).give(*max_price);
                     }
                 });
+                all_reduce_state.advance(&notificator.frontier(0));
+                state_handle.advance(&notificator.frontier(0));
             },
         )
 }
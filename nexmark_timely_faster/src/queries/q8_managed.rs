@@ -18,11 +18,12 @@ pub fn q8_managed<S: Scope<Timestamp = usize>>(
     // Used for producing output
     let mut capabilities: HashMap<usize, Capability<usize>> = HashMap::new();
 
-    people.binary_frontier(
+    people.binary_frontier_named(
         &auctions,
         Exchange::new(|p: &(usize, _)| p.0 as u64),
         Exchange::new(|a: &(usize, _)| a.0 as u64),
         "Q8 join",
+        "q8-join",
         |_capability, _info, state_handle| {
             let mut new_people = state_handle.get_managed_map("new_people");
             let mut auctions_state = state_handle.get_managed_value("auctions");
@@ -30,9 +31,7 @@ pub fn q8_managed<S: Scope<Timestamp = usize>>(
             move |input1, input2, output| {
                 // Notice new people.
                 input1.for_each(|_time, data| {
-                    for (person, p_time) in data.iter().cloned() {
-                        new_people.insert(person, p_time);
-                    }
+                    new_people.insert_batch(data.iter().cloned().collect());
                 });
 
                 // Notice new auctions.
@@ -97,6 +96,7 @@ pub fn q8_managed<S: Scope<Timestamp = usize>>(
                 auctions_vec.retain(|&(_, ref list)| !list.is_empty());
                 auctions_state.set(auctions_vec);
                 //});
+                state_handle.advance(&[complete]);
             }
         },
     )
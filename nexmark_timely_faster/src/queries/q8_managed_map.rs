@@ -3,6 +3,7 @@ use timely::dataflow::operators::{Map, Operator};
 use timely::dataflow::{Scope, Stream};
 
 use crate::queries::{NexmarkInput, NexmarkTimer};
+use std::ops::Bound;
 
 pub fn q8_managed_map<S: Scope<Timestamp = usize>>(
     input: &NexmarkInput,
@@ -14,16 +15,16 @@ pub fn q8_managed_map<S: Scope<Timestamp = usize>>(
 
     let people = input.people(scope).map(|p| (p.id, p.date_time));
 
-    people.binary_notify(
+    people.binary_notify_named(
         &auctions,
         Exchange::new(|p: &(usize, _)| p.0 as u64),
         Exchange::new(|a: &(usize, _)| a.0 as u64),
         "Q8 join",
+        "q8-join",
         None,
         move |input1, input2, output, notificator, state_handle| {
             let mut new_people = state_handle.get_managed_map("new_people");
-            let mut auctions_state = state_handle.get_managed_map("auctions");
-            let mut index_state = state_handle.get_managed_value("entries");
+            let mut auctions_state = state_handle.get_managed_sorted_map("auctions");
 
             // Notice new people.
             input1.for_each(|time, data| {
@@ -46,10 +47,11 @@ pub fn q8_managed_map<S: Scope<Timestamp = usize>>(
 
             notificator.for_each(|cap, _, _| {
                 let capability_time = *cap.time();
-                let mut entries_to_check = index_state.take().unwrap_or(Vec::new());
-                entries_to_check.push(capability_time);
-                let mut to_keep = Vec::new();
-                for ts in entries_to_check { // ts <= capability_time
+                let entries_to_check: Vec<usize> = auctions_state
+                    .range(Bound::Unbounded, Bound::Included(capability_time))
+                    .map(|(ts, _)| *ts)
+                    .collect();
+                for ts in entries_to_check {
                     if let Some(mut auctions) = auctions_state.remove(&ts) {
                         let mut session = output.session(&cap);
                         for &(person, time) in auctions.iter() {
@@ -65,13 +67,17 @@ pub fn q8_managed_map<S: Scope<Timestamp = usize>>(
                         if auctions.len() > 0 {
                             // Put it back in state
                             auctions_state.insert(ts, auctions);
-                            to_keep.push(ts)
                         }
                     } 
                 }
-                // Update entries
-                index_state.set(to_keep);
             });
+            let frontier: Vec<usize> = notificator
+                .frontier(0)
+                .iter()
+                .chain(notificator.frontier(1).iter())
+                .cloned()
+                .collect();
+            state_handle.advance(&frontier);
         },
     )
 }
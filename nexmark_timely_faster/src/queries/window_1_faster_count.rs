@@ -4,6 +4,7 @@ use timely::dataflow::{Scope, Stream};
 use crate::queries::{NexmarkInput, NexmarkTimer};
 use timely::dataflow::operators::generic::operator::Operator;
 use timely::dataflow::operators::map::Map;
+use timely::state::primitives::ManagedMap;
 
 pub fn window_1_faster_count<S: Scope<Timestamp = usize>>(
     input: &NexmarkInput,
@@ -29,9 +30,9 @@ pub fn window_1_faster_count<S: Scope<Timestamp = usize>>(
             None,
             move |input, output, notificator, state_handle| {
                 // Slide end timestamp -> event timestamps
-                let mut slide_index = state_handle.get_managed_map("slide_index");
+                let mut slide_index = state_handle.get_expiring_map("slide_index");
                 // Event timestamp -> auction id
-                let mut window_contents = state_handle.get_managed_map("window_contents");
+                let mut window_contents = state_handle.get_expiring_map("window_contents");
                 let mut buffer = Vec::new();
                 input.for_each(|time, data| {
                     // The end timestamp of the slide the current epoch corresponds to
@@ -48,11 +49,13 @@ pub fn window_1_faster_count<S: Scope<Timestamp = usize>>(
                         }
                         last_slide_seen = current_slide;
                     }
+                    // The slide is read until the end of the last window that contains it
+                    let expiry = (current_slide + window_slide_ns * (window_slice_count - 1)) as u64;
                     data.swap(&mut buffer);
                     for record in buffer.iter() {
-                        window_contents.insert(record.1, record.0);
+                        window_contents.insert_until(record.1, record.0, expiry);
                         // println!("Inserting timestamp in the index: slide: {:?}, timestamp: {:?}", current_slide, record.1);
-                        slide_index.rmw(current_slide, vec![record.1]);
+                        slide_index.rmw_until(current_slide, vec![record.1], expiry);
                     }
                 });
 
@@ -74,17 +77,11 @@ pub fn window_1_faster_count<S: Scope<Timestamp = usize>>(
                     }
                     // println!("*** End of window: {:?}, Count: {:?}", cap.time(), count);
                     output.session(&cap).give((*cap.time(), count));
-
-                    // println!("Removing slide {:?}", &(cap.time() - (window_slice_count - 1) * window_slide_ns));
-                    if let Some(keys_to_remove) = slide_index.remove(&(cap.time() - (window_slice_count - 1) * window_slide_ns)) {
-                        for timestamp in keys_to_remove {
-                            let _ = window_contents.remove(&timestamp).expect("Timestamp to remove must exist");
-                        }
-                    }
-                    else {
-                        println!("Tried to remove slide {} of last window, which doesn't exist.", cap.time() - (window_slice_count - 1) * window_slide_ns);
-                    }
                 });
+                // Expire the slides of windows that have ended
+                slide_index.advance(&notificator.frontier(0));
+                window_contents.advance(&notificator.frontier(0));
+                state_handle.advance(&notificator.frontier(0));
             },
         )
 }
@@ -4,6 +4,7 @@ use timely::dataflow::{Scope, Stream};
 use crate::queries::{NexmarkInput, NexmarkTimer};
 use timely::dataflow::operators::generic::operator::Operator;
 use timely::dataflow::operators::map::Map;
+use timely::state::primitives::ManagedMap;
 
 pub fn window_1_faster_count_custom_slice<S: Scope<Timestamp = usize>>(
     input: &NexmarkInput,
@@ -30,9 +31,9 @@ pub fn window_1_faster_count_custom_slice<S: Scope<Timestamp = usize>>(
             None,
             move |input, output, notificator, state_handle| {
                 // Slide end timestamp -> event timestamps
-                let mut slide_index = state_handle.get_managed_map("slide_index");
+                let mut slide_index = state_handle.get_expiring_map("slide_index");
                 // Event timestamp -> auction id
-                let mut window_contents = state_handle.get_managed_map("window_contents");
+                let mut window_contents = state_handle.get_expiring_map("window_contents");
                 let mut buffer = Vec::new();
                 input.for_each(|time, data| {
                     // The end timestamp of the slide the current epoch corresponds to
@@ -51,10 +52,13 @@ pub fn window_1_faster_count_custom_slice<S: Scope<Timestamp = usize>>(
                     }
                     data.swap(&mut buffer);
                     for record in buffer.iter() {
-                        window_contents.insert(record.1, record.0);
                         let record_slice = ((record.1 / 1_000_000_000) + 1) * 1_000_000_000;  // Use slices of 1s each to index state
+                        // The end of the last window that reads the slide the slice belongs to
+                        let slide = (record_slice + window_slide_ns - 1) / window_slide_ns * window_slide_ns;
+                        let expiry = (slide + window_slide_ns * (window_slice_count - 1)) as u64;
                         // println!("Inserting timestamp in the index: slide: {:?}, timestamp: {:?}", record_slice, record.1);
-                        slide_index.rmw(record_slice, vec![record.1]);
+                        slide_index.rmw_until(record_slice, vec![record.1], expiry);
+                        window_contents.insert_until(record.1, record.0, expiry);
                     }
                 });
 
@@ -77,21 +81,11 @@ pub fn window_1_faster_count_custom_slice<S: Scope<Timestamp = usize>>(
                     }
                     // println!("*** End of window: {:?}, Count: {:?}", cap.time(), count);
                     output.session(&cap).give((*cap.time(), count));
-                    let slide = cap.time() - (window_slice_count - 1) * window_slide_ns;
-                    // println!("Removing slide {:?}", slide);
-                    // Remove all slices of the first slide
-                    for i in 0..num_slices {
-                        // println!("Removing slice {:?}", slide - i * 1_000_000_000);
-                        if let Some(keys_to_remove) = slide_index.remove(&(slide - i * 1_000_000_000)) {
-                            for timestamp in keys_to_remove {
-                                let _ = window_contents.remove(&timestamp).expect("Timestamp to remove must exist");
-                            }
-                        }
-                        else {
-                            println!("Tried to remove slide {} of last window, which doesn't exist.", slide - i * 1_000_000_000);
-                        }
-                    }
                 });
+                // Expire the slides of windows that have ended
+                slide_index.advance(&notificator.frontier(0));
+                window_contents.advance(&notificator.frontier(0));
+                state_handle.advance(&notificator.frontier(0));
             },
         )
 }
@@ -1,10 +1,12 @@
 use timely::dataflow::channels::pact::Exchange;
 use timely::dataflow::{Scope, Stream};
-use bincode;
 
 use crate::queries::{NexmarkInput, NexmarkTimer};
 use timely::dataflow::operators::generic::operator::Operator;
 use timely::dataflow::operators::map::Map;
+use timely::state::primitives::{IterDirection, KeyRange, ManagedMap};
+
+use std::ops::Bound;
 
 
 pub fn window_1_rocksdb<S: Scope<Timestamp = usize>>(
@@ -30,8 +32,12 @@ pub fn window_1_rocksdb<S: Scope<Timestamp = usize>>(
             "Accumulate records",
             None,
             move |input, output, notificator, state_handle| {
-                let mut window_contents = state_handle.get_managed_map("window_contents");
-                let prefix_key_len: usize = window_contents.as_ref().get_key_prefix_length();
+                let mut window_contents = state_handle.get_expiring_map("window_contents");
+                // Records, and the dummy record at the start of their slide, are kept until
+                // the end of the last window that contains the slide
+                let expiry = |timestamp: usize| {
+                    (timestamp / window_slide_ns * window_slide_ns + window_slide_ns * window_slice_count) as u64
+                };
                 let mut buffer = Vec::new();
 
                 input.for_each(|time, data| {
@@ -49,9 +55,9 @@ pub fn window_1_rocksdb<S: Scope<Timestamp = usize>>(
                             notificator.notify_at(time.delayed(&window_end));
                             // Add window margins so that we can iterate over its contents upon notification
                             // println!("Inserting dummy record:: time: {:?}, value:{:?}", sl - window_slide_ns, 0);
-                            window_contents.insert((sl - window_slide_ns).to_be(), 0);  // Start timestamp of window
+                            window_contents.insert_until((sl - window_slide_ns).to_be(), 0, expiry(sl - window_slide_ns));  // Start timestamp of window
                             // println!("Inserting dummy record:: time: {:?}, value:{:?}", window_end, 0);
-                            window_contents.insert(window_end.to_be(), 0);  // End timestamp of window
+                            window_contents.insert_until(window_end.to_be(), 0, expiry(window_end));  // End timestamp of window
                         }
                         last_slide_seen = current_slide;
                     }
@@ -62,49 +68,33 @@ pub fn window_1_rocksdb<S: Scope<Timestamp = usize>>(
                         let auction_id = record.0;
                         // Add record 
                         // println!("Inserting window record:: time: {}, value:{}", key, auction_id);
-                        window_contents.insert(key.to_be(), auction_id);
+                        window_contents.insert_until(key.to_be(), auction_id, expiry(key));
                     }
                 });
 
                 notificator.for_each(|cap, _, _| {
                     let window_end = cap.time(); 
                     let window_start = window_end - (window_slide_ns * window_slice_count);  
-                    let first_slide_end = window_start + window_slide_ns; // To know which records to delete
                     // println!("Start of window: {}", window_start);
                     // println!("End of window: {}", *window_end);
-                    // println!("End of first slide: {}", first_slide_end);
-                    let mut to_delete = Vec::new();  // Keep keys to delete here
-                    to_delete.push(window_start);
                     {
-                        let mut window_iter = window_contents.iter(window_start.to_be());
-                        let _ = window_iter.next();  // Skip dummy record
-                        for (ser_key, ser_value) in window_iter {
-                            let k = &ser_key[prefix_key_len..];  // Ignore prefix
-                            let mut timestamp: usize = bincode::deserialize(unsafe {
-                                                        std::slice::from_raw_parts(k.as_ptr(), k.len())
-                                                    }).expect("Cannot deserialize timestamp");
-                            timestamp = usize::from_be(timestamp);
-                            let auction_id: usize = bincode::deserialize(unsafe {
-                                                        std::slice::from_raw_parts(ser_value.as_ptr(), ser_value.len())
-                                                    }).expect("Cannot deserialize auction id");
-                            // println!("Found record:: time: {}, value:{}", timestamp, auction_id);
-                            if timestamp == *window_end {  // Omit dummy record
-                                break;
-                            }
-                            assert!(timestamp < *window_end);
+                        // Skip the dummy records at the window start and end
+                        let window_range = KeyRange::Bounded(
+                            Bound::Excluded(window_start.to_be()),
+                            Bound::Excluded(window_end.to_be()),
+                        );
+                        let window_iter = window_contents.iter(window_range, IterDirection::Forward)
+                                                         .expect("Window contents must support iteration");
+                        for (key, auction_id) in window_iter {
+                            let timestamp = usize::from_be(*key);
                             // println!("Output record:: time: {}, value:{}", timestamp, auction_id);
-                            output.session(&cap).give((timestamp, auction_id));
-                            if timestamp < first_slide_end {
-                                to_delete.push(timestamp);
-                            }
+                            output.session(&cap).give((timestamp, *auction_id));
                         }
                     }
-                    // Purge state of first slide in window
-                    for ts in to_delete {
-                        // println!("Removing record:: time: {}", ts);
-                        window_contents.remove(&ts.to_be()).expect("Record to remove must exist");
-                    }
                 });
+                // Expire the slides of windows that have ended
+                window_contents.advance(&notificator.frontier(0));
+                state_handle.advance(&notificator.frontier(0));
             },
         )
 }
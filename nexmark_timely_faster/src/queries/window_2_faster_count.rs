@@ -65,6 +65,7 @@ pub fn window_2_faster_count<S: Scope<Timestamp = usize>>(
                     // println!("*** Window start: {}, count {}.", cap.time() - window_size, count);
                     output.session(&cap).give((*cap.time(), count));
                 });
+                state_handle.advance(&notificator.frontier(0));
             },
         )
 }
@@ -43,7 +43,7 @@ pub fn window_2_faster_rank<S: Scope<Timestamp = usize>>(
             move |input, output, notificator, state_handle| {
                 let window_size = window_slice_count * window_slide_ns;
                 // window_start_timestamp -> window_contents
-                let mut window_buckets = state_handle.get_managed_map("window_buckets");
+                let mut window_buckets = state_handle.get_managed_bag("window_buckets");
                 let mut buffer = Vec::new();
                 input.for_each(|time, data| {
                     data.swap(&mut buffer);
@@ -53,7 +53,7 @@ pub fn window_2_faster_rank<S: Scope<Timestamp = usize>>(
                             // Notify at end of this window
                             notificator.notify_at(time.delayed(&(win + window_size)));
                             // println!("Asking notification for end of window: {:?}", win + window_size);
-                            window_buckets.rmw(win, vec![*record]);
+                            window_buckets.append(win, *record);
                             // println!("Appending record with timestamp {} to window with start timestamp {}.", record.1, win);
                         }
                     }
@@ -61,12 +61,10 @@ pub fn window_2_faster_rank<S: Scope<Timestamp = usize>>(
 
                 notificator.for_each(|cap, _, _| {
                     // println!("Firing and cleaning window with start timestamp {}.", cap.time() - window_size);
-                    let records = window_buckets.remove(&(cap.time() - window_size)).expect("Must exist");
-                    let mut auctions = Vec::new();
-                    for record in records.iter() {
-                        auctions.push(record.0);
-                    }
-                    // println!("*** Window start: {}, contents {:?}.", cap.time() - window_size, records);
+                    let mut auctions: Vec<usize> = window_buckets
+                        .drain(&(cap.time() - window_size))
+                        .map(|record: (usize, usize)| record.0)
+                        .collect();
                     auctions.sort_unstable();
                     let mut rank = 1;
                     let mut count = 0;
@@ -84,6 +82,7 @@ pub fn window_2_faster_rank<S: Scope<Timestamp = usize>>(
                         // println!("*** Start of window: {:?}, Auction: {:?}, Rank: {:?}", cap.time(), auction, rank);
                     }
                 });
+                state_handle.advance(&notificator.frontier(0));
             },
         )
 }
@@ -69,6 +69,7 @@ pub fn window_2a_rocksdb<S: Scope<Timestamp = usize>>(
                         output.session(&cap).give(record.clone());
                     }
                 });
+                state_handle.advance(&notificator.frontier(0));
             },
         )
 }
@@ -83,6 +83,7 @@ pub fn window_2a_rocksdb_rank<S: Scope<Timestamp = usize>>(
                         // println!("*** End of window: {:?}, Auction: {:?}, Rank: {:?}", cap.time(), auction.0, rank);
                     }
                 });
+                state_handle.advance(&notificator.frontier(0));
             },
         )
 }
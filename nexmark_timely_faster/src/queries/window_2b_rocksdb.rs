@@ -82,6 +82,7 @@ pub fn window_2b_rocksdb<S: Scope<Timestamp = usize>>(
                         output.session(&cap).give(record.clone());
                     }
                 });
+                state_handle.advance(&notificator.frontier(0));
             },
         )
 }
@@ -80,6 +80,7 @@ pub fn window_2b_rocksdb_count<S: Scope<Timestamp = usize>>(
                     // println!("*** Window start: {}, count {}.", cap.time() - window_size, count);
                     output.session(&cap).give((*cap.time(), count));
                 });
+                state_handle.advance(&notificator.frontier(0));
             },
         )
 }
@@ -4,6 +4,7 @@ use timely::dataflow::{Scope, Stream};
 use crate::queries::{NexmarkInput, NexmarkTimer};
 use timely::dataflow::operators::generic::operator::Operator;
 use timely::dataflow::operators::map::Map;
+use timely::state::primitives::ManagedMap;
 
 pub fn window_3_faster_count<S: Scope<Timestamp = usize>>(
     input: &NexmarkInput,
@@ -29,7 +30,9 @@ pub fn window_3_faster_count<S: Scope<Timestamp = usize>>(
             None,
             move |input, output, notificator, state_handle| {
                 // pane end timestamp -> pane contents
-                let mut pane_buckets = state_handle.get_managed_map("pane_buckets");
+                let mut pane_buckets = state_handle.get_expiring_map("pane_buckets");
+                // A pane is kept until the end of the last window that contains it
+                let expiry = |pane: usize| (pane + window_slide_ns * (window_slice_count - 1)) as u64;
                 let mut buffer = Vec::new();
                 input.for_each(|time, data| {
                     // The end timestamp of the slide the current epoch corresponds to
@@ -51,7 +54,7 @@ pub fn window_3_faster_count<S: Scope<Timestamp = usize>>(
                     for record in buffer.iter() {
                         let pane = ((record.1 / window_slide_ns) + 1) * window_slide_ns;  // Pane size equals slide size as window is a multiple of slide
                         // println!("Inserting record with time {:?} in pane {:?}", record.1, pane);
-                        pane_buckets.rmw(pane, 1);
+                        pane_buckets.rmw_until(pane, 1, expiry(pane));
                     }
                 });
 
@@ -67,15 +70,13 @@ pub fn window_3_faster_count<S: Scope<Timestamp = usize>>(
                         } else {
                             println!("Processing pane {} of last window.", cap.time() - window_slide_ns * i);
                         }
-                        // remove the first slide of the fired window
-                        if i == window_slice_count - 1 {
-                            // println!("Removing pane {:?}", pane);
-                            let _ = pane_buckets.remove(&pane).expect("Pane to remove must exist");
-                        }
                     }
                     // println!("*** End of window: {:?}, Count: {:?}", cap.time(), count);
                     output.session(&cap).give((*cap.time(), count));
                 });
+                // Expire the panes of windows that have ended
+                pane_buckets.advance(&notificator.frontier(0));
+                state_handle.advance(&notificator.frontier(0));
             }
         )
 }
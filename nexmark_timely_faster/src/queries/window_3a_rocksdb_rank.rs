@@ -4,6 +4,9 @@ use timely::dataflow::{Scope, Stream};
 use crate::queries::{NexmarkInput, NexmarkTimer};
 use timely::dataflow::operators::generic::operator::Operator;
 use timely::dataflow::operators::map::Map;
+use timely::state::primitives::{IterDirection, KeyRange, ManagedMap};
+
+use std::ops::Bound;
 
 pub fn window_3a_rocksdb_rank<S: Scope<Timestamp = usize>>(
     input: &NexmarkInput,
@@ -30,8 +33,9 @@ pub fn window_3a_rocksdb_rank<S: Scope<Timestamp = usize>>(
             None,
             move |input, output, notificator, state_handle| {
                 // pane end timestamp -> pane contents
-                let mut pane_buckets = state_handle.get_managed_map("pane_buckets");
-                let prefix_key_len: usize = pane_buckets.as_ref().get_key_prefix_length();
+                let mut pane_buckets = state_handle.get_expiring_map("pane_buckets");
+                // A pane is kept until the end of the last window that contains it
+                let expiry = |pane: usize| (pane + window_slide_ns * (window_slice_count - 1)) as u64;
                 let mut buffer = Vec::new();
                 input.for_each(|time, data| {
                     // The end timestamp of the slide the current epoch corresponds to
@@ -54,7 +58,7 @@ pub fn window_3a_rocksdb_rank<S: Scope<Timestamp = usize>>(
                         let end = current_slide + window_slide_ns;
                         for window_start in (max_window_seen..end).step_by(window_slide_ns) {
                             // println!("First PUT operation for window start: {:?}", window_start);
-                            pane_buckets.insert(window_start.to_be(), vec![]);  // Initialize window state
+                            pane_buckets.insert_until(window_start.to_be(), vec![], expiry(window_start));  // Initialize window state
                         }
                         max_window_seen = end;
                     }
@@ -62,35 +66,27 @@ pub fn window_3a_rocksdb_rank<S: Scope<Timestamp = usize>>(
                     for record in buffer.iter() {
                         let pane = ((record.1 / window_slide_ns) + 1) * window_slide_ns;  // Pane size equals slide size as window is a multiple of slide
                         // println!("Inserting record with time {:?} in pane {:?}", record.1, pane);
-                        pane_buckets.rmw(pane.to_be(), vec![*record]);
+                        pane_buckets.rmw_until(pane.to_be(), vec![*record], expiry(pane));
                     }
                 });
 
                 notificator.for_each(|cap, _, _| {
                     let window_end = cap.time(); 
                     let window_start = window_end - (window_slide_ns * window_slice_count);  
-                    let first_pane_end = window_start + window_slide_ns; // To know which records to delete
                     // println!("Start of window: {}", window_start);
                     // println!("End of window: {}", *window_end);
-                    // println!("End of first slide: {}", first_pane_end);
                     let mut window_records = Vec::new();
                     {// Iterate over the panes belonging to the current window
-                        let mut window_iter = pane_buckets.iter(window_start.to_be());
-                        let _ = window_iter.next();  // Skip dummy record
-                        for (ser_key, ser_value) in window_iter {
-                            let k = &ser_key[prefix_key_len..];  // Ignore prefix
-                            let mut timestamp: usize = bincode::deserialize(unsafe {
-                                                        std::slice::from_raw_parts(k.as_ptr(), k.len())
-                                                    }).expect("Cannot deserialize timestamp");
-                            timestamp = usize::from_be(timestamp);  // The end timestamp of the pane
-                            let records: Vec<(usize, usize)> = bincode::deserialize(unsafe {
-                                                                std::slice::from_raw_parts(ser_value.as_ptr(), ser_value.len())
-                                                            }).expect("Cannot deserialize auction id");
-                            if timestamp > *window_end {  // Outside window
-                                break;
-                            }
+                        // Skip the dummy record at the window start and stop at the window end
+                        let window_range = KeyRange::Bounded(
+                            Bound::Excluded(window_start.to_be()),
+                            Bound::Included(window_end.to_be()),
+                        );
+                        let window_iter = pane_buckets.iter(window_range, IterDirection::Forward)
+                                                      .expect("Pane buckets must support iteration");
+                        for (_pane_end, records) in window_iter {
                             // println!("Found pane:: time: {}, records:{:?}", timestamp, records);
-                            window_records.extend(records);
+                            window_records.extend(records.iter().cloned());
                         }
                     }
                     // Apply the rank function to the window
@@ -111,10 +107,10 @@ pub fn window_3a_rocksdb_rank<S: Scope<Timestamp = usize>>(
                         output.session(&cap).give((*cap.time(), auction.0, rank));
                         // println!("*** End of window: {:?}, Auction: {:?}, Rank: {:?}", cap.time(), auction.0, rank);
                     }
-                    // Purge state of first slide/pane in window
-                    // println!("Removing pane with end timestamp time: {}", first_pane_end);
-                    pane_buckets.remove(&first_pane_end.to_be()).expect("Pane to remove must exist");
                 });
+                // Expire the panes of windows that have ended
+                pane_buckets.advance(&notificator.frontier(0));
+                state_handle.advance(&notificator.frontier(0));
             }
         )
 }
@@ -1,4 +1,5 @@
 use streaming_harness::util::ToNanos;
+use timely::state::checkpoint::WorkerCheckpoints;
 
 pub fn statm_reporter() -> ::std::sync::Arc<::std::sync::atomic::AtomicBool> {
     // Read and report RSS every 100ms
@@ -34,3 +35,25 @@ pub fn statm_reporter() -> ::std::sync::Arc<::std::sync::atomic::AtomicBool> {
     }
     statm_reporter_running
 }
+
+// The file in each worker's checkpoint that records how far its generator got
+const GENERATOR_FILE: &str = "generator";
+
+// Records that the worker had created 'events_so_far' events for input times up to
+// 'target_ns' when the state of 'epoch' was checkpointed
+pub fn write_generator_position(checkpoints: &WorkerCheckpoints, epoch: u64, events_so_far: usize, target_ns: u64) {
+    let directory = checkpoints.epoch_directory(epoch);
+    std::fs::create_dir_all(&directory).expect("Unable to create the checkpoint directory");
+    std::fs::write(directory.join(GENERATOR_FILE), format!("{} {}", events_so_far, target_ns))
+        .expect("Unable to record the generator position");
+}
+
+pub fn read_generator_position(checkpoints: &WorkerCheckpoints, epoch: u64) -> (usize, u64) {
+    let path = checkpoints.epoch_directory(epoch).join(GENERATOR_FILE);
+    let position = std::fs::read_to_string(path).expect("Unable to read the generator position");
+    let mut fields = position.split_whitespace().map(|field| field.parse::<u64>().expect("Invalid generator position"));
+    match (fields.next(), fields.next()) {
+        (Some(events_so_far), Some(target_ns)) => (events_so_far as usize, target_ns),
+        _ => panic!("Invalid generator position"),
+    }
+}
@@ -29,16 +29,27 @@ pub use self::q6::q6;
 pub use self::q7::q7;
 pub use self::q8::q8;
 use faster_rs::FasterKv;
+use timely::state::config::BackendConfig;
+use timely::state::session::Session;
+
+// Each store has its own session, maintained as FASTER backends are configured to be
+fn faster_session() -> Session {
+    let config = BackendConfig::from_env().expect("Invalid state backend configuration");
+    Session::new(config.faster.session)
+}
 
 #[inline(always)]
-fn maybe_refresh_faster(faster: &FasterKv, monotonic_serial_number: &mut u64) {
-    if *monotonic_serial_number % (1 << 4) == 0 {
+fn maybe_refresh_faster(faster: &FasterKv, session: &mut Session) {
+    let maintenance = session.after_op();
+    if maintenance.refresh {
         faster.refresh();
-        if *monotonic_serial_number % (1 << 10) == 0 {
-            faster.complete_pending(true);
-        }
     }
-    *monotonic_serial_number += 1;
+    if maintenance.complete_pending {
+        faster.complete_pending(true);
+    }
+    if maintenance.report_size {
+        log::info!("FASTER size after {} operations: {}", session.serial(), faster.size());
+    }
 }
 
 pub struct NexmarkInput<'a> {
@@ -34,7 +34,7 @@ pub fn q3<S: Scope<Timestamp = usize>>(
     )
     .expect("Couldn't initialise FASTER");
     people_store.start_session();
-    let mut people_store_serial = 0;
+    let mut people_store_session = super::faster_session();
 
     let auctions_dir = TempDir::new_in(".").expect("Unable to create FASTER directory");
     let auctions_store = FasterKv::new_auctions_store(
@@ -44,7 +44,7 @@ pub fn q3<S: Scope<Timestamp = usize>>(
     )
     .expect("Couldn't initialise FASTER");
     auctions_store.start_session();
-    let mut auctions_store_serial = 0;
+    let mut auctions_store_session = super::faster_session();
 
     auctions.binary(
         &people,
@@ -59,8 +59,8 @@ pub fn q3<S: Scope<Timestamp = usize>>(
                     let mut session = output.session(&time);
                     for auction in auctions_buffer.drain(..) {
                         let (read_status, recv_person) =
-                            people_store.read_person(auction.seller as u64, people_store_serial);
-                        maybe_refresh_faster(&people_store, &mut people_store_serial);
+                            people_store.read_person(auction.seller as u64, people_store_session.serial());
+                        maybe_refresh_faster(&people_store, &mut people_store_session);
                         if read_status == status::PENDING {
                             people_store.complete_pending(true);
                         }
@@ -85,9 +85,9 @@ pub fn q3<S: Scope<Timestamp = usize>>(
                         auctions_store.rmw_auction(
                             auction.seller as u64,
                             auction.id as u64,
-                            auctions_store_serial,
+                            auctions_store_session.serial(),
                         );
-                        maybe_refresh_faster(&auctions_store, &mut auctions_store_serial);
+                        maybe_refresh_faster(&auctions_store, &mut auctions_store_session);
                     }
                 });
 
@@ -97,8 +97,8 @@ pub fn q3<S: Scope<Timestamp = usize>>(
                     let mut session = output.session(&time);
                     for person in people_buffer.drain(..) {
                         let (read_status, recv_auctions) =
-                            auctions_store.read_auctions(person.id as u64, auctions_store_serial);
-                        maybe_refresh_faster(&auctions_store, &mut auctions_store_serial);
+                            auctions_store.read_auctions(person.id as u64, auctions_store_session.serial());
+                        maybe_refresh_faster(&auctions_store, &mut auctions_store_session);
                         if read_status == status::PENDING {
                             auctions_store.complete_pending(true);
                         }
@@ -117,9 +117,9 @@ pub fn q3<S: Scope<Timestamp = usize>>(
                             &person.name,
                             &person.city,
                             &person.state,
-                            people_store_serial,
+                            people_store_session.serial(),
                         );
-                        maybe_refresh_faster(&people_store, &mut people_store_serial);
+                        maybe_refresh_faster(&people_store, &mut people_store_session);
                     }
                 });
             }
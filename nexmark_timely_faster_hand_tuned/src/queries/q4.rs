@@ -20,7 +20,7 @@ pub fn q4<S: Scope<Timestamp = usize>>(
         aggs_directory.to_str().unwrap().to_string(),
     )
     .unwrap();
-    let mut aggs_store_serial = 0;
+    let mut aggs_store_session = super::faster_session();
     input
         .closed_auctions(scope)
         .map(|(a, (_, b))| (a, b))
@@ -33,11 +33,11 @@ pub fn q4<S: Scope<Timestamp = usize>>(
                         let mut session = output.session(&time);
                         for (category, price) in data.iter().cloned() {
                             let (res, recv) =
-                                aggs.read_u64_pair(category as u64, aggs_store_serial);
+                                aggs.read_u64_pair(category as u64, aggs_store_session.serial());
                             if res == status::PENDING {
                                 aggs.complete_pending(true);
                             }
-                            maybe_refresh_faster(&aggs, &mut aggs_store_serial);
+                            maybe_refresh_faster(&aggs, &mut aggs_store_session);
                             let avg = match recv.recv() {
                                 Ok((sum, count)) => {
                                     *sum += (price as u64);
@@ -48,9 +48,9 @@ pub fn q4<S: Scope<Timestamp = usize>>(
                                     aggs.upsert_u64_pair(
                                         category as u64,
                                         (price as u64, 1),
-                                        aggs_store_serial,
+                                        aggs_store_session.serial(),
                                     );
-                                    maybe_refresh_faster(&aggs, &mut aggs_store_serial);
+                                    maybe_refresh_faster(&aggs, &mut aggs_store_session);
                                     price as u64
                                 }
                             };
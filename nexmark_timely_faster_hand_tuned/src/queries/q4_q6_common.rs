@@ -34,7 +34,7 @@ pub fn q4_q6_common<S: Scope<Timestamp = usize>>(
     )
     .expect("Couldn't initialise FASTER");
     state.start_session();
-    let mut state_serial = 0;
+    let mut state_session = super::faster_session();
 
     let expirations_dir = TempDir::new_in(".").expect("Unable to create FASTER directory");
     let expirations = FasterKv::new_auctions_store(
@@ -44,7 +44,7 @@ pub fn q4_q6_common<S: Scope<Timestamp = usize>>(
     )
     .expect("Couldn't initialise FASTER");
     expirations.start_session();
-    let mut expirations_serial = 0;
+    let mut expirations_session = super::faster_session();
 
     bids.binary_notify(
         &auctions,
@@ -57,11 +57,11 @@ pub fn q4_q6_common<S: Scope<Timestamp = usize>>(
             // NB: We don't summarize as the max, because we don't know which are valid.
             input1.for_each(|time, data| {
                 for bid in data.iter().cloned() {
-                    let (res, recv) = state.read_auction_bids(bid.auction as u64, state_serial);
+                    let (res, recv) = state.read_auction_bids(bid.auction as u64, state_session.serial());
                     if res == status::PENDING {
                         state.complete_pending(true);
                     }
-                    maybe_refresh_faster(&state, &mut state_serial);
+                    maybe_refresh_faster(&state, &mut state_session);
                     match recv.recv() {
                         Ok(entry) => {
                             if let Some(auction) = entry.0 {
@@ -81,9 +81,9 @@ pub fn q4_q6_common<S: Scope<Timestamp = usize>>(
                                             *bid.date_time,
                                             bid.price,
                                             bid.bidder,
-                                            state_serial,
+                                            state_session.serial(),
                                         );
-                                        maybe_refresh_faster(&state, &mut state_serial);
+                                        maybe_refresh_faster(&state, &mut state_session);
                                     }
                                 }
                             }
@@ -94,9 +94,9 @@ pub fn q4_q6_common<S: Scope<Timestamp = usize>>(
                                 *bid.date_time,
                                 bid.price,
                                 bid.bidder,
-                                state_serial,
+                                state_session.serial(),
                             );
-                            maybe_refresh_faster(&state, &mut state_serial);
+                            maybe_refresh_faster(&state, &mut state_session);
                         }
                     }
                 }
@@ -109,9 +109,9 @@ pub fn q4_q6_common<S: Scope<Timestamp = usize>>(
                     expirations.rmw_auction(
                         nt.from_nexmark_time(auction.expires) as u64,
                         auction.id as u64,
-                        expirations_serial,
+                        expirations_session.serial(),
                     );
-                    maybe_refresh_faster(&expirations, &mut expirations_serial);
+                    maybe_refresh_faster(&expirations, &mut expirations_session);
                     state.rmw_auction_bids_auction(
                         auction.id as u64,
                         auction.id,
@@ -119,14 +119,14 @@ pub fn q4_q6_common<S: Scope<Timestamp = usize>>(
                         *auction.date_time,
                         *auction.expires,
                         auction.reserve,
-                        state_serial,
+                        state_session.serial(),
                     );
-                    maybe_refresh_faster(&state, &mut state_serial);
-                    let (res, recv) = state.read_auction_bids(auction.id as u64, state_serial);
+                    maybe_refresh_faster(&state, &mut state_session);
+                    let (res, recv) = state.read_auction_bids(auction.id as u64, state_session.serial());
                     if res == status::PENDING {
                         state.complete_pending(true);
                     }
-                    maybe_refresh_faster(&state, &mut state_serial);
+                    maybe_refresh_faster(&state, &mut state_session);
                     let (_, bids) = recv.recv().unwrap();
                     if let Some(bid) = bids.iter().max_by_key(|bid| bid.price) {
                         bids[0] = CBid {
@@ -140,17 +140,17 @@ pub fn q4_q6_common<S: Scope<Timestamp = usize>>(
 
             notificator.for_each(|cap, _, _| {
                 let mut session = output.session(&cap);
-                let (res, recv) = expirations.read_auctions(*cap.time() as u64, expirations_serial);
+                let (res, recv) = expirations.read_auctions(*cap.time() as u64, expirations_session.serial());
                 if res == status::PENDING {
                     expirations.complete_pending(true);
                 }
-                maybe_refresh_faster(&expirations, &mut expirations_serial);
+                maybe_refresh_faster(&expirations, &mut expirations_session);
                 for auction_id in recv.recv().unwrap() {
-                    let (res, recv) = state.read_auction_bids(*auction_id, state_serial);
+                    let (res, recv) = state.read_auction_bids(*auction_id, state_session.serial());
                     if res == status::PENDING {
                         state.complete_pending(true);
                     }
-                    maybe_refresh_faster(&state, &mut state_serial);
+                    maybe_refresh_faster(&state, &mut state_session);
                     let delete = match recv.recv() {
                         Err(_) => false,
                         Ok((auction, bids)) => match auction {
@@ -176,12 +176,12 @@ pub fn q4_q6_common<S: Scope<Timestamp = usize>>(
                         },
                     };
                     if delete {
-                        state.delete_auction_bids(*auction_id, state_serial);
-                        maybe_refresh_faster(&state, &mut state_serial);
+                        state.delete_auction_bids(*auction_id, state_session.serial());
+                        maybe_refresh_faster(&state, &mut state_session);
                     }
                 }
-                expirations.delete_auctions(*cap.time() as u64, expirations_serial);
-                maybe_refresh_faster(&expirations, &mut expirations_serial);
+                expirations.delete_auctions(*cap.time() as u64, expirations_session.serial());
+                maybe_refresh_faster(&expirations, &mut expirations_session);
             });
         },
     )
@@ -25,7 +25,7 @@ pub fn q5<S: Scope<Timestamp = usize>>(
         additions_directory.to_str().unwrap().to_string(),
     )
     .unwrap();
-    let mut additions_store_serial = 0;
+    let mut additions_store_session = super::faster_session();
     //let mut deletions = HashMap::new();
     let deletions_directory = TempDir::new_in(".").unwrap().into_path();
     let deletions = FasterKv::new_auctions_store(
@@ -34,7 +34,7 @@ pub fn q5<S: Scope<Timestamp = usize>>(
         deletions_directory.to_str().unwrap().to_string(),
     )
     .unwrap();
-    let mut deletions_store_serial = 0;
+    let mut deletions_store_session = super::faster_session();
     let accumulations_directory = TempDir::new_in(".").unwrap().into_path();
     let accumulations = FasterKv::new_u64_store(
         1 << 24,
@@ -42,7 +42,7 @@ pub fn q5<S: Scope<Timestamp = usize>>(
         accumulations_directory.to_str().unwrap().to_string(),
     )
     .unwrap();
-    let mut accumulations_store_serial = 0;
+    let mut accumulations_store_session = super::faster_session();
     let hot_items_directory = TempDir::new_in(".").unwrap().into_path();
     let hot_items = FasterKv::new_u64_pair_store(
         1 << 24,
@@ -50,7 +50,7 @@ pub fn q5<S: Scope<Timestamp = usize>>(
         hot_items_directory.to_str().unwrap().to_string(),
     )
     .unwrap();
-    let mut hot_items_store_serial = 0;
+    let mut hot_items_store_session = super::faster_session();
 
     input
         .bids(scope)
@@ -81,9 +81,9 @@ pub fn q5<S: Scope<Timestamp = usize>>(
                             additions.rmw_auction(
                                 nt.from_nexmark_time(a_time) as u64,
                                 auction as u64,
-                                additions_store_serial,
+                                additions_store_session.serial(),
                             );
-                            maybe_refresh_faster(&additions, &mut additions_store_serial);
+                            maybe_refresh_faster(&additions, &mut additions_store_session);
                             notificator.notify_at(time.delayed(&nt.from_nexmark_time(a_time)));
                         }
                     }
@@ -94,22 +94,22 @@ pub fn q5<S: Scope<Timestamp = usize>>(
                     additions.rmw_auctions(
                         nt.from_nexmark_time(slide) as u64,
                         bids,
-                        additions_store_serial,
+                        additions_store_session.serial(),
                     );
-                    maybe_refresh_faster(&additions, &mut additions_store_serial);
+                    maybe_refresh_faster(&additions, &mut additions_store_session);
                 });
 
                 notificator.for_each(|time, _, notificator| {
                     let (status, recv) =
-                        additions.read_auctions(*time.time() as u64, additions_store_serial);
+                        additions.read_auctions(*time.time() as u64, additions_store_session.serial());
                     if status == status::PENDING {
                         additions.complete_pending(true);
                     }
-                    maybe_refresh_faster(&additions, &mut additions_store_serial);
+                    maybe_refresh_faster(&additions, &mut additions_store_session);
                     if let Ok(additions) = recv.recv() {
                         for auction in additions.iter() {
-                            accumulations.rmw_u64(*auction, 1, accumulations_store_serial);
-                            maybe_refresh_faster(&accumulations, &mut accumulations_store_serial);
+                            accumulations.rmw_u64(*auction, 1, accumulations_store_session.serial());
+                            maybe_refresh_faster(&accumulations, &mut accumulations_store_session);
                         }
                         let new_time = time.time() + (window_slice_count * window_slide_ns);
                         let mut copied_additions = Vec::with_capacity(additions.len());
@@ -117,40 +117,40 @@ pub fn q5<S: Scope<Timestamp = usize>>(
                         deletions.upsert_auctions(
                             new_time as u64,
                             copied_additions,
-                            deletions_store_serial,
+                            deletions_store_session.serial(),
                         );
-                        maybe_refresh_faster(&deletions, &mut deletions_store_serial);
+                        maybe_refresh_faster(&deletions, &mut deletions_store_session);
                         notificator.notify_at(time.delayed(&new_time));
                     }
                     let (status, recv) =
-                        deletions.read_auctions(*time.time() as u64, deletions_store_serial);
+                        deletions.read_auctions(*time.time() as u64, deletions_store_session.serial());
                     if status == status::PENDING {
                         deletions.complete_pending(true);
                     }
-                    maybe_refresh_faster(&deletions, &mut deletions_store_serial);
+                    maybe_refresh_faster(&deletions, &mut deletions_store_session);
                     if let Ok(deletions) = recv.recv() {
                         for auction in deletions {
                             let (status, recv) =
-                                accumulations.read_u64(*auction, accumulations_store_serial);
+                                accumulations.read_u64(*auction, accumulations_store_session.serial());
                             if status == status::PENDING {
                                 accumulations.complete_pending(true);
                             }
-                            maybe_refresh_faster(&accumulations, &mut accumulations_store_serial);
+                            maybe_refresh_faster(&accumulations, &mut accumulations_store_session);
                             match recv.recv() {
                                 Ok(entry) => {
                                     if entry == 1 {
                                         accumulations
-                                            .delete_u64(*auction, accumulations_store_serial);
+                                            .delete_u64(*auction, accumulations_store_session.serial());
                                     } else {
                                         accumulations.rmw_decrease_u64(
                                             *auction,
                                             1,
-                                            accumulations_store_serial,
+                                            accumulations_store_session.serial(),
                                         );
                                     }
                                     maybe_refresh_faster(
                                         &accumulations,
-                                        &mut accumulations_store_serial,
+                                        &mut accumulations_store_session,
                                     );
                                 }
                                 Err(_) => panic!("entry has to exist"),
@@ -181,11 +181,11 @@ pub fn q5<S: Scope<Timestamp = usize>>(
                 input.for_each(|time, data| {
                     data.swap(&mut buffer);
                     let (status, recv) =
-                        hot_items.read_u64_pair(*time.time() as u64, hot_items_store_serial);
+                        hot_items.read_u64_pair(*time.time() as u64, hot_items_store_session.serial());
                     if status == status::PENDING {
                         hot_items.complete_pending(true);
                     }
-                    maybe_refresh_faster(&hot_items, &mut hot_items_store_serial);
+                    maybe_refresh_faster(&hot_items, &mut hot_items_store_session);
                     let mut current_hottest = match recv.recv() {
                         Ok((left, right)) => (*left, *right),
                         Err(_) => (0, 0),
@@ -198,19 +198,19 @@ pub fn q5<S: Scope<Timestamp = usize>>(
                     hot_items.upsert_u64_pair(
                         *time.time() as u64,
                         current_hottest,
-                        hot_items_store_serial,
+                        hot_items_store_session.serial(),
                     );
-                    maybe_refresh_faster(&hot_items, &mut hot_items_store_serial);
+                    maybe_refresh_faster(&hot_items, &mut hot_items_store_session);
                     notificator.notify_at(time.delayed(&time.time()))
                 });
 
                 notificator.for_each(|cap, _, _| {
                     let (status, recv) =
-                        hot_items.read_u64_pair(*cap.time() as u64, hot_items_store_serial);
+                        hot_items.read_u64_pair(*cap.time() as u64, hot_items_store_session.serial());
                     if status == status::PENDING {
                         hot_items.complete_pending(true);
                     }
-                    maybe_refresh_faster(&hot_items, &mut hot_items_store_serial);
+                    maybe_refresh_faster(&hot_items, &mut hot_items_store_session);
                     output.session(&cap).give(*recv.recv().unwrap().0 as usize);
                 });
             },
@@ -44,7 +44,7 @@ pub fn q5_index<S: Scope<Timestamp = usize>>(
         pre_reduce_state_directory.to_str().unwrap().to_string(),
     )
         .unwrap();
-    let mut pre_reduce_state_store_serial = 0;
+    let mut pre_reduce_state_store_session = super::faster_session();
     let hot_items_directory = TempDir::new_in(".").unwrap().into_path();
     let hot_items = FasterKv::new_u64_pair_store(
         1 << 24,
@@ -52,7 +52,7 @@ pub fn q5_index<S: Scope<Timestamp = usize>>(
         hot_items_directory.to_str().unwrap().to_string(),
     )
         .unwrap();
-    let mut hot_items_store_serial = 0;
+    let mut hot_items_store_session = super::faster_session();
     let index_state_directory = TempDir::new_in(".").unwrap().into_path();
     let index_state = FasterKv::new_auctions_store(
         1 << 24,
@@ -60,7 +60,7 @@ pub fn q5_index<S: Scope<Timestamp = usize>>(
         index_state_directory.to_str().unwrap().to_string(),
     )
         .unwrap();
-    let mut index_state_store_serial = 0;
+    let mut index_state_store_session = super::faster_session();
     input
         .bids(scope)
         .map(move |b| {
@@ -94,11 +94,11 @@ pub fn q5_index<S: Scope<Timestamp = usize>>(
                         let mut exists = false;
                         {   // Check if composite key exists in the slide
                             // println!("Composite Key: {:?}",(a_time,auction));
-                            let (res, recv) = index_state.read_auctions(a_time as u64, index_state_store_serial);
+                            let (res, recv) = index_state.read_auctions(a_time as u64, index_state_store_session.serial());
                             if res == status::PENDING {
                                 index_state.complete_pending(true);
                             }
-                            super::maybe_refresh_faster(&index_state, &mut index_state_store_serial);
+                            super::maybe_refresh_faster(&index_state, &mut index_state_store_session);
                             let keys: Option<&[u64]> = recv.recv().ok();
                             if keys.is_some() {
                                 // println!("Composite keys: {:?}",keys);
@@ -112,12 +112,12 @@ pub fn q5_index<S: Scope<Timestamp = usize>>(
                             keys.push(auction);
                             state_index.insert(a_time, keys);
                             */
-                            index_state.rmw_auction(a_time as u64, auction as u64, index_state_store_serial);
-                            super::maybe_refresh_faster(&index_state, &mut index_state_store_serial);
+                            index_state.rmw_auction(a_time as u64, auction as u64, index_state_store_session.serial());
+                            super::maybe_refresh_faster(&index_state, &mut index_state_store_session);
                         }
                         let composite_key = (a_time as u64, auction as u64);
-                        pre_reduce_state.rmw_u64_composite(composite_key, 1, pre_reduce_state_store_serial);
-                        super::maybe_refresh_faster(&pre_reduce_state, &mut pre_reduce_state_store_serial);
+                        pre_reduce_state.rmw_u64_composite(composite_key, 1, pre_reduce_state_store_session.serial());
+                        super::maybe_refresh_faster(&pre_reduce_state, &mut pre_reduce_state_store_session);
                     }
                 });
 
@@ -128,21 +128,21 @@ pub fn q5_index<S: Scope<Timestamp = usize>>(
                     for i in 0..window_slice_count {
                         let slide = cap.time() - i * window_slide_ns;
                         // println!("Slide: {}",slide);
-                        let (res, recv) = index_state.read_auctions(slide as u64, index_state_store_serial);
+                        let (res, recv) = index_state.read_auctions(slide as u64, index_state_store_session.serial());
                         if res == status::PENDING {
                             index_state.complete_pending(true);
                         }
-                        super::maybe_refresh_faster(&index_state, &mut index_state_store_serial);
+                        super::maybe_refresh_faster(&index_state, &mut index_state_store_session);
                         if let Some(auction_ids) = recv.recv().ok() {
                             for auction_id in auction_ids.iter() {
                                 let composite_key = (slide as u64, *auction_id as u64);
                                 // Look up state
                                 //let count = pre_reduce_state.get(&composite_key).expect("Composite key must exist");
-                                let (res, recv) = pre_reduce_state.read_u64_composite(composite_key, pre_reduce_state_store_serial);
+                                let (res, recv) = pre_reduce_state.read_u64_composite(composite_key, pre_reduce_state_store_session.serial());
                                 if res == status::PENDING {
                                     pre_reduce_state.complete_pending(true);
                                 }
-                                super::maybe_refresh_faster(&pre_reduce_state, &mut pre_reduce_state_store_serial);
+                                super::maybe_refresh_faster(&pre_reduce_state, &mut pre_reduce_state_store_session);
                                 // println!("Found auction id {} in composite key {:?}",auction_id,composite_key);
                                 let c = counts.entry(auction_id.clone()).or_insert(0);
                                 *c += recv.recv().unwrap();
@@ -159,18 +159,18 @@ pub fn q5_index<S: Scope<Timestamp = usize>>(
                     // Remove the first slide of the expired window
                     let slide_to_remove: usize = cap.time() - (window_slice_count - 1) * window_slide_ns;
                     // println!("Slide to remove: {}",slide_to_remove);
-                    let (res, recv) = index_state.read_auctions(slide_to_remove as u64, index_state_store_serial);
+                    let (res, recv) = index_state.read_auctions(slide_to_remove as u64, index_state_store_session.serial());
                     if res == status::PENDING {
                         index_state.complete_pending(true);
                     }
-                    super::maybe_refresh_faster(&index_state, &mut index_state_store_serial);
+                    super::maybe_refresh_faster(&index_state, &mut index_state_store_session);
                     if let Some(auctions_in_slide) = recv.recv().ok() {
                         // println!("Auctions to remove: {:?}",auctions_in_slide);
-                        index_state.delete_auctions(slide_to_remove as u64, index_state_store_serial);
-                        super::maybe_refresh_faster(&index_state, &mut index_state_store_serial);
+                        index_state.delete_auctions(slide_to_remove as u64, index_state_store_session.serial());
+                        super::maybe_refresh_faster(&index_state, &mut index_state_store_session);
                         for auction in auctions_in_slide.iter() {
-                            pre_reduce_state.delete_u64_composite((slide_to_remove as u64, *auction as u64), pre_reduce_state_store_serial);
-                            super::maybe_refresh_faster(&pre_reduce_state, &mut pre_reduce_state_store_serial);
+                            pre_reduce_state.delete_u64_composite((slide_to_remove as u64, *auction as u64), pre_reduce_state_store_session.serial());
+                            super::maybe_refresh_faster(&pre_reduce_state, &mut pre_reduce_state_store_session);
                         }
                     }
                     else {
@@ -188,11 +188,11 @@ pub fn q5_index<S: Scope<Timestamp = usize>>(
                 input.for_each(|time, data| {
                     data.swap(&mut buffer);
                     let (status, recv) =
-                        hot_items.read_u64_pair(*time.time() as u64, hot_items_store_serial);
+                        hot_items.read_u64_pair(*time.time() as u64, hot_items_store_session.serial());
                     if status == status::PENDING {
                         hot_items.complete_pending(true);
                     }
-                    super::maybe_refresh_faster(&hot_items, &mut hot_items_store_serial);
+                    super::maybe_refresh_faster(&hot_items, &mut hot_items_store_session);
                     let mut current_hottest = match recv.recv() {
                         Ok((left, right)) => (*left, *right),
                         Err(_) => (0, 0),
@@ -205,19 +205,19 @@ pub fn q5_index<S: Scope<Timestamp = usize>>(
                     hot_items.upsert_u64_pair(
                         *time.time() as u64,
                         current_hottest,
-                        hot_items_store_serial,
+                        hot_items_store_session.serial(),
                     );
-                    super::maybe_refresh_faster(&hot_items, &mut hot_items_store_serial);
+                    super::maybe_refresh_faster(&hot_items, &mut hot_items_store_session);
                     notificator.notify_at(time.delayed(&time.time()))
                 });
 
                 notificator.for_each(|cap, _, _| {
                     let (status, recv) =
-                        hot_items.read_u64_pair(*cap.time() as u64, hot_items_store_serial);
+                        hot_items.read_u64_pair(*cap.time() as u64, hot_items_store_session.serial());
                     if status == status::PENDING {
                         hot_items.complete_pending(true);
                     }
-                    super::maybe_refresh_faster(&hot_items, &mut hot_items_store_serial);
+                    super::maybe_refresh_faster(&hot_items, &mut hot_items_store_session);
                     output.session(&cap).give(*recv.recv().unwrap().0 as usize);
                 });
             },
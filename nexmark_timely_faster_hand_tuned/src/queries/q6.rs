@@ -20,7 +20,7 @@ pub fn q6<S: Scope<Timestamp = usize>>(
         aggs_directory.to_str().unwrap().to_string(),
     )
     .unwrap();
-    let mut aggs_store_serial = 0;
+    let mut aggs_store_session = super::faster_session();
     input.closed_auctions(scope).map(|(_a, b)| b).unary(
         Exchange::new(|x: &(usize, usize)| x.0 as u64),
         "Q6 Average",
@@ -29,14 +29,14 @@ pub fn q6<S: Scope<Timestamp = usize>>(
                 input.for_each(|time, data| {
                     let mut session = output.session(&time);
                     for (bidder, price) in data.iter().cloned() {
-                        aggs.rmw_ten_elements(bidder as u64, price, aggs_store_serial);
-                        maybe_refresh_faster(&aggs, &mut aggs_store_serial);
+                        aggs.rmw_ten_elements(bidder as u64, price, aggs_store_session.serial());
+                        maybe_refresh_faster(&aggs, &mut aggs_store_session);
                         let (res, recv) =
-                            aggs.read_ten_elements_average(bidder as u64, aggs_store_serial);
+                            aggs.read_ten_elements_average(bidder as u64, aggs_store_session.serial());
                         if res == status::PENDING {
                             aggs.complete_pending(true);
                         }
-                        maybe_refresh_faster(&aggs, &mut aggs_store_serial);
+                        maybe_refresh_faster(&aggs, &mut aggs_store_session);
                         session.give((bidder, recv.recv().unwrap()));
                     }
                 });
@@ -33,27 +33,27 @@ pub fn q7<S: Scope<Timestamp = usize>>(
                 state_directory.to_str().unwrap().to_string(),
             )
             .unwrap();
-            let mut state_store_serial = 0;
+            let mut state_store_session = super::faster_session();
 
             let mut capabilities = Vec::<Capability<usize>>::new();
             move |input, output| {
                 input.for_each(|time, data| {
                     for (window, price) in data.iter().cloned() {
-                        let (status, recv) = state.read_u64(nt.from_nexmark_time(window) as u64, state_store_serial);
+                        let (status, recv) = state.read_u64(nt.from_nexmark_time(window) as u64, state_store_session.serial());
                         if status == status::PENDING {
                             state.complete_pending(true);
                         }
-                        maybe_refresh_faster(&state, &mut state_store_serial);
+                        maybe_refresh_faster(&state, &mut state_store_session);
                         match recv.recv() {
                             Ok(current_highest) => {
                                 if (current_highest as usize) < price {
-                                    state.upsert_u64(nt.from_nexmark_time(window) as u64, price as u64, state_store_serial);
-                                    maybe_refresh_faster(&state, &mut state_store_serial);
+                                    state.upsert_u64(nt.from_nexmark_time(window) as u64, price as u64, state_store_session.serial());
+                                    maybe_refresh_faster(&state, &mut state_store_session);
                                 }
                             },
                             Err(_) => {
-                                state.upsert_u64(nt.from_nexmark_time(window) as u64, price as u64, state_store_serial);
-                                maybe_refresh_faster(&state, &mut state_store_serial);
+                                state.upsert_u64(nt.from_nexmark_time(window) as u64, price as u64, state_store_session.serial());
+                                maybe_refresh_faster(&state, &mut state_store_session);
                                 capabilities.push(time.delayed(&nt.from_nexmark_time(window)));
                             },
                         }
@@ -62,11 +62,11 @@ pub fn q7<S: Scope<Timestamp = usize>>(
 
                 for &(ref cap) in capabilities.iter() {
                     if !input.frontier.less_than(cap.time()) {
-                        let (status, recv) = state.read_u64(*cap.time() as u64, state_store_serial);
+                        let (status, recv) = state.read_u64(*cap.time() as u64, state_store_session.serial());
                         if status == status::PENDING {
                             state.complete_pending(true);
                         }
-                        maybe_refresh_faster(&state, &mut state_store_serial);
+                        maybe_refresh_faster(&state, &mut state_store_session);
                         output
                             .session(&cap)
                             .give((*cap.time(), recv.recv().expect("Value must be present") as usize));
@@ -87,27 +87,27 @@ pub fn q7<S: Scope<Timestamp = usize>>(
                     state_directory.to_str().unwrap().to_string(),
                 )
                     .unwrap();
-                let mut state_store_serial = 0;
+                let mut state_store_session = super::faster_session();
 
                 let mut capabilities = Vec::<Capability<usize>>::new();
                 move |input, output| {
                     input.for_each(|time, data| {
                         for (window, price) in data.iter().cloned() {
-                            let (status, recv) = state.read_u64(window as u64, state_store_serial);
+                            let (status, recv) = state.read_u64(window as u64, state_store_session.serial());
                             if status == status::PENDING {
                                 state.complete_pending(true);
                             }
-                            maybe_refresh_faster(&state, &mut state_store_serial);
+                            maybe_refresh_faster(&state, &mut state_store_session);
                             match recv.recv() {
                                 Ok(current_highest) => {
                                     if (current_highest as usize) < price {
-                                        state.upsert_u64(window as u64, price as u64, state_store_serial);
-                                        maybe_refresh_faster(&state, &mut state_store_serial);
+                                        state.upsert_u64(window as u64, price as u64, state_store_session.serial());
+                                        maybe_refresh_faster(&state, &mut state_store_session);
                                     }
                                 },
                                 Err(_) => {
-                                    state.upsert_u64(window as u64, price as u64, state_store_serial);
-                                    maybe_refresh_faster(&state, &mut state_store_serial);
+                                    state.upsert_u64(window as u64, price as u64, state_store_session.serial());
+                                    maybe_refresh_faster(&state, &mut state_store_session);
                                     capabilities.push(time.delayed(&window));
                                 },
                             }
@@ -116,11 +116,11 @@ pub fn q7<S: Scope<Timestamp = usize>>(
 
                     for &(ref cap) in capabilities.iter() {
                         if !input.frontier.less_than(cap.time()) {
-                            let (status, recv) = state.read_u64(*cap.time() as u64, state_store_serial);
+                            let (status, recv) = state.read_u64(*cap.time() as u64, state_store_session.serial());
                             if status == status::PENDING {
                                 state.complete_pending(true);
                             }
-                            maybe_refresh_faster(&state, &mut state_store_serial);
+                            maybe_refresh_faster(&state, &mut state_store_session);
                             output
                                 .session(&cap)
                                 .give(recv.recv().expect("Value must be present") as usize);
@@ -25,7 +25,7 @@ pub fn q8<S: Scope<Timestamp = usize>>(
         new_people_directory.to_str().unwrap().to_string(),
     )
         .unwrap();
-    let mut new_people_store_serial = 0;
+    let mut new_people_store_session = super::faster_session();
     let auctions_state_directory = TempDir::new_in(".").unwrap().into_path();
     let auctions_state = FasterKv::new_u64_pairs_store(
         1 << 24,
@@ -33,7 +33,7 @@ pub fn q8<S: Scope<Timestamp = usize>>(
         auctions_state_directory.to_str().unwrap().to_string(),
     )
         .unwrap();
-    let mut auctions_state_store_serial = 0;
+    let mut auctions_state_store_session = super::faster_session();
 
     let mut index_state: Vec<usize> = Vec::new();
     people.binary_notify(
@@ -47,8 +47,8 @@ pub fn q8<S: Scope<Timestamp = usize>>(
             input1.for_each(|time, data| {
                 notificator.notify_at(time.retain());
                 for (person, p_time) in data.iter().cloned() {
-                    new_people.upsert_u64(person as u64, *p_time as u64, new_people_store_serial);
-                    super::maybe_refresh_faster(&new_people, &mut new_people_store_serial);
+                    new_people.upsert_u64(person as u64, *p_time as u64, new_people_store_session.serial());
+                    super::maybe_refresh_faster(&new_people, &mut new_people_store_session);
                 }
             });
 
@@ -57,8 +57,8 @@ pub fn q8<S: Scope<Timestamp = usize>>(
                 let ts = *time.time();
                 let mut data_vec = vec![];
                 data.swap(&mut data_vec);
-                auctions_state.rmw_u64_pairs(ts as u64, data_vec, auctions_state_store_serial);
-                super::maybe_refresh_faster(&auctions_state, &mut auctions_state_store_serial);
+                auctions_state.rmw_u64_pairs(ts as u64, data_vec, auctions_state_store_session.serial());
+                super::maybe_refresh_faster(&auctions_state, &mut auctions_state_store_session);
                 notificator.notify_at(time.retain());
             });
 
@@ -68,22 +68,22 @@ pub fn q8<S: Scope<Timestamp = usize>>(
                 entries_to_check.push(capability_time);
                 let mut to_keep = Vec::new();
                 for ts in entries_to_check { // ts <= capability_time
-                    let (res, recv) = auctions_state.read_u64_pairs(ts as u64, auctions_state_store_serial);
+                    let (res, recv) = auctions_state.read_u64_pairs(ts as u64, auctions_state_store_session.serial());
                     if res == status::PENDING {
                         auctions_state.complete_pending(true);
                     }
-                    super::maybe_refresh_faster(&auctions_state, &mut auctions_state_store_serial);
+                    super::maybe_refresh_faster(&auctions_state, &mut auctions_state_store_session);
                     if let Some(mut auctions) = recv.recv().ok() {
-                        auctions_state.delete_u64_pairs(ts as u64, auctions_state_store_serial);
-                        super::maybe_refresh_faster(&auctions_state, &mut auctions_state_store_serial);
+                        auctions_state.delete_u64_pairs(ts as u64, auctions_state_store_session.serial());
+                        super::maybe_refresh_faster(&auctions_state, &mut auctions_state_store_session);
                         let mut session = output.session(&cap);
                         for &(person, time) in auctions.iter() {
                             if time <= *nt.to_nexmark_time(capability_time) {
-                                let (res, recv) = new_people.read_u64(person as u64, new_people_store_serial);
+                                let (res, recv) = new_people.read_u64(person as u64, new_people_store_session.serial());
                                 if res == status::PENDING {
                                     new_people.complete_pending(true);
                                 }
-                                super::maybe_refresh_faster(&new_people, &mut new_people_store_serial);
+                                super::maybe_refresh_faster(&new_people, &mut new_people_store_session);
                                 if let Some(p_time) = recv.recv().ok() {
                                     if time < *nt.to_nexmark_time(p_time as usize + window_size_ns) {
                                         session.give(person);
@@ -94,8 +94,8 @@ pub fn q8<S: Scope<Timestamp = usize>>(
                         auctions.retain(|&(_, time)| time > *nt.to_nexmark_time(capability_time));
                         if auctions.len() > 0 {
                             // Put it back in state
-                            auctions_state.upsert_u64_pairs(ts as u64, auctions, auctions_state_store_serial);
-                            super::maybe_refresh_faster(&auctions_state, &mut auctions_state_store_serial);
+                            auctions_state.upsert_u64_pairs(ts as u64, auctions, auctions_state_store_session.serial());
+                            super::maybe_refresh_faster(&auctions_state, &mut auctions_state_store_session);
                             to_keep.push(ts)
                         }
                     }
@@ -1,6 +1,7 @@
 use timely::dataflow::operators::Inspect;
 use timely::dataflow::operators::capture::Replay;
 use timely::dataflow::operators::Accumulate;
+use timely::state::StateHandle;
 use timely::state::backends::InMemoryBackend;
 
 use rdkafka::config::ClientConfig;
@@ -8,7 +9,7 @@ use rdkafka::config::ClientConfig;
 use kafkaesque::EventConsumer;
 
 fn main() {
-    timely::execute_from_args(std::env::args(), |worker, _state_handle| {
+    timely::execute_from_args(std::env::args(), |worker, _state_handle: StateHandle<InMemoryBackend>| {
 
         let topic = std::env::args().nth(1).unwrap();
         let source_peers = std::env::args().nth(2).unwrap().parse::<usize>().unwrap();
@@ -1,5 +1,6 @@
 use timely::dataflow::operators::ToStream;
 use timely::dataflow::operators::capture::Capture;
+use timely::state::StateHandle;
 use timely::state::backends::InMemoryBackend;
 
 use rdkafka::config::ClientConfig;
@@ -7,7 +8,7 @@ use rdkafka::config::ClientConfig;
 use kafkaesque::EventProducer;
 
 fn main() {
-    timely::execute_from_args(std::env::args(), |worker, _state_handle| {
+    timely::execute_from_args(std::env::args(), |worker, _state_handle: StateHandle<InMemoryBackend>| {
 
         // target topic name.
         let topic = std::env::args().nth(1).unwrap();
@@ -1,4 +1,5 @@
 use timely::dataflow::operators::Inspect;
+use timely::state::StateHandle;
 use timely::state::backends::InMemoryBackend;
 
 use rdkafka::config::ClientConfig;
@@ -26,7 +27,7 @@ fn main() {
         .set("session.timeout.ms", "6000")
         .set("bootstrap.servers", &brokers);
 
-    timely::execute_from_args(args, move |worker, _state_handle| {
+    timely::execute_from_args(args, move |worker, _state_handle: StateHandle<InMemoryBackend>| {
 
         // A dataflow for producing spans.
         worker.dataflow::<u64,_,_,InMemoryBackend>(|scope, _| {
@@ -0,0 +1,740 @@
+//! Semantics that every backend's managed state must agree on.
+//!
+//! Backends call `check_managed_map` from their own tests, and every backend is
+//! run through the full suite of `StateBackend` checks via `backend_conformance!`
+//! at the bottom of this file, so that a divergence between backends fails the
+//! test suite instead of silently skewing benchmark results.
+
+#[cfg(feature = "faster")]
+use super::{FASTERBackend, FASTERInMemoryBackend, FASTERNodeBackend};
+#[cfg(feature = "in-memory")]
+use super::{InMemoryBackend, InMemoryNativeBackend, InMemoryOrderedBackend};
+#[cfg(feature = "rocksdb")]
+use super::{RocksDBBackend, RocksDBMergeBackend};
+#[cfg(feature = "sled")]
+use super::SledBackend;
+#[cfg(feature = "in-memory")]
+use super::CachedBackend;
+use crate::checkpoint::{CheckpointError, WorkerCheckpoints};
+use crate::codec::{Bincode, Codec, Raw, U64};
+use crate::column_family::{ColumnFamilyOptions, CompactionStyle};
+#[cfg(feature = "in-memory")]
+use crate::config::{CacheConfig, CachePolicy};
+use crate::config::BackendConfig;
+#[cfg(feature = "faster")]
+use crate::config::FasterConfig;
+use crate::expiry::SWEEP_BATCH;
+use crate::merge::MergeOperator;
+use crate::primitives::{
+    IterDirection, IterationError, KeyRange, ManagedMap, PendingReads, BAG_CHUNK_LENGTH,
+};
+use crate::{StateBackend, StateHandle};
+#[cfg(feature = "faster")]
+use faster_rs::FasterKv;
+use std::cell::Cell;
+use std::cmp;
+use std::ops::Bound;
+use std::rc::Rc;
+#[cfg(feature = "faster")]
+use std::sync::Arc;
+use tempfile::TempDir;
+
+/// Checks point inserts, lookups, existence checks and deletes on an empty map.
+pub fn check_managed_map<M>(map: &mut M)
+where
+    M: ManagedMap<u64, u64> + ?Sized,
+{
+    let key: u64 = 1;
+    let missing_key: u64 = 2;
+    let value: u64 = 1337;
+
+    assert_eq!(map.get(&missing_key), None);
+    assert!(!map.contains(&missing_key));
+    assert_eq!(map.remove(&missing_key), None);
+
+    map.insert(key, value);
+    assert!(map.contains(&key));
+    assert!(!map.contains(&missing_key));
+    assert_eq!(map.get(&key), Some(Rc::new(value)));
+
+    map.insert(key, value + 1);
+    assert_eq!(map.get(&key), Some(Rc::new(value + 1)));
+
+    assert_eq!(map.remove(&key), Some(value + 1));
+    assert!(!map.contains(&key));
+    assert_eq!(map.get(&key), None);
+    assert_eq!(map.remove(&key), None);
+}
+
+pub fn count<S: StateBackend>(handle: &StateHandle<S>) {
+    let mut count = handle.get_managed_count("count");
+    assert_eq!(count.get(), 0);
+    count.increase(5);
+    count.decrease(2);
+    assert_eq!(count.get(), 3);
+    count.set(-10);
+    assert_eq!(count.get(), -10);
+}
+
+pub fn value<S: StateBackend>(handle: &StateHandle<S>) {
+    let mut value = handle.get_managed_value::<u64>("value");
+    assert_eq!(value.get(), None);
+    assert_eq!(value.take(), None);
+
+    value.set(42);
+    assert_eq!(value.get(), Some(Rc::new(42)));
+    value.set(43);
+    assert_eq!(value.get(), Some(Rc::new(43)));
+
+    assert_eq!(value.take(), Some(43));
+    assert_eq!(value.get(), None);
+    assert_eq!(value.take(), None);
+}
+
+pub fn value_rmw_sum<S: StateBackend>(handle: &StateHandle<S>) {
+    let mut value = handle.get_managed_value::<u64>("value");
+    value.rmw(1);
+    value.rmw(2);
+    assert_eq!(value.get(), Some(Rc::new(3)));
+}
+
+pub fn value_rmw_append<S: StateBackend>(handle: &StateHandle<S>) {
+    let mut value = handle.get_managed_value::<Vec<(usize, usize)>>("appended");
+    value.rmw(vec![(1, 1)]);
+    value.rmw(vec![(2, 2)]);
+    assert_eq!(value.get(), Some(Rc::new(vec![(1, 1), (2, 2)])));
+}
+
+pub fn map<S: StateBackend>(handle: &StateHandle<S>) {
+    let mut map = handle.get_managed_map::<u64, u64>("map");
+    check_managed_map(&mut *map);
+}
+
+pub fn map_rmw_sum<S: StateBackend>(handle: &StateHandle<S>) {
+    let mut map = handle.get_managed_map::<u64, u64>("map");
+    map.rmw(1, 1);
+    map.rmw(1, 2);
+    map.rmw(2, 5);
+    assert_eq!(map.get(&1), Some(Rc::new(3)));
+    assert_eq!(map.get(&2), Some(Rc::new(5)));
+}
+
+pub fn map_rmw_append<S: StateBackend>(handle: &StateHandle<S>) {
+    let mut map = handle.get_managed_map::<u64, Vec<(usize, usize)>>("appended");
+    map.rmw(1, vec![(1, 1)]);
+    map.rmw(1, vec![(2, 2)]);
+    assert_eq!(map.get(&1), Some(Rc::new(vec![(1, 1), (2, 2)])));
+}
+
+/// Maps declaring a merge operator use it for `rmw` instead of the value's `Rmw`.
+pub fn map_merge_operators<S: StateBackend>(handle: &StateHandle<S>) {
+    let mut max = handle.get_managed_map_with_merge::<u64, u64>("max", MergeOperator::max());
+    let mut min = handle.get_managed_map_with_merge::<u64, u64>("min", MergeOperator::min());
+    for modification in &[3, 7, 5] {
+        max.rmw(1, *modification);
+        min.rmw(1, *modification);
+    }
+    assert_eq!(max.get(&1), Some(Rc::new(7)));
+    assert_eq!(min.get(&1), Some(Rc::new(3)));
+
+    let mut words = handle.get_managed_map_with_merge::<u64, Vec<String>>(
+        "words",
+        MergeOperator::append(),
+    );
+    words.insert(1, vec!["a".to_string()]);
+    words.rmw(1, vec!["b".to_string()]);
+    assert_eq!(words.get(&1), Some(Rc::new(vec!["a".to_string(), "b".to_string()])));
+
+    let mut products = handle.get_managed_map_with_codecs_and_merge::<u64, u64, U64, Raw>(
+        "products",
+        MergeOperator::new(|existing, modification| existing * modification),
+    );
+    products.rmw(1, 2);
+    products.rmw(1, 3);
+    products.rmw(1, 4);
+    assert_eq!(products.get(&1), Some(Rc::new(24)));
+    assert_eq!(products.remove(&1), Some(24));
+    assert_eq!(products.get(&1), None);
+
+    // Values that do not implement Rmw merge all the same
+    let mut ranges = handle.get_managed_map_with_merge::<u64, (u64, u64)>(
+        "ranges",
+        MergeOperator::new(|(low, high): (u64, u64), (other_low, other_high)| {
+            (cmp::min(low, other_low), cmp::max(high, other_high))
+        }),
+    );
+    ranges.merge(1, (3, 5));
+    ranges.merge(1, (1, 4));
+    ranges.merge(2, (7, 7));
+    assert_eq!(ranges.get(&1), Some(Rc::new((1, 5))));
+    assert_eq!(ranges.get(&2), Some(Rc::new((7, 7))));
+}
+
+/// Batches behave as the same operations applied one at a time, in order.
+pub fn map_batches<S: StateBackend>(handle: &StateHandle<S>) {
+    let mut map = handle.get_managed_map::<u64, u64>("map");
+    assert_eq!(map.multi_get(&[]), vec![]);
+    map.insert_batch(vec![(1, 1), (2, 2), (1, 3)]);
+    assert_eq!(map.multi_get(&[1, 4, 2]), vec![Some(Rc::new(3)), None, Some(Rc::new(2))]);
+
+    map.rmw_batch(vec![(1, 1), (4, 4), (1, 10), (2, 0)]);
+    assert_eq!(
+        map.multi_get(&[1, 2, 4]),
+        vec![Some(Rc::new(14)), Some(Rc::new(2)), Some(Rc::new(4))]
+    );
+
+    let mut max = handle.get_managed_map_with_merge::<u64, u64>("max", MergeOperator::max());
+    max.insert_batch(vec![(1, 5)]);
+    max.rmw_batch(vec![(1, 3), (2, 2), (1, 7), (2, 1)]);
+    assert_eq!(max.multi_get(&[1, 2]), vec![Some(Rc::new(7)), Some(Rc::new(2))]);
+}
+
+/// Every lookup submitted is handed back once with its context, however the backend completes it.
+pub fn async_reads<S: StateBackend>(handle: &StateHandle<S>) {
+    let mut map = handle.get_managed_map::<u64, u64>("map");
+    map.insert_batch((0..100).map(|key| (key, key * 2)).collect());
+
+    let wakes = Rc::new(Cell::new(0));
+    let mut reads = PendingReads::new();
+    let woken = Rc::clone(&wakes);
+    reads.set_waker(move || woken.set(woken.get() + 1));
+    for key in 90..110 {
+        reads.submit(&*map, &key, key);
+    }
+
+    let mut completed = reads.drain(&*map);
+    if reads.pending() == 0 {
+        assert_eq!(wakes.get(), 0);
+    } else {
+        assert_eq!(wakes.get(), 1);
+    }
+    completed.extend(reads.wait(&*map));
+    assert!(reads.is_empty());
+    completed.sort_by_key(|(key, _)| *key);
+    let expected: Vec<(u64, Option<Rc<u64>>)> = (90..110)
+        .map(|key| (key, if key < 100 { Some(Rc::new(key * 2)) } else { None }))
+        .collect();
+    assert_eq!(completed, expected);
+    assert!(reads.drain(&*map).is_empty());
+}
+
+/// Backends without ordered keys may refuse to iterate, but must not return wrong entries.
+pub fn map_iteration<S: StateBackend>(handle: &StateHandle<S>) {
+    let mut map = handle.get_managed_map::<u64, u64>("map");
+    let mut neighbour = handle.get_managed_map::<u64, u64>("map_neighbour");
+    for key in 1..=5u64 {
+        map.insert(key.to_be(), key * 10);
+        neighbour.insert(key.to_be(), 0);
+    }
+
+    let collect = |range: KeyRange<u64>, direction: IterDirection| {
+        map.iter(range, direction).map(|iter| {
+            iter.map(|(key, value)| (u64::from_be(*key), *value))
+                .collect::<Vec<_>>()
+        })
+    };
+
+    match collect(KeyRange::All, IterDirection::Forward) {
+        Err(IterationError::Unsupported(_)) => return,
+        Ok(entries) => assert_eq!(entries, vec![(1, 10), (2, 20), (3, 30), (4, 40), (5, 50)]),
+    }
+    assert_eq!(
+        collect(KeyRange::All, IterDirection::Reverse).unwrap(),
+        vec![(5, 50), (4, 40), (3, 30), (2, 20), (1, 10)]
+    );
+    let bounded = || KeyRange::Bounded(Bound::Excluded(1u64.to_be()), Bound::Included(4u64.to_be()));
+    assert_eq!(
+        collect(bounded(), IterDirection::Forward).unwrap(),
+        vec![(2, 20), (3, 30), (4, 40)]
+    );
+    assert_eq!(
+        collect(bounded(), IterDirection::Reverse).unwrap(),
+        vec![(4, 40), (3, 30), (2, 20)]
+    );
+}
+
+/// Tagged entries disappear once the frontier passes their expiry, and untagged ones never do.
+pub fn map_expiry<S: StateBackend>(handle: &StateHandle<S>) {
+    let mut map = handle.get_expiring_map::<u64, u64>("map");
+    check_managed_map(&mut map);
+    map.insert_until(1, 1, 10);
+    map.insert_until(2, 2, 20);
+    map.insert_until(3, 3, 10);
+    map.insert(4, 4);
+    map.insert_until(3, 3, 30);
+    assert_eq!(map.expiry(&1), Some(10));
+
+    map.advance(&[10u64]);
+    assert_eq!(map.get(&1), Some(Rc::new(1)));
+    map.advance(&[11u64]);
+    assert_eq!(map.get(&1), None);
+    assert!(!map.contains(&1));
+    assert_eq!(map.get(&3), Some(Rc::new(3)));
+    assert_eq!(map.expiry(&3), Some(30));
+    map.rmw(2, 5);
+    assert_eq!(map.expiry(&2), Some(20));
+
+    // Removed entries no longer carry their expiry
+    map.insert_until(5, 5, 20);
+    assert_eq!(map.remove(&5), Some(5));
+    map.rmw(5, 5);
+    assert_eq!(map.expiry(&5), None);
+
+    map.advance(&[25u64]);
+    assert_eq!(map.get(&2), None);
+    assert_eq!(map.remove(&2), None);
+    assert_eq!(map.get(&5), Some(Rc::new(5)));
+    assert_eq!(map.get(&3), Some(Rc::new(3)));
+    map.advance::<u64>(&[]);
+    assert_eq!(map.sweep(SWEEP_BATCH), 0);
+    // Backends without iteration still expire entries
+    if let Ok(entries) = map.iter(KeyRange::All, IterDirection::Forward) {
+        assert_eq!(entries.map(|(key, _)| *key).collect::<Vec<_>>(), vec![4, 5]);
+    }
+    assert_eq!(map.get(&4), Some(Rc::new(4)));
+}
+
+/// Maps with non-default codecs behave identically, and `U64` keys iterate in numeric order.
+pub fn map_codecs<S: StateBackend>(handle: &StateHandle<S>) {
+    let mut map = handle.get_managed_map_with_codecs::<u64, u64, U64, Raw>("map");
+    check_managed_map(&mut *map);
+
+    for key in &[256u64, 1, 255] {
+        map.insert(*key, key + 1);
+    }
+    match map.iter(KeyRange::from(2), IterDirection::Forward) {
+        Err(IterationError::Unsupported(_)) => {}
+        Ok(iter) => assert_eq!(
+            iter.map(|(key, value)| (*key, *value)).collect::<Vec<_>>(),
+            vec![(255, 256), (256, 257)]
+        ),
+    };
+}
+
+thread_local! {
+    static ENCODED: Cell<usize> = Cell::new(0);
+}
+
+// Encodes like `Bincode`, counting the values it encodes on this thread
+struct Counting;
+
+impl Codec<u64> for Counting {
+    fn encode(value: &u64, bytes: &mut Vec<u8>) {
+        ENCODED.with(|encoded| encoded.set(encoded.get() + 1));
+        Bincode::encode(value, bytes)
+    }
+
+    fn decode(bytes: &[u8]) -> u64 {
+        Bincode::decode(bytes)
+    }
+}
+
+/// Backends that store values as bytes encode them with the map's value codec.
+pub fn value_codecs<S: StateBackend>(handle: &StateHandle<S>) {
+    let mut map = handle.get_managed_map_with_codecs::<u64, u64, Bincode, Counting>("encoded");
+    let encoded = ENCODED.with(Cell::get);
+    map.insert(1, 2);
+    map.rmw(1, 3);
+    assert!(ENCODED.with(Cell::get) > encoded, "the map ignored its value codec");
+    assert_eq!(map.get(&1), Some(Rc::new(5)));
+}
+
+/// Bags keep each key's items in the order they were appended, across chunks, until drained.
+pub fn bags<S: StateBackend>(handle: &StateHandle<S>) {
+    let mut bag = handle.get_managed_bag::<u64, u64>("bag");
+    assert_eq!(bag.len(&1), 0);
+    assert_eq!(bag.drain(&1).count(), 0);
+
+    let items = BAG_CHUNK_LENGTH as u64 * 2 + 1;
+    for item in 0..items {
+        bag.append(1, item);
+    }
+    bag.append(2, 42);
+    assert_eq!(bag.len(&1), items as usize);
+    assert_eq!(bag.iter(&1).collect::<Vec<_>>(), (0..items).collect::<Vec<_>>());
+
+    // Items the drain does not visit are removed all the same
+    assert_eq!(bag.drain(&1).take(3).collect::<Vec<_>>(), vec![0, 1, 2]);
+    assert_eq!(bag.len(&1), 0);
+    assert_eq!(bag.iter(&1).count(), 0);
+    bag.append(1, 7);
+    assert_eq!(bag.iter(&1).collect::<Vec<_>>(), vec![7]);
+    assert_eq!(bag.drain(&2).collect::<Vec<_>>(), vec![42]);
+
+    let mut list = handle.get_managed_list::<String>("list");
+    assert!(list.is_empty());
+    list.push("a".to_owned());
+    list.push("b".to_owned());
+    assert_eq!(list.len(), 2);
+    assert_eq!(list.drain().collect::<Vec<_>>(), vec!["a".to_owned(), "b".to_owned()]);
+    assert!(list.is_empty());
+}
+
+/// Sorted maps hand out entries in the order of their keys, not of their encodings.
+pub fn sorted_maps<S: StateBackend>(handle: &StateHandle<S>) {
+    let mut map = handle.get_managed_sorted_map::<(i64, u64), String>("sorted");
+    assert_eq!(map.first(), None);
+    assert_eq!(map.pop_first(), None);
+    for key in &[(3, 1), (-2, 9), (3, 0), (256, 0), (-300, 5)] {
+        map.insert(*key, format!("{:?}", key));
+    }
+    assert_eq!(map.first().map(|(key, _)| *key), Some((-300, 5)));
+    assert_eq!(map.last().map(|(key, _)| *key), Some((256, 0)));
+    let range: Vec<_> = map
+        .range(Bound::Excluded((-300, 5)), Bound::Included((3, 1)))
+        .map(|(key, _)| *key)
+        .collect();
+    assert_eq!(range, vec![(-2, 9), (3, 0), (3, 1)]);
+    assert_eq!(map.pop_first(), Some(((-300, 5), "(-300, 5)".to_owned())));
+    assert_eq!(map.get(&(-300, 5)), None);
+    let split: Vec<_> = map.split_off(&(3, 1)).into_iter().map(|(key, _)| key).collect();
+    assert_eq!(split, vec![(3, 1), (256, 0)]);
+    assert_eq!(map.last().map(|(key, _)| *key), Some((3, 0)));
+    assert_eq!(map.remove(&(3, 0)), Some("(3, 0)".to_owned()));
+    assert_eq!(map.first().map(|(key, _)| *key), Some((-2, 9)));
+
+    // Opening a sorted map by the same name gives the same entries, and values
+    // that are still held are removed intact
+    let mut reopened = handle.get_managed_sorted_map::<(i64, u64), String>("sorted");
+    assert_eq!(reopened.first().map(|(key, _)| *key), Some((-2, 9)));
+    let held = map.get(&(-2, 9));
+    assert_eq!(reopened.remove(&(-2, 9)), Some("(-2, 9)".to_owned()));
+    assert_eq!(held, Some(Rc::new("(-2, 9)".to_owned())));
+    assert_eq!(map.first(), None);
+}
+
+pub fn priority_queues<S: StateBackend>(handle: &StateHandle<S>) {
+    let mut queue = handle.get_managed_priority_queue::<u64, char>("queue");
+    assert_eq!(queue.pop(), None);
+    queue.push(20, 'c');
+    queue.push(10, 'a');
+    queue.push(20, 'd');
+    queue.push(10, 'b');
+    queue.push(256, 'e');
+    assert_eq!(queue.peek(), Some((10, Rc::new('a'))));
+    assert_eq!(queue.pop_until(&20), vec![(10, 'a'), (10, 'b')]);
+    assert_eq!(queue.pop(), Some((20, 'c')));
+    assert_eq!(queue.pop_until(&256), vec![(20, 'd')]);
+    assert_eq!(queue.pop(), Some((256, 'e')));
+    assert_eq!(queue.peek(), None);
+}
+
+/// State kept in its own column family behaves like any other state.
+pub fn column_families<S: StateBackend>(handle: &StateHandle<S>) {
+    let options = ColumnFamilyOptions {
+        block_size: Some(16 * 1024),
+        bloom_filter_bits: Some(10),
+        compaction_style: Some(CompactionStyle::Universal),
+        ..ColumnFamilyOptions::default()
+    };
+    handle.create_column_family("isolated", &options);
+    handle.create_column_family("isolated", &options);
+
+    let mut map = handle.get_managed_map::<u64, u64>("isolated");
+    let mut shared = handle.get_managed_map::<u64, u64>("shared");
+    check_managed_map(&mut *map);
+    map.insert(1u64.to_be(), 1);
+    map.rmw(1u64.to_be(), 2);
+    map.insert(2u64.to_be(), 5);
+    assert_eq!(map.get(&1u64.to_be()), Some(Rc::new(3)));
+    assert!(!shared.contains(&1u64.to_be()));
+    shared.insert(1u64.to_be(), 0);
+    if let Ok(iter) = map.iter(KeyRange::All, IterDirection::Forward) {
+        let entries: Vec<_> = iter.map(|(key, value)| (u64::from_be(*key), *value)).collect();
+        assert_eq!(entries, vec![(1, 3), (2, 5)]);
+    }
+
+    handle.create_column_family("isolated_count", &ColumnFamilyOptions::default());
+    let mut count = handle.get_managed_count("isolated_count");
+    count.increase(5);
+    count.decrease(2);
+    assert_eq!(count.get(), 3);
+
+    handle.drop_column_family("isolated");
+    handle.drop_column_family("isolated");
+    assert_eq!(shared.get(&1u64.to_be()), Some(Rc::new(0)));
+}
+
+/// State created through sibling handles must never alias, even when the
+/// concatenation of handle and state names is the same.
+pub fn namespaces<S: StateBackend>(handle: &StateHandle<S>) {
+    let (left, right) = (handle.create_sub_handle("a"), handle.create_sub_handle("ab"));
+
+    let mut left_map = left.get_managed_map::<u64, u64>("bm");
+    let mut right_map = right.get_managed_map::<u64, u64>("m");
+    left_map.insert(1, 1);
+    assert!(!right_map.contains(&1));
+    right_map.insert(1, 2);
+    assert_eq!(left_map.get(&1), Some(Rc::new(1)));
+    assert_eq!(right_map.get(&1), Some(Rc::new(2)));
+
+    let mut left_value = left.get_managed_value::<u64>("value");
+    let mut right_value = right.get_managed_value::<u64>("value");
+    left_value.set(1);
+    assert_eq!(right_value.get(), None);
+    right_value.set(2);
+    assert_eq!(left_value.get(), Some(Rc::new(1)));
+
+    let mut left_count = left.get_managed_count("count");
+    let mut right_count = right.get_managed_count("count");
+    left_count.increase(1);
+    assert_eq!(right_count.get(), 0);
+    right_count.increase(2);
+    assert_eq!(left_count.get(), 1);
+}
+
+/// Backends either checkpoint into the worker's manifest or say they cannot.
+pub fn checkpoints<S: StateBackend>(handle: &StateHandle<S>) {
+    let mut map = handle.get_managed_map::<u64, u64>("map");
+    let mut value = handle.get_managed_value::<String>("value");
+    let mut count = handle.get_managed_count("count");
+    map.insert(1, 1);
+    value.set("value".to_owned());
+    count.increase(2);
+
+    let directory = TempDir::new().unwrap();
+    let checkpoints = WorkerCheckpoints::new(directory.path(), 0);
+    match checkpoints.checkpoint(5, handle) {
+        Ok(snapshot) => {
+            assert!(snapshot.path.exists());
+            let manifest = checkpoints.manifest(5).unwrap().unwrap();
+            assert_eq!(manifest.states.get(handle.name()), Some(&snapshot));
+        }
+        Err(CheckpointError::Unsupported) => assert_eq!(checkpoints.manifest(5).unwrap(), None),
+        Err(error) => panic!("{}", error),
+    }
+
+    map.insert(2, 2);
+    assert_eq!(map.get(&1), Some(Rc::new(1)));
+    assert_eq!(value.get(), Some(Rc::new("value".to_owned())));
+    assert_eq!(count.get(), 2);
+}
+
+/// A restored snapshot holds the state as it was checkpointed, apart from the original.
+pub fn restores<S: StateBackend>(handle: &StateHandle<S>) {
+    let mut map = handle.get_managed_map::<u64, u64>("map");
+    let mut value = handle.get_managed_value::<String>("value");
+    let mut count = handle.get_managed_count("count");
+    let mut sorted = handle.get_managed_sorted_map::<u64, u64>("sorted");
+    map.insert(1, 1);
+    map.rmw(2, 2);
+    value.set("value".to_owned());
+    count.increase(2);
+    sorted.insert(7, 7);
+
+    let directory = TempDir::new().unwrap();
+    let snapshot = match WorkerCheckpoints::new(directory.path(), 0).checkpoint(5, handle) {
+        Ok(snapshot) => snapshot,
+        Err(CheckpointError::Unsupported) => return,
+        Err(error) => panic!("{}", error),
+    };
+    map.insert(3, 3);
+    count.increase(1);
+    sorted.insert(1, 1);
+
+    let config = BackendConfig {
+        directory: directory.path().to_owned(),
+        ..BackendConfig::default()
+    };
+    let restored = StateHandle::new(Rc::new(S::restore(&config, &snapshot).unwrap()), handle.name());
+    let mut map = restored.get_managed_map::<u64, u64>("map");
+    assert_eq!(map.get(&1), Some(Rc::new(1)));
+    assert_eq!(map.get(&2), Some(Rc::new(2)));
+    assert_eq!(map.get(&3), None);
+    map.rmw(2, 2);
+    assert_eq!(map.get(&2), Some(Rc::new(4)));
+    assert_eq!(restored.get_managed_value::<String>("value").get(), Some(Rc::new("value".to_owned())));
+    assert_eq!(restored.get_managed_count("count").get(), 2);
+    let sorted = restored.get_managed_sorted_map::<u64, u64>("sorted");
+    assert_eq!(sorted.first(), Some((Rc::new(7), Rc::new(7))));
+    assert_eq!(sorted.last(), Some((Rc::new(7), Rc::new(7))));
+
+    // Writes to the restored state leave the snapshot as it was
+    let again = StateHandle::new(Rc::new(S::restore(&config, &snapshot).unwrap()), handle.name());
+    assert_eq!(again.get_managed_map::<u64, u64>("map").get(&2), Some(Rc::new(2)));
+}
+
+pub fn large_values<S: StateBackend>(handle: &StateHandle<S>) {
+    let large: Vec<u8> = (0..1 << 20).map(|i| i as u8).collect();
+
+    let mut value = handle.get_managed_value::<Vec<u8>>("value");
+    value.set(large.clone());
+    assert_eq!(value.get(), Some(Rc::new(large.clone())));
+
+    let mut map = handle.get_managed_map::<u64, Vec<u8>>("map");
+    map.insert(1, large.clone());
+    map.insert(2, vec![]);
+    assert_eq!(map.remove(&1), Some(large));
+    assert_eq!(map.get(&2), Some(Rc::new(vec![])));
+}
+
+pub fn many_keys<S: StateBackend>(handle: &StateHandle<S>) {
+    let keys = 10_000u64;
+    let mut map = handle.get_managed_map::<u64, u64>("map");
+    for key in 0..keys {
+        map.insert(key, key + 1);
+    }
+    for key in 0..keys {
+        assert_eq!(map.get(&key), Some(Rc::new(key + 1)));
+    }
+    for key in (0..keys).step_by(2) {
+        assert_eq!(map.remove(&key), Some(key + 1));
+    }
+    for key in 0..keys {
+        assert_eq!(map.contains(&key), key % 2 == 1);
+    }
+}
+
+/// Runs every conformance check against a fresh backend built by `$backend`.
+///
+/// Checks can be listed explicitly to leave out behaviour a backend is known not to share.
+macro_rules! backend_conformance {
+    ($module:ident, $backend:expr) => {
+        backend_conformance!($module, $backend, [
+            count, value, value_rmw_sum, value_rmw_append, map, map_rmw_sum, map_rmw_append,
+            map_merge_operators, map_batches, async_reads, map_iteration, map_expiry, map_codecs, bags, sorted_maps, priority_queues, column_families,
+            namespaces,
+            checkpoints, restores, large_values, many_keys
+        ]);
+    };
+    ($module:ident, $backend:expr, [$($check:ident),* $(,)*]) => {
+        mod $module {
+            use super::*;
+            $(
+                #[test]
+                fn $check() {
+                    let handle = StateHandle::new(Rc::new($backend), "conformance");
+                    super::$check(&handle);
+                }
+            )*
+        }
+    };
+}
+
+// In-memory backends hold values as they are, so only these encode them
+#[cfg(feature = "faster")]
+backend_conformance!(
+    faster_value_codecs,
+    FASTERBackend::new_from_existing(&Arc::new(FasterKv::default())),
+    [value_codecs]
+);
+#[cfg(feature = "faster")]
+backend_conformance!(
+    faster_in_memory_value_codecs,
+    FASTERInMemoryBackend::new_from_existing(&Arc::new(FasterKv::default())),
+    [value_codecs]
+);
+#[cfg(feature = "faster")]
+backend_conformance!(
+    faster_node_value_codecs,
+    FASTERNodeBackend::new_from_existing(
+        &Arc::new(FasterKv::default()),
+        &Arc::new(TempDir::new().unwrap())
+    ),
+    [value_codecs]
+);
+#[cfg(feature = "rocksdb")]
+backend_conformance!(
+    rocksdb_value_codecs,
+    RocksDBBackend::new(&BackendConfig {
+        directory: std::env::temp_dir(),
+        ..BackendConfig::default()
+    }),
+    [value_codecs]
+);
+#[cfg(feature = "rocksdb")]
+backend_conformance!(
+    rocksdbmerge_value_codecs,
+    RocksDBMergeBackend::new(&BackendConfig {
+        directory: std::env::temp_dir(),
+        ..BackendConfig::default()
+    }),
+    [value_codecs]
+);
+#[cfg(feature = "sled")]
+backend_conformance!(
+    sled_value_codecs,
+    SledBackend::new(&BackendConfig {
+        directory: std::env::temp_dir(),
+        ..BackendConfig::default()
+    }),
+    [value_codecs]
+);
+
+#[cfg(feature = "faster")]
+backend_conformance!(faster, FASTERBackend::new_from_existing(&Arc::new(FasterKv::default())));
+// Opened on a directory of its own, so that it can checkpoint
+#[cfg(feature = "faster")]
+backend_conformance!(
+    faster_on_disk,
+    FASTERBackend::new(&BackendConfig {
+        directory: std::env::temp_dir(),
+        faster: FasterConfig {
+            table_size: 1 << 16,
+            log_size: 1 << 26,
+            pre_allocate_log: false,
+            ..FasterConfig::default()
+        },
+        ..BackendConfig::default()
+    })
+);
+#[cfg(feature = "faster")]
+backend_conformance!(
+    faster_in_memory,
+    FASTERInMemoryBackend::new_from_existing(&Arc::new(FasterKv::default()))
+);
+#[cfg(feature = "faster")]
+backend_conformance!(
+    faster_node,
+    FASTERNodeBackend::new_from_existing(
+        &Arc::new(FasterKv::default()),
+        &Arc::new(TempDir::new().unwrap())
+    )
+);
+#[cfg(feature = "in-memory")]
+backend_conformance!(in_memory, InMemoryBackend::new(&BackendConfig::default()));
+#[cfg(feature = "in-memory")]
+backend_conformance!(in_memory_native, InMemoryNativeBackend::new(&BackendConfig::default()));
+#[cfg(feature = "in-memory")]
+backend_conformance!(in_memory_ordered, InMemoryOrderedBackend::new(&BackendConfig::default()));
+#[cfg(feature = "in-memory")]
+backend_conformance!(
+    cached_in_memory,
+    CachedBackend::<InMemoryBackend>::new(&BackendConfig::default())
+);
+// A budget of a few entries evicts on almost every write
+#[cfg(feature = "in-memory")]
+backend_conformance!(
+    cached_in_memory_evicting,
+    CachedBackend::wrap(
+        InMemoryBackend::new(&BackendConfig::default()),
+        &CacheConfig {
+            budget: 64,
+            policy: CachePolicy::Epoch,
+        }
+    )
+);
+#[cfg(feature = "rocksdb")]
+backend_conformance!(
+    rocksdb,
+    RocksDBBackend::new(&BackendConfig {
+        directory: std::env::temp_dir(),
+        ..BackendConfig::default()
+    })
+);
+#[cfg(feature = "rocksdb")]
+backend_conformance!(
+    rocksdbmerge,
+    RocksDBMergeBackend::new(&BackendConfig {
+        directory: std::env::temp_dir(),
+        ..BackendConfig::default()
+    })
+);
+#[cfg(feature = "sled")]
+backend_conformance!(
+    sled,
+    SledBackend::new(&BackendConfig {
+        directory: std::env::temp_dir(),
+        ..BackendConfig::default()
+    })
+);
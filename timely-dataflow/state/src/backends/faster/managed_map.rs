@@ -1,58 +1,83 @@
-use crate::backends::faster::{faster_read, faster_rmw, faster_upsert};
-use crate::primitives::ManagedMap;
+use crate::backends::faster::{
+    faster_delete, faster_read, faster_read_wait, faster_rmw, faster_upsert,
+};
+use crate::backends::faster_cell::FasterCell;
+use crate::codec::{Bincode, Codec};
+use crate::primitives::{
+    IterDirection, IterationError, KeyRange, ManagedMap, ManagedMapIter, ReadStatus,
+};
+use crate::session::Session;
+use crate::{Rmw, StateKey, StateValue};
 use bincode::serialize;
-use faster_rs::{status, FasterKey, FasterKv, FasterRmw, FasterValue};
+use faster_rs::{status, FasterKv};
 use std::cell::RefCell;
-use std::hash::Hash;
 use std::marker::PhantomData;
 use std::rc::Rc;
-use std::sync::mpsc::Receiver;
+use std::sync::mpsc::{Receiver, TryRecvError};
 use std::sync::Arc;
-use rocksdb::DBIterator;
 
-pub struct FASTERManagedMap<K, V>
+type PendingRead<V, VC> = (u64, Receiver<FasterCell<V, VC>>);
+
+pub struct FASTERManagedMap<K, V, KC = Bincode, VC = Bincode>
 where
-    K: 'static + FasterKey + Hash + Eq + std::fmt::Debug,
-    V: 'static + FasterValue + FasterRmw,
+    K: StateKey,
+    V: StateValue,
 {
     faster: Arc<FasterKv>,
-    monotonic_serial_number: Rc<RefCell<u64>>,
+    session: Rc<RefCell<Session>>,
     serialised_name: Vec<u8>,
+    // Reads started by get_async that FASTER has yet to complete, by ticket
+    pending: RefCell<Vec<PendingRead<V, VC>>>,
     key: PhantomData<K>,
     value: PhantomData<V>,
+    key_codec: PhantomData<KC>,
+    value_codec: PhantomData<VC>,
 }
 
+#[cfg(test)]
 impl<K, V> FASTERManagedMap<K, V>
 where
-    K: 'static + FasterKey + Hash + Eq + std::fmt::Debug,
-    V: 'static + FasterValue + FasterRmw,
+    K: StateKey,
+    V: StateValue,
 {
-    pub fn new(
-        faster: Arc<FasterKv>,
-        monotonic_serial_number: Rc<RefCell<u64>>,
-        name: &str,
-    ) -> Self {
+    pub fn new(faster: Arc<FasterKv>, session: Rc<RefCell<Session>>, name: &str) -> Self {
+        FASTERManagedMap::with_codecs(faster, session, name)
+    }
+}
+
+impl<K, V, KC, VC> FASTERManagedMap<K, V, KC, VC>
+where
+    K: StateKey,
+    V: StateValue,
+    KC: Codec<K>,
+    VC: Codec<V>,
+{
+    pub fn with_codecs(faster: Arc<FasterKv>, session: Rc<RefCell<Session>>, name: &str) -> Self {
         FASTERManagedMap {
             faster,
-            monotonic_serial_number,
+            session,
             serialised_name: serialize(name).unwrap(),
+            pending: RefCell::new(Vec::new()),
             key: PhantomData,
             value: PhantomData,
+            key_codec: PhantomData,
+            value_codec: PhantomData,
         }
     }
 
     fn prefix_key(&self, key: &K) -> Vec<u8> {
-        let mut serialised_key = serialize(key).unwrap();
         let mut prefixed_key = self.serialised_name.clone();
-        prefixed_key.append(&mut serialised_key);
+        KC::encode(key, &mut prefixed_key);
         prefixed_key
     }
 }
 
-impl<K, V> ManagedMap<K, V> for FASTERManagedMap<K, V>
+impl<K, V, KC, VC> ManagedMap<K, V> for FASTERManagedMap<K, V, KC, VC>
 where
-    K: 'static + FasterKey + Hash + Eq + std::fmt::Debug,
-    V: 'static + FasterValue + FasterRmw,
+    K: StateKey,
+    V: StateValue,
+    KC: Codec<K>,
+    VC: Codec<V>,
 {
     fn get_key_prefix_length(&self) -> usize {
         self.serialised_name.len()
@@ -63,60 +88,108 @@ where
         faster_upsert(
             &self.faster,
             &prefixed_key,
-            &value,
-            &self.monotonic_serial_number,
+            &FasterCell::<V, VC>::new(value),
+            &self.session,
         );
     }
 
     fn get(&self, key: &K) -> Option<Rc<V>> {
         let prefixed_key = self.prefix_key(key);
-        let (status, recv) =
-            faster_read(&self.faster, &prefixed_key, &self.monotonic_serial_number);
-        if status != status::OK {
-            return None;
-        }
-        return match recv.recv() {
-            Ok(val) => Some(Rc::new(val)),
-            Err(_) => None,
-        };
+        faster_read_wait(&self.faster, &prefixed_key, &self.session)
+            .map(|FasterCell::<V, VC>(val, _)| Rc::new(val))
     }
 
+    // The delete is issued whatever the read finds, so that no record of the key
+    // outlives the removal
     fn remove(&mut self, key: &K) -> Option<V> {
         let prefixed_key = self.prefix_key(key);
-        let (status, recv) =
-            faster_read(&self.faster, &prefixed_key, &self.monotonic_serial_number);
-        if status != status::OK {
-            return None;
-        }
-        return match recv.recv() {
-            Ok(val) => Some(val),
-            Err(_) => None,
-        };
+        let result = faster_read_wait(&self.faster, &prefixed_key, &self.session)
+            .map(|FasterCell::<V, VC>(val, _)| val);
+        faster_delete(&self.faster, &prefixed_key, &self.session);
+        result
     }
 
-    fn rmw(&mut self, key: K, modification: V) {
+    fn rmw(&mut self, key: K, modification: V)
+    where
+        V: Rmw,
+    {
         let prefixed_key = self.prefix_key(&key);
         faster_rmw(
             &self.faster,
             &prefixed_key,
-            &modification,
-            &self.monotonic_serial_number,
+            &FasterCell::<V, VC>::new(modification),
+            &self.session,
         );
     }
 
     fn contains(&self, key: &K) -> bool {
         let prefixed_key = self.prefix_key(key);
-        let (status, _): (u8, Receiver<V>) =
-            faster_read(&self.faster, &prefixed_key, &self.monotonic_serial_number);
-        return status == status::OK;
+        faster_read_wait::<_, FasterCell<V, VC>>(&self.faster, &prefixed_key, &self.session).is_some()
     }
 
-    fn iter(&mut self, key: K) -> DBIterator {
-        panic!("FASTER's managed map does not support iteration.");
+    // Issues every read before waiting on any, so that reads which go to disk
+    // complete together instead of one after the other
+    fn multi_get(&self, keys: &[K]) -> Vec<Option<Rc<V>>> {
+        let reads: Vec<(u8, Receiver<FasterCell<V, VC>>)> = keys
+            .iter()
+            .map(|key| faster_read(&self.faster, &self.prefix_key(key), &self.session))
+            .collect();
+        if reads.iter().any(|(status, _)| *status == status::PENDING) {
+            self.faster.complete_pending(true);
+        }
+        reads
+            .into_iter()
+            .map(|(status, recv)| {
+                if status != status::OK && status != status::PENDING {
+                    return None;
+                }
+                recv.recv().ok().map(|FasterCell::<V, VC>(val, _)| Rc::new(val))
+            })
+            .collect()
     }
 
-    fn next(&mut self, iter: DBIterator) -> Option<(Rc<K>,Rc<V>)> {
-        panic!("FASTER's managed map does not support iteration.");
+    fn get_async(&self, key: &K, ticket: u64) -> ReadStatus<V> {
+        let prefixed_key = self.prefix_key(key);
+        let (status, recv) = faster_read(&self.faster, &prefixed_key, &self.session);
+        match status {
+            status::OK => ReadStatus::Ready(recv.recv().ok().map(|FasterCell::<V, VC>(val, _)| Rc::new(val))),
+            status::PENDING => {
+                self.pending.borrow_mut().push((ticket, recv));
+                ReadStatus::Pending
+            }
+            _ => ReadStatus::Ready(None),
+        }
+    }
+
+    // A read that completes without finding the key drops its sender
+    fn complete_reads(&self, wait: bool) -> Vec<(u64, Option<Rc<V>>)> {
+        let mut pending = self.pending.borrow_mut();
+        if pending.is_empty() {
+            return Vec::new();
+        }
+        self.faster.complete_pending(wait);
+        let mut completed = Vec::new();
+        pending.retain(|(ticket, recv)| match recv.try_recv() {
+            Ok(FasterCell::<V, VC>(val, _)) => {
+                completed.push((*ticket, Some(Rc::new(val))));
+                false
+            }
+            Err(TryRecvError::Disconnected) => {
+                completed.push((*ticket, None));
+                false
+            }
+            Err(TryRecvError::Empty) => true,
+        });
+        completed
+    }
+
+    // FASTER's hash index has no key order to iterate in
+    fn iter(
+        &self,
+        _range: KeyRange<K>,
+        _direction: IterDirection,
+    ) -> Result<ManagedMapIter<'_, K, V>, IterationError> {
+        Err(IterationError::Unsupported("FASTER's managed map"))
     }
 }
 
@@ -125,9 +198,12 @@ mod tests {
     extern crate faster_rs;
     extern crate tempfile;
 
+    use crate::backends::conformance;
     use crate::backends::faster::FASTERManagedMap;
-    use crate::primitives::ManagedMap;
-    use faster_rs::FasterKv;
+    use crate::config::SessionPolicy;
+    use crate::primitives::{IterDirection, IterationError, KeyRange, ManagedMap};
+    use crate::session::Session;
+    use faster_rs::{FasterKv, FasterKvBuilder};
     use std::cell::RefCell;
     use std::rc::Rc;
     use std::sync::Arc;
@@ -135,16 +211,18 @@ mod tests {
 
     const TABLE_SIZE: u64 = 1 << 14;
     const LOG_SIZE: u64 = 17179869184;
+    // Writing twice this much pushes the first writes out of memory and onto disk
+    const EVICTING_LOG_SIZE: u64 = 1 << 26;
 
     #[test]
     fn map_insert_get() {
         let store = Arc::new(FasterKv::default());
-        let monotonic_serial_number = Rc::new(RefCell::new(1));
+        let session = Rc::new(RefCell::new(Session::new(SessionPolicy::default())));
 
         let key: u64 = 1;
         let value: u64 = 1337;
 
-        let mut managed_map = FASTERManagedMap::new(store, monotonic_serial_number, "test");
+        let mut managed_map = FASTERManagedMap::new(store, session, "test");
         managed_map.insert(key, value);
         assert_eq!(managed_map.get(&key), Some(Rc::new(value)));
     }
@@ -152,42 +230,87 @@ mod tests {
     #[test]
     fn map_contains() {
         let store = Arc::new(FasterKv::default());
-        let monotonic_serial_number = Rc::new(RefCell::new(1));
+        let session = Rc::new(RefCell::new(Session::new(SessionPolicy::default())));
 
         let key: u64 = 1;
         let value: u64 = 1337;
 
-        let mut managed_map = FASTERManagedMap::new(store, monotonic_serial_number, "test");
+        let mut managed_map = FASTERManagedMap::new(store, session, "test");
         managed_map.insert(key, value);
         assert!(managed_map.contains(&key));
     }
 
+    #[test]
+    fn map_conforms() {
+        let store = Arc::new(FasterKv::default());
+        let session = Rc::new(RefCell::new(Session::new(SessionPolicy::default())));
+
+        let mut managed_map = FASTERManagedMap::new(store, session, "test");
+        conformance::check_managed_map(&mut managed_map);
+    }
+
     #[test]
     fn map_rmw() {
         let store = Arc::new(FasterKv::default());
-        let monotonic_serial_number = Rc::new(RefCell::new(1));
+        let session = Rc::new(RefCell::new(Session::new(SessionPolicy::default())));
 
         let key: u64 = 1;
         let value: u64 = 1337;
         let modification: u64 = 10;
 
-        let mut managed_map = FASTERManagedMap::new(store, monotonic_serial_number, "test");
+        let mut managed_map = FASTERManagedMap::new(store, session, "test");
         managed_map.insert(key, value);
         managed_map.rmw(key, modification);
         assert_eq!(managed_map.get(&key), Some(Rc::new(value + modification)));
     }
 
     #[test]
-    fn map_remove_does_not_remove() {
+    fn map_remove() {
         let store = Arc::new(FasterKv::default());
-        let monotonic_serial_number = Rc::new(RefCell::new(1));
+        let session = Rc::new(RefCell::new(Session::new(SessionPolicy::default())));
 
         let key: u64 = 1;
         let value: u64 = 1337;
 
-        let mut managed_map = FASTERManagedMap::new(store, monotonic_serial_number, "test");
+        let mut managed_map = FASTERManagedMap::new(store, session, "test");
         managed_map.insert(key, value);
         assert_eq!(managed_map.remove(&key), Some(value));
-        assert_eq!(managed_map.remove(&key), Some(value));
+        assert_eq!(managed_map.remove(&key), None);
+        assert!(!managed_map.contains(&key));
+    }
+
+    #[test]
+    fn map_remove_evicted() {
+        let directory = TempDir::new().unwrap();
+        let mut builder = FasterKvBuilder::new(TABLE_SIZE, EVICTING_LOG_SIZE);
+        builder.with_disk(directory.path().to_str().unwrap());
+        let store = Arc::new(builder.build().unwrap());
+        let session = Rc::new(RefCell::new(Session::new(SessionPolicy::default())));
+
+        let value = vec![7u8; 1 << 16];
+
+        let mut managed_map = FASTERManagedMap::new(Arc::clone(&store), session, "test");
+        for key in 0..((2 * EVICTING_LOG_SIZE) >> 16) {
+            managed_map.insert(key, value.clone());
+        }
+        assert!(store.head_address() > store.begin_address());
+        assert_eq!(managed_map.get(&0), Some(Rc::new(value.clone())));
+        assert_eq!(managed_map.remove(&0), Some(value));
+        assert_eq!(managed_map.remove(&0), None);
+        assert!(!managed_map.contains(&0));
+    }
+
+    #[test]
+    fn map_iter_is_unsupported() {
+        let store = Arc::new(FasterKv::default());
+        let session = Rc::new(RefCell::new(Session::new(SessionPolicy::default())));
+
+        let managed_map: FASTERManagedMap<u64, u64> = FASTERManagedMap::new(store, session, "test");
+        assert_eq!(
+            managed_map
+                .iter(KeyRange::All, IterDirection::Forward)
+                .err(),
+            Some(IterationError::Unsupported("FASTER's managed map"))
+        );
     }
 }
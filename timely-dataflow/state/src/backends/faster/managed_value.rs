@@ -1,70 +1,58 @@
-use crate::backends::faster::{faster_read, faster_rmw, faster_upsert};
+use crate::backends::faster::{faster_delete, faster_read_wait, faster_rmw, faster_upsert};
+use crate::backends::faster_cell::FasterCell;
+use crate::codec::Bincode;
 use crate::primitives::ManagedValue;
-use faster_rs::{status, FasterKv, FasterRmw, FasterValue};
+use crate::session::Session;
+use crate::{Rmw, StateValue};
+use faster_rs::FasterKv;
 use std::cell::RefCell;
 use std::marker::PhantomData;
 use std::rc::Rc;
 use std::sync::Arc;
 
-pub struct FASTERManagedValue<V: 'static + FasterValue + FasterRmw> {
+pub struct FASTERManagedValue<V: StateValue> {
     faster: Arc<FasterKv>,
-    monotonic_serial_number: Rc<RefCell<u64>>,
+    session: Rc<RefCell<Session>>,
     name: String,
     value: PhantomData<V>,
 }
 
-impl<V: 'static + FasterValue + FasterRmw> FASTERManagedValue<V> {
-    pub fn new(
-        faster: Arc<FasterKv>,
-        monotonic_serial_number: Rc<RefCell<u64>>,
-        name: &str,
-    ) -> Self {
+impl<V: StateValue> FASTERManagedValue<V> {
+    pub fn new(faster: Arc<FasterKv>, session: Rc<RefCell<Session>>, name: &str) -> Self {
         FASTERManagedValue {
             faster,
-            monotonic_serial_number,
+            session,
             name: name.to_owned(),
             value: PhantomData,
         }
     }
 }
 
-impl<V: 'static + FasterValue + FasterRmw> ManagedValue<V> for FASTERManagedValue<V> {
+impl<V: StateValue> ManagedValue<V> for FASTERManagedValue<V> {
     fn set(&mut self, value: V) {
-        faster_upsert(
-            &self.faster,
-            &self.name,
-            &value,
-            &self.monotonic_serial_number,
-        );
+        faster_upsert(&self.faster, &self.name, &FasterCell::<V, Bincode>::new(value), &self.session);
     }
     fn get(&self) -> Option<Rc<V>> {
-        let (status, recv) = faster_read(&self.faster, &self.name, &self.monotonic_serial_number);
-        if status != status::OK {
-            return None;
-        }
-        return match recv.recv() {
-            Ok(val) => Some(Rc::new(val)),
-            Err(_) => None,
-        };
+        faster_read_wait(&self.faster, &self.name, &self.session)
+            .map(|FasterCell::<V, Bincode>(val, _)| Rc::new(val))
     }
 
     fn take(&mut self) -> Option<V> {
-        let (status, recv) = faster_read(&self.faster, &self.name, &self.monotonic_serial_number);
-        if status != status::OK {
-            return None;
-        }
-        return match recv.recv() {
-            Ok(val) => Some(val),
-            Err(_) => None,
-        };
+        let result =
+            faster_read_wait(&self.faster, &self.name, &self.session).map(|FasterCell::<V, Bincode>(val, _)| val);
+        faster_delete(&self.faster, &self.name, &self.session);
+        result
     }
 
-    fn rmw(&mut self, modification: V) {
+    fn rmw(&mut self, modification: V)
+    where
+        V: Rmw,
+    {
         faster_rmw(
             &self.faster,
             &self.name,
-            &modification,
-            &self.monotonic_serial_number,
+            &FasterCell::<V, Bincode>::new(modification),
+            &self.session,
         );
     }
 }
@@ -75,8 +63,10 @@ mod tests {
     extern crate tempfile;
 
     use crate::backends::faster::FASTERManagedValue;
+    use crate::config::SessionPolicy;
     use crate::primitives::ManagedValue;
-    use faster_rs::FasterKv;
+    use crate::session::Session;
+    use faster_rs::{FasterKv, FasterKvBuilder};
     use std::cell::RefCell;
     use std::rc::Rc;
     use std::sync::Arc;
@@ -84,15 +74,17 @@ mod tests {
 
     const TABLE_SIZE: u64 = 1 << 14;
     const LOG_SIZE: u64 = 17179869184;
+    // Writing twice this much pushes the first writes out of memory and onto disk
+    const EVICTING_LOG_SIZE: u64 = 1 << 26;
 
     #[test]
     fn value_set_get() {
         let store = Arc::new(FasterKv::default());
-        let monotonic_serial_number = Rc::new(RefCell::new(1));
+        let session = Rc::new(RefCell::new(Session::new(SessionPolicy::default())));
 
         let value: u64 = 1337;
 
-        let mut managed_value = FASTERManagedValue::new(store, monotonic_serial_number, "test");
+        let mut managed_value = FASTERManagedValue::new(store, session, "test");
         managed_value.set(value);
         assert_eq!(managed_value.get(), Some(Rc::new(value)));
     }
@@ -100,14 +92,52 @@ mod tests {
     #[test]
     fn value_rmw() {
         let store = Arc::new(FasterKv::default());
-        let monotonic_serial_number = Rc::new(RefCell::new(1));
+        let session = Rc::new(RefCell::new(Session::new(SessionPolicy::default())));
 
         let value: u64 = 1337;
         let modification: u64 = 10;
 
-        let mut managed_value = FASTERManagedValue::new(store, monotonic_serial_number, "test");
+        let mut managed_value = FASTERManagedValue::new(store, session, "test");
         managed_value.set(value);
         managed_value.rmw(modification);
         assert_eq!(managed_value.get(), Some(Rc::new(value + modification)));
     }
+
+    #[test]
+    fn value_take_removes_value() {
+        let store = Arc::new(FasterKv::default());
+        let session = Rc::new(RefCell::new(Session::new(SessionPolicy::default())));
+
+        let value: u64 = 1337;
+
+        let mut managed_value = FASTERManagedValue::new(store, session, "test");
+        managed_value.set(value);
+        assert_eq!(managed_value.take(), Some(value));
+        assert_eq!(managed_value.take(), None);
+        assert_eq!(managed_value.get(), None);
+    }
+
+    #[test]
+    fn value_take_evicted() {
+        let directory = TempDir::new().unwrap();
+        let mut builder = FasterKvBuilder::new(TABLE_SIZE, EVICTING_LOG_SIZE);
+        builder.with_disk(directory.path().to_str().unwrap());
+        let store = Arc::new(builder.build().unwrap());
+        let session = Rc::new(RefCell::new(Session::new(SessionPolicy::default())));
+
+        let value = vec![7u8; 1 << 16];
+
+        let mut managed_value =
+            FASTERManagedValue::new(Arc::clone(&store), Rc::clone(&session), "test");
+        managed_value.set(value.clone());
+        for filler in 0..((2 * EVICTING_LOG_SIZE) >> 16) {
+            let name = format!("filler-{}", filler);
+            FASTERManagedValue::new(Arc::clone(&store), Rc::clone(&session), &name)
+                .set(value.clone());
+        }
+        assert!(store.head_address() > store.begin_address());
+        assert_eq!(managed_value.take(), Some(value));
+        assert_eq!(managed_value.take(), None);
+        assert_eq!(managed_value.get(), None);
+    }
 }
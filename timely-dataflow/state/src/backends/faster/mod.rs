@@ -9,158 +9,396 @@ mod managed_count;
 mod managed_map;
 mod managed_value;
 
-use crate::primitives::{ManagedCount, ManagedMap, ManagedValue};
-use crate::StateBackend;
-use faster_rs::{FasterKey, FasterKv, FasterKvBuilder, FasterRmw, FasterValue};
+use crate::checkpoint::{CheckpointError, Snapshot};
+use crate::codec::{Codec, SortKey};
+use crate::compaction::CompactionStats;
+use crate::config::{BackendConfig, FasterConfig};
+use crate::primitives::{
+    ChunkedBag, ManagedBag, ManagedCount, ManagedMap, ManagedSortedMap, ManagedValue, SortedMaps,
+};
+use crate::session::{Session, SessionStats};
+use crate::{StateBackend, StateKey, StateValue};
+use faster_rs::{status, FasterKey, FasterKv, FasterKvBuilder, FasterRmw, FasterValue};
 use std::cell::RefCell;
-use std::hash::Hash;
+use std::fs;
 use std::rc::Rc;
 use std::sync::mpsc::Receiver;
 use std::sync::Arc;
 use std::time::Duration;
 use tempfile::TempDir;
-use std::fs::File;
-use std::io::BufRead;
-use std::io::BufReader;
-use std::iter::FromIterator;
 use std::path::Path;
 
 #[allow(dead_code)]
 pub struct FASTERBackend {
     faster: Arc<FasterKv>,
-    monotonic_serial_number: Rc<RefCell<u64>>,
-}
-
-fn maybe_refresh_faster(faster: &Arc<FasterKv>, monotonic_serial_number: u64) {
-    if monotonic_serial_number % (1 << 4) == 0 {
-        faster.refresh();
-        if monotonic_serial_number % (1 << 10) == 0 {
-            faster.complete_pending(true);
-        }
-    }
-    if monotonic_serial_number % (1 << 20) == 0 {
-        println!("Size: {}", faster.size());
-    }
+    session: Rc<RefCell<Session>>,
+    // Where FASTER keeps its log and checkpoints, removed along with the backend.
+    // None for an existing instance, whose directory is unknown
+    directory: Option<TempDir>,
+    sorted_maps: SortedMaps,
 }
 
 fn faster_upsert<K: FasterKey, V: FasterValue>(
     faster: &Arc<FasterKv>,
     key: &K,
     value: &V,
-    monotonic_serial_number: &Rc<RefCell<u64>>,
+    session: &Rc<RefCell<Session>>,
 ) {
-    let old_monotonic_serial_number = *monotonic_serial_number.borrow();
-    *monotonic_serial_number.borrow_mut() = old_monotonic_serial_number + 1;
-    faster.upsert(key, value, old_monotonic_serial_number);
-    maybe_refresh_faster(faster, old_monotonic_serial_number);
+    let mut session = session.borrow_mut();
+    let status = faster.upsert(key, value, session.serial());
+    session.note_write(false);
+    session.maintain(faster, status);
 }
 
 fn faster_read<K: FasterKey, V: FasterValue>(
     faster: &Arc<FasterKv>,
     key: &K,
-    monotonic_serial_number: &Rc<RefCell<u64>>,
+    session: &Rc<RefCell<Session>>,
 ) -> (u8, Receiver<V>) {
-    let old_monotonic_serial_number = *monotonic_serial_number.borrow();
-    *monotonic_serial_number.borrow_mut() = old_monotonic_serial_number + 1;
-    let (status, recv) = faster.read(key, old_monotonic_serial_number);
-    maybe_refresh_faster(faster, old_monotonic_serial_number);
+    let mut session = session.borrow_mut();
+    let (status, recv) = faster.read(key, session.serial());
+    session.maintain(faster, status);
     (status, recv)
 }
 
-fn faster_rmw<K: FasterKey, V: FasterValue + FasterRmw>(
+// Reads 'key', waiting for FASTER to fetch it from disk if it has been evicted there
+fn faster_read_wait<K: FasterKey, V: FasterValue>(
+    faster: &Arc<FasterKv>,
+    key: &K,
+    session: &Rc<RefCell<Session>>,
+) -> Option<V> {
+    let (status, recv) = faster_read(faster, key, session);
+    match status {
+        status::OK => recv.recv().ok(),
+        status::PENDING => {
+            faster.complete_pending(true);
+            recv.recv().ok()
+        }
+        _ => None,
+    }
+}
+
+fn faster_delete<K: FasterKey>(
+    faster: &Arc<FasterKv>,
+    key: &K,
+    session: &Rc<RefCell<Session>>,
+) -> u8 {
+    let mut session = session.borrow_mut();
+    let status = faster.delete(key, session.serial());
+    session.note_write(true);
+    session.maintain(faster, status);
+    status
+}
+
+fn faster_rmw<K: FasterKey, V: FasterRmw>(
     faster: &Arc<FasterKv>,
     key: &K,
     modification: &V,
-    monotonic_serial_number: &Rc<RefCell<u64>>,
+    session: &Rc<RefCell<Session>>,
 ) {
-    let old_monotonic_serial_number = *monotonic_serial_number.borrow();
-    *monotonic_serial_number.borrow_mut() = old_monotonic_serial_number + 1;
-    faster.rmw(key, modification, old_monotonic_serial_number);
-    maybe_refresh_faster(faster, old_monotonic_serial_number);
+    let mut session = session.borrow_mut();
+    let status = faster.rmw(key, modification, session.serial());
+    session.note_write(false);
+    session.maintain(faster, status);
 }
 
-// read faster configuration from a file
-fn read_faster_config() -> (u64, u64) {
-    let config_path = String::from("faster.config");
-    let file = File::open(config_path).expect("Config file not found or cannot be opened");
-    let content = BufReader::new(&file);
-    let mut tablesize = 0;
-    let mut logsize = 0;
-    for line in content.lines() {
-        let line = line.expect("Could not read the line");
-        let line = line.trim();
-        if line.starts_with("#") || line.starts_with(";") || line.is_empty() {
-            continue;
-        }
-        let tokens = Vec::from_iter(line.split_whitespace());
-        let name = tokens.first().unwrap();
-        let tokens = tokens.get(1..).unwrap();
-        let tokens = tokens.iter().filter(|t| !t.starts_with("="));
-        let tokens = tokens.take_while(|t| !t.starts_with("#") && !t.starts_with(";"));
-        let mut parameters = String::new();
-        tokens.for_each(|t| { parameters.push_str(t); parameters.push(' '); });
-        let parameters = parameters.split(',').map(|s| s.trim());
-        let parameters: Vec<String> = parameters.map(|s| s.to_string()).collect();
-
-        // Setting the config parameters
-        match name.to_lowercase().as_str() {
-            "tablesize" => tablesize = parameters.get(0).unwrap().parse::<u64>().expect("couldn't parse tablesize"),
-            "logsize" => logsize = parameters.get(0).unwrap().parse::<u64>().expect("couldn't parse logsize"),
-            _ => (),
+// Copies FASTER's log in 'from' into 'to', along with the files of the checkpoint
+// 'token' but none of the other checkpoints. Checkpoint files are never written
+// again, so they are hard-linked where the file system allows it
+fn copy_checkpoint(from: &Path, to: &Path, token: &str) -> Result<(), CheckpointError> {
+    fn copy(from: &Path, to: &Path, link: bool, token: Option<&str>) -> Result<(), CheckpointError> {
+        fs::create_dir_all(to).map_err(|error| CheckpointError::Io(to.to_owned(), error))?;
+        let entries = fs::read_dir(from).map_err(|error| CheckpointError::Io(from.to_owned(), error))?;
+        for entry in entries {
+            let path = entry
+                .map_err(|error| CheckpointError::Io(from.to_owned(), error))?
+                .path();
+            let file_name = path.file_name().expect("FASTER file without a name");
+            let target = to.join(file_name);
+            if path.is_dir() {
+                match token {
+                    Some(token) if CHECKPOINTS.iter().any(|name| file_name == *name) => {
+                        copy(&path.join(token), &target.join(token), true, None)?
+                    }
+                    _ => copy(&path, &target, link, token)?,
+                }
+            } else if !link || fs::hard_link(&path, &target).is_err() {
+                fs::copy(&path, &target).map_err(|error| CheckpointError::Io(path.clone(), error))?;
+            }
         }
+        Ok(())
+    }
+    copy(from, to, false, Some(token))
+}
+
+// Where FASTER keeps the files of each checkpoint, in directories named by their token
+const CHECKPOINTS: [&str; 2] = ["cpr-checkpoints", "index-checkpoints"];
+
+// Opens a FASTER instance that keeps its log and checkpoints in 'directory'
+fn open(directory: &Path, config: &FasterConfig) -> FasterKv {
+    println!("Configuring a FASTER instance with hash index {:?} and log size {:?}", config.table_size, config.log_size);
+    let mut builder = FasterKvBuilder::new(config.table_size, config.log_size);
+    builder
+        .with_disk(directory.to_str().unwrap())
+        .set_pre_allocate_log(config.pre_allocate_log);
+    if let Some(budget) = config.mutable_budget {
+        builder.set_mutable_fraction(budget as f64 / config.log_size as f64);
     }
-    (tablesize, logsize)
+    builder.build().unwrap()
 }
 
 impl StateBackend for FASTERBackend {
-    fn new() -> Self {
-        let faster_directory = TempDir::new_in(".")
-            .expect("Unable to create directory for FASTER")
-            .into_path();
-        let faster_directory_string = faster_directory.to_str().unwrap();
-        // TODO: check sizing
-        let (tablesize, logsize) = read_faster_config();
-        println!("Configuring a FASTER instance with hash index {:?} and log size {:?}", tablesize, logsize);
-        let mut builder = FasterKvBuilder::new(tablesize, logsize);
-        builder
-            .with_disk(faster_directory_string)
-            .set_pre_allocate_log(true);
-        let faster_kv = Arc::new(builder.build().unwrap());
-        faster_kv.start_session();
+    fn new(config: &BackendConfig) -> Self {
+        let directory =
+            TempDir::new_in(&config.directory).expect("Unable to create directory for FASTER");
+        let faster_kv = Arc::new(open(directory.path(), &config.faster));
+        let mut session = Session::from_config(&config.faster);
+        session.set_guid(faster_kv.start_session());
         FASTERBackend {
             faster: faster_kv,
-            monotonic_serial_number: Rc::new(RefCell::new(1)),
+            session: Rc::new(RefCell::new(session)),
+            directory: Some(directory),
+            sorted_maps: SortedMaps::default(),
         }
     }
 
     fn get_managed_count(&self, name: &str) -> Box<ManagedCount> {
         Box::new(FASTERManagedCount::new(
             Arc::clone(&self.faster),
-            Rc::clone(&self.monotonic_serial_number),
+            Rc::clone(&self.session),
             name,
         ))
     }
 
-    fn get_managed_value<V: 'static + FasterValue + FasterRmw>(
+    fn get_managed_value<V: StateValue>(
         &self,
         name: &str,
     ) -> Box<ManagedValue<V>> {
         Box::new(FASTERManagedValue::new(
             Arc::clone(&self.faster),
-            Rc::clone(&self.monotonic_serial_number),
+            Rc::clone(&self.session),
             name,
         ))
     }
 
-    fn get_managed_map<K, V>(&self, name: &str) -> Box<ManagedMap<K, V>>
+    fn get_managed_map_with_codecs<K, V, KC, VC>(&self, name: &str) -> Box<ManagedMap<K, V>>
     where
-        K: 'static + FasterKey + Hash + Eq + std::fmt::Debug,
-        V: 'static + FasterValue + FasterRmw,
+        K: StateKey,
+        V: StateValue,
+        KC: Codec<K>,
+        VC: Codec<V>,
     {
-        Box::new(FASTERManagedMap::new(
+        Box::new(FASTERManagedMap::<K, V, KC, VC>::with_codecs(
             Arc::clone(&self.faster),
-            Rc::clone(&self.monotonic_serial_number),
+            Rc::clone(&self.session),
             name,
         ))
     }
+
+    // Chunks are appended to with FASTER's own rmw rather than a get+merge+put
+    fn get_managed_bag<K, T>(&self, name: &str) -> Box<ManagedBag<K, T>>
+    where
+        K: StateKey,
+        T: StateValue + Clone,
+    {
+        Box::new(ChunkedBag::new(
+            self.get_managed_map(&format!("{}.lengths", name)),
+            self.get_managed_map(&format!("{}.chunks", name)),
+        ))
+    }
+
+    // FASTER's hash index has no key order, so sorted maps stay on the heap and
+    // are checkpointed next to FASTER's files
+    fn get_managed_sorted_map<K, V>(&self, name: &str) -> Box<ManagedSortedMap<K, V>>
+    where
+        K: SortKey,
+        V: StateValue,
+    {
+        Box::new(self.sorted_maps.get(name))
+    }
+
+    // FASTER checkpoints into the directory it was opened on, and the snapshot is
+    // a copy of its log and that checkpoint's files in 'directory', along with the
+    // sorted maps kept on the heap
+    fn checkpoint(&self, directory: &Path) -> Result<Snapshot, CheckpointError> {
+        let faster_directory = self
+            .directory
+            .as_ref()
+            .ok_or(CheckpointError::Unsupported)?
+            .path();
+        let checkpoint = self
+            .faster
+            .checkpoint()
+            .map_err(|_| CheckpointError::Backend("FASTER could not checkpoint".to_owned()))?;
+        if !checkpoint.checked {
+            return Err(CheckpointError::Backend(
+                "FASTER is already taking a checkpoint".to_owned(),
+            ));
+        }
+        self.faster.complete_pending(true);
+        copy_checkpoint(faster_directory, directory, &checkpoint.token)?;
+        self.sorted_maps.checkpoint(directory)?;
+        Ok(Snapshot {
+            backend: "faster".to_owned(),
+            path: directory.to_owned(),
+            token: Some(checkpoint.token),
+            session: self.session.borrow().guid().map(|guid| guid.to_owned()),
+        })
+    }
+
+    // Recovers from a copy of the snapshot, as FASTER goes on writing its log in the
+    // directory it recovered in, and the snapshot may be restored again. The session
+    // that took the checkpoint continues after the last operation it persisted
+    fn restore(config: &BackendConfig, snapshot: &Snapshot) -> Result<Self, CheckpointError> {
+        if snapshot.backend != "faster" {
+            let error = format!("faster cannot restore a {} snapshot", snapshot.backend);
+            return Err(CheckpointError::Backend(error));
+        }
+        let token = snapshot
+            .token
+            .clone()
+            .ok_or_else(|| CheckpointError::Backend("FASTER snapshot without a token".to_owned()))?;
+        let sorted_maps = SortedMaps::restore(&snapshot.path)?;
+        let directory = TempDir::new_in(&config.directory)
+            .map_err(|error| CheckpointError::Io(config.directory.clone(), error))?;
+        copy_checkpoint(&snapshot.path, directory.path(), &token)?;
+        let faster_kv = Arc::new(open(directory.path(), &config.faster));
+        faster_kv
+            .recover(token.clone(), token)
+            .map_err(|_| CheckpointError::Backend("FASTER could not recover".to_owned()))?;
+        let mut session = Session::from_config(&config.faster);
+        match &snapshot.session {
+            Some(guid) => {
+                let persisted = faster_kv.continue_session(guid.clone());
+                session.set_guid(guid.clone());
+                session.resume(persisted);
+            }
+            None => session.set_guid(faster_kv.start_session()),
+        }
+        Ok(FASTERBackend {
+            faster: faster_kv,
+            session: Rc::new(RefCell::new(session)),
+            directory: Some(directory),
+            sorted_maps,
+        })
+    }
+
+    // Compacts the log once per epoch, if the policy says so
+    fn advance(&self, frontier: u64) {
+        let mut session = self.session.borrow_mut();
+        if session.advance(frontier) {
+            session.compact(&self.faster);
+        }
+    }
+
+    fn compaction_stats(&self) -> Option<CompactionStats> {
+        Some(self.session.borrow().compaction_stats())
+    }
+
+    fn session_stats(&self) -> Option<SessionStats> {
+        Some(self.session.borrow().stats())
+    }
+}
+
+impl FASTERBackend {
+    // The directory of an existing instance is unknown, so it cannot be checkpointed
+    pub fn new_from_existing(faster_kv: &Arc<FasterKv>) -> Self {
+        FASTERBackend {
+            faster: Arc::clone(faster_kv),
+            session: Rc::new(RefCell::new(Session::from_config(&FasterConfig::default()))),
+            directory: None,
+            sorted_maps: SortedMaps::default(),
+        }
+    }
+
+    /// Maintains and compacts this backend's session as `config` sets out, from its next
+    /// operation on.
+    pub fn with_session_config(self, config: &FasterConfig) -> Self {
+        {
+            let mut session = self.session.borrow_mut();
+            session.set_policy(config.session.clone());
+            session.set_compaction(config.compaction.clone());
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FASTERBackend;
+    use crate::config::{BackendConfig, CompactionPolicy, CompactionTrigger, FasterConfig};
+    use crate::{StateBackend, StateHandle};
+    use std::rc::Rc;
+    use tempfile::TempDir;
+
+    // Writing twice this much pushes the first writes out of memory and onto disk
+    const LOG_SIZE: u64 = 1 << 26;
+
+    #[test]
+    fn restore_continues_the_checkpointed_session() {
+        let directory = TempDir::new().unwrap();
+        let config = BackendConfig {
+            directory: std::env::temp_dir(),
+            faster: FasterConfig {
+                table_size: 1 << 14,
+                log_size: LOG_SIZE,
+                pre_allocate_log: false,
+                ..FasterConfig::default()
+            },
+            ..BackendConfig::default()
+        };
+        let backend = FASTERBackend::new(&config);
+        backend.get_managed_value::<u64>("value").set(5);
+        let snapshot = backend.checkpoint(directory.path()).unwrap();
+        let guid = backend.session.borrow().guid().map(|guid| guid.to_owned());
+        assert!(guid.is_some());
+        assert_eq!(snapshot.session, guid);
+
+        let restored = FASTERBackend::restore(&config, &snapshot).unwrap();
+        assert_eq!(restored.session.borrow().guid(), guid.as_deref());
+        assert_eq!(restored.get_managed_value::<u64>("value").get(), Some(Rc::new(5)));
+    }
+
+    #[test]
+    fn compaction_keeps_live_keys() {
+        let config = BackendConfig {
+            directory: std::env::temp_dir(),
+            faster: FasterConfig {
+                table_size: 1 << 14,
+                log_size: LOG_SIZE,
+                pre_allocate_log: false,
+                compaction: CompactionPolicy {
+                    trigger: CompactionTrigger::Epoch,
+                    fraction: 1.0,
+                },
+                mutable_budget: Some(LOG_SIZE / 4),
+                ..FasterConfig::default()
+            },
+            ..BackendConfig::default()
+        };
+        let backend = Rc::new(FASTERBackend::new(&config));
+        let handle = StateHandle::new(Rc::clone(&backend), "compaction");
+        let mut map = handle.get_managed_map::<u64, String>("map");
+
+        let value = "7".repeat(1 << 16);
+        let keys = (2 * LOG_SIZE) >> 16;
+        for key in 0..keys {
+            map.insert(key, value.clone());
+        }
+        for key in (0..keys).step_by(2) {
+            map.remove(&key);
+        }
+        let begin = backend.faster.begin_address();
+        assert!(backend.faster.head_address() > begin);
+
+        handle.advance(&[1u64]);
+        let stats = handle.compaction_stats().unwrap();
+        assert_eq!(stats.compactions, 1);
+        assert!(stats.reclaimed_bytes > 0);
+        assert_eq!(backend.faster.begin_address(), begin + stats.reclaimed_bytes);
+        for key in 0..keys {
+            let expected = if key % 2 == 0 { None } else { Some(Rc::new(value.clone())) };
+            assert_eq!(map.get(&key), expected);
+        }
+    }
 }
@@ -0,0 +1,51 @@
+//! Adapts the state crate's value traits to the ones faster-rs requires.
+
+use crate::codec::{Bincode, Codec};
+use crate::{Rmw, StateValue};
+use faster_rs::FasterRmw;
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use std::any::TypeId;
+use std::marker::PhantomData;
+
+/// A value as stored in FASTER. faster-rs serialises values itself, so values
+/// encoded with `Bincode` are serialised exactly like the value they wrap, and
+/// values of any other codec as the bytes that codec encodes them to.
+pub(crate) struct FasterCell<V, VC = Bincode>(pub V, pub PhantomData<VC>);
+
+impl<V, VC> FasterCell<V, VC> {
+    pub fn new(value: V) -> Self {
+        FasterCell(value, PhantomData)
+    }
+}
+
+fn is_bincode<VC: 'static>() -> bool {
+    TypeId::of::<VC>() == TypeId::of::<Bincode>()
+}
+
+impl<V: StateValue, VC: Codec<V>> Serialize for FasterCell<V, VC> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if is_bincode::<VC>() {
+            self.0.serialize(serializer)
+        } else {
+            serializer.serialize_bytes(&VC::encoded(&self.0))
+        }
+    }
+}
+
+impl<'de, V: StateValue, VC: Codec<V>> Deserialize<'de> for FasterCell<V, VC> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if is_bincode::<VC>() {
+            V::deserialize(deserializer).map(FasterCell::new)
+        } else {
+            Vec::<u8>::deserialize(deserializer).map(|bytes| FasterCell::new(VC::decode(&bytes)))
+        }
+    }
+}
+
+// Only values that implement `Rmw` are ever passed to FASTER's rmw
+impl<V: Rmw, VC: Codec<V>> FasterRmw for FasterCell<V, VC> {
+    fn rmw(&self, modification: Self) -> Self {
+        FasterCell::new(self.0.rmw(modification.0))
+    }
+}
@@ -8,11 +8,16 @@ mod managed_count;
 mod managed_map;
 mod managed_value;
 
-use crate::primitives::{ManagedCount, ManagedMap, ManagedValue};
-use crate::StateBackend;
-use faster_rs::{FasterKey, FasterKv, FasterKvBuilder, FasterRmw, FasterValue};
+use crate::codec::{Codec, SortKey};
+use crate::compaction::CompactionStats;
+use crate::config::{BackendConfig, FasterConfig};
+use crate::primitives::{
+    ChunkedBag, ManagedBag, ManagedCount, ManagedMap, ManagedSortedMap, ManagedValue, SortedMaps,
+};
+use crate::session::{Session, SessionStats};
+use crate::{StateBackend, StateKey, StateValue};
+use faster_rs::{status, FasterKey, FasterKv, FasterKvBuilder, FasterRmw, FasterValue};
 use std::cell::RefCell;
-use std::hash::Hash;
 use std::rc::Rc;
 use std::sync::mpsc::Receiver;
 use std::sync::Arc;
@@ -20,94 +25,182 @@ use std::sync::Arc;
 #[allow(dead_code)]
 pub struct FASTERInMemoryBackend {
     faster: Arc<FasterKv>,
-    monotonic_serial_number: Rc<RefCell<u64>>,
-}
-
-fn maybe_refresh_faster(faster: &Arc<FasterKv>, monotonic_serial_number: u64) {
-    if monotonic_serial_number % (1 << 10) == 0 {
-        faster.complete_pending(true);
-    } else if monotonic_serial_number % (1 << 5) == 0 {
-        faster.refresh();
-    }
+    session: Rc<RefCell<Session>>,
+    sorted_maps: SortedMaps,
 }
 
 fn faster_upsert<K: FasterKey, V: FasterValue>(
     faster: &Arc<FasterKv>,
     key: &K,
     value: &V,
-    monotonic_serial_number: &Rc<RefCell<u64>>,
+    session: &Rc<RefCell<Session>>,
 ) {
-    let old_monotonic_serial_number = *monotonic_serial_number.borrow();
-    *monotonic_serial_number.borrow_mut() = old_monotonic_serial_number + 1;
-    faster.upsert(key, value, old_monotonic_serial_number);
-    maybe_refresh_faster(faster, old_monotonic_serial_number);
+    let mut session = session.borrow_mut();
+    let status = faster.upsert(key, value, session.serial());
+    session.note_write(false);
+    session.maintain(faster, status);
 }
 
 fn faster_read<K: FasterKey, V: FasterValue>(
     faster: &Arc<FasterKv>,
     key: &K,
-    monotonic_serial_number: &Rc<RefCell<u64>>,
+    session: &Rc<RefCell<Session>>,
 ) -> (u8, Receiver<V>) {
-    let old_monotonic_serial_number = *monotonic_serial_number.borrow();
-    *monotonic_serial_number.borrow_mut() = old_monotonic_serial_number + 1;
-    let (status, recv) = faster.read(key, old_monotonic_serial_number);
-    maybe_refresh_faster(faster, old_monotonic_serial_number);
+    let mut session = session.borrow_mut();
+    let (status, recv) = faster.read(key, session.serial());
+    session.maintain(faster, status);
     (status, recv)
 }
 
-fn faster_rmw<K: FasterKey, V: FasterValue + FasterRmw>(
+// Reads 'key', waiting for FASTER to fetch it from disk if it has been evicted there
+fn faster_read_wait<K: FasterKey, V: FasterValue>(
+    faster: &Arc<FasterKv>,
+    key: &K,
+    session: &Rc<RefCell<Session>>,
+) -> Option<V> {
+    let (status, recv) = faster_read(faster, key, session);
+    match status {
+        status::OK => recv.recv().ok(),
+        status::PENDING => {
+            faster.complete_pending(true);
+            recv.recv().ok()
+        }
+        _ => None,
+    }
+}
+
+fn faster_delete<K: FasterKey>(
+    faster: &Arc<FasterKv>,
+    key: &K,
+    session: &Rc<RefCell<Session>>,
+) -> u8 {
+    let mut session = session.borrow_mut();
+    let status = faster.delete(key, session.serial());
+    session.note_write(true);
+    session.maintain(faster, status);
+    status
+}
+
+fn faster_rmw<K: FasterKey, V: FasterRmw>(
     faster: &Arc<FasterKv>,
     key: &K,
     modification: &V,
-    monotonic_serial_number: &Rc<RefCell<u64>>,
+    session: &Rc<RefCell<Session>>,
 ) {
-    let old_monotonic_serial_number = *monotonic_serial_number.borrow();
-    *monotonic_serial_number.borrow_mut() = old_monotonic_serial_number + 1;
-    faster.rmw(key, modification, old_monotonic_serial_number);
-    maybe_refresh_faster(faster, old_monotonic_serial_number);
+    let mut session = session.borrow_mut();
+    let status = faster.rmw(key, modification, session.serial());
+    session.note_write(false);
+    session.maintain(faster, status);
 }
 
 impl StateBackend for FASTERInMemoryBackend {
-    fn new() -> Self {
-        // TODO: check sizing
-        let mut builder = FasterKvBuilder::new(1 << 24, 12 * 1024 * 1024 * 1024);
-        builder.set_pre_allocate_log(true);
+    fn new(config: &BackendConfig) -> Self {
+        let config = &config.faster_in_memory;
+        let mut builder = FasterKvBuilder::new(config.table_size, config.log_size);
+        builder.set_pre_allocate_log(config.pre_allocate_log);
+        if let Some(budget) = config.mutable_budget {
+            builder.set_mutable_fraction(budget as f64 / config.log_size as f64);
+        }
         let faster_kv = Arc::new(builder.build().unwrap());
         faster_kv.start_session();
         FASTERInMemoryBackend {
             faster: faster_kv,
-            monotonic_serial_number: Rc::new(RefCell::new(1)),
+            session: Rc::new(RefCell::new(Session::from_config(config))),
+            sorted_maps: SortedMaps::default(),
         }
     }
 
     fn get_managed_count(&self, name: &str) -> Box<ManagedCount> {
         Box::new(FASTERManagedCount::new(
             Arc::clone(&self.faster),
-            Rc::clone(&self.monotonic_serial_number),
+            Rc::clone(&self.session),
             name,
         ))
     }
 
-    fn get_managed_value<V: 'static + FasterValue + FasterRmw>(
+    fn get_managed_value<V: StateValue>(
         &self,
         name: &str,
     ) -> Box<ManagedValue<V>> {
         Box::new(FASTERManagedValue::new(
             Arc::clone(&self.faster),
-            Rc::clone(&self.monotonic_serial_number),
+            Rc::clone(&self.session),
             name,
         ))
     }
 
-    fn get_managed_map<K, V>(&self, name: &str) -> Box<ManagedMap<K, V>>
+    fn get_managed_map_with_codecs<K, V, KC, VC>(&self, name: &str) -> Box<ManagedMap<K, V>>
     where
-        K: 'static + FasterKey + Hash + Eq + std::fmt::Debug,
-        V: 'static + FasterValue + FasterRmw
+        K: StateKey,
+        V: StateValue,
+        KC: Codec<K>,
+        VC: Codec<V>,
     {
-        Box::new(FASTERManagedMap::new(
+        Box::new(FASTERManagedMap::<K, V, KC, VC>::with_codecs(
             Arc::clone(&self.faster),
-            Rc::clone(&self.monotonic_serial_number),
+            Rc::clone(&self.session),
             name,
         ))
     }
+
+    // Chunks are appended to with FASTER's own rmw rather than a get+merge+put
+    fn get_managed_bag<K, T>(&self, name: &str) -> Box<ManagedBag<K, T>>
+    where
+        K: StateKey,
+        T: StateValue + Clone,
+    {
+        Box::new(ChunkedBag::new(
+            self.get_managed_map(&format!("{}.lengths", name)),
+            self.get_managed_map(&format!("{}.chunks", name)),
+        ))
+    }
+
+    // FASTER's hash index has no key order, so sorted maps stay on the heap
+    // and are not part of FASTER's checkpoints
+    fn get_managed_sorted_map<K, V>(&self, name: &str) -> Box<ManagedSortedMap<K, V>>
+    where
+        K: SortKey,
+        V: StateValue,
+    {
+        Box::new(self.sorted_maps.get(name))
+    }
+
+    // Compacts the log once per epoch, if the policy says so
+    fn advance(&self, frontier: u64) {
+        let mut session = self.session.borrow_mut();
+        if session.advance(frontier) {
+            session.compact(&self.faster);
+        }
+    }
+
+    fn compaction_stats(&self) -> Option<CompactionStats> {
+        Some(self.session.borrow().compaction_stats())
+    }
+
+    fn session_stats(&self) -> Option<SessionStats> {
+        Some(self.session.borrow().stats())
+    }
+}
+
+impl FASTERInMemoryBackend {
+    pub fn new_from_existing(faster_kv: &Arc<FasterKv>) -> Self {
+        FASTERInMemoryBackend {
+            faster: Arc::clone(faster_kv),
+            session: Rc::new(RefCell::new(Session::from_config(
+                &BackendConfig::default().faster_in_memory,
+            ))),
+            sorted_maps: SortedMaps::default(),
+        }
+    }
+
+    /// Maintains and compacts this backend's session as `config` sets out, from its next
+    /// operation on.
+    pub fn with_session_config(self, config: &FasterConfig) -> Self {
+        {
+            let mut session = self.session.borrow_mut();
+            session.set_policy(config.session.clone());
+            session.set_compaction(config.compaction.clone());
+        }
+        self
+    }
 }
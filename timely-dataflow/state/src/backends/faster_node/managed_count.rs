@@ -1,25 +1,22 @@
-use crate::backends::faster_node::{faster_read, faster_rmw, faster_upsert};
+use crate::backends::faster_node::{faster_read_wait, faster_rmw, faster_upsert};
 use crate::primitives::ManagedCount;
-use faster_rs::{status, FasterKv};
+use crate::session::Session;
+use faster_rs::FasterKv;
 use std::cell::RefCell;
 use std::rc::Rc;
 use std::sync::Arc;
 
 pub struct FASTERManagedCount {
     faster: Arc<FasterKv>,
-    monotonic_serial_number: Rc<RefCell<u64>>,
+    session: Rc<RefCell<Session>>,
     name: String,
 }
 
 impl FASTERManagedCount {
-    pub fn new(
-        faster: Arc<FasterKv>,
-        monotonic_serial_number: Rc<RefCell<u64>>,
-        name: &str,
-    ) -> Self {
+    pub fn new(faster: Arc<FasterKv>, session: Rc<RefCell<Session>>, name: &str) -> Self {
         FASTERManagedCount {
             faster,
-            monotonic_serial_number,
+            session,
             name: name.to_owned(),
         }
     }
@@ -27,40 +24,18 @@ impl FASTERManagedCount {
 
 impl ManagedCount for FASTERManagedCount {
     fn decrease(&mut self, amount: i64) {
-        faster_rmw(
-            &self.faster,
-            &self.name,
-            &-amount,
-            &self.monotonic_serial_number,
-        );
+        faster_rmw(&self.faster, &self.name, &-amount, &self.session);
     }
 
     fn increase(&mut self, amount: i64) {
-        faster_rmw(
-            &self.faster,
-            &self.name,
-            &amount,
-            &self.monotonic_serial_number,
-        );
+        faster_rmw(&self.faster, &self.name, &amount, &self.session);
     }
 
     fn get(&self) -> i64 {
-        let (status, recv) = faster_read(&self.faster, &self.name, &self.monotonic_serial_number);
-        if status != status::OK {
-            return 0;
-        }
-        return match recv.recv() {
-            Ok(count) => count,
-            Err(_) => 0,
-        };
+        faster_read_wait(&self.faster, &self.name, &self.session).unwrap_or(0)
     }
 
     fn set(&mut self, value: i64) {
-        faster_upsert(
-            &self.faster,
-            &self.name,
-            &value,
-            &self.monotonic_serial_number,
-        );
+        faster_upsert(&self.faster, &self.name, &value, &self.session);
     }
 }
@@ -1,43 +1,60 @@
-use crate::primitives::ManagedMap;
-use faster_rs::{FasterKey, FasterRmw, FasterValue};
+use crate::codec::{Bincode, Codec};
+use crate::primitives::{
+    sort_entries, unshared, IterDirection, IterationError, KeyRange, ManagedMap, ManagedMapIter,
+};
+use crate::{Rmw, StateKey, StateValue};
 use std::any::Any;
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::hash::Hash;
 use std::marker::PhantomData;
 use std::rc::Rc;
-use rocksdb::DBIterator;
 
-pub struct InMemoryManagedMap<K, V>
+pub struct InMemoryManagedMap<K, V, KC = Bincode>
 where
-    K: 'static + FasterKey + Hash + Eq,
-    V: 'static + FasterValue + FasterRmw,
+    K: StateKey,
+    V: StateValue,
 {
     name: String,
     backend: Rc<RefCell<HashMap<String, Rc<Any>>>>,
     phantom_key: PhantomData<K>,
     phantom_value: PhantomData<V>,
+    phantom_key_codec: PhantomData<KC>,
 }
 
+#[cfg(test)]
 impl<K, V> InMemoryManagedMap<K, V>
 where
-    K: 'static + FasterKey + Hash + Eq + std::fmt::Debug,
-    V: 'static + FasterValue + FasterRmw,
+    K: StateKey,
+    V: StateValue,
 {
     pub fn new(name: &str, backend: Rc<RefCell<HashMap<String, Rc<Any>>>>) -> Self {
+        InMemoryManagedMap::with_codecs(name, backend)
+    }
+}
+
+// Entries are stored unencoded, so the key codec only decides iteration order
+impl<K, V, KC> InMemoryManagedMap<K, V, KC>
+where
+    K: StateKey,
+    V: StateValue,
+    KC: Codec<K>,
+{
+    pub fn with_codecs(name: &str, backend: Rc<RefCell<HashMap<String, Rc<Any>>>>) -> Self {
         InMemoryManagedMap {
             name: name.to_string(),
             backend,
             phantom_key: PhantomData,
             phantom_value: PhantomData,
+            phantom_key_codec: PhantomData,
         }
     }
 }
 
-impl<K, V> ManagedMap<K, V> for InMemoryManagedMap<K, V>
+impl<K, V, KC> ManagedMap<K, V> for InMemoryManagedMap<K, V, KC>
 where
-    K: 'static + FasterKey + Hash + Eq + std::fmt::Debug,
-    V: 'static + FasterValue + FasterRmw,
+    K: StateKey,
+    V: StateValue,
+    KC: Codec<K>,
 {
     fn get_key_prefix_length(&self) -> usize {
         self.name.len()
@@ -94,7 +111,7 @@ where
         };
         let result = match inner_map.remove(&key) {
             None => None,
-            Some(val) => Rc::try_unwrap(val).ok(),
+            Some(val) => Some(unshared(val)),
         };
         self.backend
             .borrow_mut()
@@ -102,7 +119,10 @@ where
         result
     }
 
-    fn rmw(&mut self, key: K, modification: V) {
+    fn rmw(&mut self, key: K, modification: V)
+    where
+        V: Rmw,
+    {
         let mut inner_map: HashMap<K, Rc<V>> = match self.backend.borrow_mut().remove(&self.name) {
             None => HashMap::new(),
             Some(rc_any) => match rc_any.downcast() {
@@ -115,7 +135,7 @@ where
         };
         let old_value = match inner_map.remove(&key) {
             None => None,
-            Some(val) => Rc::try_unwrap(val).ok(),
+            Some(val) => Some(unshared(val)),
         };
         match old_value {
             None => inner_map.insert(key, Rc::new(modification)),
@@ -144,19 +164,35 @@ where
         result
     }
 
-    fn iter(&mut self, key: K) -> DBIterator {
-        panic!("In-memory managed map does not support iteration.");
-    }
-
-    fn next(&mut self, iter: DBIterator) -> Option<(Rc<K>,Rc<V>)> {
-        panic!("In-memory managed map does not support iteration.");
+    // Sorts a snapshot of the matching entries by their encoded keys
+    fn iter(
+        &self,
+        range: KeyRange<K>,
+        direction: IterDirection,
+    ) -> Result<ManagedMapIter<'_, K, V>, IterationError> {
+        let inner_map: HashMap<K, Rc<V>> = match self.backend.borrow_mut().remove(&self.name) {
+            None => HashMap::new(),
+            Some(rc_any) => match rc_any.downcast() {
+                Ok(rc_map) => match Rc::try_unwrap(rc_map) {
+                    Ok(map) => map,
+                    Err(_) => HashMap::new(),
+                },
+                Err(_) => HashMap::new(),
+            },
+        };
+        let result = sort_entries::<_, _, KC, _>(inner_map.iter(), &range, direction);
+        self.backend
+            .borrow_mut()
+            .insert(self.name.clone(), Rc::new(inner_map));
+        Ok(result)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::InMemoryManagedMap;
-    use crate::primitives::ManagedMap;
+    use crate::backends::conformance;
+    use crate::primitives::{IterDirection, KeyRange, ManagedMap};
     use std::cell::RefCell;
     use std::collections::HashMap;
     use std::rc::Rc;
@@ -181,6 +217,12 @@ mod tests {
         assert_eq!(map.get(&key), None);
     }
 
+    #[test]
+    fn map_conforms() {
+        let mut map = InMemoryManagedMap::new("", Rc::new(RefCell::new(HashMap::new())));
+        conformance::check_managed_map(&mut map);
+    }
+
     #[test]
     fn map_rmw() {
         let mut map: InMemoryManagedMap<String, i32> =
@@ -195,6 +237,30 @@ mod tests {
         assert_eq!(map.get(&key), Some(Rc::new(value + modification)));
     }
 
+    #[test]
+    fn map_iter_in_serialised_key_order() {
+        let mut map: InMemoryManagedMap<u64, i32> =
+            InMemoryManagedMap::new("", Rc::new(RefCell::new(HashMap::new())));
+
+        for key in 0u64..10 {
+            map.insert(key.to_be(), key as i32);
+        }
+
+        let forward: Vec<i32> = map
+            .iter(KeyRange::from(7u64.to_be()), IterDirection::Forward)
+            .unwrap()
+            .map(|(_, value)| *value)
+            .collect();
+        let reverse: Vec<i32> = map
+            .iter(KeyRange::All, IterDirection::Reverse)
+            .unwrap()
+            .map(|(_, value)| *value)
+            .take(2)
+            .collect();
+        assert_eq!(forward, vec![7, 8, 9]);
+        assert_eq!(reverse, vec![9, 8]);
+    }
+
     #[test]
     fn map_drop() {
         let backend = Rc::new(RefCell::new(HashMap::new()));
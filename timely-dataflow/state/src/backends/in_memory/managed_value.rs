@@ -1,18 +1,18 @@
-use crate::primitives::ManagedValue;
-use faster_rs::{FasterRmw, FasterValue};
+use crate::primitives::{unshared, ManagedValue};
+use crate::{Rmw, StateValue};
 use std::any::Any;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::rc::Rc;
 
-pub struct InMemoryManagedValue<V: FasterValue + FasterRmw> {
+pub struct InMemoryManagedValue<V: StateValue> {
     name: String,
     backend: Rc<RefCell<HashMap<String, Rc<Any>>>>,
     phantom: PhantomData<V>,
 }
 
-impl<V: 'static + FasterValue + FasterRmw> InMemoryManagedValue<V> {
+impl<V: StateValue> InMemoryManagedValue<V> {
     pub fn new(name: &str, backend: Rc<RefCell<HashMap<String, Rc<Any>>>>) -> Self {
         InMemoryManagedValue {
             name: name.to_string(),
@@ -22,7 +22,7 @@ impl<V: 'static + FasterValue + FasterRmw> InMemoryManagedValue<V> {
     }
 }
 
-impl<V: 'static + FasterValue + FasterRmw> ManagedValue<V> for InMemoryManagedValue<V> {
+impl<V: StateValue> ManagedValue<V> for InMemoryManagedValue<V> {
     fn set(&mut self, value: V) {
         self.backend
             .borrow_mut()
@@ -33,7 +33,7 @@ impl<V: 'static + FasterValue + FasterRmw> ManagedValue<V> for InMemoryManagedVa
         let result: Option<V> = match self.backend.borrow_mut().remove(&self.name) {
             None => None,
             Some(value) => match value.downcast::<V>() {
-                Ok(value) => Rc::try_unwrap(value).ok(),
+                Ok(value) => Some(unshared(value)),
                 Err(_) => None,
             },
         };
@@ -52,13 +52,16 @@ impl<V: 'static + FasterValue + FasterRmw> ManagedValue<V> for InMemoryManagedVa
         match self.backend.borrow_mut().remove(&self.name) {
             None => None,
             Some(value) => match value.downcast() {
-                Ok(value) => Rc::try_unwrap(value).ok(),
+                Ok(value) => Some(unshared(value)),
                 Err(_) => None,
             },
         }
     }
 
-    fn rmw(&mut self, modification: V) {
+    fn rmw(&mut self, modification: V)
+    where
+        V: Rmw,
+    {
         match self.take() {
             None => self.set(modification),
             Some(value) => self.set(value.rmw(modification)),
@@ -6,42 +6,151 @@ mod managed_count;
 mod managed_map;
 mod managed_value;
 
+use crate::checkpoint::{CheckpointError, Snapshot};
+use crate::codec::Codec;
+use crate::config::BackendConfig;
 use crate::primitives::{ManagedCount, ManagedMap, ManagedValue};
-use crate::StateBackend;
-use faster_rs::{FasterKey, FasterRmw, FasterValue};
+use crate::{StateBackend, StateKey, StateValue};
 use std::any::Any;
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::hash::Hash;
+use std::fs;
+use std::path::Path;
 use std::rc::Rc;
 
+// The file of a checkpoint that holds the serialised state
+const DUMP_FILE: &str = "state.bin";
+
+type Serialiser = fn(&Any) -> Option<Vec<u8>>;
+type Deserialiser = fn(&[u8]) -> Rc<Any>;
+
 pub struct InMemoryBackend {
     backend: Rc<RefCell<HashMap<String, Rc<Any>>>>,
+    // State is stored untyped, so each piece records how to serialise it when created
+    serialisers: RefCell<HashMap<String, Serialiser>>,
+    // Restored state stays serialised until it is created again, when its type is known
+    restored: RefCell<HashMap<String, Vec<u8>>>,
+}
+
+fn serialise<T: StateValue>(state: &Any) -> Option<Vec<u8>> {
+    state
+        .downcast_ref::<T>()
+        .map(|state| bincode::serialize(state).unwrap())
+}
+
+fn serialise_map<K: StateKey, V: StateValue>(state: &Any) -> Option<Vec<u8>> {
+    state.downcast_ref::<HashMap<K, Rc<V>>>().map(|map| {
+        let entries: Vec<(&K, &V)> = map.iter().map(|(key, value)| (key, &**value)).collect();
+        bincode::serialize(&entries).unwrap()
+    })
+}
+
+fn deserialise<T: StateValue>(serialised: &[u8]) -> Rc<Any> {
+    Rc::new(bincode::deserialize::<T>(serialised).unwrap())
+}
+
+fn deserialise_map<K: StateKey, V: StateValue>(serialised: &[u8]) -> Rc<Any> {
+    let entries: Vec<(K, V)> = bincode::deserialize(serialised).unwrap();
+    let map: HashMap<K, Rc<V>> = entries
+        .into_iter()
+        .map(|(key, value)| (key, Rc::new(value)))
+        .collect();
+    Rc::new(map)
+}
+
+impl InMemoryBackend {
+    fn register(&self, name: &str, serialiser: Serialiser, deserialiser: Deserialiser) {
+        self.serialisers
+            .borrow_mut()
+            .insert(name.to_owned(), serialiser);
+        if let Some(serialised) = self.restored.borrow_mut().remove(name) {
+            self.backend
+                .borrow_mut()
+                .insert(name.to_owned(), deserialiser(&serialised));
+        }
+    }
 }
 
 impl StateBackend for InMemoryBackend {
-    fn new() -> Self {
+    fn new(_config: &BackendConfig) -> Self {
         InMemoryBackend {
             backend: Rc::new(RefCell::new(HashMap::new())),
+            serialisers: RefCell::new(HashMap::new()),
+            restored: RefCell::new(HashMap::new()),
         }
     }
 
     fn get_managed_count(&self, name: &str) -> Box<ManagedCount> {
+        self.register(name, serialise::<i64>, deserialise::<i64>);
         Box::new(InMemoryManagedCount::new(name, Rc::clone(&self.backend)))
     }
 
-    fn get_managed_value<V: 'static + FasterValue + FasterRmw>(
+    fn get_managed_value<V: StateValue>(
         &self,
         name: &str,
     ) -> Box<ManagedValue<V>> {
+        self.register(name, serialise::<V>, deserialise::<V>);
         Box::new(InMemoryManagedValue::new(name, Rc::clone(&self.backend)))
     }
 
-    fn get_managed_map<K, V>(&self, name: &str) -> Box<ManagedMap<K, V>>
+    fn get_managed_map_with_codecs<K, V, KC, VC>(&self, name: &str) -> Box<ManagedMap<K, V>>
     where
-        K: 'static + FasterKey + Hash + Eq + std::fmt::Debug,
-        V: 'static + FasterValue + FasterRmw,
+        K: StateKey,
+        V: StateValue,
+        KC: Codec<K>,
+        VC: Codec<V>,
     {
-        Box::new(InMemoryManagedMap::new(name, Rc::clone(&self.backend)))
+        self.register(name, serialise_map::<K, V>, deserialise_map::<K, V>);
+        Box::new(InMemoryManagedMap::<K, V, KC>::with_codecs(
+            name,
+            Rc::clone(&self.backend),
+        ))
+    }
+
+    fn checkpoint(&self, directory: &Path) -> Result<Snapshot, CheckpointError> {
+        let serialisers = self.serialisers.borrow();
+        let mut dump = Vec::new();
+        for (name, state) in self.backend.borrow().iter() {
+            let serialised = serialisers
+                .get(name)
+                .and_then(|serialise| serialise(&**state))
+                .ok_or_else(|| {
+                    CheckpointError::Backend(format!("state {} cannot be serialised", name))
+                })?;
+            dump.push((name.clone(), serialised));
+        }
+        // Restored state that was not created again since is still part of the state
+        for (name, serialised) in self.restored.borrow().iter() {
+            dump.push((name.clone(), serialised.clone()));
+        }
+        dump.sort();
+        fs::create_dir_all(directory)
+            .map_err(|error| CheckpointError::Io(directory.to_owned(), error))?;
+        let path = directory.join(DUMP_FILE);
+        fs::write(&path, bincode::serialize(&dump).unwrap())
+            .map_err(|error| CheckpointError::Io(path, error))?;
+        Ok(Snapshot {
+            backend: "in_memory".to_owned(),
+            path: directory.to_owned(),
+            token: None,
+            session: None,
+        })
+    }
+
+    fn restore(_config: &BackendConfig, snapshot: &Snapshot) -> Result<Self, CheckpointError> {
+        if snapshot.backend != "in_memory" {
+            let error = format!("in_memory cannot restore a {} snapshot", snapshot.backend);
+            return Err(CheckpointError::Backend(error));
+        }
+        let path = snapshot.path.join(DUMP_FILE);
+        let dump = fs::read(&path).map_err(|error| CheckpointError::Io(path.clone(), error))?;
+        let dump: Vec<(String, Vec<u8>)> = bincode::deserialize(&dump).map_err(|error| {
+            CheckpointError::Backend(format!("cannot read {}: {}", path.display(), error))
+        })?;
+        Ok(InMemoryBackend {
+            backend: Rc::new(RefCell::new(HashMap::new())),
+            serialisers: RefCell::new(HashMap::new()),
+            restored: RefCell::new(dump.into_iter().collect()),
+        })
     }
 }
@@ -0,0 +1,49 @@
+use crate::primitives::{ManagedBag, ManagedListIter};
+use crate::{StateKey, StateValue};
+use std::collections::HashMap;
+use std::iter;
+
+pub struct InMemoryNativeManagedBag<K, T>
+where
+    K: StateKey,
+    T: StateValue,
+{
+    bag: HashMap<K, Vec<T>>,
+}
+
+impl<K, T> InMemoryNativeManagedBag<K, T>
+where
+    K: StateKey,
+    T: StateValue,
+{
+    pub fn new() -> Self {
+        InMemoryNativeManagedBag {
+            bag: HashMap::new(),
+        }
+    }
+}
+
+impl<K, T> ManagedBag<K, T> for InMemoryNativeManagedBag<K, T>
+where
+    K: StateKey,
+    T: StateValue + Clone,
+{
+    fn append(&mut self, key: K, item: T) {
+        self.bag.entry(key).or_insert_with(Vec::new).push(item);
+    }
+
+    fn len(&self, key: &K) -> usize {
+        self.bag.get(key).map_or(0, Vec::len)
+    }
+
+    fn iter(&self, key: &K) -> ManagedListIter<'_, T> {
+        match self.bag.get(key) {
+            None => Box::new(iter::empty()),
+            Some(items) => Box::new(items.iter().cloned()),
+        }
+    }
+
+    fn drain(&mut self, key: &K) -> ManagedListIter<'_, T> {
+        Box::new(self.bag.remove(key).unwrap_or_default().into_iter())
+    }
+}
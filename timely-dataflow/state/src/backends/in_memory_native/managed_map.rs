@@ -1,35 +1,52 @@
-use crate::primitives::ManagedMap;
-use faster_rs::{FasterKey, FasterRmw, FasterValue};
+use crate::codec::{Bincode, Codec};
+use crate::primitives::{
+    sort_entries, unshared, IterDirection, IterationError, KeyRange, ManagedMap, ManagedMapIter,
+};
+use crate::{Rmw, StateKey, StateValue};
 use std::collections::HashMap;
 use std::marker::PhantomData;
-use std::hash::Hash;
 use std::rc::Rc;
-use rocksdb::DBIterator;
 
-pub struct InMemoryNativeManagedMap<K, V>
+pub struct InMemoryNativeManagedMap<K, V, KC = Bincode>
 where
-    K: 'static + FasterKey + Hash + Eq + std::fmt::Debug,
-    V: 'static + FasterValue + FasterRmw,
+    K: StateKey,
+    V: StateValue,
 {
     map: HashMap<K, Rc<V>>,
+    key_codec: PhantomData<KC>,
 }
 
+#[cfg(test)]
 impl<K, V> InMemoryNativeManagedMap<K, V>
 where
-    K: 'static + FasterKey + Hash + Eq + std::fmt::Debug,
-    V: 'static + FasterValue + FasterRmw,
+    K: StateKey,
+    V: StateValue,
 {
     pub fn new() -> Self {
+        InMemoryNativeManagedMap::with_codecs()
+    }
+}
+
+// Entries are stored unencoded, so the key codec only decides iteration order
+impl<K, V, KC> InMemoryNativeManagedMap<K, V, KC>
+where
+    K: StateKey,
+    V: StateValue,
+    KC: Codec<K>,
+{
+    pub fn with_codecs() -> Self {
         InMemoryNativeManagedMap {
             map: HashMap::new(),
+            key_codec: PhantomData,
         }
     }
 }
 
-impl<K, V> ManagedMap<K, V> for InMemoryNativeManagedMap<K, V>
+impl<K, V, KC> ManagedMap<K, V> for InMemoryNativeManagedMap<K, V, KC>
 where
-    K: 'static + FasterKey + Hash + Eq + std::fmt::Debug,
-    V: 'static + FasterValue + FasterRmw,
+    K: StateKey,
+    V: StateValue,
+    KC: Codec<K>,
 {
     fn get_key_prefix_length(&self) -> usize {
         0
@@ -49,11 +66,14 @@ where
     fn remove(&mut self, key: &K) -> Option<V> {
         match self.map.remove(key) {
             None => None,
-            Some(val) => Rc::try_unwrap(val).ok(),
+            Some(val) => Some(unshared(val)),
         }
     }
 
-    fn rmw(&mut self, key: K, modification: V) {
+    fn rmw(&mut self, key: K, modification: V)
+    where
+        V: Rmw,
+    {
         let new_value = match self.get(&key) {
             None => modification,
             Some(val) => val.rmw(modification),
@@ -65,11 +85,24 @@ where
         self.map.contains_key(key)
     }
 
-    fn iter(&mut self, key: K) -> DBIterator {
-        panic!("In-memory managed map does not support iteration.");
+    // Sorts a snapshot of the matching entries by their encoded keys
+    fn iter(
+        &self,
+        range: KeyRange<K>,
+        direction: IterDirection,
+    ) -> Result<ManagedMapIter<'_, K, V>, IterationError> {
+        Ok(sort_entries::<_, _, KC, _>(self.map.iter(), &range, direction))
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InMemoryNativeManagedMap;
+    use crate::backends::conformance;
 
-    fn next(&mut self, iter: DBIterator) -> Option<(Rc<K>,Rc<V>)> {
-        panic!("In-memory managed map does not support iteration.");
+    #[test]
+    fn map_conforms() {
+        let mut map = InMemoryNativeManagedMap::new();
+        conformance::check_managed_map(&mut map);
     }
 }
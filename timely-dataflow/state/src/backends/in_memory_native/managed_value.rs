@@ -1,18 +1,18 @@
-use crate::primitives::ManagedValue;
-use faster_rs::{FasterRmw, FasterValue};
+use crate::primitives::{unshared, ManagedValue};
+use crate::{Rmw, StateValue};
 use std::rc::Rc;
 
-pub struct InMemoryNativeManagedValue<V: FasterValue + FasterRmw> {
+pub struct InMemoryNativeManagedValue<V: StateValue> {
     value: Option<Rc<V>>,
 }
 
-impl<V: 'static + FasterValue + FasterRmw> InMemoryNativeManagedValue<V> {
+impl<V: StateValue> InMemoryNativeManagedValue<V> {
     pub fn new() -> Self {
         InMemoryNativeManagedValue { value: None }
     }
 }
 
-impl<V: 'static + FasterValue + FasterRmw> ManagedValue<V> for InMemoryNativeManagedValue<V> {
+impl<V: StateValue> ManagedValue<V> for InMemoryNativeManagedValue<V> {
     fn set(&mut self, value: V) {
         self.value.replace(Rc::new(value));
     }
@@ -27,11 +27,14 @@ impl<V: 'static + FasterValue + FasterRmw> ManagedValue<V> for InMemoryNativeMan
     fn take(&mut self) -> Option<V> {
         match self.value.take() {
             None => None,
-            Some(val) => Rc::try_unwrap(val).ok(),
+            Some(val) => Some(unshared(val)),
         }
     }
 
-    fn rmw(&mut self, modification: V) {
+    fn rmw(&mut self, modification: V)
+    where
+        V: Rmw,
+    {
         self.value = match &self.value {
             None => Some(Rc::new(modification)),
             Some(val) => Some(Rc::new(val.rmw(modification))),
@@ -1,39 +1,65 @@
+use managed_bag::InMemoryNativeManagedBag;
 use managed_count::InMemoryNativeManagedCount;
 use managed_map::InMemoryNativeManagedMap;
 use managed_value::InMemoryNativeManagedValue;
 
+mod managed_bag;
 mod managed_count;
 mod managed_map;
 mod managed_value;
 
-use crate::primitives::{ManagedCount, ManagedMap, ManagedValue};
-use crate::StateBackend;
-use faster_rs::{FasterKey, FasterRmw, FasterValue};
-use std::hash::Hash;
+use crate::codec::{Codec, SortKey};
+use crate::config::BackendConfig;
+use crate::primitives::{
+    ManagedBag, ManagedCount, ManagedMap, ManagedSortedMap, ManagedValue, SortedMaps,
+};
+use crate::{StateBackend, StateKey, StateValue};
 
-pub struct InMemoryNativeBackend {}
+pub struct InMemoryNativeBackend {
+    sorted_maps: SortedMaps,
+}
 
 impl StateBackend for InMemoryNativeBackend {
-    fn new() -> Self {
-        InMemoryNativeBackend {}
+    fn new(_config: &BackendConfig) -> Self {
+        InMemoryNativeBackend {
+            sorted_maps: SortedMaps::default(),
+        }
     }
 
     fn get_managed_count(&self, _name: &str) -> Box<ManagedCount> {
         Box::new(InMemoryNativeManagedCount::new())
     }
 
-    fn get_managed_value<V: 'static + FasterValue + FasterRmw>(
+    fn get_managed_value<V: StateValue>(
         &self,
         _name: &str,
     ) -> Box<ManagedValue<V>> {
         Box::new(InMemoryNativeManagedValue::new())
     }
 
-    fn get_managed_map<K, V>(&self, _name: &str) -> Box<ManagedMap<K, V>>
+    fn get_managed_map_with_codecs<K, V, KC, VC>(&self, _name: &str) -> Box<ManagedMap<K, V>>
+    where
+        K: StateKey,
+        V: StateValue,
+        KC: Codec<K>,
+        VC: Codec<V>,
+    {
+        Box::new(InMemoryNativeManagedMap::<K, V, KC>::with_codecs())
+    }
+
+    fn get_managed_bag<K, T>(&self, _name: &str) -> Box<ManagedBag<K, T>>
+    where
+        K: StateKey,
+        T: StateValue + Clone,
+    {
+        Box::new(InMemoryNativeManagedBag::new())
+    }
+
+    fn get_managed_sorted_map<K, V>(&self, name: &str) -> Box<ManagedSortedMap<K, V>>
     where
-        K: 'static + FasterKey + Hash + Eq + std::fmt::Debug,
-        V: 'static + FasterValue + FasterRmw,
+        K: SortKey,
+        V: StateValue,
     {
-        Box::new(InMemoryNativeManagedMap::new())
+        Box::new(self.sorted_maps.get(name))
     }
 }
@@ -0,0 +1,35 @@
+use super::OrderedTree;
+use crate::primitives::ManagedCount;
+
+// A count is the only entry of its tree, under the empty key
+pub struct InMemoryOrderedManagedCount {
+    tree: OrderedTree,
+}
+
+impl InMemoryOrderedManagedCount {
+    pub fn new(tree: OrderedTree) -> Self {
+        InMemoryOrderedManagedCount { tree }
+    }
+}
+
+impl ManagedCount for InMemoryOrderedManagedCount {
+    fn decrease(&mut self, amount: i64) {
+        let count = self.get();
+        self.set(count - amount);
+    }
+
+    fn increase(&mut self, amount: i64) {
+        let count = self.get();
+        self.set(count + amount);
+    }
+
+    fn get(&self) -> i64 {
+        self.tree
+            .read(&[], |count| bincode::deserialize(count).unwrap())
+            .unwrap_or(0)
+    }
+
+    fn set(&mut self, value: i64) {
+        self.tree.insert(Vec::new(), bincode::serialize(&value).unwrap());
+    }
+}
@@ -0,0 +1,104 @@
+use super::OrderedTree;
+use crate::codec::{Bincode, Codec};
+use crate::primitives::{
+    IterDirection, IterationError, KeyRange, ManagedMap, ManagedMapIter, SerialisedRange,
+};
+use crate::{Rmw, StateKey, StateValue};
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+pub struct InMemoryOrderedManagedMap<K, V, KC = Bincode, VC = Bincode> {
+    tree: OrderedTree,
+    key: PhantomData<K>,
+    value: PhantomData<V>,
+    codecs: PhantomData<(KC, VC)>,
+}
+
+impl<K, V, KC, VC> InMemoryOrderedManagedMap<K, V, KC, VC>
+where
+    K: StateKey,
+    V: StateValue,
+    KC: Codec<K>,
+    VC: Codec<V>,
+{
+    pub fn with_codecs(tree: OrderedTree) -> Self {
+        InMemoryOrderedManagedMap {
+            tree,
+            key: PhantomData,
+            value: PhantomData,
+            codecs: PhantomData,
+        }
+    }
+}
+
+// Every map has a tree of its own, so keys are not prefixed with its name
+impl<K, V, KC, VC> ManagedMap<K, V> for InMemoryOrderedManagedMap<K, V, KC, VC>
+where
+    K: StateKey,
+    V: StateValue,
+    KC: Codec<K>,
+    VC: Codec<V>,
+{
+    fn get_key_prefix_length(&self) -> usize {
+        0
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        self.tree.insert(KC::encoded(&key), VC::encoded(&value));
+    }
+
+    fn get(&self, key: &K) -> Option<Rc<V>> {
+        self.tree
+            .read(&KC::encoded(key), |value| Rc::new(VC::decode(value)))
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.tree
+            .remove(&KC::encoded(key))
+            .map(|value| VC::decode(&value))
+    }
+
+    fn rmw(&mut self, key: K, modification: V)
+    where
+        V: Rmw,
+    {
+        let encoded_key = KC::encoded(&key);
+        let modified = match self.tree.read(&encoded_key, VC::decode) {
+            Some(value) => value.rmw(modification),
+            None => modification,
+        };
+        self.tree.insert(encoded_key, VC::encoded(&modified));
+    }
+
+    fn contains(&self, key: &K) -> bool {
+        self.tree.contains(&KC::encoded(key))
+    }
+
+    // Walks the tree's own order, one lookup per entry
+    fn iter(
+        &self,
+        range: KeyRange<K>,
+        direction: IterDirection,
+    ) -> Result<ManagedMapIter<'_, K, V>, IterationError> {
+        let range = SerialisedRange::new::<K, KC>(&[], &range);
+        Ok(Box::new(self.tree.range(range, direction).map(|(key, value)| {
+            (Rc::new(KC::decode(&key)), Rc::new(VC::decode(&value)))
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InMemoryOrderedManagedMap;
+    use crate::backends::in_memory_ordered::InMemoryOrderedBackend;
+    use crate::backends::conformance;
+    use crate::config::BackendConfig;
+    use crate::StateBackend;
+
+    #[test]
+    fn map_conforms() {
+        let backend = InMemoryOrderedBackend::new(&BackendConfig::default());
+        let mut map = InMemoryOrderedManagedMap::<u64, u64>::with_codecs(backend.tree("map"));
+        conformance::check_managed_map(&mut map);
+    }
+}
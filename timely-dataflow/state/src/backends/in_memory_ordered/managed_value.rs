@@ -0,0 +1,43 @@
+use super::OrderedTree;
+use crate::primitives::ManagedValue;
+use crate::{Rmw, StateValue};
+use std::rc::Rc;
+
+// A value is the only entry of its tree, under the empty key
+pub struct InMemoryOrderedManagedValue {
+    tree: OrderedTree,
+}
+
+impl InMemoryOrderedManagedValue {
+    pub fn new(tree: OrderedTree) -> Self {
+        InMemoryOrderedManagedValue { tree }
+    }
+}
+
+impl<V: StateValue> ManagedValue<V> for InMemoryOrderedManagedValue {
+    fn set(&mut self, value: V) {
+        self.tree.insert(Vec::new(), bincode::serialize(&value).unwrap());
+    }
+
+    fn get(&self) -> Option<Rc<V>> {
+        self.tree
+            .read(&[], |value| Rc::new(bincode::deserialize(value).unwrap()))
+    }
+
+    fn take(&mut self) -> Option<V> {
+        self.tree
+            .remove(&[])
+            .map(|value| bincode::deserialize(&value).unwrap())
+    }
+
+    fn rmw(&mut self, modification: V)
+    where
+        V: Rmw,
+    {
+        let modified = match self.tree.read(&[], |value| bincode::deserialize::<V>(value).unwrap()) {
+            Some(value) => value.rmw(modification),
+            None => modification,
+        };
+        self.set(modified);
+    }
+}
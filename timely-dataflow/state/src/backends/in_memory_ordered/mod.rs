@@ -0,0 +1,265 @@
+//! An in-memory backend that keeps its state in order, so that maps iterate
+//! natively like those of the on-disk backends.
+//!
+//! Each piece of state is a `BTreeMap` from encoded keys to encoded values,
+//! shared by everything created with the same name. Keeping state encoded
+//! means maps order and copy their entries exactly as RocksDB's do, which
+//! makes this backend a stand-in for RocksDB when checking the results of
+//! queries that iterate. Keys and values are counted at their encoded size
+//! by `StateHandle::memory_usage`.
+
+use managed_count::InMemoryOrderedManagedCount;
+use managed_map::InMemoryOrderedManagedMap;
+use managed_value::InMemoryOrderedManagedValue;
+
+mod managed_count;
+mod managed_map;
+mod managed_value;
+
+use crate::checkpoint::{CheckpointError, Snapshot};
+use crate::codec::Codec;
+use crate::config::BackendConfig;
+use crate::primitives::{
+    IterDirection, ManagedCount, ManagedMap, ManagedValue, SerialisedRange,
+};
+use crate::{StateBackend, StateKey, StateValue};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+// The file of a checkpoint that holds the serialised state
+const DUMP_FILE: &str = "state.bin";
+
+type Entries = BTreeMap<Vec<u8>, Vec<u8>>;
+
+/// The entries of one piece of state, and the bytes that all of a backend's entries take.
+#[derive(Clone)]
+pub(crate) struct OrderedTree {
+    entries: Rc<RefCell<Entries>>,
+    bytes: Rc<Cell<u64>>,
+}
+
+impl OrderedTree {
+    /// Decodes the value of `key` with `decode`, if there is one.
+    pub fn read<T, F: FnOnce(&[u8]) -> T>(&self, key: &[u8], decode: F) -> Option<T> {
+        self.entries.borrow().get(key).map(|value| decode(value))
+    }
+
+    pub fn insert(&self, key: Vec<u8>, value: Vec<u8>) -> Option<Vec<u8>> {
+        let added = (key.len() + value.len()) as u64;
+        self.bytes.set(self.bytes.get() + added);
+        let key_length = key.len() as u64;
+        let previous = self.entries.borrow_mut().insert(key, value);
+        if let Some(previous) = &previous {
+            self.bytes.set(self.bytes.get() - key_length - previous.len() as u64);
+        }
+        previous
+    }
+
+    pub fn remove(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let removed = self.entries.borrow_mut().remove(key);
+        if let Some(value) = &removed {
+            self.bytes.set(self.bytes.get() - (key.len() + value.len()) as u64);
+        }
+        removed
+    }
+
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.entries.borrow().contains_key(key)
+    }
+
+    /// The entries in `range`, visited in `direction`.
+    pub fn range(&self, range: SerialisedRange, direction: IterDirection) -> Cursor {
+        Cursor {
+            entries: Rc::clone(&self.entries),
+            range,
+            direction,
+        }
+    }
+}
+
+/// Iterates over a range of an `OrderedTree`, looking up each entry when it is
+/// reached, so that the tree is not borrowed between calls to `next`.
+pub(crate) struct Cursor {
+    entries: Rc<RefCell<Entries>>,
+    range: SerialisedRange,
+    direction: IterDirection,
+}
+
+impl Iterator for Cursor {
+    type Item = (Vec<u8>, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bounds = self.range.bounds()?;
+        let (key, value) = {
+            let entries = self.entries.borrow();
+            let mut in_range = entries.range(bounds);
+            let entry = match self.direction {
+                IterDirection::Forward => in_range.next(),
+                IterDirection::Reverse => in_range.next_back(),
+            };
+            entry.map(|(key, value)| (key.clone(), value.clone()))?
+        };
+        self.range.advance_past(key.clone(), self.direction);
+        Some((key, value))
+    }
+}
+
+pub struct InMemoryOrderedBackend {
+    trees: RefCell<HashMap<String, Rc<RefCell<Entries>>>>,
+    bytes: Rc<Cell<u64>>,
+}
+
+impl InMemoryOrderedBackend {
+    fn with_trees(trees: HashMap<String, Entries>) -> Self {
+        let bytes = trees
+            .values()
+            .flat_map(|entries| entries.iter())
+            .map(|(key, value)| (key.len() + value.len()) as u64)
+            .sum();
+        let trees = trees
+            .into_iter()
+            .map(|(name, entries)| (name, Rc::new(RefCell::new(entries))))
+            .collect();
+        InMemoryOrderedBackend {
+            trees: RefCell::new(trees),
+            bytes: Rc::new(Cell::new(bytes)),
+        }
+    }
+
+    // The entries of the state called 'name', created empty the first time
+    fn tree(&self, name: &str) -> OrderedTree {
+        let mut trees = self.trees.borrow_mut();
+        let entries = trees
+            .entry(name.to_owned())
+            .or_insert_with(|| Rc::new(RefCell::new(BTreeMap::new())));
+        OrderedTree {
+            entries: Rc::clone(entries),
+            bytes: Rc::clone(&self.bytes),
+        }
+    }
+}
+
+impl StateBackend for InMemoryOrderedBackend {
+    fn new(_config: &BackendConfig) -> Self {
+        InMemoryOrderedBackend::with_trees(HashMap::new())
+    }
+
+    fn get_managed_count(&self, name: &str) -> Box<ManagedCount> {
+        Box::new(InMemoryOrderedManagedCount::new(self.tree(name)))
+    }
+
+    fn get_managed_value<V: StateValue>(&self, name: &str) -> Box<ManagedValue<V>> {
+        Box::new(InMemoryOrderedManagedValue::new(self.tree(name)))
+    }
+
+    fn get_managed_map_with_codecs<K, V, KC, VC>(&self, name: &str) -> Box<ManagedMap<K, V>>
+    where
+        K: StateKey,
+        V: StateValue,
+        KC: Codec<K>,
+        VC: Codec<V>,
+    {
+        Box::new(InMemoryOrderedManagedMap::<K, V, KC, VC>::with_codecs(
+            self.tree(name),
+        ))
+    }
+
+    fn checkpoint(&self, directory: &Path) -> Result<Snapshot, CheckpointError> {
+        let mut dump: Vec<(&String, Vec<(Vec<u8>, Vec<u8>)>)> = Vec::new();
+        let trees = self.trees.borrow();
+        for (name, entries) in trees.iter() {
+            let entries = entries.borrow();
+            dump.push((name, entries.iter().map(|(key, value)| (key.clone(), value.clone())).collect()));
+        }
+        dump.sort();
+        fs::create_dir_all(directory)
+            .map_err(|error| CheckpointError::Io(directory.to_owned(), error))?;
+        let path = directory.join(DUMP_FILE);
+        fs::write(&path, bincode::serialize(&dump).unwrap())
+            .map_err(|error| CheckpointError::Io(path, error))?;
+        Ok(Snapshot {
+            backend: "in_memory_ordered".to_owned(),
+            path: directory.to_owned(),
+            token: None,
+            session: None,
+        })
+    }
+
+    fn restore(_config: &BackendConfig, snapshot: &Snapshot) -> Result<Self, CheckpointError> {
+        if snapshot.backend != "in_memory_ordered" {
+            let error = format!("in_memory_ordered cannot restore a {} snapshot", snapshot.backend);
+            return Err(CheckpointError::Backend(error));
+        }
+        let path = snapshot.path.join(DUMP_FILE);
+        let dump = fs::read(&path).map_err(|error| CheckpointError::Io(path.clone(), error))?;
+        let dump: Vec<(String, Vec<(Vec<u8>, Vec<u8>)>)> = bincode::deserialize(&dump)
+            .map_err(|error| {
+                CheckpointError::Backend(format!("cannot read {}: {}", path.display(), error))
+            })?;
+        let trees = dump
+            .into_iter()
+            .map(|(name, entries)| (name, entries.into_iter().collect()))
+            .collect();
+        Ok(InMemoryOrderedBackend::with_trees(trees))
+    }
+
+    fn memory_usage(&self) -> Option<u64> {
+        Some(self.bytes.get())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InMemoryOrderedBackend;
+    use crate::config::BackendConfig;
+    use crate::primitives::{IterDirection, KeyRange};
+    use crate::{StateBackend, StateHandle};
+    use std::rc::Rc;
+
+    #[test]
+    fn counts_encoded_bytes() {
+        let handle = StateHandle::new(
+            Rc::new(InMemoryOrderedBackend::new(&BackendConfig::default())),
+            "",
+        );
+        assert_eq!(handle.memory_usage(), Some(0));
+
+        let mut map = handle.get_managed_map::<u64, u64>("map");
+        map.insert(1, 1);
+        map.insert(2, 2);
+        assert_eq!(handle.memory_usage(), Some(32));
+        map.insert(1, 3);
+        assert_eq!(handle.memory_usage(), Some(32));
+        map.remove(&2);
+        assert_eq!(handle.memory_usage(), Some(16));
+
+        let mut value = handle.get_managed_value::<String>("value");
+        value.set("value".to_owned());
+        assert_eq!(handle.memory_usage(), Some(16 + 13));
+        value.take();
+        assert_eq!(handle.memory_usage(), Some(16));
+    }
+
+    #[test]
+    fn iterates_while_the_map_changes() {
+        let handle = StateHandle::new(
+            Rc::new(InMemoryOrderedBackend::new(&BackendConfig::default())),
+            "",
+        );
+        let mut map = handle.get_managed_map::<u64, u64>("map");
+        let mut alias = handle.get_managed_map::<u64, u64>("map");
+        for key in 0u64..4 {
+            map.insert(key.to_be(), key);
+        }
+
+        let mut iter = map.iter(KeyRange::All, IterDirection::Reverse).unwrap();
+        assert_eq!(iter.next().map(|(_, value)| *value), Some(3));
+        alias.remove(&2u64.to_be());
+        alias.insert(5u64.to_be(), 5);
+        let rest: Vec<u64> = iter.map(|(_, value)| *value).collect();
+        assert_eq!(rest, vec![1, 0]);
+    }
+}
@@ -1,17 +1,48 @@
+pub use crate::cache::CachedBackend;
+#[cfg(feature = "faster")]
 pub use faster::FASTERBackend;
+#[cfg(feature = "faster")]
 pub use faster_in_memory::FASTERInMemoryBackend;
+#[cfg(feature = "faster")]
 pub use faster_node::FASTERNodeBackend;
+#[cfg(feature = "in-memory")]
 pub use in_memory::InMemoryBackend;
+#[cfg(feature = "in-memory")]
 pub use in_memory_native::InMemoryNativeBackend;
+#[cfg(feature = "in-memory")]
+pub use in_memory_ordered::InMemoryOrderedBackend;
+#[cfg(feature = "rocksdb")]
 pub use self::rocksdb::RocksDBBackend;
+#[cfg(feature = "rocksdb")]
 pub use rocksdbmerge::RocksDBMergeBackend;
-pub use rocksdbmerge2::RocksDBMergeBackend2;
+#[cfg(feature = "rocksdb")]
+#[allow(deprecated)]
+pub use rocksdbmerge::RocksDBMergeBackend2;
+#[cfg(feature = "sled")]
+pub use self::sled::SledBackend;
 
+#[cfg(all(
+    test,
+    any(feature = "faster", feature = "in-memory", feature = "rocksdb", feature = "sled")
+))]
+mod conformance;
+#[cfg(feature = "faster")]
 mod faster;
+#[cfg(feature = "faster")]
+mod faster_cell;
+#[cfg(feature = "faster")]
 mod faster_in_memory;
+#[cfg(feature = "faster")]
 mod faster_node;
+#[cfg(feature = "in-memory")]
 mod in_memory;
+#[cfg(feature = "in-memory")]
 mod in_memory_native;
+#[cfg(feature = "in-memory")]
+mod in_memory_ordered;
+#[cfg(feature = "rocksdb")]
 mod rocksdb;
+#[cfg(feature = "rocksdb")]
 mod rocksdbmerge;
-mod rocksdbmerge2;
+#[cfg(feature = "sled")]
+mod sled;
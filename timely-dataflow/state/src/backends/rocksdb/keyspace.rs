@@ -0,0 +1,204 @@
+//! Where a RocksDB backend keeps one piece of managed state.
+//!
+//! State lives in the default column family unless a column family of the
+//! same name was created for it, in which case every read and write of that
+//! state goes to the column family instead.
+
+use super::merge::set_merge_operator;
+use crate::column_family::{ColumnFamilyOptions, CompactionStyle};
+use rocksdb::{
+    BlockBasedOptions, ColumnFamily, DBCompactionStyle, DBIterator, DBVector, Error,
+    IteratorMode, Options, WriteBatch, DB,
+};
+use std::rc::Rc;
+
+#[derive(Clone)]
+pub(crate) struct Keyspace {
+    db: Rc<DB>,
+    column_family: Option<String>,
+}
+
+impl Keyspace {
+    /// The keyspace of the state called `name`.
+    pub fn of(db: &Rc<DB>, name: &str) -> Self {
+        let column_family = db.cf_handle(name).map(|_| name.to_owned());
+        Keyspace {
+            db: Rc::clone(db),
+            column_family,
+        }
+    }
+
+    fn column_family(&self) -> Option<ColumnFamily> {
+        self.column_family.as_ref().map(|name| {
+            self.db
+                .cf_handle(name)
+                .expect("Column family used after it was dropped")
+        })
+    }
+
+    pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Result<Option<DBVector>, Error> {
+        match self.column_family() {
+            Some(column_family) => self.db.get_cf(column_family, key),
+            None => self.db.get(key),
+        }
+    }
+
+    // Writes skip the WAL, as state is not recovered from it
+    pub fn put<K: AsRef<[u8]>, V: AsRef<[u8]>>(&self, key: K, value: V) -> Result<(), Error> {
+        let mut batch = WriteBatch::default();
+        match self.column_family() {
+            Some(column_family) => batch.put_cf(column_family, key, value)?,
+            None => batch.put(key, value)?,
+        }
+        self.db.write_without_wal(batch)
+    }
+
+    // Lookups go one at a time, as these bindings do not expose RocksDB's MultiGet
+    pub fn multi_get<K: AsRef<[u8]>>(&self, keys: &[K]) -> Result<Vec<Option<DBVector>>, Error> {
+        keys.iter().map(|key| self.get(key)).collect()
+    }
+
+    /// Puts every entry in a single write batch, skipping the WAL like `put`.
+    pub fn put_batch<I>(&self, entries: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+    {
+        let column_family = self.column_family();
+        let mut batch = WriteBatch::default();
+        for (key, value) in entries {
+            match column_family {
+                Some(column_family) => batch.put_cf(column_family, key, value)?,
+                None => batch.put(key, value)?,
+            }
+        }
+        self.db.write_without_wal(batch)
+    }
+
+    /// Merges every operand in a single write batch, skipping the WAL like `put`.
+    pub fn merge_batch<I>(&self, operands: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+    {
+        let column_family = self.column_family();
+        let mut batch = WriteBatch::default();
+        for (key, operand) in operands {
+            match column_family {
+                Some(column_family) => batch.merge_cf(column_family, key, operand)?,
+                None => batch.merge(key, operand)?,
+            }
+        }
+        self.db.write_without_wal(batch)
+    }
+
+    pub fn merge<K: AsRef<[u8]>, V: AsRef<[u8]>>(&self, key: K, value: V) -> Result<(), Error> {
+        match self.column_family() {
+            Some(column_family) => self.db.merge_cf(column_family, key, value),
+            None => self.db.merge(key, value),
+        }
+    }
+
+    pub fn delete<K: AsRef<[u8]>>(&self, key: K) -> Result<(), Error> {
+        match self.column_family() {
+            Some(column_family) => self.db.delete_cf(column_family, key),
+            None => self.db.delete(key),
+        }
+    }
+
+    pub fn iterator(&self, mode: IteratorMode) -> DBIterator {
+        match self.column_family() {
+            Some(column_family) => self.db.iterator_cf(column_family, mode).unwrap(),
+            None => self.db.iterator(mode),
+        }
+    }
+}
+
+impl From<Rc<DB>> for Keyspace {
+    fn from(db: Rc<DB>) -> Self {
+        Keyspace {
+            db,
+            column_family: None,
+        }
+    }
+}
+
+// Creates the column family 'name' unless it already exists
+pub(crate) fn create_column_family(db: &DB, name: &str, options: &ColumnFamilyOptions) {
+    if db.cf_handle(name).is_none() {
+        db.create_cf(name, &column_family_options(options))
+            .expect("Unable to create column family");
+    }
+}
+
+pub(crate) fn drop_column_family(db: &DB, name: &str) {
+    if db.cf_handle(name).is_some() {
+        db.drop_cf(name).expect("Unable to drop column family");
+    }
+}
+
+pub(crate) fn column_family_options(options: &ColumnFamilyOptions) -> Options {
+    let mut block_based_options = BlockBasedOptions::default();
+    if let Some(block_size) = options.block_size {
+        block_based_options.set_block_size(block_size);
+    }
+    if let Some(block_cache_size) = options.block_cache_size {
+        block_based_options.set_lru_cache(block_cache_size);
+    }
+    if let Some(bits) = options.bloom_filter_bits {
+        block_based_options.set_bloom_filter(bits, true);
+    }
+    let mut column_family_options = Options::default();
+    set_merge_operator(&mut column_family_options);
+    column_family_options.set_block_based_table_factory(&block_based_options);
+    if let Some(write_buffer_size) = options.write_buffer_size {
+        column_family_options.set_write_buffer_size(write_buffer_size);
+    }
+    if let Some(compaction_style) = options.compaction_style {
+        column_family_options.set_compaction_style(match compaction_style {
+            CompactionStyle::Level => DBCompactionStyle::Level,
+            CompactionStyle::Universal => DBCompactionStyle::Universal,
+            CompactionStyle::Fifo => DBCompactionStyle::Fifo,
+        });
+    }
+    column_family_options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{create_column_family, drop_column_family, Keyspace};
+    use crate::backends::rocksdb::merge::set_merge_operator;
+    use crate::column_family::ColumnFamilyOptions;
+    use rocksdb::{Options, DB};
+    use std::rc::Rc;
+    use tempfile::TempDir;
+
+    #[test]
+    fn column_family_is_separate_and_droppable() {
+        let directory = TempDir::new().unwrap();
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        set_merge_operator(&mut options);
+        let db = DB::open(&options, directory.path()).expect("Unable to instantiate RocksDB");
+        let db = Rc::new(db);
+
+        create_column_family(&db, "state", &ColumnFamilyOptions::default());
+        let isolated = Keyspace::of(&db, "state");
+        let default = Keyspace::of(&db, "other");
+        isolated.put(b"state", b"isolated").unwrap();
+        default.put(b"other", b"default").unwrap();
+        assert_eq!(&*isolated.get(b"state").unwrap().unwrap(), b"isolated");
+        assert!(default.get(b"state").unwrap().is_none());
+        assert!(isolated.get(b"other").unwrap().is_none());
+
+        isolated
+            .put_batch(vec![(b"a".to_vec(), b"1".to_vec()), (b"b".to_vec(), b"2".to_vec())])
+            .unwrap();
+        let values = isolated.multi_get(&[&b"a"[..], &b"c"[..], &b"b"[..]]).unwrap();
+        let values: Vec<_> = values.iter().map(|value| value.as_ref().map(|value| value.to_vec())).collect();
+        assert_eq!(values, vec![Some(b"1".to_vec()), None, Some(b"2".to_vec())]);
+        assert!(default.get(b"a").unwrap().is_none());
+
+        drop_column_family(&db, "state");
+        assert!(Keyspace::of(&db, "state").get(b"state").unwrap().is_none());
+        assert_eq!(&*default.get(b"other").unwrap().unwrap(), b"default");
+    }
+}
@@ -1,30 +1,32 @@
+use super::keyspace::Keyspace;
+use super::merge::register;
+use crate::codec::{Bincode, Codec};
+use crate::merge::MergeOperator;
 use crate::primitives::ManagedCount;
-use rocksdb::{WriteBatch, DB};
-use std::rc::Rc;
 
 pub struct RocksDBManagedCount {
-    db: Rc<DB>,
+    db: Keyspace,
     name: Vec<u8>,
 }
 
 impl RocksDBManagedCount {
-    pub fn new(db: Rc<DB>, name: &AsRef<str>) -> Self {
+    pub fn new(db: impl Into<Keyspace>, name: &AsRef<str>) -> Self {
+        let name = bincode::serialize(name.as_ref()).unwrap();
+        register::<i64, Bincode>(&name, MergeOperator::sum());
         RocksDBManagedCount {
-            db,
-            name: bincode::serialize(name.as_ref()).unwrap(),
+            db: db.into(),
+            name,
         }
     }
 }
 
 impl ManagedCount for RocksDBManagedCount {
     fn decrease(&mut self, amount: i64) {
-        self.db
-            .merge(&self.name, bincode::serialize(&(-amount)).unwrap());
+        self.db.merge(&self.name, <Bincode as Codec<i64>>::encoded(&(-amount)));
     }
 
     fn increase(&mut self, amount: i64) {
-        self.db
-            .merge(&self.name, bincode::serialize(&amount).unwrap());
+        self.db.merge(&self.name, <Bincode as Codec<i64>>::encoded(&amount));
     }
 
     fn get(&self) -> i64 {
@@ -39,15 +41,13 @@ impl ManagedCount for RocksDBManagedCount {
     }
 
     fn set(&mut self, value: i64) {
-        let mut batch = WriteBatch::default();
-        batch.put(&self.name, bincode::serialize(&value).unwrap());
-        self.db.write_without_wal(batch);
+        self.db.put(&self.name, bincode::serialize(&value).unwrap());
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::super::merge_numbers;
+    use super::super::merge::set_merge_operator;
     use super::RocksDBManagedCount;
     use crate::primitives::ManagedCount;
     use rocksdb::{Options, DB};
@@ -59,9 +59,9 @@ mod tests {
         let directory = TempDir::new().unwrap();
         let mut options = Options::default();
         options.create_if_missing(true);
-        options.set_merge_operator("merge_numbers", merge_numbers, Some(merge_numbers));
+        set_merge_operator(&mut options);
         let db = DB::open(&options, directory.path()).expect("Unable to instantiate RocksDB");
-        let count = RocksDBManagedCount::new(Rc::new(db), &"");
+        let count = RocksDBManagedCount::new(Rc::new(db), &"count");
         assert_eq!(count.get(), 0);
     }
 
@@ -70,9 +70,9 @@ mod tests {
         let directory = TempDir::new().unwrap();
         let mut options = Options::default();
         options.create_if_missing(true);
-        options.set_merge_operator("merge_numbers", merge_numbers, Some(merge_numbers));
+        set_merge_operator(&mut options);
         let db = DB::open(&options, directory.path()).expect("Unable to instantiate RocksDB");
-        let mut count = RocksDBManagedCount::new(Rc::new(db), &"");
+        let mut count = RocksDBManagedCount::new(Rc::new(db), &"count");
         count.increase(42);
         assert_eq!(count.get(), 42);
     }
@@ -82,9 +82,9 @@ mod tests {
         let directory = TempDir::new().unwrap();
         let mut options = Options::default();
         options.create_if_missing(true);
-        options.set_merge_operator("merge_numbers", merge_numbers, Some(merge_numbers));
+        set_merge_operator(&mut options);
         let db = DB::open(&options, directory.path()).expect("Unable to instantiate RocksDB");
-        let mut count = RocksDBManagedCount::new(Rc::new(db), &"");
+        let mut count = RocksDBManagedCount::new(Rc::new(db), &"count");
         count.decrease(42);
         assert_eq!(count.get(), -42);
     }
@@ -94,9 +94,9 @@ mod tests {
         let directory = TempDir::new().unwrap();
         let mut options = Options::default();
         options.create_if_missing(true);
-        options.set_merge_operator("merge_numbers", merge_numbers, Some(merge_numbers));
+        set_merge_operator(&mut options);
         let db = DB::open(&options, directory.path()).expect("Unable to instantiate RocksDB");
-        let mut count = RocksDBManagedCount::new(Rc::new(db), &"");
+        let mut count = RocksDBManagedCount::new(Rc::new(db), &"count");
         count.set(42);
         assert_eq!(count.get(), 42);
     }
@@ -1,42 +1,70 @@
-use crate::primitives::ManagedMap;
-use faster_rs::{FasterKey, FasterRmw, FasterValue};
-use rocksdb::{DBIterator, Direction, IteratorMode, WriteBatch, DB};
-use std::hash::Hash;
+use super::keyspace::Keyspace;
+use super::iterate;
+use super::merge::register;
+use crate::codec::{Bincode, Codec};
+use crate::merge::MergeOperator;
+use crate::primitives::{IterDirection, IterationError, KeyRange, ManagedMap, ManagedMapIter};
+use crate::{Rmw, StateKey, StateValue};
+use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::rc::Rc;
 
-pub struct RocksDBManagedMap<K, V> {
-    db: Rc<DB>,
+pub struct RocksDBManagedMap<K, V, KC = Bincode, VC = Bincode> {
+    db: Keyspace,
     name: Vec<u8>,
     key: PhantomData<K>,
     value: PhantomData<V>,
+    codecs: PhantomData<(KC, VC)>,
+    // Whether a merge operator is registered under this map's name
+    merges: bool,
 }
 
-impl<K: 'static + FasterKey + Hash + Eq + std::fmt::Debug, V: 'static + FasterValue + FasterRmw>
-    RocksDBManagedMap<K, V>
+#[cfg(test)]
+impl<K: StateKey, V: StateValue> RocksDBManagedMap<K, V> {
+    pub fn new(db: impl Into<Keyspace>, name: &AsRef<str>) -> Self {
+        RocksDBManagedMap::with_codecs(db, name)
+    }
+}
+
+impl<K, V, KC, VC> RocksDBManagedMap<K, V, KC, VC>
+where
+    K: StateKey,
+    V: StateValue,
+    KC: Codec<K>,
+    VC: Codec<V>,
 {
-    pub fn new(db: Rc<DB>, name: &AsRef<str>) -> Self {
+    pub fn with_codecs(db: impl Into<Keyspace>, name: &AsRef<str>) -> Self {
         RocksDBManagedMap {
-            db,
+            db: db.into(),
             name: bincode::serialize(name.as_ref()).unwrap(),
             key: PhantomData,
             value: PhantomData,
+            codecs: PhantomData,
+            merges: false,
         }
     }
 
+    // Makes 'rmw' a RocksDB merge with 'operator' instead of get+put
+    pub fn with_merge(mut self, operator: MergeOperator<V>) -> Self {
+        register::<V, VC>(&self.name, operator);
+        self.merges = true;
+        self
+    }
+
     fn prefix_key(&self, key: &K) -> Vec<u8> {
-        let mut serialised_key = bincode::serialize(key).unwrap();
         let mut prefixed_key = self.name.clone();
-        prefixed_key.append(&mut serialised_key);
+        KC::encode(key, &mut prefixed_key);
         prefixed_key
     }
 
 }
 
-impl<K, V> ManagedMap<K, V> for RocksDBManagedMap<K, V>
+impl<K, V, KC, VC> ManagedMap<K, V> for RocksDBManagedMap<K, V, KC, VC>
 where
-    K: 'static + FasterKey + Hash + Eq + std::fmt::Debug,
-    V: 'static + FasterValue + FasterRmw,
+    K: StateKey,
+    V: StateValue,
+    KC: Codec<K>,
+    VC: Codec<V>,
 {
     fn get_key_prefix_length(&self) -> usize {
         self.name.len()
@@ -44,9 +72,7 @@ where
 
     fn insert(&mut self, key: K, value: V) {
         let prefixed_key = self.prefix_key(&key);
-        let mut batch = WriteBatch::default();
-        batch.put(prefixed_key, bincode::serialize(&value).unwrap());
-        self.db.write_without_wal(batch);
+        self.db.put(prefixed_key, VC::encoded(&value));
     }
 
     fn get(&self, key: &K) -> Option<Rc<V>> {
@@ -54,36 +80,40 @@ where
         let db_vector = self.db.get(prefixed_key).unwrap();
         db_vector.map(|db_vector| {
             Rc::new(
-                bincode::deserialize(unsafe {
+                VC::decode(unsafe {
                     std::slice::from_raw_parts(db_vector.as_ptr(), db_vector.len())
-                })
-                .unwrap(),
+                }),
             )
         })
     }
 
     fn remove(&mut self, key: &K) -> Option<V> {
         let prefixed_key = self.prefix_key(key);
-        let db_vector = self.db.get(prefixed_key).unwrap();
+        let db_vector = self.db.get(&prefixed_key).unwrap();
         let result = db_vector.map(|db_vector| {
-            bincode::deserialize(unsafe {
+            VC::decode(unsafe {
                 std::slice::from_raw_parts(db_vector.as_ptr(), db_vector.len())
             })
-            .unwrap()
         });
-        self.db.delete(&self.name);
+        self.db.delete(prefixed_key);
         result
     }
 
-    // Updates values using get+put
-    fn rmw(&mut self, key: K, modification: V) {
+    // Updates values using get+put, unless the map was given a merge operator
+    fn rmw(&mut self, key: K, modification: V)
+    where
+        V: Rmw,
+    {
+        if self.merges {
+            ManagedMap::merge(self, key, modification);
+            return;
+        }
         let prefixed_key = self.prefix_key(&key);
         let db_vector = self.db.get(prefixed_key).unwrap();
         let result = db_vector.map(|db_vector| {
-            bincode::deserialize::<V>(unsafe {
+            VC::decode(unsafe {
                 std::slice::from_raw_parts(db_vector.as_ptr(), db_vector.len())
             })
-            .unwrap()
         });
         let modified = match result {
             Some(val) => val.rmw(modification),
@@ -92,47 +122,90 @@ where
         self.insert(key, modified);
     }
 
-    // Returns a forward DBIterator starting from 'key'
-    fn iter(&mut self, key: K) -> DBIterator {
+    fn merge(&mut self, key: K, modification: V) {
+        assert!(self.merges, "Only maps created with a merge operator can merge");
         let prefixed_key = self.prefix_key(&key);
+        self.db.merge(prefixed_key, VC::encoded(&modification)).unwrap();
+    }
+
+    fn multi_get(&self, keys: &[K]) -> Vec<Option<Rc<V>>> {
+        let prefixed_keys: Vec<Vec<u8>> = keys.iter().map(|key| self.prefix_key(key)).collect();
         self.db
-            .iterator(IteratorMode::From(&prefixed_key, Direction::Forward))
-    }
-
-    // Returns the next value of the given DBIterator
-    fn next(&mut self, mut iter: DBIterator) -> Option<(Rc<K>, Rc<V>)> {
-        if let Some((raw_key, raw_value)) = iter.next() {
-            let raw_key = &raw_key[self.name.len()..];  // Ignore prefix
-            let key = Rc::new(
-                bincode::deserialize(unsafe {
-                    std::slice::from_raw_parts(raw_key.as_ptr(), raw_key.len())
-                })
-                .unwrap(),
-            );
-            let value = Rc::new(
-                bincode::deserialize(unsafe {
-                    std::slice::from_raw_parts(raw_value.as_ptr(), raw_value.len())
-                })
-                .unwrap(),
-            );
-            return Some((key, value));
+            .multi_get(&prefixed_keys)
+            .unwrap()
+            .into_iter()
+            .map(|db_vector| db_vector.map(|db_vector| Rc::new(VC::decode(&db_vector))))
+            .collect()
+    }
+
+    fn insert_batch(&mut self, entries: Vec<(K, V)>) {
+        let encoded: Vec<_> = entries
+            .iter()
+            .map(|(key, value)| (self.prefix_key(key), VC::encoded(value)))
+            .collect();
+        self.db.put_batch(encoded).unwrap();
+    }
+
+    // Merges in one write batch, or without a merge operator reads every key
+    // at once and puts the merged values in one write batch
+    fn rmw_batch(&mut self, modifications: Vec<(K, V)>)
+    where
+        V: Rmw,
+    {
+        if self.merges {
+            let operands: Vec<_> = modifications
+                .iter()
+                .map(|(key, modification)| (self.prefix_key(key), VC::encoded(modification)))
+                .collect();
+            self.db.merge_batch(operands).unwrap();
+            return;
         }
-        None
+        let prefixed_keys: Vec<Vec<u8>> = modifications
+            .iter()
+            .map(|(key, _)| self.prefix_key(key))
+            .collect();
+        let stored = self.db.multi_get(&prefixed_keys).unwrap();
+        // Keys modified more than once in the batch merge into the value modified so far
+        let mut modified: HashMap<Vec<u8>, V> = HashMap::new();
+        for ((prefixed_key, (_, modification)), stored) in
+            prefixed_keys.into_iter().zip(modifications).zip(stored)
+        {
+            let current = modified
+                .remove(&prefixed_key)
+                .or_else(|| stored.map(|db_vector| VC::decode(&db_vector)));
+            let value = match current {
+                Some(value) => value.rmw(modification),
+                None => modification,
+            };
+            modified.insert(prefixed_key, value);
+        }
+        self.db
+            .put_batch(modified.into_iter().map(|(key, value)| (key, VC::encoded(&value))))
+            .unwrap();
+    }
+
+    // Iterates in serialised key order without leaving this map's prefix
+    fn iter(
+        &self,
+        range: KeyRange<K>,
+        direction: IterDirection,
+    ) -> Result<ManagedMapIter<'_, K, V>, IterationError> {
+        Ok(iterate::<K, V, KC, VC>(&self.db, &self.name, range, direction))
     }
 
     fn contains(&self, key: &K) -> bool {
         let prefixed_key = self.prefix_key(key);
-        self.db.get(prefixed_key).is_ok()
+        self.db.get(prefixed_key).unwrap().is_some()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::RocksDBManagedMap;
-    use crate::primitives::ManagedMap;
-    use bincode;
-    use rocksdb::{DBIterator, Options, DB};
-    use std::convert::TryFrom;
+    use crate::backends::conformance;
+    use crate::primitives::{IterDirection, KeyRange, ManagedMap};
+    use rocksdb::{Options, DB};
+    use std::ops::Bound;
     use std::rc::Rc;
     use tempfile::TempDir;
 
@@ -169,7 +242,7 @@ mod tests {
     }
 
     #[test]
-    fn map_remove_does_not_remove() {
+    fn map_remove() {
         let directory = TempDir::new().unwrap();
         let mut options = Options::default();
         options.create_if_missing(true);
@@ -181,7 +254,19 @@ mod tests {
 
         managed_map.insert(key, value);
         assert_eq!(managed_map.remove(&key), Some(value));
-        assert_eq!(managed_map.remove(&key), Some(value));
+        assert_eq!(managed_map.remove(&key), None);
+        assert_eq!(managed_map.get(&key), None);
+    }
+
+    #[test]
+    fn map_conforms() {
+        let directory = TempDir::new().unwrap();
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        let db = DB::open(&options, directory.path()).expect("Unable to instantiate RocksDB");
+        let mut managed_map = RocksDBManagedMap::new(Rc::new(db), &"");
+
+        conformance::check_managed_map(&mut managed_map);
     }
 
     #[test]
@@ -191,7 +276,6 @@ mod tests {
         options.create_if_missing(true);
         let db = DB::open(&options, directory.path()).expect("Unable to instantiate RocksDB");
         let mut managed_map = RocksDBManagedMap::new(Rc::new(db), &"");
-        let prefix_length = managed_map.get_key_prefix_length();
 
         let key: u64 = 1;
         let value: u64 = 1337;
@@ -200,39 +284,58 @@ mod tests {
         let key_3: u64 = 3;
         let value_3: u64 = 1333;
 
-        managed_map.insert(key, value);
-        managed_map.insert(key_2, value_2);
-        managed_map.insert(key_3, value_3);
-
-        // Get the iterator
-        let mut iter = managed_map.iter(key);
-
-        // TODO (john): Deserialization should be transparent. The following is ugly but ok for now.
-
-        // Start iterating
-        let (k, _) = iter.next().unwrap();
-        let kk = &k[prefix_length..];  // Ignore prefix
-        let ki: u64 = bincode::deserialize(unsafe {
-                    std::slice::from_raw_parts(kk.as_ptr(), kk.len())
-                }).unwrap();
-        assert_eq!(ki, key);
-        let (k2, _) = iter.next().unwrap();
-        let kk2 = &k2[prefix_length..];  // Ignore prefix
-        let ki2: u64 = bincode::deserialize(unsafe {
-                    std::slice::from_raw_parts(kk2.as_ptr(), kk.len())
-                }).unwrap();
-        assert_eq!(ki2, key_2);
-        let (k3, _) = iter.next().unwrap();
-        let kk3 = &k3[prefix_length..];  // Ignore prefix
-        let ki3: u64 = bincode::deserialize(unsafe {
-                    std::slice::from_raw_parts(kk3.as_ptr(), kk.len())
-                }).unwrap();
-        assert_eq!(ki3, key_3);
-
-        // Verify end of iteration
+        managed_map.insert(key.to_be(), value);
+        managed_map.insert(key_2.to_be(), value_2);
+        managed_map.insert(key_3.to_be(), value_3);
+
+        let mut iter = managed_map
+            .iter(KeyRange::from(key.to_be()), IterDirection::Forward)
+            .unwrap();
+        assert_eq!(iter.next(), Some((Rc::new(key.to_be()), Rc::new(value))));
+        assert_eq!(iter.next(), Some((Rc::new(key_2.to_be()), Rc::new(value_2))));
+        assert_eq!(iter.next(), Some((Rc::new(key_3.to_be()), Rc::new(value_3))));
         assert_eq!(iter.next(), None);
     }
 
+    #[test]
+    fn db_iterate_bounded_reverse() {
+        let directory = TempDir::new().unwrap();
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        let db = DB::open(&options, directory.path()).expect("Unable to instantiate RocksDB");
+        let mut managed_map = RocksDBManagedMap::new(Rc::new(db), &"");
+
+        for key in 0u64..10 {
+            managed_map.insert(key.to_be(), key * 10);
+        }
+
+        let range = KeyRange::Bounded(Bound::Excluded(2u64.to_be()), Bound::Included(5u64.to_be()));
+        let keys: Vec<u64> = managed_map
+            .iter(range, IterDirection::Reverse)
+            .unwrap()
+            .map(|(key, _)| u64::from_be(*key))
+            .collect();
+        assert_eq!(keys, vec![5, 4, 3]);
+    }
+
+    #[test]
+    fn db_iterate_stops_at_map_prefix() {
+        let directory = TempDir::new().unwrap();
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        let db = Rc::new(DB::open(&options, directory.path()).expect("Unable to instantiate RocksDB"));
+        let mut managed_map = RocksDBManagedMap::new(Rc::clone(&db), &"a");
+        let mut neighbour = RocksDBManagedMap::new(Rc::clone(&db), &"b");
+
+        managed_map.insert(1u64, 1u64);
+        neighbour.insert(2u64, 2u64);
+
+        let forward: Vec<_> = managed_map.iter(KeyRange::All, IterDirection::Forward).unwrap().collect();
+        let reverse: Vec<_> = neighbour.iter(KeyRange::All, IterDirection::Reverse).unwrap().collect();
+        assert_eq!(forward, vec![(Rc::new(1), Rc::new(1))]);
+        assert_eq!(reverse, vec![(Rc::new(2), Rc::new(2))]);
+    }
+
     #[test]
     fn db_contains() {
         let directory = TempDir::new().unwrap();
@@ -249,5 +352,6 @@ mod tests {
         let value_3: u64 = 1333;
         managed_map.insert(key, value);
         assert_eq!(managed_map.contains(&key), true);
+        assert_eq!(managed_map.contains(&key_2), false);
     }
 }
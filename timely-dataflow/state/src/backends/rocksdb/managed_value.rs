@@ -1,27 +1,25 @@
+use super::keyspace::Keyspace;
 use crate::primitives::ManagedValue;
-use faster_rs::{FasterRmw, FasterValue};
-use rocksdb::{WriteBatch, DB};
+use crate::{Rmw, StateValue};
 use std::rc::Rc;
 
 pub struct RocksDBManagedValue {
-    db: Rc<DB>,
+    db: Keyspace,
     name: Vec<u8>,
 }
 
 impl RocksDBManagedValue {
-    pub fn new(db: Rc<DB>, name: &AsRef<str>) -> Self {
+    pub fn new(db: impl Into<Keyspace>, name: &AsRef<str>) -> Self {
         RocksDBManagedValue {
-            db,
+            db: db.into(),
             name: bincode::serialize(name.as_ref()).unwrap(),
         }
     }
 }
 
-impl<V: 'static + FasterValue + FasterRmw> ManagedValue<V> for RocksDBManagedValue {
+impl<V: StateValue> ManagedValue<V> for RocksDBManagedValue {
     fn set(&mut self, value: V) {
-        let mut batch = WriteBatch::default();
-        batch.put(&self.name, bincode::serialize(&value).unwrap());
-        self.db.write_without_wal(batch);
+        self.db.put(&self.name, bincode::serialize(&value).unwrap());
     }
 
     fn get(&self) -> Option<Rc<V>> {
@@ -48,7 +46,10 @@ impl<V: 'static + FasterValue + FasterRmw> ManagedValue<V> for RocksDBManagedVal
         result
     }
 
-    fn rmw(&mut self, modification: V) {
+    fn rmw(&mut self, modification: V)
+    where
+        V: Rmw,
+    {
         let db_vector = self.db.get(&self.name).unwrap();
         let result = db_vector.map(|db_vector| {
             bincode::deserialize::<V>(unsafe {
@@ -0,0 +1,102 @@
+//! Applies typed `MergeOperator`s inside RocksDB.
+//!
+//! RocksDB only accepts plain functions as merge operators, and those are
+//! shared by every database in the process. Each backend therefore installs
+//! `full_merge` and `partial_merge`, which run the operator registered under
+//! the name of the state being merged. Every key of a piece of state starts
+//! with its serialised name, which is also the name of its column family if it
+//! has one, so operands are stored as plain encoded modifications.
+
+use crate::codec::Codec;
+use crate::merge::MergeOperator;
+use crate::StateValue;
+use rocksdb::{MergeOperands, Options};
+use std::any::TypeId;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+type RawMerge = Fn(Option<&[u8]>, &mut Iterator<Item = &[u8]>) -> Option<Vec<u8>> + Send + Sync;
+
+struct Registered {
+    // The value type and codec the operator decodes operands with
+    types: TypeId,
+    merge: Arc<RawMerge>,
+}
+
+// Operators by the serialised name of the state they merge. Operators stay
+// registered once their state is dropped, as RocksDB may still merge its
+// operands, but registering a name again replaces its operator
+static REGISTRY: RwLock<BTreeMap<Vec<u8>, Registered>> = RwLock::new(BTreeMap::new());
+
+/// Registers `operator` for the state whose serialised name is `name` and whose
+/// values are encoded with `VC`.
+///
+/// The workers of a process may each register the same state's operator, but a
+/// name cannot be merged as values of two different types in one process.
+pub(crate) fn register<V, VC>(name: &[u8], operator: MergeOperator<V>)
+where
+    V: StateValue,
+    VC: Codec<V>,
+{
+    let raw = move |existing: Option<&[u8]>, operands: &mut Iterator<Item = &[u8]>| {
+        let mut merged = existing.map(VC::decode);
+        for operand in operands {
+            let modification = VC::decode(operand);
+            merged = Some(match merged {
+                Some(value) => operator.merge(value, modification),
+                None => modification,
+            });
+        }
+        merged.map(|merged| VC::encoded(&merged))
+    };
+    let types = TypeId::of::<(V, VC)>();
+    let mut registry = REGISTRY.write().unwrap();
+    if let Some(registered) = registry.get(name) {
+        assert!(
+            registered.types == types,
+            "State {:?} is already merged as values of another type",
+            String::from_utf8_lossy(name)
+        );
+    }
+    registry.insert(
+        name.to_vec(),
+        Registered {
+            types,
+            merge: Arc::new(raw),
+        },
+    );
+}
+
+// The operator registered for the state that 'key' belongs to
+fn operator(key: &[u8]) -> Option<Arc<RawMerge>> {
+    let length: u64 = bincode::deserialize(key.get(..8)?).ok()?;
+    let name = key.get(..8 + length as usize)?;
+    let registry = REGISTRY.read().unwrap();
+    registry.get(name).map(|registered| Arc::clone(&registered.merge))
+}
+
+// Without an operator for the key's state, the merge fails and RocksDB reports it.
+// Restored databases only compact once their maps have registered their operators
+pub(crate) fn full_merge(
+    key: &[u8],
+    existing: Option<&[u8]>,
+    mut operands: &mut MergeOperands,
+) -> Option<Vec<u8>> {
+    let operator = operator(key)?;
+    operator(existing, &mut operands).or_else(|| existing.map(|value| value.to_vec()))
+}
+
+// Combines operands into a single operand, which merges like the operands did
+pub(crate) fn partial_merge(
+    key: &[u8],
+    _existing: Option<&[u8]>,
+    mut operands: &mut MergeOperands,
+) -> Option<Vec<u8>> {
+    let operator = operator(key)?;
+    operator(None, &mut operands)
+}
+
+/// Installs the merge operator that every RocksDB backend relies on.
+pub(crate) fn set_merge_operator(options: &mut Options) {
+    options.set_merge_operator("typed_merge", full_merge, Some(partial_merge));
+}
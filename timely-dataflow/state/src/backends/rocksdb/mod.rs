@@ -1,121 +1,283 @@
 extern crate rocksdb;
 use self::rocksdb::BlockBasedOptions;
-use crate::primitives::{ManagedCount, ManagedMap, ManagedValue};
-use crate::StateBackend;
-use faster_rs::{FasterKey, FasterRmw, FasterValue};
+use crate::checkpoint::{CheckpointError, Snapshot};
+use crate::codec::Codec;
+use crate::column_family::ColumnFamilyOptions;
+use crate::config::{BackendConfig, RocksDBConfig};
+use crate::merge::MergeOperator;
+use crate::primitives::{
+    IterDirection, KeyRange, ManagedCount, ManagedMap, ManagedMapIter, ManagedValue,
+    SerialisedRange,
+};
+use crate::{StateBackend, StateKey, StateValue};
+use keyspace::{column_family_options, create_column_family, drop_column_family, Keyspace};
 use managed_count::RocksDBManagedCount;
 use managed_map::RocksDBManagedMap;
 use managed_value::RocksDBManagedValue;
-use rocksdb::MergeOperands;
-use rocksdb::{Options, DB};
-use std::hash::Hash;
+use merge::set_merge_operator;
+use rocksdb::checkpoint::Checkpoint;
+use rocksdb::{ColumnFamilyDescriptor, Direction, IteratorMode, Options, DB};
+use std::cell::Cell;
+use std::fs;
+use std::path::Path;
 use std::rc::Rc;
 use tempfile::TempDir;
-use std::fs::File;
-use std::io::BufRead;
-use std::io::BufReader;
-use std::iter::FromIterator;
-use std::path::Path;
 
+pub(crate) mod keyspace;
 mod managed_count;
 mod managed_map;
 mod managed_value;
+pub(crate) mod merge;
 
 pub struct RocksDBBackend {
     db: Rc<DB>,
+    // Removed along with the backend; None when the caller chose the directory
+    _directory: Option<TempDir>,
+    // Whether the database was restored and does not compact until the first advance
+    restored: Cell<bool>,
+}
+
+// Creates a RocksDB checkpoint of 'db', which includes every column family.
+// Merge operands are compacted away first, so that a restored database holds
+// plain values until its maps are reopened and register their operators
+pub(crate) fn checkpoint(
+    db: &DB,
+    directory: &Path,
+    backend: &str,
+) -> Result<Snapshot, CheckpointError> {
+    let error = |error: rocksdb::Error| CheckpointError::Backend(error.to_string());
+    db.compact_range(None::<&[u8]>, None::<&[u8]>);
+    for name in DB::list_cf(&Options::default(), db.path()).map_err(error)? {
+        if let Some(column_family) = db.cf_handle(&name) {
+            db.compact_range_cf(column_family, None::<&[u8]>, None::<&[u8]>);
+        }
+    }
+    Checkpoint::new(db)
+        .map_err(error)?
+        .create_checkpoint(directory)
+        .map_err(error)?;
+    Ok(Snapshot {
+        backend: backend.to_owned(),
+        path: directory.to_owned(),
+        token: None,
+        session: None,
+    })
 }
 
-fn merge_numbers(
-    new_key: &[u8],
-    existing_val: Option<&[u8]>,
-    operands: &mut MergeOperands,
-) -> Option<Vec<u8>> {
-    let mut result: i64 = 0;
-    if let Some(val) = existing_val {
-        result += bincode::deserialize::<i64>(val).unwrap();
+// Opens a copy of the RocksDB checkpoint in 'snapshot' inside 'directory',
+// so that the checkpoint itself can be restored again. The copy lasts as long
+// as the returned directory.
+//
+// Merge operators are registered by the maps that use them, which are only
+// reopened after the database, and a compaction that met operands of a state
+// without an operator would put the database in an error state. The copy is
+// therefore opened without automatic compactions, which `resume_compactions`
+// turns back on once its maps are open again
+pub(crate) fn restore(
+    directory: &Path,
+    snapshot: &Snapshot,
+    backend: &str,
+    mut options: Options,
+) -> Result<(DB, TempDir), CheckpointError> {
+    if snapshot.backend != backend {
+        let error = format!("{} cannot restore a {} snapshot", backend, snapshot.backend);
+        return Err(CheckpointError::Backend(error));
     }
-    for operand in operands {
-        result += bincode::deserialize::<i64>(operand).unwrap();
+    let copy = TempDir::new_in(directory)
+        .map_err(|error| CheckpointError::Io(directory.to_owned(), error))?;
+    let entries = fs::read_dir(&snapshot.path)
+        .map_err(|error| CheckpointError::Io(snapshot.path.clone(), error))?;
+    for entry in entries {
+        let path = entry
+            .map_err(|error| CheckpointError::Io(snapshot.path.clone(), error))?
+            .path();
+        let file_name = path.file_name().expect("Checkpoint entry without a name");
+        fs::copy(&path, copy.path().join(file_name)).map_err(|error| CheckpointError::Io(path.clone(), error))?;
     }
-    Some(bincode::serialize(&result).unwrap())
+    let error = |error: rocksdb::Error| CheckpointError::Backend(error.to_string());
+    options.set_disable_auto_compactions(true);
+    let column_families = DB::list_cf(&options, copy.path())
+        .map_err(error)?
+        .into_iter()
+        .filter(|name| name != "default")
+        .map(|name| {
+            let mut options = column_family_options(&ColumnFamilyOptions::default());
+            options.set_disable_auto_compactions(true);
+            ColumnFamilyDescriptor::new(name, options)
+        })
+        .collect();
+    let db = DB::open_cf_descriptors(&options, copy.path(), column_families).map_err(error)?;
+    Ok((db, copy))
 }
 
-// read RocksDB configuration from a file
-fn read_rocksdb_config() -> (usize, usize, usize, u64) {
-    let config_path = String::from("rocksdb.config");
-    let file = File::open(config_path).expect("Config file not found or cannot be opened");
-    let content = BufReader::new(&file);
-    let mut blocksize = 0;
-    let mut lrusize = 0;
-    let mut write_buffer_size = 0;
-    let mut hash_index_size = 0;
-    for line in content.lines() {
-        let line = line.expect("Could not read the line");
-        let line = line.trim();
-        if line.starts_with("#") || line.starts_with(";") || line.is_empty() {
-            continue;
-        }
-        let tokens = Vec::from_iter(line.split_whitespace());
-        let name = tokens.first().unwrap();
-        let tokens = tokens.get(1..).unwrap();
-        let tokens = tokens.iter().filter(|t| !t.starts_with("="));
-        let tokens = tokens.take_while(|t| !t.starts_with("#") && !t.starts_with(";"));
-        let mut parameters = String::new();
-        tokens.for_each(|t| { parameters.push_str(t); parameters.push(' '); });
-        let parameters = parameters.split(',').map(|s| s.trim());
-        let parameters: Vec<String> = parameters.map(|s| s.to_string()).collect();
-
-        // Setting the config parameters
-        match name.to_lowercase().as_str() {
-            "blocksize" => blocksize = parameters.get(0).unwrap().parse::<usize>().expect("couldn't parse tablesize"),
-            "lrusize" => lrusize = parameters.get(0).unwrap().parse::<usize>().expect("couldn't parse logsize"),
-            "writebuffersize" => write_buffer_size = parameters.get(0).unwrap().parse::<usize>().expect("couldn't parse writebuffersize"),
-            "hashindexsize" => hash_index_size = parameters.get(0).unwrap().parse::<u64>().expect("couldn't parse hashindexsize"),
-            _ => (),
+// Turns automatic compactions of a database that `restore` opened back on
+pub(crate) fn resume_compactions(db: &DB) {
+    let enable = [("disable_auto_compactions", "false")];
+    db.set_options(&enable).expect("Unable to resume RocksDB compactions");
+    for name in DB::list_cf(&Options::default(), db.path()).expect("Unable to list column families") {
+        if let Some(column_family) = db.cf_handle(&name) {
+            db.set_options_cf(column_family, &enable)
+                .expect("Unable to resume RocksDB compactions");
         }
     }
-    (blocksize, lrusize, write_buffer_size, hash_index_size)
 }
 
-impl StateBackend for RocksDBBackend {
-    fn new() -> Self {
-        let directory = TempDir::new_in(".").expect("Unable to create directory for RocksDB");
-        let mut block_based_options = BlockBasedOptions::default();
-        let (block_size, lru_cache, write_buffer_size, hash_index_size) = read_rocksdb_config();
-        println!("Configuring a RocksDB instance with block size {:?}, cache {:?}, write buffer size {:?}, and hash index size {:?}",
-                 block_size, lru_cache, write_buffer_size, hash_index_size);
+// Iterates over the keys of the map called 'name' that fall in 'range'
+pub(crate) fn iterate<'a, K, V, KC, VC>(
+    db: &'a Keyspace,
+    name: &[u8],
+    range: KeyRange<K>,
+    direction: IterDirection,
+) -> ManagedMapIter<'a, K, V>
+where
+    K: 'static,
+    V: 'static,
+    KC: Codec<K>,
+    VC: Codec<V>,
+{
+    let range = Rc::new(SerialisedRange::new::<K, KC>(name, &range));
+    let iter = match (direction, range.seek_key(direction)) {
+        (IterDirection::Forward, Some(key)) => {
+            db.iterator(IteratorMode::From(key, Direction::Forward))
+        }
+        (IterDirection::Reverse, Some(key)) => {
+            db.iterator(IteratorMode::From(key, Direction::Reverse))
+        }
+        (IterDirection::Forward, None) => db.iterator(IteratorMode::Start),
+        (IterDirection::Reverse, None) => db.iterator(IteratorMode::End),
+    };
+    let (skip_range, take_range) = (Rc::clone(&range), range);
+    let name_length = name.len();
+    let in_range = iter
+        .skip_while(move |(key, _)| match direction {
+            IterDirection::Forward => !skip_range.above_lower(key),
+            IterDirection::Reverse => !skip_range.below_upper(key),
+        })
+        .take_while(move |(key, _)| take_range.contains(key));
+    Box::new(in_range.map(move |(raw_key, raw_value)| {
+        let key = KC::decode(&raw_key[name_length..]);
+        let value = VC::decode(&raw_value);
+        (Rc::new(key), Rc::new(value))
+    }))
+}
+
+// Tunes RocksDB as the 'rocksdb' configuration asks
+fn options(config: &RocksDBConfig) -> Options {
+    println!("Configuring a RocksDB instance with {:?}", config);
+    let mut block_based_options = BlockBasedOptions::default();
+    if let Some(block_size) = config.block_size {
         block_based_options.set_block_size(block_size);
-        block_based_options.set_lru_cache(lru_cache);
-        block_based_options.set_cache_index_and_filter_blocks(true);
-        let mut options = Options::default();
-        options.create_if_missing(true);
-        options.set_merge_operator("merge_numbers", merge_numbers, Some(merge_numbers));
-        options.set_use_fsync(false);
-        options.set_min_write_buffer_number(2);
-        options.set_max_write_buffer_number(4);
+    }
+    if let Some(lru_size) = config.lru_size {
+        block_based_options.set_lru_cache(lru_size);
+    }
+    block_based_options.set_cache_index_and_filter_blocks(true);
+    let mut options = Options::default();
+    options.create_if_missing(true);
+    set_merge_operator(&mut options);
+    options.set_use_fsync(false);
+    options.set_min_write_buffer_number(2);
+    options.set_max_write_buffer_number(4);
+    if let Some(write_buffer_size) = config.write_buffer_size {
         options.set_write_buffer_size(write_buffer_size);
-        options.set_block_based_table_factory(&block_based_options);
+    }
+    options.set_block_based_table_factory(&block_based_options);
+    if let Some(hash_index_size) = config.hash_index_size {
         options.optimize_for_point_lookup(hash_index_size);
-        let db = DB::open(&options, directory.into_path()).expect("Unable to instantiate RocksDB");
-        RocksDBBackend { db: Rc::new(db) }
+    }
+    options
+}
+
+impl StateBackend for RocksDBBackend {
+    fn new(config: &BackendConfig) -> Self {
+        let directory = TempDir::new_in(&config.directory).expect("Unable to create directory for RocksDB");
+        let db = DB::open(&options(&config.rocksdb), directory.path()).expect("Unable to instantiate RocksDB");
+        RocksDBBackend {
+            db: Rc::new(db),
+            _directory: Some(directory),
+            restored: Cell::new(false),
+        }
     }
 
     fn get_managed_count(&self, name: &str) -> Box<ManagedCount> {
-        Box::new(RocksDBManagedCount::new(Rc::clone(&self.db), &name))
+        Box::new(RocksDBManagedCount::new(Keyspace::of(&self.db, name), &name))
     }
 
-    fn get_managed_value<V: 'static + FasterValue + FasterRmw>(
+    fn get_managed_value<V: StateValue>(
         &self,
         name: &str,
     ) -> Box<ManagedValue<V>> {
-        Box::new(RocksDBManagedValue::new(Rc::clone(&self.db), &name))
+        Box::new(RocksDBManagedValue::new(Keyspace::of(&self.db, name), &name))
     }
 
-    fn get_managed_map<K, V>(&self, name: &str) -> Box<ManagedMap<K, V>>
+    fn get_managed_map_with_codecs<K, V, KC, VC>(&self, name: &str) -> Box<ManagedMap<K, V>>
     where
-        K: 'static + FasterKey + Hash + Eq + std::fmt::Debug,
-        V: 'static + FasterValue + FasterRmw,
+        K: StateKey,
+        V: StateValue,
+        KC: Codec<K>,
+        VC: Codec<V>,
     {
-        Box::new(RocksDBManagedMap::new(Rc::clone(&self.db), &name))
+        let keyspace = Keyspace::of(&self.db, name);
+        Box::new(RocksDBManagedMap::<K, V, KC, VC>::with_codecs(keyspace, &name))
+    }
+
+    fn get_managed_map_with_merge<K, V, KC, VC>(
+        &self,
+        name: &str,
+        merge: MergeOperator<V>,
+    ) -> Box<ManagedMap<K, V>>
+    where
+        K: StateKey,
+        V: StateValue,
+        KC: Codec<K>,
+        VC: Codec<V>,
+    {
+        let keyspace = Keyspace::of(&self.db, name);
+        Box::new(RocksDBManagedMap::<K, V, KC, VC>::with_codecs(keyspace, &name).with_merge(merge))
+    }
+
+    fn create_column_family(&self, name: &str, options: &ColumnFamilyOptions) {
+        create_column_family(&self.db, name, options);
+    }
+
+    fn drop_column_family(&self, name: &str) {
+        drop_column_family(&self.db, name);
+    }
+
+    fn checkpoint(&self, directory: &Path) -> Result<Snapshot, CheckpointError> {
+        checkpoint(&self.db, directory, "rocksdb")
+    }
+
+    fn restore(config: &BackendConfig, snapshot: &Snapshot) -> Result<Self, CheckpointError> {
+        let (db, directory) = restore(&config.directory, snapshot, "rocksdb", options(&config.rocksdb))?;
+        Ok(RocksDBBackend {
+            db: Rc::new(db),
+            _directory: Some(directory),
+            restored: Cell::new(true),
+        })
+    }
+
+    // Operators open their maps before they first advance, so by then every
+    // merge operator of the restored state is registered
+    fn advance(&self, _frontier: u64) {
+        if self.restored.replace(false) {
+            resume_compactions(&self.db);
+        }
+    }
+}
+
+impl RocksDBBackend {
+    // Opens a RocksDB instance in the given 'directory' with RocksDB's default
+    // options, instead of in the configured directory with the configured tuning
+    pub fn new_in(directory: &Path) -> Self {
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        set_merge_operator(&mut options);
+        let db = DB::open(&options, directory).expect("Unable to instantiate RocksDB");
+        RocksDBBackend {
+            db: Rc::new(db),
+            _directory: None,
+            restored: Cell::new(false),
+        }
     }
 }
@@ -1,16 +1,15 @@
+use crate::backends::rocksdb::keyspace::Keyspace;
 use crate::primitives::ManagedCount;
-use rocksdb::{WriteBatch, DB};
-use std::rc::Rc;
 
 pub struct RocksDBManagedCount {
-    db: Rc<DB>,
+    db: Keyspace,
     name: Vec<u8>,
 }
 
 impl RocksDBManagedCount {
-    pub fn new(db: Rc<DB>, name: &AsRef<str>) -> Self {
+    pub fn new(db: impl Into<Keyspace>, name: &AsRef<str>) -> Self {
         RocksDBManagedCount {
-            db,
+            db: db.into(),
             name: bincode::serialize(name.as_ref()).unwrap(),
         }
     }
@@ -39,9 +38,7 @@ impl ManagedCount for RocksDBManagedCount {
     }
 
     fn set(&mut self, value: i64) {
-        let mut batch = WriteBatch::default();
-        batch.put(&self.name, bincode::serialize(&value).unwrap());
-        self.db.write_without_wal(batch);
+        self.db.put(&self.name, bincode::serialize(&value).unwrap());
     }
 }
 
@@ -1,26 +1,65 @@
-use crate::primitives::ManagedMap;
-use faster_rs::{FasterKey, FasterRmw, FasterValue};
-use rocksdb::{WriteBatch, DB, DBIterator, Direction, IteratorMode};
-use std::hash::Hash;
+use crate::backends::rocksdb::iterate;
+use crate::backends::rocksdb::keyspace::Keyspace;
+use crate::backends::rocksdb::merge::register;
+use crate::codec::{Bincode, Codec};
+use crate::merge::MergeOperator;
+use crate::primitives::{IterDirection, IterationError, KeyRange, ManagedMap, ManagedMapIter};
+use crate::{Rmw, StateKey, StateValue};
+use std::marker::PhantomData;
 use std::rc::Rc;
 
-pub struct RocksDBManagedMap {
-    db: Rc<DB>,
+pub struct RocksDBManagedMap<KC = Bincode, VC = Bincode> {
+    db: Keyspace,
     name: Vec<u8>,
+    codecs: PhantomData<(KC, VC)>,
+    // Whether a merge operator is registered under this map's name
+    merges: bool,
 }
 
+#[cfg(test)]
 impl RocksDBManagedMap {
-    pub fn new(db: Rc<DB>, name: &AsRef<str>) -> Self {
+    pub fn new(db: impl Into<Keyspace>, name: &AsRef<str>) -> Self {
+        RocksDBManagedMap::with_codecs(db, name)
+    }
+}
+
+impl<KC, VC> RocksDBManagedMap<KC, VC> {
+    pub fn with_codecs(db: impl Into<Keyspace>, name: &AsRef<str>) -> Self {
         RocksDBManagedMap {
-            db,
+            db: db.into(),
             name: bincode::serialize(name.as_ref()).unwrap(),
+            codecs: PhantomData,
+            merges: false,
+        }
+    }
+
+    // Merges with 'operator' instead of the value type's 'Rmw' implementation
+    pub fn with_merge<V: StateValue>(mut self, operator: MergeOperator<V>) -> Self
+    where
+        VC: Codec<V>,
+    {
+        register::<V, VC>(&self.name, operator);
+        self.merges = true;
+        self
+    }
+
+    // Merges with the value type's 'Rmw' implementation, unless given an operator
+    fn merge_with_rmw<V: StateValue + Rmw>(&mut self)
+    where
+        VC: Codec<V>,
+    {
+        if !self.merges {
+            register::<V, VC>(&self.name, MergeOperator::rmw());
+            self.merges = true;
         }
     }
 
-    fn prefix_key<K: 'static + FasterKey + Hash + Eq + std::fmt::Debug>(&self, key: &K) -> Vec<u8> {
-        let mut serialised_key = bincode::serialize(key).unwrap();
+    fn prefix_key<K: StateKey>(&self, key: &K) -> Vec<u8>
+    where
+        KC: Codec<K>,
+    {
         let mut prefixed_key = self.name.clone();
-        prefixed_key.append(&mut serialised_key);
+        KC::encode(key, &mut prefixed_key);
         prefixed_key
     }
 
@@ -29,10 +68,12 @@ impl RocksDBManagedMap {
     }
 }
 
-impl<K, V> ManagedMap<K, V> for RocksDBManagedMap
+impl<K, V, KC, VC> ManagedMap<K, V> for RocksDBManagedMap<KC, VC>
 where
-    K: 'static + FasterKey + Hash + Eq + std::fmt::Debug,
-    V: 'static + FasterValue + FasterRmw,
+    K: StateKey,
+    V: StateValue,
+    KC: Codec<K>,
+    VC: Codec<V>,
 {
     fn get_key_prefix_length(&self) -> usize {
         self.name.len()
@@ -40,9 +81,7 @@ where
 
     fn insert(&mut self, key: K, value: V) {
         let prefixed_key = self.prefix_key(&key);
-        let mut batch = WriteBatch::default();
-        batch.put(prefixed_key, bincode::serialize(&value).unwrap());
-        self.db.write_without_wal(batch);
+        self.db.put(prefixed_key, VC::encoded(&value));
     }
 
     fn get(&self, key: &K) -> Option<Rc<V>> {
@@ -50,95 +89,102 @@ where
         let db_vector = self.db.get(prefixed_key).unwrap();
         db_vector.map(|db_vector| {
             Rc::new(
-                bincode::deserialize(unsafe {
+                VC::decode(unsafe {
                     std::slice::from_raw_parts(db_vector.as_ptr(), db_vector.len())
-                })
-                .unwrap(),
+                }),
             )
         })
     }
 
     fn remove(&mut self, key: &K) -> Option<V> {
         let prefixed_key = self.prefix_key(key);
-        let db_vector = self.db.get(prefixed_key).unwrap();
+        let db_vector = self.db.get(&prefixed_key).unwrap();
         let result = db_vector.map(|db_vector| {
-            bincode::deserialize(unsafe {
+            VC::decode(unsafe {
                 std::slice::from_raw_parts(db_vector.as_ptr(), db_vector.len())
             })
-            .unwrap()
         });
-        self.db.delete(&self.name);
+        self.db.delete(prefixed_key);
         result
     }
 
-    // Appends elements to vectors using 'merge'
-    fn rmw(&mut self, key: K, modification: V) {
-        let prefixed_key = self.prefix_key(&key);
-        self.db.merge(&prefixed_key, bincode::serialize(&modification).unwrap());
+    // Updates values using 'merge'
+    fn rmw(&mut self, key: K, modification: V)
+    where
+        V: Rmw,
+    {
+        self.merge_with_rmw::<V>();
+        ManagedMap::merge(self, key, modification);
     }
 
-    // Returns a forward DBIterator starting from 'key'
-    fn iter(&mut self, key: K) -> DBIterator {
+    fn merge(&mut self, key: K, modification: V) {
+        assert!(self.merges, "Only maps created with a merge operator can merge");
         let prefixed_key = self.prefix_key(&key);
-        self.db.iterator(IteratorMode::From(&prefixed_key, Direction::Forward))
-    }
-
-    // Returns the next value of the given DBIterator
-    fn next(&mut self, mut iter: DBIterator) -> Option<(Rc<K>,Rc<V>)> {
-        if let Some((raw_key, raw_value)) = iter.next() {
-            let key = Rc::new(
-                bincode::deserialize(unsafe {
-                    std::slice::from_raw_parts(raw_key.as_ptr(), raw_key.len())
-                })
-                .unwrap(),
-            );
-            let value = Rc::new(
-                bincode::deserialize(unsafe {
-                    std::slice::from_raw_parts(raw_value.as_ptr(), raw_value.len())
-                })
-                .unwrap(),
-            );
-            return Some((key, value));
-        }
-        None
+        self.db.merge(&prefixed_key, VC::encoded(&modification)).unwrap();
+    }
+
+    fn multi_get(&self, keys: &[K]) -> Vec<Option<Rc<V>>> {
+        let prefixed_keys: Vec<Vec<u8>> = keys.iter().map(|key| self.prefix_key(key)).collect();
+        self.db
+            .multi_get(&prefixed_keys)
+            .unwrap()
+            .into_iter()
+            .map(|db_vector| db_vector.map(|db_vector| Rc::new(VC::decode(&db_vector))))
+            .collect()
+    }
+
+    fn insert_batch(&mut self, entries: Vec<(K, V)>) {
+        let encoded: Vec<_> = entries
+            .iter()
+            .map(|(key, value)| (self.prefix_key(key), VC::encoded(value)))
+            .collect();
+        self.db.put_batch(encoded).unwrap();
+    }
+
+    // Merges every modification in one write batch
+    fn rmw_batch(&mut self, modifications: Vec<(K, V)>)
+    where
+        V: Rmw,
+    {
+        self.merge_with_rmw::<V>();
+        let operands: Vec<_> = modifications
+            .iter()
+            .map(|(key, modification)| (self.prefix_key(key), VC::encoded(modification)))
+            .collect();
+        self.db.merge_batch(operands).unwrap();
+    }
+
+    // Iterates in serialised key order without leaving this map's prefix
+    fn iter(
+        &self,
+        range: KeyRange<K>,
+        direction: IterDirection,
+    ) -> Result<ManagedMapIter<'_, K, V>, IterationError> {
+        Ok(iterate::<K, V, KC, VC>(&self.db, &self.name, range, direction))
     }
 
     fn contains(&self, key: &K) -> bool {
         let prefixed_key = self.prefix_key(key);
-        self.db.get(prefixed_key).is_ok()
+        self.db.get(prefixed_key).unwrap().is_some()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::RocksDBManagedMap;
+    use crate::backends::rocksdb::merge::set_merge_operator;
+    use crate::backends::conformance;
     use crate::primitives::ManagedMap;
     use rocksdb::{Options, DB};
     use std::rc::Rc;
     use tempfile::TempDir;
-    use rocksdb::MergeOperands;
-
-    fn merge_operator(
-        new_key: &[u8],
-        existing_val: Option<&[u8]>,
-        operands: &mut MergeOperands,
-    ) -> Option<Vec<u8>> {
-        let mut result: i64 = 0;
-        if let Some(val) = existing_val {
-            result += bincode::deserialize::<i64>(val).unwrap();
-        }
-        for operand in operands {
-            result += bincode::deserialize::<i64>(operand).unwrap();
-        }
-        Some(bincode::serialize(&result).unwrap())
-    }
 
     #[test]
     fn map_insert_get() {
         let directory = TempDir::new().unwrap();
         let mut options = Options::default();
         options.create_if_missing(true);
-        options.set_merge_operator("merge_operator", merge_operator, Some(merge_operator));
+        set_merge_operator(&mut options);
         let db = DB::open(&options, directory.path()).expect("Unable to instantiate RocksDB");
         let mut managed_map = RocksDBManagedMap::new(Rc::new(db), &"");
 
@@ -153,7 +199,7 @@ mod tests {
     fn map_rmw() {
         let directory = TempDir::new().unwrap();
         let mut options = Options::default();
-        options.set_merge_operator("merge_operator", merge_operator, Some(merge_operator));
+        set_merge_operator(&mut options);
         options.create_if_missing(true);
         let db = DB::open(&options, directory.path()).expect("Unable to instantiate RocksDB");
         let mut managed_map = RocksDBManagedMap::new(Rc::new(db), &"");
@@ -168,10 +214,10 @@ mod tests {
     }
 
     #[test]
-    fn map_remove_does_not_remove() {
+    fn map_remove() {
         let directory = TempDir::new().unwrap();
         let mut options = Options::default();
-        options.set_merge_operator("merge_operator", merge_operator, Some(merge_operator));
+        set_merge_operator(&mut options);
         options.create_if_missing(true);
         let db = DB::open(&options, directory.path()).expect("Unable to instantiate RocksDB");
         let mut managed_map = RocksDBManagedMap::new(Rc::new(db), &"");
@@ -181,6 +227,18 @@ mod tests {
 
         managed_map.insert(key, value);
         assert_eq!(managed_map.remove(&key), Some(value));
-        assert_eq!(managed_map.remove(&key), Some(value));
+        assert_eq!(managed_map.remove(&key), None::<u64>);
+        assert_eq!(managed_map.get(&key), None::<Rc<u64>>);
+    }
+
+    #[test]
+    fn map_conforms() {
+        let directory = TempDir::new().unwrap();
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        let db = DB::open(&options, directory.path()).expect("Unable to instantiate RocksDB");
+        let mut managed_map = RocksDBManagedMap::new(Rc::new(db), &"");
+
+        conformance::check_managed_map(&mut managed_map);
     }
 }
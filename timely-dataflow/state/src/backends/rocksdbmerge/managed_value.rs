@@ -1,27 +1,31 @@
+use crate::backends::rocksdb::keyspace::Keyspace;
+use crate::backends::rocksdb::merge::register;
+use crate::codec::{Bincode, Codec};
+use crate::merge::MergeOperator;
 use crate::primitives::ManagedValue;
-use faster_rs::{FasterRmw, FasterValue};
-use rocksdb::{WriteBatch, DB};
+use crate::{Rmw, StateValue};
 use std::rc::Rc;
 
 pub struct RocksDBManagedValue {
-    db: Rc<DB>,
+    db: Keyspace,
     name: Vec<u8>,
+    // Whether the value type's merge operator is registered under this value's name
+    merges: bool,
 }
 
 impl RocksDBManagedValue {
-    pub fn new(db: Rc<DB>, name: &AsRef<str>) -> Self {
+    pub fn new(db: impl Into<Keyspace>, name: &AsRef<str>) -> Self {
         RocksDBManagedValue {
-            db,
+            db: db.into(),
             name: bincode::serialize(name.as_ref()).unwrap(),
+            merges: false,
         }
     }
 }
 
-impl<V: 'static + FasterValue + FasterRmw> ManagedValue<V> for RocksDBManagedValue {
+impl<V: StateValue> ManagedValue<V> for RocksDBManagedValue {
     fn set(&mut self, value: V) {
-        let mut batch = WriteBatch::default();
-        batch.put(&self.name, bincode::serialize(&value).unwrap());
-        self.db.write_without_wal(batch);
+        self.db.put(&self.name, bincode::serialize(&value).unwrap());
     }
 
     fn get(&self) -> Option<Rc<V>> {
@@ -48,8 +52,15 @@ impl<V: 'static + FasterValue + FasterRmw> ManagedValue<V> for RocksDBManagedVal
         result
     }
 
-    fn rmw(&mut self, modification: V) {
-        self.db.merge(&self.name, bincode::serialize(&modification).unwrap());
+    fn rmw(&mut self, modification: V)
+    where
+        V: Rmw,
+    {
+        if !self.merges {
+            register::<V, Bincode>(&self.name, MergeOperator::rmw());
+            self.merges = true;
+        }
+        self.db.merge(&self.name, Bincode::encoded(&modification));
     }
 }
 
@@ -57,32 +68,18 @@ impl<V: 'static + FasterValue + FasterRmw> ManagedValue<V> for RocksDBManagedVal
 mod tests {
 
     use super::RocksDBManagedValue;
+    use crate::backends::rocksdb::merge::set_merge_operator;
     use crate::primitives::ManagedValue;
-    use rocksdb::{Options, DB, MergeOperands};
+    use rocksdb::{Options, DB};
     use std::rc::Rc;
     use tempfile::TempDir;
 
-    fn merge_operator(
-        new_key: &[u8],
-        existing_val: Option<&[u8]>,
-        operands: &mut MergeOperands,
-    ) -> Option<Vec<u8>> {
-        let mut result: i64 = 0;
-        if let Some(val) = existing_val {
-            result += bincode::deserialize::<i64>(val).unwrap();
-        }
-        for operand in operands {
-            result += bincode::deserialize::<i64>(operand).unwrap();
-        }
-        Some(bincode::serialize(&result).unwrap())
-    }
-
     #[test]
     fn value_set_get() {
         let directory = TempDir::new().unwrap();
         let mut options = Options::default();
         options.create_if_missing(true);
-        options.set_merge_operator("merge_operator", merge_operator, Some(merge_operator));
+        set_merge_operator(&mut options);
         let db = DB::open(&options, directory.path()).expect("Unable to instantiate RocksDB");
         let mut managed_value = RocksDBManagedValue::new(Rc::new(db), &"");
 
@@ -96,7 +93,7 @@ mod tests {
         let directory = TempDir::new().unwrap();
         let mut options = Options::default();
         options.create_if_missing(true);
-        options.set_merge_operator("merge_operator", merge_operator, Some(merge_operator));
+        set_merge_operator(&mut options);
         let db = DB::open(&options, directory.path()).expect("Unable to instantiate RocksDB");
         let mut managed_value = RocksDBManagedValue::new(Rc::new(db), &"");
 
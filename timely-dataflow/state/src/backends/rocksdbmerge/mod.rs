@@ -1,20 +1,24 @@
 extern crate rocksdb;
 use self::rocksdb::BlockBasedOptions;
+use crate::backends::rocksdb::{checkpoint, restore, resume_compactions};
+use crate::backends::rocksdb::keyspace::{
+    create_column_family, drop_column_family, Keyspace,
+};
+use crate::backends::rocksdb::merge::set_merge_operator;
+use crate::checkpoint::{CheckpointError, Snapshot};
+use crate::codec::Codec;
+use crate::column_family::ColumnFamilyOptions;
+use crate::config::{BackendConfig, RocksDBConfig};
+use crate::merge::MergeOperator;
 use crate::primitives::{ManagedCount, ManagedMap, ManagedValue};
-use crate::StateBackend;
-use faster_rs::{FasterKey, FasterRmw, FasterValue};
+use crate::{StateBackend, StateKey, StateValue};
 use managed_count::RocksDBManagedCount;
 use managed_map::RocksDBManagedMap;
 use managed_value::RocksDBManagedValue;
-use rocksdb::MergeOperands;
 use rocksdb::{Options, DB};
-use std::hash::Hash;
+use std::cell::Cell;
 use std::rc::Rc;
 use tempfile::TempDir;
-use std::fs::File;
-use std::io::BufRead;
-use std::io::BufReader;
-use std::iter::FromIterator;
 use std::path::Path;
 
 mod managed_count;
@@ -23,101 +27,133 @@ mod managed_value;
 
 pub struct RocksDBMergeBackend {
     db: Rc<DB>,
+    // Removed along with the backend; None when the caller chose the directory
+    _directory: Option<TempDir>,
+    // Whether the database was restored and does not compact until the first advance
+    restored: Cell<bool>,
 }
 
-// Appends elements to a vector
-fn merge_vectors(
-    new_key: &[u8],
-    existing_val: Option<&[u8]>,
-    operands: &mut MergeOperands,
-) -> Option<Vec<u8>> {
-   
-   let mut result: Vec<(usize,usize)> = Vec::with_capacity(operands.size_hint().0);
+/// `RocksDBMergeBackend` under the name it had while merges went through `Rmw`
+/// values alone. It reads the `rocksdb_merge` configuration.
+#[deprecated(note = "please use `RocksDBMergeBackend` instead")]
+pub type RocksDBMergeBackend2 = RocksDBMergeBackend;
 
-    if let Some(val) = existing_val {
-        result.extend(bincode::deserialize::<Vec<(usize,usize)>>(val).unwrap());
+// Tunes RocksDB as the 'rocksdb_merge' configuration asks
+fn options(config: &RocksDBConfig) -> Options {
+    println!("Configuring a RocksDB instance with {:?}", config);
+    let mut block_based_options = BlockBasedOptions::default();
+    if let Some(block_size) = config.block_size {
+        block_based_options.set_block_size(block_size);
     }
-    for operand in operands {
-        result.extend(bincode::deserialize::<Vec<(usize,usize)>>(operand).unwrap());
+    if let Some(lru_size) = config.lru_size {
+        block_based_options.set_lru_cache(lru_size);
     }
-    Some(bincode::serialize(&result).unwrap())
-}
-
-// read RocksDB configuration from a file
-fn read_rocksdb_config() -> (usize, usize, usize, u64) {
-    let config_path = String::from("rocksdbmerge.config");
-    let file = File::open(config_path).expect("Config file not found or cannot be opened");
-    let content = BufReader::new(&file);
-    let mut blocksize = 0;
-    let mut lrusize = 0;
-    let mut write_buffer_size = 0;
-    let mut hash_index_size = 0;
-    for line in content.lines() {
-        let line = line.expect("Could not read the line");
-        let line = line.trim();
-        if line.starts_with("#") || line.starts_with(";") || line.is_empty() {
-            continue;
-        }
-        let tokens = Vec::from_iter(line.split_whitespace());
-        let name = tokens.first().unwrap();
-        let tokens = tokens.get(1..).unwrap();
-        let tokens = tokens.iter().filter(|t| !t.starts_with("="));
-        let tokens = tokens.take_while(|t| !t.starts_with("#") && !t.starts_with(";"));
-        let mut parameters = String::new();
-        tokens.for_each(|t| { parameters.push_str(t); parameters.push(' '); });
-        let parameters = parameters.split(',').map(|s| s.trim());
-        let parameters: Vec<String> = parameters.map(|s| s.to_string()).collect();
-
-        // Setting the config parameters
-        match name.to_lowercase().as_str() {
-            "blocksize" => blocksize = parameters.get(0).unwrap().parse::<usize>().expect("couldn't parse tablesize"),
-            "lrusize" => lrusize = parameters.get(0).unwrap().parse::<usize>().expect("couldn't parse logsize"),
-            "writebuffersize" => write_buffer_size = parameters.get(0).unwrap().parse::<usize>().expect("couldn't parse writebuffersize"),
-            "hashindexsize" => hash_index_size = parameters.get(0).unwrap().parse::<u64>().expect("couldn't parse hashindexsize"),
-            _ => (),
-        }
+    let mut options = Options::default();
+    options.create_if_missing(true);
+    set_merge_operator(&mut options);
+    options.set_use_fsync(false);
+    options.set_min_write_buffer_number(2);
+    options.set_max_write_buffer_number(4);
+    if let Some(write_buffer_size) = config.write_buffer_size {
+        options.set_write_buffer_size(write_buffer_size);
     }
-    (blocksize, lrusize, write_buffer_size, hash_index_size)
+    options.set_block_based_table_factory(&block_based_options);
+    if let Some(hash_index_size) = config.hash_index_size {
+        options.optimize_for_point_lookup(hash_index_size);
+    }
+    options
 }
 
 impl StateBackend for RocksDBMergeBackend {
-    fn new() -> Self {
-        let directory = TempDir::new_in(".").expect("Unable to create directory for FASTER");
-        let mut block_based_options = BlockBasedOptions::default();
-        let (block_size, lru_cache, write_buffer_size, hash_index_size) = read_rocksdb_config();
-        println!("Configuring a RocksDB instance with block size {:?}, cache {:?}, write buffer size {:?}, and hash index size {:?}",
-                 block_size, lru_cache, write_buffer_size, hash_index_size);
-        block_based_options.set_block_size(block_size);
-        block_based_options.set_lru_cache(lru_cache);
-        let mut options = Options::default();
-        options.create_if_missing(true);
-        options.set_merge_operator("merge_vectors", merge_vectors, Some(merge_vectors));
-        options.set_use_fsync(false);
-        options.set_min_write_buffer_number(2);
-        options.set_max_write_buffer_number(4);
-        options.set_write_buffer_size(write_buffer_size);
-        options.set_block_based_table_factory(&block_based_options);
-        options.optimize_for_point_lookup(hash_index_size);
-        let db = DB::open(&options, directory.into_path()).expect("Unable to instantiate RocksDBMerge");
-        RocksDBMergeBackend { db: Rc::new(db) }
+    fn new(config: &BackendConfig) -> Self {
+        let directory = TempDir::new_in(&config.directory).expect("Unable to create directory for RocksDB");
+        let db = DB::open(&options(&config.rocksdb_merge), directory.path()).expect("Unable to instantiate RocksDBMerge");
+        RocksDBMergeBackend {
+            db: Rc::new(db),
+            _directory: Some(directory),
+            restored: Cell::new(false),
+        }
     }
 
     fn get_managed_count(&self, name: &str) -> Box<ManagedCount> {
-        Box::new(RocksDBManagedCount::new(Rc::clone(&self.db), &name))
+        Box::new(RocksDBManagedCount::new(Keyspace::of(&self.db, name), &name))
     }
 
-    fn get_managed_value<V: 'static + FasterValue + FasterRmw>(
+    fn get_managed_value<V: StateValue>(
         &self,
         name: &str,
     ) -> Box<ManagedValue<V>> {
-        Box::new(RocksDBManagedValue::new(Rc::clone(&self.db), &name))
+        Box::new(RocksDBManagedValue::new(Keyspace::of(&self.db, name), &name))
+    }
+
+    fn get_managed_map_with_codecs<K, V, KC, VC>(&self, name: &str) -> Box<ManagedMap<K, V>>
+    where
+        K: StateKey,
+        V: StateValue,
+        KC: Codec<K>,
+        VC: Codec<V>,
+    {
+        let keyspace = Keyspace::of(&self.db, name);
+        Box::new(RocksDBManagedMap::<KC, VC>::with_codecs(keyspace, &name))
     }
 
-    fn get_managed_map<K, V>(&self, name: &str) -> Box<ManagedMap<K, V>>
+    fn get_managed_map_with_merge<K, V, KC, VC>(
+        &self,
+        name: &str,
+        merge: MergeOperator<V>,
+    ) -> Box<ManagedMap<K, V>>
     where
-        K: 'static + FasterKey + Hash + Eq + std::fmt::Debug,
-        V: 'static + FasterValue + FasterRmw,
+        K: StateKey,
+        V: StateValue,
+        KC: Codec<K>,
+        VC: Codec<V>,
     {
-        Box::new(RocksDBManagedMap::new(Rc::clone(&self.db), &name))
+        let keyspace = Keyspace::of(&self.db, name);
+        Box::new(RocksDBManagedMap::<KC, VC>::with_codecs(keyspace, &name).with_merge(merge))
+    }
+
+    fn create_column_family(&self, name: &str, options: &ColumnFamilyOptions) {
+        create_column_family(&self.db, name, options);
+    }
+
+    fn drop_column_family(&self, name: &str) {
+        drop_column_family(&self.db, name);
+    }
+
+    fn checkpoint(&self, directory: &Path) -> Result<Snapshot, CheckpointError> {
+        checkpoint(&self.db, directory, "rocksdb_merge")
+    }
+
+    fn restore(config: &BackendConfig, snapshot: &Snapshot) -> Result<Self, CheckpointError> {
+        let (db, directory) = restore(&config.directory, snapshot, "rocksdb_merge", options(&config.rocksdb_merge))?;
+        Ok(RocksDBMergeBackend {
+            db: Rc::new(db),
+            _directory: Some(directory),
+            restored: Cell::new(true),
+        })
+    }
+
+    // Operators open their maps before they first advance, so by then every
+    // merge operator of the restored state is registered
+    fn advance(&self, _frontier: u64) {
+        if self.restored.replace(false) {
+            resume_compactions(&self.db);
+        }
+    }
+}
+
+impl RocksDBMergeBackend {
+    // Opens a RocksDB instance in the given 'directory' with RocksDB's default
+    // options, instead of in the configured directory with the configured tuning
+    pub fn new_in(directory: &Path) -> Self {
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        set_merge_operator(&mut options);
+        let db = DB::open(&options, directory).expect("Unable to instantiate RocksDB");
+        RocksDBMergeBackend {
+            db: Rc::new(db),
+            _directory: None,
+            restored: Cell::new(false),
+        }
     }
 }
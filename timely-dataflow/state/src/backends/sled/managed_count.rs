@@ -0,0 +1,83 @@
+use crate::primitives::ManagedCount;
+use sled::Tree;
+
+pub struct SledManagedCount {
+    tree: Tree,
+    name: Vec<u8>,
+}
+
+impl SledManagedCount {
+    pub fn new(tree: Tree, name: &str) -> Self {
+        SledManagedCount {
+            tree,
+            name: bincode::serialize(name).unwrap(),
+        }
+    }
+
+    // Adds 'amount' in a single update of sled's, rather than a get+put
+    fn add(&mut self, amount: i64) {
+        self.tree
+            .update_and_fetch(&self.name, |count| {
+                let count: i64 = count.map_or(0, |count| bincode::deserialize(count).unwrap());
+                Some(bincode::serialize(&(count + amount)).unwrap())
+            })
+            .unwrap();
+    }
+}
+
+impl ManagedCount for SledManagedCount {
+    fn decrease(&mut self, amount: i64) {
+        self.add(-amount);
+    }
+
+    fn increase(&mut self, amount: i64) {
+        self.add(amount);
+    }
+
+    fn get(&self) -> i64 {
+        match self.tree.get(&self.name).unwrap() {
+            None => 0,
+            Some(count) => bincode::deserialize(&count).unwrap(),
+        }
+    }
+
+    fn set(&mut self, value: i64) {
+        self.tree
+            .insert(&self.name, bincode::serialize(&value).unwrap())
+            .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SledManagedCount;
+    use crate::primitives::ManagedCount;
+    use sled::Tree;
+
+    fn tree() -> Tree {
+        let db = sled::Config::new().temporary(true).open().expect("Unable to instantiate sled");
+        Tree::clone(&db)
+    }
+
+    #[test]
+    fn new_count_returns_0() {
+        let count = SledManagedCount::new(tree(), "");
+        assert_eq!(count.get(), 0);
+    }
+
+    #[test]
+    fn count_can_increase_and_decrease() {
+        let mut count = SledManagedCount::new(tree(), "");
+        count.increase(42);
+        count.decrease(2);
+        assert_eq!(count.get(), 40);
+    }
+
+    #[test]
+    fn count_can_set_directly() {
+        let mut count = SledManagedCount::new(tree(), "");
+        count.set(42);
+        count.increase(1);
+        assert_eq!(count.get(), 43);
+    }
+}
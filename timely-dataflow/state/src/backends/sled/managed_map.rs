@@ -0,0 +1,180 @@
+use super::iterate;
+use crate::codec::{Bincode, Codec};
+use crate::primitives::{IterDirection, IterationError, KeyRange, ManagedMap, ManagedMapIter};
+use crate::{Rmw, StateKey, StateValue};
+use sled::{Batch, Tree};
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+pub struct SledManagedMap<K, V, KC = Bincode, VC = Bincode> {
+    tree: Tree,
+    name: Vec<u8>,
+    key: PhantomData<K>,
+    value: PhantomData<V>,
+    codecs: PhantomData<(KC, VC)>,
+}
+
+#[cfg(test)]
+impl<K: StateKey, V: StateValue> SledManagedMap<K, V> {
+    pub fn new(tree: Tree, name: &str) -> Self {
+        SledManagedMap::with_codecs(tree, name)
+    }
+}
+
+impl<K, V, KC, VC> SledManagedMap<K, V, KC, VC>
+where
+    K: StateKey,
+    V: StateValue,
+    KC: Codec<K>,
+    VC: Codec<V>,
+{
+    pub fn with_codecs(tree: Tree, name: &str) -> Self {
+        SledManagedMap {
+            tree,
+            name: bincode::serialize(name).unwrap(),
+            key: PhantomData,
+            value: PhantomData,
+            codecs: PhantomData,
+        }
+    }
+
+    fn prefix_key(&self, key: &K) -> Vec<u8> {
+        let mut prefixed_key = self.name.clone();
+        KC::encode(key, &mut prefixed_key);
+        prefixed_key
+    }
+}
+
+impl<K, V, KC, VC> ManagedMap<K, V> for SledManagedMap<K, V, KC, VC>
+where
+    K: StateKey,
+    V: StateValue,
+    KC: Codec<K>,
+    VC: Codec<V>,
+{
+    fn get_key_prefix_length(&self) -> usize {
+        self.name.len()
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        let prefixed_key = self.prefix_key(&key);
+        self.tree.insert(prefixed_key, VC::encoded(&value)).unwrap();
+    }
+
+    fn get(&self, key: &K) -> Option<Rc<V>> {
+        let prefixed_key = self.prefix_key(key);
+        let value = self.tree.get(prefixed_key).unwrap();
+        value.map(|value| Rc::new(VC::decode(&value)))
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        let prefixed_key = self.prefix_key(key);
+        let value = self.tree.remove(prefixed_key).unwrap();
+        value.map(|value| VC::decode(&value))
+    }
+
+    fn rmw(&mut self, key: K, modification: V)
+    where
+        V: Rmw,
+    {
+        let prefixed_key = self.prefix_key(&key);
+        let modified = match self.tree.get(&prefixed_key).unwrap() {
+            Some(value) => VC::decode(&value).rmw(modification),
+            None => modification,
+        };
+        self.tree.insert(prefixed_key, VC::encoded(&modified)).unwrap();
+    }
+
+    // Applies every insertion at once, in one of sled's atomic batches
+    fn insert_batch(&mut self, entries: Vec<(K, V)>) {
+        let mut batch = Batch::default();
+        for (key, value) in entries {
+            batch.insert(self.prefix_key(&key), VC::encoded(&value));
+        }
+        self.tree.apply_batch(batch).unwrap();
+    }
+
+    // Iterates in serialised key order without leaving this map's prefix
+    fn iter(
+        &self,
+        range: KeyRange<K>,
+        direction: IterDirection,
+    ) -> Result<ManagedMapIter<'_, K, V>, IterationError> {
+        Ok(iterate::<K, V, KC, VC>(&self.tree, &self.name, range, direction))
+    }
+
+    fn contains(&self, key: &K) -> bool {
+        let prefixed_key = self.prefix_key(key);
+        self.tree.contains_key(prefixed_key).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SledManagedMap;
+    use crate::backends::conformance;
+    use crate::primitives::{IterDirection, KeyRange, ManagedMap};
+    use sled::Tree;
+    use std::ops::Bound;
+    use std::rc::Rc;
+
+    fn tree() -> Tree {
+        let db = sled::Config::new().temporary(true).open().expect("Unable to instantiate sled");
+        Tree::clone(&db)
+    }
+
+    #[test]
+    fn map_conforms() {
+        let mut managed_map = SledManagedMap::new(tree(), "");
+
+        conformance::check_managed_map(&mut managed_map);
+    }
+
+    #[test]
+    fn map_rmw() {
+        let mut managed_map = SledManagedMap::new(tree(), "");
+
+        managed_map.insert(1u64, 1337u64);
+        managed_map.rmw(1u64, 10u64);
+        managed_map.rmw(2u64, 10u64);
+        assert_eq!(managed_map.get(&1), Some(Rc::new(1347)));
+        assert_eq!(managed_map.get(&2), Some(Rc::new(10)));
+        assert_eq!(managed_map.remove(&2), Some(10));
+        assert!(!managed_map.contains(&2));
+    }
+
+    #[test]
+    fn iterate_bounded_reverse() {
+        let mut managed_map = SledManagedMap::new(tree(), "");
+
+        for key in 0u64..10 {
+            managed_map.insert(key.to_be(), key * 10);
+        }
+
+        let range = KeyRange::Bounded(Bound::Excluded(2u64.to_be()), Bound::Included(5u64.to_be()));
+        let keys: Vec<u64> = managed_map
+            .iter(range, IterDirection::Reverse)
+            .unwrap()
+            .map(|(key, _)| u64::from_be(*key))
+            .collect();
+        assert_eq!(keys, vec![5, 4, 3]);
+
+        let empty = KeyRange::Bounded(Bound::Excluded(5u64.to_be()), Bound::Excluded(5u64.to_be()));
+        assert_eq!(managed_map.iter(empty, IterDirection::Forward).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn iterate_stops_at_map_prefix() {
+        let tree = tree();
+        let mut managed_map = SledManagedMap::new(tree.clone(), "a");
+        let mut neighbour = SledManagedMap::new(tree, "b");
+
+        managed_map.insert(1u64, 1u64);
+        neighbour.insert(2u64, 2u64);
+
+        let forward: Vec<_> = managed_map.iter(KeyRange::All, IterDirection::Forward).unwrap().collect();
+        let reverse: Vec<_> = neighbour.iter(KeyRange::All, IterDirection::Reverse).unwrap().collect();
+        assert_eq!(forward, vec![(Rc::new(1), Rc::new(1))]);
+        assert_eq!(reverse, vec![(Rc::new(2), Rc::new(2))]);
+    }
+}
@@ -0,0 +1,82 @@
+use crate::primitives::ManagedValue;
+use crate::{Rmw, StateValue};
+use sled::Tree;
+use std::rc::Rc;
+
+pub struct SledManagedValue {
+    tree: Tree,
+    name: Vec<u8>,
+}
+
+impl SledManagedValue {
+    pub fn new(tree: Tree, name: &str) -> Self {
+        SledManagedValue {
+            tree,
+            name: bincode::serialize(name).unwrap(),
+        }
+    }
+}
+
+impl<V: StateValue> ManagedValue<V> for SledManagedValue {
+    fn set(&mut self, value: V) {
+        self.tree
+            .insert(&self.name, bincode::serialize(&value).unwrap())
+            .unwrap();
+    }
+
+    fn get(&self) -> Option<Rc<V>> {
+        let value = self.tree.get(&self.name).unwrap();
+        value.map(|value| Rc::new(bincode::deserialize(&value).unwrap()))
+    }
+
+    // sled's remove returns the value it removed, so this is a single operation
+    fn take(&mut self) -> Option<V> {
+        let value = self.tree.remove(&self.name).unwrap();
+        value.map(|value| bincode::deserialize(&value).unwrap())
+    }
+
+    fn rmw(&mut self, modification: V)
+    where
+        V: Rmw,
+    {
+        let value = self.tree.get(&self.name).unwrap();
+        let modified = match value {
+            Some(value) => bincode::deserialize::<V>(&value).unwrap().rmw(modification),
+            None => modification,
+        };
+        self.set(modified);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SledManagedValue;
+    use crate::primitives::ManagedValue;
+    use sled::Tree;
+    use std::rc::Rc;
+
+    fn tree() -> Tree {
+        let db = sled::Config::new().temporary(true).open().expect("Unable to instantiate sled");
+        Tree::clone(&db)
+    }
+
+    #[test]
+    fn value_set_get_take() {
+        let mut managed_value = SledManagedValue::new(tree(), "");
+
+        let value: u64 = 1337;
+        managed_value.set(value);
+        assert_eq!(managed_value.get(), Some(Rc::new(value)));
+        assert_eq!(managed_value.take(), Some(value));
+        assert_eq!(ManagedValue::<u64>::get(&managed_value), None);
+    }
+
+    #[test]
+    fn value_rmw() {
+        let mut managed_value = SledManagedValue::new(tree(), "");
+
+        managed_value.rmw(1337u64);
+        managed_value.rmw(10u64);
+        assert_eq!(managed_value.get(), Some(Rc::new(1347u64)));
+    }
+}
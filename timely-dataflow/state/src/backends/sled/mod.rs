@@ -0,0 +1,189 @@
+//! A disk-backed backend built on sled, an embedded ordered key-value store
+//! written in Rust, so that it needs no native toolchain.
+//!
+//! Like the RocksDB backend, every piece of state is kept under its
+//! serialised name in one ordered keyspace, unless a column family of the
+//! same name was created for it, which sled keeps as a tree of its own.
+
+extern crate sled;
+use self::sled::{Db, Tree};
+use crate::checkpoint::{CheckpointError, Snapshot};
+use crate::codec::Codec;
+use crate::column_family::ColumnFamilyOptions;
+use crate::config::{BackendConfig, SledConfig};
+use crate::primitives::{
+    IterDirection, KeyRange, ManagedCount, ManagedMap, ManagedMapIter, ManagedValue,
+    SerialisedRange,
+};
+use crate::{StateBackend, StateKey, StateValue};
+use managed_count::SledManagedCount;
+use managed_map::SledManagedMap;
+use managed_value::SledManagedValue;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use tempfile::TempDir;
+
+mod managed_count;
+mod managed_map;
+mod managed_value;
+
+pub struct SledBackend {
+    db: Db,
+    _directory: TempDir,
+}
+
+// Opens a sled instance in a new directory inside 'directory', which is
+// removed once the returned TempDir is dropped
+fn open(directory: &Path, config: &SledConfig) -> sled::Result<(Db, TempDir)> {
+    let directory = TempDir::new_in(directory).expect("Unable to create directory for sled");
+    let mut sled_config = sled::Config::new()
+        .path(directory.path())
+        .flush_every_ms(config.flush_every_ms);
+    if let Some(cache_capacity) = config.cache_capacity {
+        sled_config = sled_config.cache_capacity(cache_capacity);
+    }
+    Ok((sled_config.open()?, directory))
+}
+
+// The tree that holds the state called 'name'
+fn tree_of(db: &Db, name: &str) -> Tree {
+    if db.tree_names().iter().any(|tree| &tree[..] == name.as_bytes()) {
+        db.open_tree(name).expect("Unable to open sled tree")
+    } else {
+        Tree::clone(db)
+    }
+}
+
+// Iterates over the keys of the map called 'name' that fall in 'range',
+// which sled seeks to and stops at itself
+pub(crate) fn iterate<K, V, KC, VC>(
+    tree: &Tree,
+    name: &[u8],
+    range: KeyRange<K>,
+    direction: IterDirection,
+) -> ManagedMapIter<'static, K, V>
+where
+    K: 'static,
+    V: 'static,
+    KC: Codec<K>,
+    VC: Codec<V>,
+{
+    let bounds = match SerialisedRange::new::<K, KC>(name, &range).bounds() {
+        Some(bounds) => bounds,
+        None => return Box::new(std::iter::empty()),
+    };
+    let entries = tree.range(bounds);
+    let entries: Box<Iterator<Item = sled::Result<(sled::IVec, sled::IVec)>>> = match direction {
+        IterDirection::Forward => Box::new(entries),
+        IterDirection::Reverse => Box::new(entries.rev()),
+    };
+    let name_length = name.len();
+    Box::new(entries.map(move |entry| {
+        let (raw_key, raw_value) = entry.expect("Unable to iterate over sled");
+        let key = KC::decode(&raw_key[name_length..]);
+        let value = VC::decode(&raw_value);
+        (Rc::new(key), Rc::new(value))
+    }))
+}
+
+impl StateBackend for SledBackend {
+    fn new(config: &BackendConfig) -> Self {
+        let (db, directory) = open(&config.directory, &config.sled).expect("Unable to instantiate sled");
+        SledBackend {
+            db,
+            _directory: directory,
+        }
+    }
+
+    fn get_managed_count(&self, name: &str) -> Box<ManagedCount> {
+        Box::new(SledManagedCount::new(tree_of(&self.db, name), name))
+    }
+
+    fn get_managed_value<V: StateValue>(&self, name: &str) -> Box<ManagedValue<V>> {
+        Box::new(SledManagedValue::new(tree_of(&self.db, name), name))
+    }
+
+    fn get_managed_map_with_codecs<K, V, KC, VC>(&self, name: &str) -> Box<ManagedMap<K, V>>
+    where
+        K: StateKey,
+        V: StateValue,
+        KC: Codec<K>,
+        VC: Codec<V>,
+    {
+        let tree = tree_of(&self.db, name);
+        Box::new(SledManagedMap::<K, V, KC, VC>::with_codecs(tree, name))
+    }
+
+    // sled trees have no tuning of their own, so the options are ignored
+    fn create_column_family(&self, name: &str, _options: &ColumnFamilyOptions) {
+        self.db.open_tree(name).expect("Unable to create sled tree");
+    }
+
+    fn drop_column_family(&self, name: &str) {
+        self.db.drop_tree(name).expect("Unable to drop sled tree");
+    }
+
+    // Copies every tree into a new instance in 'directory', which sled cannot
+    // import into if it already holds a database
+    fn checkpoint(&self, directory: &Path) -> Result<Snapshot, CheckpointError> {
+        let error = |error: sled::Error| CheckpointError::Backend(error.to_string());
+        if directory.exists() {
+            let io_error = |error| CheckpointError::Io(directory.to_owned(), error);
+            if fs::read_dir(directory).map_err(io_error)?.next().is_some() {
+                let error = format!("{} is not empty", directory.display());
+                return Err(CheckpointError::Backend(error));
+            }
+        }
+        let copy = sled::Config::new().path(directory).open().map_err(error)?;
+        copy.import(self.db.export());
+        copy.flush().map_err(error)?;
+        Ok(Snapshot {
+            backend: "sled".to_owned(),
+            path: directory.to_owned(),
+            token: None,
+            session: None,
+        })
+    }
+
+    // Copies the checkpoint into a new instance, so that it can be restored again
+    fn restore(config: &BackendConfig, snapshot: &Snapshot) -> Result<Self, CheckpointError> {
+        if snapshot.backend != "sled" {
+            let error = format!("sled cannot restore a {} snapshot", snapshot.backend);
+            return Err(CheckpointError::Backend(error));
+        }
+        let error = |error: sled::Error| CheckpointError::Backend(error.to_string());
+        let checkpoint = sled::Config::new().path(&snapshot.path).open().map_err(error)?;
+        let (db, directory) = open(&config.directory, &config.sled).map_err(error)?;
+        db.import(checkpoint.export());
+        Ok(SledBackend {
+            db,
+            _directory: directory,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SledBackend;
+    use crate::checkpoint::CheckpointError;
+    use crate::config::BackendConfig;
+    use crate::StateBackend;
+    use tempfile::TempDir;
+
+    #[test]
+    fn checkpoint_refuses_a_directory_in_use() {
+        let config = BackendConfig {
+            directory: std::env::temp_dir(),
+            ..BackendConfig::default()
+        };
+        let backend = SledBackend::new(&config);
+        let directory = TempDir::new().unwrap();
+        let snapshot = directory.path().join("snapshot");
+        backend.checkpoint(&snapshot).unwrap();
+        match backend.checkpoint(&snapshot) {
+            Err(CheckpointError::Backend(_)) => {}
+            other => panic!("Checkpointed into a used directory: {:?}", other.map(|_| ())),
+        }
+    }
+}
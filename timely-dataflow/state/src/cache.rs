@@ -0,0 +1,836 @@
+//! A write-back cache in front of any state backend.
+//!
+//! `CachedBackend<S>` keeps the entries an operator reads and writes on the
+//! heap, and writes those it changed back to the backend `S` only when the
+//! operator's input frontier advances, when the caches of the backend together
+//! hold more than its byte budget, before a checkpoint, or when the map is
+//! dropped. An operator that reads and updates the same keys many times per
+//! epoch thus pays for one read and one write per key, instead of one per access.
+//!
+//! Operators report their frontier with `StateHandle::advance`, which other
+//! backends ignore. The `[cache]` section of the `BackendConfig` sets the
+//! budget and chooses between two policies:
+//!
+//! - `lru` keeps entries across epochs. Once the caches are over budget, the
+//!   map in use evicts its least recently used entries.
+//! - `epoch` also forgets every entry once the frontier advances, so that the
+//!   cache only ever holds the working set of the current epochs.
+//!
+//! Counts, values and maps are cached. Bags and sorted maps are left to `S`,
+//! whose own implementations already append and order without reading whole values.
+//!
+//! Hits and misses are counted per backend by `StateHandle::cache_stats`, and
+//! over the whole process by `process_stats`, for the benchmark harness.
+
+use crate::checkpoint::{CheckpointError, Snapshot};
+use crate::codec::{Bincode, Codec, SortKey};
+use crate::column_family::ColumnFamilyOptions;
+use crate::compaction::CompactionStats;
+use crate::config::{BackendConfig, CacheConfig, CachePolicy};
+use crate::merge::MergeOperator;
+use crate::primitives::{
+    unshared, IterDirection, IterationError, KeyRange, ManagedBag, ManagedCount, ManagedMap,
+    ManagedMapIter, ManagedSortedMap, ManagedValue,
+};
+use crate::session::SessionStats;
+use crate::{Rmw, StateBackend, StateKey, StateValue};
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::mem;
+use std::path::Path;
+use std::rc::{Rc, Weak};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// How often the caches of a backend, or of the whole process, were used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Reads and updates of entries that were cached.
+    pub hits: u64,
+    /// Reads and updates that had to read the entry from the backend.
+    pub misses: u64,
+    /// Changed entries written back to the backend.
+    pub write_backs: u64,
+    /// Entries forgotten to keep the caches within the budget.
+    pub evictions: u64,
+}
+
+#[derive(Clone, Copy)]
+enum Event {
+    Hit,
+    Miss,
+    WriteBack,
+    Eviction,
+}
+
+static PROCESS_STATS: [AtomicU64; 4] = [
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+];
+
+/// The use of every cache of this process so far, summed over all workers.
+pub fn process_stats() -> CacheStats {
+    let load = |event: Event| PROCESS_STATS[event as usize].load(Ordering::Relaxed);
+    CacheStats {
+        hits: load(Event::Hit),
+        misses: load(Event::Miss),
+        write_backs: load(Event::WriteBack),
+        evictions: load(Event::Eviction),
+    }
+}
+
+// The statistics of one backend, shared by its caches
+#[derive(Clone, Default)]
+struct Stats(Rc<Cell<CacheStats>>);
+
+impl Stats {
+    fn record(&self, event: Event) {
+        let mut stats = self.0.get();
+        match event {
+            Event::Hit => stats.hits += 1,
+            Event::Miss => stats.misses += 1,
+            Event::WriteBack => stats.write_backs += 1,
+            Event::Eviction => stats.evictions += 1,
+        }
+        self.0.set(stats);
+        PROCESS_STATS[event as usize].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// The bytes that the caches of one backend hold together, and may hold
+#[derive(Clone)]
+struct Budget {
+    used: Rc<Cell<u64>>,
+    limit: u64,
+}
+
+impl Budget {
+    fn new(limit: u64) -> Self {
+        Budget {
+            used: Rc::new(Cell::new(0)),
+            limit,
+        }
+    }
+
+    fn exceeded(&self) -> bool {
+        self.used.get() > self.limit
+    }
+}
+
+/// A backend that caches the state of `S` and writes it back once per epoch.
+pub struct CachedBackend<S: StateBackend> {
+    inner: S,
+    config: CacheConfig,
+    caches: RefCell<Vec<Registered>>,
+    // Entries changed before this time have been written back
+    frontier: Cell<u64>,
+    budget: Budget,
+    stats: Stats,
+}
+
+// A cache that is still in use, reachable both to write it back and to share it
+struct Registered {
+    name: String,
+    flush: Weak<RefCell<Flush>>,
+    typed: Weak<Any>,
+}
+
+impl<S: StateBackend> CachedBackend<S> {
+    /// Caches the state of `inner` as configured by `config`.
+    pub fn wrap(inner: S, config: &CacheConfig) -> Self {
+        CachedBackend {
+            inner,
+            config: config.clone(),
+            caches: RefCell::new(Vec::new()),
+            frontier: Cell::new(0),
+            budget: Budget::new(config.budget),
+            stats: Stats::default(),
+        }
+    }
+
+    // The cache of the map 'name', shared with any other handle on it still open
+    fn cached<K, V, F>(&self, name: &str, merge: Option<MergeOperator<V>>, open: F) -> CachedMap<K, V>
+    where
+        K: StateKey,
+        V: StateValue,
+        F: FnOnce() -> Box<ManagedMap<K, V>>,
+    {
+        let mut caches = self.caches.borrow_mut();
+        caches.retain(|registered| registered.flush.upgrade().is_some());
+        let shared = caches
+            .iter()
+            .filter(|registered| registered.name == name)
+            .filter_map(|registered| registered.typed.upgrade())
+            .filter_map(|typed| typed.downcast::<RefCell<MapCache<K, V>>>().ok())
+            .next();
+        if let Some(cache) = shared {
+            return CachedMap { cache };
+        }
+        let cache = Rc::new(RefCell::new(MapCache {
+            inner: open(),
+            merge,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            uses: 0,
+            bytes: 0,
+            budget: self.budget.clone(),
+            policy: self.config.policy,
+            stats: self.stats.clone(),
+        }));
+        let flush: Rc<RefCell<Flush>> = cache.clone();
+        let typed: Rc<Any> = cache.clone();
+        caches.push(Registered {
+            name: name.to_owned(),
+            flush: Rc::downgrade(&flush),
+            typed: Rc::downgrade(&typed),
+        });
+        CachedMap { cache }
+    }
+
+    fn for_each_cache<F: FnMut(&str, &mut Flush)>(&self, mut action: F) {
+        for registered in self.caches.borrow().iter() {
+            if let Some(cache) = registered.flush.upgrade() {
+                action(&registered.name, &mut *cache.borrow_mut());
+            }
+        }
+    }
+}
+
+impl<S: StateBackend> StateBackend for CachedBackend<S> {
+    fn new(config: &BackendConfig) -> Self {
+        CachedBackend::wrap(S::new(config), &config.cache)
+    }
+
+    fn get_managed_count(&self, name: &str) -> Box<ManagedCount> {
+        Box::new(CachedCount {
+            map: self.cached(name, None, || Box::new(CountMap(self.inner.get_managed_count(name)))),
+        })
+    }
+
+    fn get_managed_value<V: StateValue>(&self, name: &str) -> Box<ManagedValue<V>> {
+        Box::new(CachedValue {
+            map: self.cached(name, None, || Box::new(ValueMap(self.inner.get_managed_value(name)))),
+        })
+    }
+
+    fn get_managed_map_with_codecs<K, V, KC, VC>(&self, name: &str) -> Box<ManagedMap<K, V>>
+    where
+        K: StateKey,
+        V: StateValue,
+        KC: Codec<K>,
+        VC: Codec<V>,
+    {
+        Box::new(self.cached(name, None, || {
+            self.inner.get_managed_map_with_codecs::<K, V, KC, VC>(name)
+        }))
+    }
+
+    fn get_managed_map_with_merge<K, V, KC, VC>(
+        &self,
+        name: &str,
+        merge: MergeOperator<V>,
+    ) -> Box<ManagedMap<K, V>>
+    where
+        K: StateKey,
+        V: StateValue,
+        KC: Codec<K>,
+        VC: Codec<V>,
+    {
+        let inner = merge.clone();
+        Box::new(self.cached(name, Some(merge), || {
+            self.inner.get_managed_map_with_merge::<K, V, KC, VC>(name, inner)
+        }))
+    }
+
+    fn get_managed_bag<K, T>(&self, name: &str) -> Box<ManagedBag<K, T>>
+    where
+        K: StateKey,
+        T: StateValue + Clone,
+    {
+        self.inner.get_managed_bag(name)
+    }
+
+    fn get_managed_sorted_map<K, V>(&self, name: &str) -> Box<ManagedSortedMap<K, V>>
+    where
+        K: SortKey,
+        V: StateValue,
+    {
+        self.inner.get_managed_sorted_map(name)
+    }
+
+    fn create_column_family(&self, name: &str, options: &ColumnFamilyOptions) {
+        self.inner.create_column_family(name, options)
+    }
+
+    fn drop_column_family(&self, name: &str) {
+        self.for_each_cache(|cached, cache| {
+            if cached == name {
+                cache.discard();
+            }
+        });
+        self.inner.drop_column_family(name)
+    }
+
+    fn checkpoint(&self, directory: &Path) -> Result<Snapshot, CheckpointError> {
+        self.for_each_cache(|_, cache| cache.write_back());
+        self.inner.checkpoint(directory)
+    }
+
+    fn restore(config: &BackendConfig, snapshot: &Snapshot) -> Result<Self, CheckpointError> {
+        S::restore(config, snapshot).map(|inner| CachedBackend::wrap(inner, &config.cache))
+    }
+
+    fn advance(&self, frontier: u64) {
+        if frontier > self.frontier.get() {
+            self.frontier.set(frontier);
+            self.for_each_cache(|_, cache| cache.end_epoch());
+        }
+        self.inner.advance(frontier);
+    }
+
+    fn cache_stats(&self) -> Option<CacheStats> {
+        Some(self.stats.0.get())
+    }
+
+    fn compaction_stats(&self) -> Option<CompactionStats> {
+        self.inner.compaction_stats()
+    }
+
+    fn session_stats(&self) -> Option<SessionStats> {
+        self.inner.session_stats()
+    }
+
+    fn memory_usage(&self) -> Option<u64> {
+        self.inner.memory_usage()
+    }
+}
+
+// What the backend does with all of its caches at once
+trait Flush {
+    /// Writes changed entries back and, if the cache is scoped to epochs, forgets every entry.
+    fn end_epoch(&mut self);
+    /// Writes changed entries back.
+    fn write_back(&mut self);
+    /// Forgets every entry without writing it back.
+    fn discard(&mut self);
+}
+
+struct Entry<V> {
+    // None if the key is known to be absent
+    value: Option<Rc<V>>,
+    dirty: bool,
+    size: u64,
+    used: u64,
+}
+
+struct MapCache<K: StateKey, V: StateValue> {
+    inner: Box<ManagedMap<K, V>>,
+    merge: Option<MergeOperator<V>>,
+    // The entries by encoded key
+    entries: HashMap<Vec<u8>, Entry<V>>,
+    // The keys of the entries by when they were last used, least recently first
+    recency: BTreeMap<u64, Vec<u8>>,
+    uses: u64,
+    // The bytes of this map's entries, which also count towards the budget
+    bytes: u64,
+    budget: Budget,
+    policy: CachePolicy,
+    stats: Stats,
+}
+
+// Keys and values are counted at their bincode size, which is what most backends store
+fn entry_size<V: StateValue>(encoded: &[u8], value: &Option<Rc<V>>) -> u64 {
+    let value = value
+        .as_ref()
+        .map_or(0, |value| bincode::serialized_size(&**value).unwrap_or(0));
+    encoded.len() as u64 + value
+}
+
+impl<K: StateKey, V: StateValue> MapCache<K, V> {
+    fn next_use(&mut self) -> u64 {
+        self.uses += 1;
+        self.uses
+    }
+
+    fn grow(&mut self, size: u64) {
+        self.bytes += size;
+        self.budget.used.set(self.budget.used.get() + size);
+    }
+
+    fn shrink(&mut self, size: u64) {
+        self.bytes -= size;
+        self.budget.used.set(self.budget.used.get() - size);
+    }
+
+    // Reads the entry of 'key' into the cache unless it is there, and returns its encoded key
+    fn load(&mut self, key: &K) -> Vec<u8> {
+        let encoded = Bincode::encoded(key);
+        let used = self.next_use();
+        match self.entries.get_mut(&encoded) {
+            Some(entry) => {
+                self.stats.record(Event::Hit);
+                self.recency.remove(&entry.used);
+                entry.used = used;
+            }
+            None => {
+                self.stats.record(Event::Miss);
+                let value = self.inner.get(key);
+                let size = entry_size(&encoded, &value);
+                self.grow(size);
+                self.entries.insert(
+                    encoded.clone(),
+                    Entry {
+                        value,
+                        dirty: false,
+                        size,
+                        used,
+                    },
+                );
+            }
+        }
+        self.recency.insert(used, encoded.clone());
+        encoded
+    }
+
+    fn take(&mut self, encoded: &[u8]) -> Option<Rc<V>> {
+        self.entries
+            .get_mut(encoded)
+            .and_then(|entry| entry.value.take())
+    }
+
+    // Caches a change to the entry of 'encoded', to be written back later
+    fn store(&mut self, encoded: Vec<u8>, value: Option<V>) {
+        let value = value.map(Rc::new);
+        let size = entry_size(&encoded, &value);
+        let used = self.next_use();
+        if let Some(entry) = self.entries.remove(&encoded) {
+            self.recency.remove(&entry.used);
+            self.shrink(entry.size);
+        }
+        self.grow(size);
+        self.recency.insert(used, encoded.clone());
+        self.entries.insert(
+            encoded,
+            Entry {
+                value,
+                dirty: true,
+                size,
+                used,
+            },
+        );
+    }
+
+    // Forgets the least recently used entries of this map until the caches are within budget
+    fn evict(&mut self) {
+        while self.budget.exceeded() {
+            let (used, encoded) = match self.recency.iter().next() {
+                Some((used, encoded)) => (*used, encoded.clone()),
+                None => break,
+            };
+            self.recency.remove(&used);
+            let entry = self.entries.remove(&encoded).expect("Recently used entries are cached");
+            self.shrink(entry.size);
+            if entry.dirty {
+                self.write(&encoded, entry.value);
+            }
+            self.stats.record(Event::Eviction);
+        }
+    }
+
+    fn write(&mut self, encoded: &[u8], value: Option<Rc<V>>) {
+        let key: K = Bincode::decode(encoded);
+        match value {
+            Some(value) => self.inner.insert(key, unshared(value)),
+            None => {
+                self.inner.remove(&key);
+            }
+        }
+        self.stats.record(Event::WriteBack);
+    }
+}
+
+impl<K: StateKey, V: StateValue> Flush for MapCache<K, V> {
+    fn end_epoch(&mut self) {
+        match self.policy {
+            CachePolicy::Lru => self.write_back(),
+            CachePolicy::Epoch => {
+                let entries = mem::replace(&mut self.entries, HashMap::new());
+                self.recency.clear();
+                let bytes = self.bytes;
+                self.shrink(bytes);
+                for (encoded, entry) in entries {
+                    if entry.dirty {
+                        self.write(&encoded, entry.value);
+                    }
+                }
+            }
+        }
+    }
+
+    fn write_back(&mut self) {
+        let dirty: Vec<(Vec<u8>, Option<Rc<V>>)> = self
+            .entries
+            .iter_mut()
+            .filter(|(_, entry)| entry.dirty)
+            .map(|(encoded, entry)| {
+                entry.dirty = false;
+                (encoded.clone(), entry.value.clone())
+            })
+            .collect();
+        for (encoded, value) in dirty {
+            self.write(&encoded, value);
+        }
+    }
+
+    fn discard(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+        let bytes = self.bytes;
+        self.shrink(bytes);
+    }
+}
+
+impl<K: StateKey, V: StateValue> Drop for MapCache<K, V> {
+    fn drop(&mut self) {
+        self.write_back();
+        let bytes = self.bytes;
+        self.shrink(bytes);
+    }
+}
+
+/// A map whose entries are cached by a `CachedBackend`.
+pub struct CachedMap<K: StateKey, V: StateValue> {
+    cache: Rc<RefCell<MapCache<K, V>>>,
+}
+
+impl<K: StateKey, V: StateValue> ManagedMap<K, V> for CachedMap<K, V> {
+    fn get_key_prefix_length(&self) -> usize {
+        self.cache.borrow().inner.get_key_prefix_length()
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        let mut cache = self.cache.borrow_mut();
+        cache.store(Bincode::encoded(&key), Some(value));
+        cache.evict();
+    }
+
+    fn get(&self, key: &K) -> Option<Rc<V>> {
+        let mut cache = self.cache.borrow_mut();
+        let encoded = cache.load(key);
+        let value = cache.entries[&encoded].value.clone();
+        cache.evict();
+        value
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        let mut cache = self.cache.borrow_mut();
+        let encoded = cache.load(key);
+        let value = cache.take(&encoded);
+        if value.is_some() {
+            cache.store(encoded, None);
+        }
+        cache.evict();
+        value.map(unshared)
+    }
+
+    /// Merges in the cache, reading the entry first if it is not cached.
+    fn rmw(&mut self, key: K, modification: V)
+    where
+        V: Rmw,
+    {
+        let mut cache = self.cache.borrow_mut();
+        let encoded = cache.load(&key);
+        let merged = match cache.take(&encoded) {
+            Some(existing) => match cache.merge {
+                Some(ref merge) => merge.merge(unshared(existing), modification),
+                None => existing.rmw(modification),
+            },
+            None => modification,
+        };
+        cache.store(encoded, Some(merged));
+        cache.evict();
+    }
+
+    /// Merges in the cache like `rmw`, with the map's merge operator.
+    fn merge(&mut self, key: K, modification: V) {
+        let mut cache = self.cache.borrow_mut();
+        let merge = cache
+            .merge
+            .clone()
+            .expect("Only maps created with a merge operator can merge");
+        let encoded = cache.load(&key);
+        let merged = match cache.take(&encoded) {
+            Some(existing) => merge.merge(unshared(existing), modification),
+            None => modification,
+        };
+        cache.store(encoded, Some(merged));
+        cache.evict();
+    }
+
+    fn contains(&self, key: &K) -> bool {
+        let mut cache = self.cache.borrow_mut();
+        let encoded = cache.load(key);
+        let contained = cache.entries[&encoded].value.is_some();
+        cache.evict();
+        contained
+    }
+
+    /// Writes the map's changes back and reads the entries of `range` at once.
+    fn iter(
+        &self,
+        range: KeyRange<K>,
+        direction: IterDirection,
+    ) -> Result<ManagedMapIter<'_, K, V>, IterationError> {
+        let mut cache = self.cache.borrow_mut();
+        cache.write_back();
+        let entries: Vec<_> = cache.inner.iter(range, direction)?.collect();
+        Ok(Box::new(entries.into_iter()))
+    }
+}
+
+// A value seen as a map with a single key, so that it is cached like a map
+struct ValueMap<V: StateValue>(Box<ManagedValue<V>>);
+
+impl<V: StateValue> ManagedMap<(), V> for ValueMap<V> {
+    fn get_key_prefix_length(&self) -> usize {
+        0
+    }
+
+    fn insert(&mut self, _key: (), value: V) {
+        self.0.set(value)
+    }
+
+    fn get(&self, _key: &()) -> Option<Rc<V>> {
+        self.0.get()
+    }
+
+    fn remove(&mut self, _key: &()) -> Option<V> {
+        self.0.take()
+    }
+
+    fn rmw(&mut self, _key: (), modification: V)
+    where
+        V: Rmw,
+    {
+        self.0.rmw(modification)
+    }
+
+    fn contains(&self, _key: &()) -> bool {
+        self.0.get().is_some()
+    }
+
+    fn iter(
+        &self,
+        _range: KeyRange<()>,
+        _direction: IterDirection,
+    ) -> Result<ManagedMapIter<'_, (), V>, IterationError> {
+        Err(IterationError::Unsupported("A managed value"))
+    }
+}
+
+struct CachedValue<V: StateValue> {
+    map: CachedMap<(), V>,
+}
+
+impl<V: StateValue> ManagedValue<V> for CachedValue<V> {
+    fn set(&mut self, value: V) {
+        self.map.insert((), value)
+    }
+
+    fn get(&self) -> Option<Rc<V>> {
+        self.map.get(&())
+    }
+
+    fn take(&mut self) -> Option<V> {
+        self.map.remove(&())
+    }
+
+    fn rmw(&mut self, modification: V)
+    where
+        V: Rmw,
+    {
+        self.map.rmw((), modification)
+    }
+}
+
+// A count seen as a map with a single key, which is never absent
+struct CountMap(Box<ManagedCount>);
+
+impl ManagedMap<(), i64> for CountMap {
+    fn get_key_prefix_length(&self) -> usize {
+        0
+    }
+
+    fn insert(&mut self, _key: (), value: i64) {
+        self.0.set(value)
+    }
+
+    fn get(&self, _key: &()) -> Option<Rc<i64>> {
+        Some(Rc::new(self.0.get()))
+    }
+
+    fn remove(&mut self, _key: &()) -> Option<i64> {
+        let count = self.0.get();
+        self.0.set(0);
+        Some(count)
+    }
+
+    fn rmw(&mut self, _key: (), modification: i64) {
+        self.0.increase(modification)
+    }
+
+    fn contains(&self, _key: &()) -> bool {
+        true
+    }
+
+    fn iter(
+        &self,
+        _range: KeyRange<()>,
+        _direction: IterDirection,
+    ) -> Result<ManagedMapIter<'_, (), i64>, IterationError> {
+        Err(IterationError::Unsupported("A managed count"))
+    }
+}
+
+struct CachedCount {
+    map: CachedMap<(), i64>,
+}
+
+impl ManagedCount for CachedCount {
+    fn decrease(&mut self, amount: i64) {
+        self.map.rmw((), -amount)
+    }
+
+    fn increase(&mut self, amount: i64) {
+        self.map.rmw((), amount)
+    }
+
+    fn get(&self) -> i64 {
+        self.map.get(&()).map_or(0, |count| *count)
+    }
+
+    fn set(&mut self, value: i64) {
+        self.map.insert((), value)
+    }
+}
+
+#[cfg(all(test, feature = "in-memory"))]
+mod tests {
+    use super::{CacheStats, CachedBackend};
+    use crate::backends::InMemoryBackend;
+    use crate::config::{BackendConfig, CachePolicy};
+    use crate::{StateBackend, StateHandle};
+    use std::rc::Rc;
+
+    fn backend(budget: u64, policy: CachePolicy) -> CachedBackend<InMemoryBackend> {
+        let mut config = BackendConfig::default();
+        config.cache.budget = budget;
+        config.cache.policy = policy;
+        CachedBackend::new(&config)
+    }
+
+    #[test]
+    fn changes_are_written_back_as_the_frontier_advances() {
+        let backend = Rc::new(backend(1 << 20, CachePolicy::Lru));
+        let handle = StateHandle::new(Rc::clone(&backend), "cached");
+        let mut map = handle.get_managed_map::<u64, u64>("map");
+        let written = backend.inner.get_managed_map::<u64, u64>("cached.map");
+
+        map.insert(1, 1);
+        map.rmw(1, 2);
+        map.rmw(2, 5);
+        assert_eq!(written.get(&1), None);
+        handle.advance(&[5u64]);
+        assert_eq!(written.get(&1), Some(Rc::new(3)));
+        assert_eq!(written.get(&2), Some(Rc::new(5)));
+
+        map.remove(&1);
+        handle.advance(&[5u64]);
+        assert_eq!(written.get(&1), Some(Rc::new(3)));
+        handle.advance::<u64>(&[]);
+        assert_eq!(written.get(&1), None);
+    }
+
+    #[test]
+    fn repeated_accesses_hit() {
+        let backend = Rc::new(backend(1 << 20, CachePolicy::Lru));
+        let handle = StateHandle::new(backend, "cached");
+        let mut map = handle.get_managed_map::<u64, u64>("map");
+        let mut count = handle.get_managed_count("count");
+        map.rmw(1, 1);
+        map.rmw(1, 1);
+        assert_eq!(map.get(&1), Some(Rc::new(2)));
+        count.increase(3);
+        count.decrease(1);
+        assert_eq!(count.get(), 2);
+        assert_eq!(
+            handle.cache_stats(),
+            Some(CacheStats {
+                hits: 4,
+                misses: 2,
+                write_backs: 0,
+                evictions: 0,
+            })
+        );
+
+        // Maps opened twice share their cache
+        let other = handle.get_managed_map::<u64, u64>("map");
+        assert_eq!(other.get(&1), Some(Rc::new(2)));
+        assert_eq!(handle.cache_stats().unwrap().hits, 5);
+    }
+
+    #[test]
+    fn maps_over_budget_evict_the_least_recently_used_entries() {
+        let backend = Rc::new(backend(64, CachePolicy::Lru));
+        let handle = StateHandle::new(Rc::clone(&backend), "cached");
+        let mut map = handle.get_managed_map::<u64, u64>("map");
+        let written = backend.inner.get_managed_map::<u64, u64>("cached.map");
+        for key in 0..5 {
+            map.insert(key, key);
+        }
+        assert_eq!(map.get(&4), Some(Rc::new(4)));
+        let stats = handle.cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.evictions), (1, 1));
+        assert_eq!(written.get(&0), Some(Rc::new(0)));
+        assert_eq!(written.get(&4), None);
+        assert_eq!(map.get(&0), Some(Rc::new(0)));
+    }
+
+    #[test]
+    fn maps_share_the_budget_of_their_backend() {
+        let backend = Rc::new(backend(64, CachePolicy::Lru));
+        let handle = StateHandle::new(Rc::clone(&backend), "cached");
+        let mut first = handle.get_managed_map::<u64, u64>("first");
+        let mut second = handle.get_managed_map::<u64, u64>("second");
+        let written = backend.inner.get_managed_map::<u64, u64>("cached.second");
+        for key in 0..3 {
+            first.insert(key, key);
+        }
+        assert_eq!(handle.cache_stats().unwrap().evictions, 0);
+        second.insert(0, 0);
+        second.insert(1, 1);
+        assert_eq!(handle.cache_stats().unwrap().evictions, 1);
+        assert_eq!(written.get(&0), Some(Rc::new(0)));
+
+        // Dropped maps give their bytes back to the budget
+        drop(first);
+        second.insert(2, 2);
+        second.insert(3, 3);
+        assert_eq!(handle.cache_stats().unwrap().evictions, 1);
+    }
+
+    #[test]
+    fn epoch_caches_forget_their_entries() {
+        let backend = Rc::new(backend(1 << 20, CachePolicy::Epoch));
+        let handle = StateHandle::new(backend, "cached");
+        let mut value = handle.get_managed_value::<String>("value");
+        value.set("value".to_owned());
+        assert_eq!(value.get(), Some(Rc::new("value".to_owned())));
+        handle.advance(&[1u64]);
+        assert_eq!(value.get(), Some(Rc::new("value".to_owned())));
+        let stats = handle.cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses, stats.write_backs), (1, 1, 1));
+    }
+}
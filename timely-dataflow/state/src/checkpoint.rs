@@ -0,0 +1,541 @@
+//! Checkpoints of managed state, taken as input frontiers pass checkpoint epochs.
+//!
+//! A worker's checkpoint of an epoch lives in `<checkpoint_directory>/<epoch>/<worker>`.
+//! Every state handle checkpointed there writes a snapshot of its backend next
+//! to a `manifest.toml` that records each snapshot under the handle's name, so
+//! that the state can be found again when the dataflow is resumed.
+//!
+//! Backends snapshot their state in their own way: RocksDB backends create a
+//! RocksDB checkpoint, `FASTERBackend` copies its log and FASTER's checkpoint
+//! files and records the token to recover from and the session to continue,
+//! and `InMemoryBackend` writes a serialised dump.
+//!
+//! `Checkpointer` decides when to checkpoint from an operator's input frontier:
+//! a frontier passes epoch `e` once it holds no time at or before `e`, at which
+//! point the operator's state reflects every update up to `e`.
+//!
+//! `WorkerState` opens the state handles of a worker's dataflows, checkpoints
+//! all of them at once, and after `restore_from` opens each handle with the
+//! state recorded under its name in that epoch's manifest.
+
+use crate::config::BackendConfig;
+use crate::{earliest, StateBackend, StateHandle};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use toml::Value;
+
+/// The file in each worker's checkpoint directory that lists its snapshots.
+pub const MANIFEST_FILE: &str = "manifest.toml";
+
+/// What a backend wrote for a checkpoint, enough to recover its state.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    /// The kind of backend that wrote the snapshot, such as `rocksdb`.
+    pub backend: String,
+    /// Where the snapshot's files are.
+    pub path: PathBuf,
+    /// Identifies the checkpoint for backends that keep several in one place.
+    pub token: Option<String>,
+    /// The FASTER session whose progress the checkpoint recorded, to be continued on restore.
+    pub session: Option<String>,
+}
+
+/// One worker's checkpoint of an epoch.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Manifest {
+    pub epoch: u64,
+    pub worker: usize,
+    /// Snapshots by the name of the state handle that took them.
+    pub states: BTreeMap<String, Snapshot>,
+}
+
+/// Why a checkpoint could not be taken or read.
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(PathBuf, io::Error),
+    /// The backend cannot take checkpoints.
+    Unsupported,
+    Backend(String),
+    /// A manifest that is not the one a worker wrote.
+    Manifest(PathBuf, String),
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CheckpointError::Io(path, error) => write!(f, "cannot access {}: {}", path.display(), error),
+            CheckpointError::Unsupported => write!(f, "the state backend cannot take checkpoints"),
+            CheckpointError::Backend(error) => write!(f, "cannot checkpoint state: {}", error),
+            CheckpointError::Manifest(path, reason) => {
+                write!(f, "invalid checkpoint manifest {}: {}", path.display(), reason)
+            }
+        }
+    }
+}
+
+impl Error for CheckpointError {}
+
+/// The checkpoints one worker keeps under a checkpoint directory.
+#[derive(Clone, Debug)]
+pub struct WorkerCheckpoints {
+    directory: PathBuf,
+    worker: usize,
+}
+
+impl WorkerCheckpoints {
+    pub fn new<P: Into<PathBuf>>(directory: P, worker: usize) -> Self {
+        WorkerCheckpoints {
+            directory: directory.into(),
+            worker,
+        }
+    }
+
+    /// Where the worker's checkpoint of `epoch` is kept.
+    pub fn epoch_directory(&self, epoch: u64) -> PathBuf {
+        self.directory
+            .join(epoch.to_string())
+            .join(self.worker.to_string())
+    }
+
+    /// Checkpoints the backend of `state` as of `epoch` and records it in the epoch's manifest.
+    pub fn checkpoint<S: StateBackend>(
+        &self,
+        epoch: u64,
+        state: &StateHandle<S>,
+    ) -> Result<Snapshot, CheckpointError> {
+        let directory = self.epoch_directory(epoch);
+        fs::create_dir_all(&directory).map_err(|error| CheckpointError::Io(directory.clone(), error))?;
+        let snapshot = state.checkpoint(&directory.join(snapshot_directory(state.name())))?;
+        let mut manifest = self.manifest(epoch)?.unwrap_or_else(|| Manifest {
+            epoch,
+            worker: self.worker,
+            states: BTreeMap::new(),
+        });
+        manifest.states.insert(state.name().to_owned(), snapshot.clone());
+        manifest.write(&directory)?;
+        Ok(snapshot)
+    }
+
+    /// The manifest of `epoch`, if the worker checkpointed it.
+    pub fn manifest(&self, epoch: u64) -> Result<Option<Manifest>, CheckpointError> {
+        let directory = self.epoch_directory(epoch);
+        let path = directory.join(MANIFEST_FILE);
+        let toml = match fs::read_to_string(&path) {
+            Ok(toml) => toml,
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(CheckpointError::Io(path, error)),
+        };
+        let manifest = Manifest::parse(&toml, &directory)
+            .map_err(|reason| CheckpointError::Manifest(path.clone(), reason))?;
+        if manifest.epoch != epoch || manifest.worker != self.worker {
+            let reason = format!("written for epoch {} of worker {}", manifest.epoch, manifest.worker);
+            return Err(CheckpointError::Manifest(path, reason));
+        }
+        Ok(Some(manifest))
+    }
+
+    /// The latest epoch that each of the first `peers` workers checkpointed.
+    pub fn latest_epoch(&self, peers: usize) -> Result<Option<u64>, CheckpointError> {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(CheckpointError::Io(self.directory.clone(), error)),
+        };
+        let mut epochs = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|error| CheckpointError::Io(self.directory.clone(), error))?;
+            if let Some(epoch) = entry.file_name().to_str().and_then(|name| name.parse().ok()) {
+                epochs.push(epoch);
+            }
+        }
+        epochs.sort();
+        let complete = |epoch: u64| {
+            (0..peers).all(|worker| {
+                WorkerCheckpoints::new(self.directory.clone(), worker)
+                    .epoch_directory(epoch)
+                    .join(MANIFEST_FILE)
+                    .is_file()
+            })
+        };
+        Ok(epochs.into_iter().rev().find(|&epoch| complete(epoch)))
+    }
+}
+
+impl Manifest {
+    // Snapshots inside the checkpoint are recorded relative to it, so it can be moved
+    fn write(&self, directory: &Path) -> Result<(), CheckpointError> {
+        let mut toml = format!("epoch = {}\nworker = {}\n", self.epoch, self.worker);
+        for (name, snapshot) in &self.states {
+            let path = snapshot.path.strip_prefix(directory).unwrap_or(&snapshot.path);
+            toml.push_str("\n[[state]]\n");
+            toml.push_str(&format!("name = {}\n", quote(name)));
+            toml.push_str(&format!("backend = {}\n", quote(&snapshot.backend)));
+            toml.push_str(&format!("path = {}\n", quote(&path.to_string_lossy())));
+            if let Some(token) = &snapshot.token {
+                toml.push_str(&format!("token = {}\n", quote(token)));
+            }
+            if let Some(session) = &snapshot.session {
+                toml.push_str(&format!("session = {}\n", quote(session)));
+            }
+        }
+        // Replaces the previous manifest atomically, so a crash leaves either of them
+        let path = directory.join(MANIFEST_FILE);
+        let partial = directory.join(format!("{}.partial", MANIFEST_FILE));
+        fs::write(&partial, toml).map_err(|error| CheckpointError::Io(partial.clone(), error))?;
+        fs::rename(&partial, &path).map_err(|error| CheckpointError::Io(path, error))
+    }
+
+    fn parse(toml: &str, directory: &Path) -> Result<Self, String> {
+        let value = toml.parse::<Value>().map_err(|error| error.to_string())?;
+        let integer = |key: &str| {
+            value
+                .get(key)
+                .and_then(Value::as_integer)
+                .ok_or_else(|| format!("missing `{}`", key))
+        };
+        let epoch = integer("epoch")?;
+        let worker = integer("worker")?;
+        let mut states = BTreeMap::new();
+        let entries = match value.get("state") {
+            Some(entries) => entries.as_array().ok_or("`state` is not an array")?.clone(),
+            None => Vec::new(),
+        };
+        for entry in &entries {
+            let string = |key: &str| entry.get(key).and_then(Value::as_str).map(str::to_owned);
+            let field = |key: &str| string(key).ok_or_else(|| format!("state without `{}`", key));
+            let snapshot = Snapshot {
+                backend: field("backend")?,
+                path: directory.join(field("path")?),
+                token: string("token"),
+                session: string("session"),
+            };
+            states.insert(field("name")?, snapshot);
+        }
+        Ok(Manifest {
+            epoch: epoch.try_into().map_err(|_| "`epoch` is negative")?,
+            worker: worker.try_into().map_err(|_| "`worker` is negative")?,
+            states,
+        })
+    }
+}
+
+/// Checkpoints state whenever an input frontier passes the next checkpoint epoch.
+///
+/// A checkpoint is due whenever the frontier passes a multiple of the interval,
+/// and is labelled with the latest time the state is complete up to, so that a
+/// source resuming after that epoch neither repeats nor misses updates.
+/// Frontiers of times that do not convert to `u64` are taken to have passed every epoch.
+pub struct Checkpointer {
+    checkpoints: WorkerCheckpoints,
+    interval: u64,
+    next_epoch: u64,
+}
+
+impl Checkpointer {
+    pub fn new(checkpoints: WorkerCheckpoints, interval: u64) -> Self {
+        assert!(interval > 0, "Checkpoint interval must not be zero");
+        Checkpointer {
+            checkpoints,
+            interval,
+            next_epoch: interval,
+        }
+    }
+
+    pub fn checkpoints(&self) -> &WorkerCheckpoints {
+        &self.checkpoints
+    }
+
+    /// The epoch to checkpoint if `frontier` has passed the next multiple of the interval.
+    ///
+    /// An empty frontier passes no epoch, as state whose input is complete need not be resumed.
+    pub fn due<T: Copy + TryInto<u64>>(&mut self, frontier: &[T]) -> Option<u64> {
+        let earliest = earliest(frontier)?;
+        if earliest <= self.next_epoch {
+            return None;
+        }
+        let epoch = earliest - 1;
+        self.next_epoch = epoch / self.interval * self.interval + self.interval;
+        Some(epoch)
+    }
+
+    /// Checkpoints `state` if `frontier` has passed a checkpoint epoch, returning the epoch.
+    ///
+    /// Several multiples of the interval passed at once make a single checkpoint.
+    pub fn advance<T, S>(
+        &mut self,
+        frontier: &[T],
+        state: &StateHandle<S>,
+    ) -> Result<Option<u64>, CheckpointError>
+    where
+        T: Copy + TryInto<u64>,
+        S: StateBackend,
+    {
+        match self.due(frontier) {
+            Some(epoch) => self.checkpoints.checkpoint(epoch, state).map(|_| Some(epoch)),
+            None => Ok(None),
+        }
+    }
+}
+
+type Checkpoint = Box<Fn(&WorkerCheckpoints, u64) -> Result<Snapshot, CheckpointError>>;
+
+/// The managed state of all dataflows of one worker.
+///
+/// Handles opened here are checkpointed together, and once the worker restores
+/// from an epoch, each handle opens with the state recorded under its name then.
+/// Names must therefore be the same in the run that checkpoints and the run
+/// that resumes. Handles without a snapshot, such as those of dataflows built
+/// after the checkpoint, open empty.
+pub struct WorkerState {
+    config: Rc<BackendConfig>,
+    checkpoints: WorkerCheckpoints,
+    restoring: RefCell<Option<Manifest>>,
+    opened: RefCell<Vec<Checkpoint>>,
+    names: RefCell<HashSet<String>>,
+}
+
+impl WorkerState {
+    pub fn new(config: Rc<BackendConfig>, worker: usize) -> Self {
+        let checkpoints = WorkerCheckpoints::new(config.checkpoint_directory.clone(), worker);
+        WorkerState {
+            config,
+            checkpoints,
+            restoring: RefCell::new(None),
+            opened: RefCell::new(Vec::new()),
+            names: RefCell::new(HashSet::new()),
+        }
+    }
+
+    pub fn config(&self) -> Rc<BackendConfig> {
+        Rc::clone(&self.config)
+    }
+
+    pub fn checkpoints(&self) -> &WorkerCheckpoints {
+        &self.checkpoints
+    }
+
+    /// Opens handles from now on with the state of the worker's checkpoint of `epoch`.
+    pub fn restore_from(&self, epoch: u64) -> Result<(), CheckpointError> {
+        let manifest = self.checkpoints.manifest(epoch)?.ok_or_else(|| {
+            let path = self.checkpoints.epoch_directory(epoch).join(MANIFEST_FILE);
+            CheckpointError::Manifest(path, "no checkpoint was written".to_owned())
+        })?;
+        *self.restoring.borrow_mut() = Some(manifest);
+        Ok(())
+    }
+
+    /// The epoch the worker restores from, if any.
+    pub fn restored_epoch(&self) -> Option<u64> {
+        self.restoring.borrow().as_ref().map(|manifest| manifest.epoch)
+    }
+
+    /// Reserves the handle name `name` for one piece of the worker's state.
+    ///
+    /// Panics if the name is already taken, as two handles of the same name
+    /// would share their state and their snapshot.
+    pub fn claim(&self, name: &str) {
+        if !self.names.borrow_mut().insert(name.to_owned()) {
+            panic!("State name {} is used more than once", name);
+        }
+    }
+
+    /// Opens the state handle `name` on a backend of its own.
+    ///
+    /// Panics if the name is already taken.
+    pub fn open<S: StateBackend>(&self, name: &str) -> Result<StateHandle<S>, CheckpointError> {
+        self.claim(name);
+        let snapshot = self
+            .restoring
+            .borrow()
+            .as_ref()
+            .and_then(|manifest| manifest.states.get(name).cloned());
+        let backend = match snapshot {
+            Some(snapshot) => S::restore(&self.config, &snapshot)?,
+            None => S::new(&self.config),
+        };
+        let handle = StateHandle::new(Rc::new(backend), name);
+        let checkpointed = handle.clone();
+        self.opened.borrow_mut().push(Box::new(move |checkpoints, epoch| {
+            checkpoints.checkpoint(epoch, &checkpointed)
+        }));
+        Ok(handle)
+    }
+
+    /// Checkpoints every handle opened so far as of `epoch`.
+    ///
+    /// The state must reflect every update up to `epoch` and none after it.
+    pub fn checkpoint(&self, epoch: u64) -> Result<(), CheckpointError> {
+        for checkpoint in self.opened.borrow().iter() {
+            checkpoint(&self.checkpoints, epoch)?;
+        }
+        Ok(())
+    }
+}
+
+// Handle names may contain anything, but each must map to its own directory
+fn snapshot_directory(name: &str) -> String {
+    let mut directory = String::from("state-");
+    for byte in name.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'_' => directory.push(byte as char),
+            _ => directory.push_str(&format!("-{:02x}", byte)),
+        }
+    }
+    directory
+}
+
+// Writes a TOML basic string
+fn quote(string: &str) -> String {
+    let mut quoted = String::from("\"");
+    for character in string.chars() {
+        match character {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            character if character.is_control() => {
+                quoted.push_str(&format!("\\u{:04X}", character as u32))
+            }
+            character => quoted.push(character),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{snapshot_directory, Checkpointer, Manifest, Snapshot, WorkerCheckpoints};
+    #[cfg(feature = "in-memory")]
+    use super::WorkerState;
+    #[cfg(feature = "in-memory")]
+    use crate::{backends::InMemoryBackend, config::BackendConfig};
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+    #[cfg(feature = "in-memory")]
+    use std::rc::Rc;
+    use tempfile::TempDir;
+
+    #[test]
+    fn frontier_passes_epochs() {
+        let mut checkpointer = Checkpointer::new(WorkerCheckpoints::new("unused", 0), 10);
+        assert_eq!(checkpointer.due(&[0u64]), None);
+        assert_eq!(checkpointer.due(&[10u64]), None);
+        assert_eq!(checkpointer.due(&[11u64]), Some(10));
+        assert_eq!(checkpointer.due(&[15u64]), None);
+        assert_eq!(checkpointer.due(&[35usize, 42]), Some(34));
+        assert_eq!(checkpointer.due::<u64>(&[]), None);
+        assert_eq!(checkpointer.due(&[40u32]), None);
+        assert_eq!(checkpointer.due(&[41u32]), Some(40));
+    }
+
+    #[test]
+    fn manifest_round_trips() {
+        let directory = TempDir::new().unwrap();
+        let checkpoints = WorkerCheckpoints::new(directory.path(), 3);
+        let epoch_directory = checkpoints.epoch_directory(7);
+        std::fs::create_dir_all(&epoch_directory).unwrap();
+        let mut states = BTreeMap::new();
+        states.insert(
+            "a \"quoted\"\tname".to_owned(),
+            Snapshot {
+                backend: "rocksdb".to_owned(),
+                path: epoch_directory.join(snapshot_directory("a")),
+                token: None,
+                session: None,
+            },
+        );
+        states.insert(
+            "".to_owned(),
+            Snapshot {
+                backend: "faster".to_owned(),
+                path: PathBuf::from("/elsewhere"),
+                token: Some("token".to_owned()),
+                session: Some("session".to_owned()),
+            },
+        );
+        let manifest = Manifest {
+            epoch: 7,
+            worker: 3,
+            states,
+        };
+        manifest.write(&epoch_directory).unwrap();
+        assert_eq!(checkpoints.manifest(7).unwrap(), Some(manifest));
+        assert_eq!(checkpoints.manifest(8).unwrap(), None);
+        std::fs::rename(
+            directory.path().join("7").join("3"),
+            directory.path().join("7").join("4"),
+        )
+        .unwrap();
+        assert!(WorkerCheckpoints::new(directory.path(), 4).manifest(7).is_err());
+    }
+
+    #[test]
+    fn latest_epoch_is_checkpointed_by_every_worker() {
+        let directory = TempDir::new().unwrap();
+        let worker = |worker| WorkerCheckpoints::new(directory.path(), worker);
+        assert_eq!(worker(0).latest_epoch(2).unwrap(), None);
+        for (epoch, workers) in &[(4, 2), (9, 2), (12, 1)] {
+            for index in 0..*workers {
+                let manifest = Manifest {
+                    epoch: *epoch,
+                    worker: index,
+                    states: BTreeMap::new(),
+                };
+                let epoch_directory = worker(index).epoch_directory(*epoch);
+                std::fs::create_dir_all(&epoch_directory).unwrap();
+                manifest.write(&epoch_directory).unwrap();
+            }
+        }
+        assert_eq!(worker(0).latest_epoch(1).unwrap(), Some(12));
+        assert_eq!(worker(1).latest_epoch(2).unwrap(), Some(9));
+        assert_eq!(worker(0).latest_epoch(3).unwrap(), None);
+    }
+
+    #[cfg(feature = "in-memory")]
+    #[test]
+    fn worker_state_restores_opened_handles() {
+        let directory = TempDir::new().unwrap();
+        let config = Rc::new(BackendConfig {
+            directory: directory.path().to_owned(),
+            checkpoint_directory: directory.path().join("checkpoints"),
+            ..BackendConfig::default()
+        });
+        let state = WorkerState::new(Rc::clone(&config), 1);
+        let handle = state.open::<InMemoryBackend>("operator").unwrap();
+        handle.get_managed_count("count").increase(3);
+        state.checkpoint(8).unwrap();
+        handle.get_managed_count("count").increase(1);
+        assert!(state.restore_from(9).is_err());
+
+        let resumed = WorkerState::new(config, 1);
+        resumed.restore_from(8).unwrap();
+        assert_eq!(resumed.restored_epoch(), Some(8));
+        let handle = resumed.open::<InMemoryBackend>("operator").unwrap();
+        assert_eq!(handle.get_managed_count("count").get(), 3);
+        let other = resumed.open::<InMemoryBackend>("other").unwrap();
+        assert_eq!(other.get_managed_count("count").get(), 0);
+    }
+
+    #[cfg(feature = "in-memory")]
+    #[test]
+    #[should_panic(expected = "State name operator is used more than once")]
+    fn worker_state_names_are_unique() {
+        let state = WorkerState::new(Rc::new(BackendConfig::default()), 0);
+        state.claim("operator.1");
+        state.open::<InMemoryBackend>("operator").unwrap();
+        state.open::<InMemoryBackend>("operator").unwrap();
+    }
+
+    #[test]
+    fn snapshot_directories_are_distinct() {
+        assert_eq!(snapshot_directory("3.map"), "state-3.map");
+        assert_ne!(snapshot_directory("a/b"), snapshot_directory("a-2fb"));
+        assert_ne!(snapshot_directory(""), snapshot_directory("-"));
+    }
+}
@@ -0,0 +1,280 @@
+//! Codecs that decide how a managed map's keys and values become bytes.
+//!
+//! Every map uses `Bincode` for both unless created with
+//! `get_managed_map_with_codecs`. Codecs only matter where a backend stores
+//! bytes: RocksDB, sled and FASTER backends encode keys and values with them,
+//! and in-memory backends only use the key codec to order iteration.
+//!
+//! RocksDB merges decode operands with the value codec of the map that wrote
+//! them, so `rmw` works with any codec.
+//!
+//! Sorted maps encode their keys with `Ordered`, which keeps the order of any
+//! `SortKey` where bincode would not.
+
+use crate::StateKey;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::mem;
+
+/// Converts values of type `T` to and from bytes.
+///
+/// Iteration visits keys in the order of their encoded bytes, so a key codec
+/// also decides the order of `ManagedMap::iter`.
+pub trait Codec<T>: 'static {
+    /// Appends the encoding of `value` to `bytes`.
+    fn encode(value: &T, bytes: &mut Vec<u8>);
+
+    /// Decodes a value previously produced by `encode`.
+    fn decode(bytes: &[u8]) -> T;
+
+    fn encoded(value: &T) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        Self::encode(value, &mut bytes);
+        bytes
+    }
+}
+
+/// Serialises with bincode. This is the default for every map.
+pub struct Bincode;
+
+impl<T: Serialize + DeserializeOwned> Codec<T> for Bincode {
+    fn encode(value: &T, bytes: &mut Vec<u8>) {
+        bincode::serialize_into(bytes, value).unwrap();
+    }
+
+    fn decode(bytes: &[u8]) -> T {
+        bincode::deserialize(bytes).unwrap()
+    }
+}
+
+/// Serialises with abomonation, timely's own wire format.
+pub struct Abomonation;
+
+impl<T: abomonation::Abomonation + Clone> Codec<T> for Abomonation {
+    fn encode(value: &T, bytes: &mut Vec<u8>) {
+        unsafe { abomonation::encode(value, bytes).unwrap() };
+    }
+
+    fn decode(bytes: &[u8]) -> T {
+        // abomonation decodes in place and needs its input aligned for T
+        let words = (bytes.len() + mem::size_of::<u64>() - 1) / mem::size_of::<u64>();
+        let mut aligned = vec![0u64; words];
+        let buffer = unsafe {
+            std::slice::from_raw_parts_mut(aligned.as_mut_ptr() as *mut u8, bytes.len())
+        };
+        buffer.copy_from_slice(bytes);
+        let (value, _) = unsafe { abomonation::decode::<T>(buffer) }
+            .expect("Bytes were not encoded with abomonation");
+        value.clone()
+    }
+}
+
+/// Types whose in-memory representation can be copied out and back as plain bytes.
+///
+/// Implementors must be `Copy`, contain no pointers or references, and have no
+/// padding bytes.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($t:ty),*) => {
+        $(
+            unsafe impl Pod for $t {}
+            unsafe impl Pod for [$t; 2] {}
+            unsafe impl Pod for [$t; 4] {}
+        )*
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+/// Copies the bytes of a `Pod` value without any serialisation.
+///
+/// The encoding is the platform's native layout, so integers do not iterate
+/// in numeric order; use `U64` for ordered integer keys.
+pub struct Raw;
+
+impl<T: Pod> Codec<T> for Raw {
+    fn encode(value: &T, bytes: &mut Vec<u8>) {
+        let raw = unsafe {
+            std::slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>())
+        };
+        bytes.extend_from_slice(raw);
+    }
+
+    fn decode(bytes: &[u8]) -> T {
+        assert_eq!(bytes.len(), mem::size_of::<T>(), "Raw value has the wrong size");
+        unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const T) }
+    }
+}
+
+/// Unsigned integers that fit in a `u64`.
+pub trait AsU64: Copy + 'static {
+    fn to_u64(self) -> u64;
+    fn from_u64(value: u64) -> Self;
+}
+
+macro_rules! impl_as_u64 {
+    ($($t:ty),*) => {
+        $(
+            impl AsU64 for $t {
+                fn to_u64(self) -> u64 {
+                    self as u64
+                }
+
+                fn from_u64(value: u64) -> Self {
+                    value as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_as_u64!(u8, u16, u32, u64, usize);
+
+/// Encodes integer keys as their eight big-endian bytes.
+///
+/// The bytes are the key itself, so nothing is serialised, and iteration
+/// visits keys in numeric order without callers calling `to_be()`.
+pub struct U64;
+
+impl<T: AsU64> Codec<T> for U64 {
+    fn encode(value: &T, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&value.to_u64().to_be_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> T {
+        let mut word = [0u8; 8];
+        word.copy_from_slice(bytes);
+        T::from_u64(u64::from_be_bytes(word))
+    }
+}
+
+/// Keys whose `Ordered` encoding sorts in the same order as the keys themselves.
+///
+/// Every encoding has a fixed width, so that tuples of sort keys also sort
+/// element by element.
+pub trait SortKey: StateKey + Ord + Clone {
+    fn encode_sorted(&self, bytes: &mut Vec<u8>);
+
+    /// Decodes a key from the start of `bytes`, along with the number of bytes it took.
+    fn decode_sorted(bytes: &[u8]) -> (Self, usize);
+}
+
+macro_rules! impl_sort_key_unsigned {
+    ($($t:ty),*) => {
+        $(
+            impl SortKey for $t {
+                fn encode_sorted(&self, bytes: &mut Vec<u8>) {
+                    bytes.extend_from_slice(&self.to_be_bytes());
+                }
+
+                fn decode_sorted(bytes: &[u8]) -> (Self, usize) {
+                    let mut word = [0u8; mem::size_of::<$t>()];
+                    word.copy_from_slice(&bytes[..mem::size_of::<$t>()]);
+                    (<$t>::from_be_bytes(word), mem::size_of::<$t>())
+                }
+            }
+        )*
+    };
+}
+
+// Flipping the sign bit moves negative numbers below positive ones
+macro_rules! impl_sort_key_signed {
+    ($($t:ty => $u:ty),*) => {
+        $(
+            impl SortKey for $t {
+                fn encode_sorted(&self, bytes: &mut Vec<u8>) {
+                    let flipped = (*self as $u) ^ (1 << (mem::size_of::<$u>() * 8 - 1));
+                    flipped.encode_sorted(bytes);
+                }
+
+                fn decode_sorted(bytes: &[u8]) -> (Self, usize) {
+                    let (flipped, length) = <$u>::decode_sorted(bytes);
+                    ((flipped ^ (1 << (mem::size_of::<$u>() * 8 - 1))) as $t, length)
+                }
+            }
+        )*
+    };
+}
+
+impl_sort_key_unsigned!(u8, u16, u32, u64, u128, usize);
+impl_sort_key_signed!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize);
+
+impl<A: SortKey, B: SortKey> SortKey for (A, B) {
+    fn encode_sorted(&self, bytes: &mut Vec<u8>) {
+        self.0.encode_sorted(bytes);
+        self.1.encode_sorted(bytes);
+    }
+
+    fn decode_sorted(bytes: &[u8]) -> (Self, usize) {
+        let (a, a_length) = A::decode_sorted(bytes);
+        let (b, b_length) = B::decode_sorted(&bytes[a_length..]);
+        ((a, b), a_length + b_length)
+    }
+}
+
+impl<A: SortKey, B: SortKey, C: SortKey> SortKey for (A, B, C) {
+    fn encode_sorted(&self, bytes: &mut Vec<u8>) {
+        self.0.encode_sorted(bytes);
+        self.1.encode_sorted(bytes);
+        self.2.encode_sorted(bytes);
+    }
+
+    fn decode_sorted(bytes: &[u8]) -> (Self, usize) {
+        let ((a, b), ab_length) = <(A, B)>::decode_sorted(bytes);
+        let (c, c_length) = C::decode_sorted(&bytes[ab_length..]);
+        ((a, b, c), ab_length + c_length)
+    }
+}
+
+/// Encodes `SortKey`s so that iteration visits them in the keys' own order.
+pub struct Ordered;
+
+impl<T: SortKey> Codec<T> for Ordered {
+    fn encode(value: &T, bytes: &mut Vec<u8>) {
+        value.encode_sorted(bytes);
+    }
+
+    fn decode(bytes: &[u8]) -> T {
+        T::decode_sorted(bytes).0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Abomonation, Bincode, Codec, Ordered, Raw, U64};
+
+    fn round_trip<T, C>(value: T)
+    where
+        T: PartialEq + std::fmt::Debug,
+        C: Codec<T>,
+    {
+        assert_eq!(C::decode(&C::encoded(&value)), value);
+    }
+
+    #[test]
+    fn codecs_round_trip() {
+        round_trip::<_, Bincode>((1u64, String::from("bid")));
+        round_trip::<_, Abomonation>(vec![(1usize, 2usize), (3, 4)]);
+        round_trip::<_, Raw>(-7i64);
+        round_trip::<_, Raw>([1.5f64, 2.5]);
+        round_trip::<_, U64>(42usize);
+        round_trip::<_, Ordered>((-3i32, 7u64, 1u8));
+    }
+
+    #[test]
+    fn ordered_keys_order_as_themselves() {
+        let mut keys = vec![(2u64, -1i64), (1, 5), (2, -7), (1, i64::min_value()), (0, 0)];
+        let mut encoded: Vec<Vec<u8>> = keys.iter().map(Ordered::encoded).collect();
+        keys.sort();
+        encoded.sort();
+        let decoded: Vec<(u64, i64)> = encoded.iter().map(|bytes| Ordered::decode(bytes)).collect();
+        assert_eq!(decoded, keys);
+    }
+
+    #[test]
+    fn u64_keys_order_numerically() {
+        assert!(U64::encoded(&255u64) < U64::encoded(&256u64));
+        assert_eq!(U64::encoded(&1u32), vec![0, 0, 0, 0, 0, 0, 0, 1]);
+    }
+}
@@ -0,0 +1,29 @@
+//! Options for state kept in its own column family.
+//!
+//! `StateHandle::create_column_family` asks the backend to keep the managed
+//! state of one name apart from all other state, tuned by these options, and
+//! `StateHandle::drop_column_family` discards all of it at once. RocksDB
+//! backends give the state its own column family; backends with a single
+//! keyspace keep the state where they always do and ignore the options.
+//!
+//! Merges in a column family still use the merge operator of the map that
+//! writes them, as everywhere else.
+
+/// How RocksDB compacts a column family.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompactionStyle {
+    Level,
+    Universal,
+    Fifo,
+}
+
+/// Tuning for a column family. Unset options keep RocksDB's defaults.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ColumnFamilyOptions {
+    pub block_size: Option<usize>,
+    pub block_cache_size: Option<usize>,
+    /// Bits per key of a bloom filter, which speeds up point lookups of missing keys.
+    pub bloom_filter_bits: Option<i32>,
+    pub write_buffer_size: Option<usize>,
+    pub compaction_style: Option<CompactionStyle>,
+}
@@ -0,0 +1,71 @@
+//! Compaction of FASTER's log.
+//!
+//! FASTER appends every update and deletion to its log, so without compaction
+//! the log of a long run only ever grows. Compacting copies the live records of
+//! the oldest part of the log to its tail and then moves the log's begin
+//! address past that part, whose bytes are reclaimed.
+//!
+//! Each worker's `Session` decides when to compact, following the
+//! `CompactionPolicy` of its backend's section of the `BackendConfig`:
+//!
+//! - `compact_above` compacts once the log holds more than that many bytes.
+//! - `compact_tombstones` compacts once that fraction of the session's writes
+//!   since the last compaction were deletions.
+//! - `compact_every_epoch` compacts whenever the operator's frontier advances,
+//!   as reported by `StateHandle::advance`.
+//!
+//! Compactions and reclaimed bytes are counted per backend by
+//! `StateHandle::compaction_stats`, and over the whole process by `process_stats`.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "faster")]
+use std::sync::atomic::AtomicBool;
+
+/// How much of the log compactions have reclaimed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CompactionStats {
+    pub compactions: u64,
+    /// Bytes between the log's begin address before and after each compaction.
+    pub reclaimed_bytes: u64,
+}
+
+static PROCESS_COMPACTIONS: AtomicU64 = AtomicU64::new(0);
+static PROCESS_RECLAIMED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+// Workers of a process share FASTER instances, which one compacts at a time
+#[cfg(feature = "faster")]
+static COMPACTING: AtomicBool = AtomicBool::new(false);
+
+/// The compactions of every FASTER instance of this process so far, summed over all workers.
+pub fn process_stats() -> CompactionStats {
+    CompactionStats {
+        compactions: PROCESS_COMPACTIONS.load(Ordering::Relaxed),
+        reclaimed_bytes: PROCESS_RECLAIMED_BYTES.load(Ordering::Relaxed),
+    }
+}
+
+/// Compacts the oldest `fraction` of the part of `faster`'s log that is no longer in memory,
+/// returning the bytes reclaimed.
+///
+/// Returns `None` without compacting if that part is empty, or while another worker of
+/// the process is compacting.
+#[cfg(feature = "faster")]
+pub(crate) fn compact(faster: &faster_rs::FasterKv, fraction: f64) -> Option<u64> {
+    if COMPACTING.swap(true, Ordering::Acquire) {
+        return None;
+    }
+    let begin = faster.begin_address();
+    let on_disk = faster.head_address().saturating_sub(begin);
+    let until = begin + (on_disk as f64 * fraction) as u64;
+    let compacted = until > begin && faster.compact(until);
+    COMPACTING.store(false, Ordering::Release);
+    if !compacted {
+        return None;
+    }
+
+    let reclaimed = faster.begin_address().saturating_sub(begin);
+    PROCESS_COMPACTIONS.fetch_add(1, Ordering::Relaxed);
+    PROCESS_RECLAIMED_BYTES.fetch_add(reclaimed, Ordering::Relaxed);
+    log::info!("Compacted FASTER's log up to {}, reclaiming {} bytes", until, reclaimed);
+    Some(reclaimed)
+}
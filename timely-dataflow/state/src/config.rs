@@ -0,0 +1,803 @@
+//! Configuration of the state backends.
+//!
+//! A `BackendConfig` holds one section per backend and is passed to
+//! `StateBackend::new`, so that backends no longer read configuration files
+//! from the current directory. It can be built in code, starting from
+//! `BackendConfig::default()`, or loaded from TOML such as
+//!
+//! ```toml
+//! directory = "/tmp/state"
+//! checkpoint_directory = "/tmp/checkpoints"
+//!
+//! [faster]
+//! table_size = 16777216
+//! log_size = 1073741824
+//! refresh_interval_ms = 10
+//! max_pending = 256
+//! compact_above = 8589934592
+//! mutable_budget = 268435456
+//!
+//! [faster_node]
+//! sharding = "node"
+//! numa_nodes = 2
+//!
+//! [rocksdb]
+//! block_size = 131072
+//! write_buffer_size = 268435456
+//!
+//! [sled]
+//! cache_capacity = 268435456
+//!
+//! [cache]
+//! budget = 67108864
+//! policy = "epoch"
+//! ```
+//!
+//! where every key is optional. `BackendConfig::from_env` reads the file named
+//! by `TIMELY_STATE_CONFIG`, if set, and then applies overrides such as
+//! `TIMELY_STATE_FASTER_LOG_SIZE=2147483648` for any key of any section.
+
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use toml::value::Table;
+use toml::Value;
+
+/// Names the TOML file that `BackendConfig::from_env` starts from.
+pub const CONFIG_FILE_VARIABLE: &str = "TIMELY_STATE_CONFIG";
+
+const ENV_PREFIX: &str = "TIMELY_STATE_";
+
+/// Configuration of every state backend.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackendConfig {
+    /// Where backends create the directories that hold their files.
+    pub directory: PathBuf,
+    /// Where workers write checkpoints of their state.
+    pub checkpoint_directory: PathBuf,
+    pub faster: FasterConfig,
+    pub faster_in_memory: FasterConfig,
+    /// The FASTER instance that `timely::execute` shares between the workers of a process.
+    pub faster_node: FasterConfig,
+    pub rocksdb: RocksDBConfig,
+    pub rocksdb_merge: RocksDBConfig,
+    /// Read by nothing since `RocksDBMergeBackend2` became `RocksDBMergeBackend`,
+    /// which reads `rocksdb_merge`; accepted so that existing files still load.
+    pub rocksdb_merge2: RocksDBConfig,
+    pub sled: SledConfig,
+    /// The write-back cache of a `CachedBackend`, whatever backend it caches.
+    pub cache: CacheConfig,
+}
+
+/// Sizing of a FASTER instance.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FasterConfig {
+    /// Number of hash index entries, a power of two.
+    pub table_size: u64,
+    /// Size of the log in bytes.
+    pub log_size: u64,
+    pub pre_allocate_log: bool,
+    /// How each worker's session with the instance is maintained.
+    pub session: SessionPolicy,
+    pub compaction: CompactionPolicy,
+    /// Bytes of the in-memory log that take updates in place, rather than FASTER's
+    /// default fraction of `log_size`.
+    pub mutable_budget: Option<u64>,
+    /// How many instances `timely::execute` shares between the workers of a process.
+    /// Only the `faster_node` section is sharded, and each shard is sized as above.
+    pub sharding: Sharding,
+    /// How many groups `Sharding::Node` splits the workers of a process into.
+    pub numa_nodes: usize,
+}
+
+/// When a `Session` refreshes, completes pending operations and reports the size of its
+/// FASTER instance.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionPolicy {
+    pub refresh: Refresh,
+    /// Completes pending operations after this many operations.
+    pub complete_pending_every: u64,
+    /// Completes pending operations once this many have gone pending, however few
+    /// operations that took.
+    pub max_pending: u64,
+    /// Logs the size of the instance after this many operations, or never if zero.
+    pub report_size_every: u64,
+}
+
+/// When the log of a FASTER instance is compacted, and how much of it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompactionPolicy {
+    pub trigger: CompactionTrigger,
+    /// The fraction of the log on disk, oldest first, that a compaction reclaims.
+    pub fraction: f64,
+}
+
+/// What makes a session compact the log.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompactionTrigger {
+    Never,
+    /// The log growing beyond this many bytes.
+    LogSize(u64),
+    /// This fraction of the session's writes since the last compaction being deletions.
+    Tombstones(f64),
+    /// The operator's input frontier advancing.
+    Epoch,
+}
+
+/// Which workers of a process share a FASTER instance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sharding {
+    /// Every worker of the process.
+    Process,
+    /// A contiguous range of the workers, by index, out of `numa_nodes` such ranges.
+    ///
+    /// Timely does not pin workers to NUMA nodes, so a range only shares its node's
+    /// memory if the workers' threads are placed on that node by other means.
+    Node,
+    /// None; each worker has an instance of its own.
+    Worker,
+}
+
+/// How often a session refreshes, publishing its progress to FASTER's other sessions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Refresh {
+    /// After this many operations.
+    Ops(u64),
+    /// On the first operation once this long has passed.
+    Interval(Duration),
+}
+
+/// Tuning of a RocksDB instance. Unset options keep RocksDB's defaults.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RocksDBConfig {
+    pub block_size: Option<usize>,
+    /// Size of the LRU block cache in bytes.
+    pub lru_size: Option<usize>,
+    pub write_buffer_size: Option<usize>,
+    /// Size of the hash index used for point lookups, in bytes.
+    pub hash_index_size: Option<u64>,
+}
+
+/// Tuning of a sled instance.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SledConfig {
+    /// Bytes of pages sled caches in memory, rather than sled's default.
+    pub cache_capacity: Option<u64>,
+    /// Flushes writes to disk this often. Unset never flushes periodically, as state
+    /// is not recovered from sled's own files.
+    pub flush_every_ms: Option<u64>,
+}
+
+/// Sizing of the write-back cache of a `CachedBackend`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CacheConfig {
+    /// Bytes of keys and values that the cached maps of a backend may hold together.
+    pub budget: u64,
+    pub policy: CachePolicy,
+}
+
+/// Which entries a `CachedBackend` keeps once the frontier advances.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// Keeps entries until they are the least recently used of a map in use over budget.
+    Lru,
+    /// Forgets every entry once it is written back.
+    Epoch,
+}
+
+/// Why a configuration could not be loaded.
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(String),
+    UnknownKey(String),
+    /// A key whose value has the wrong type or is out of range.
+    Invalid { key: String, reason: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, error) => write!(f, "cannot read {}: {}", path.display(), error),
+            ConfigError::Parse(error) => write!(f, "cannot parse state configuration: {}", error),
+            ConfigError::UnknownKey(key) => write!(f, "unknown state configuration key `{}`", key),
+            ConfigError::Invalid { key, reason } => write!(f, "invalid `{}`: {}", key, reason),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+impl Default for BackendConfig {
+    fn default() -> Self {
+        BackendConfig {
+            directory: PathBuf::from("."),
+            checkpoint_directory: PathBuf::from("checkpoints"),
+            faster: FasterConfig::default(),
+            faster_in_memory: FasterConfig {
+                log_size: 12 * 1024 * 1024 * 1024,
+                session: SessionPolicy {
+                    refresh: Refresh::Ops(1 << 5),
+                    report_size_every: 0,
+                    ..SessionPolicy::default()
+                },
+                ..FasterConfig::default()
+            },
+            faster_node: FasterConfig {
+                log_size: 12 * 1024 * 1024 * 1024,
+                pre_allocate_log: false,
+                ..FasterConfig::default()
+            },
+            rocksdb: RocksDBConfig::default(),
+            rocksdb_merge: RocksDBConfig::default(),
+            rocksdb_merge2: RocksDBConfig::default(),
+            sled: SledConfig::default(),
+            cache: CacheConfig::default(),
+        }
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            budget: 64 * 1024 * 1024,
+            policy: CachePolicy::Lru,
+        }
+    }
+}
+
+impl Default for FasterConfig {
+    fn default() -> Self {
+        FasterConfig {
+            table_size: 1 << 24,
+            log_size: 1 << 30,
+            pre_allocate_log: true,
+            session: SessionPolicy::default(),
+            compaction: CompactionPolicy::default(),
+            mutable_budget: None,
+            sharding: Sharding::Process,
+            numa_nodes: 1,
+        }
+    }
+}
+
+impl Default for CompactionPolicy {
+    fn default() -> Self {
+        CompactionPolicy {
+            trigger: CompactionTrigger::Never,
+            fraction: 0.5,
+        }
+    }
+}
+
+impl Default for SessionPolicy {
+    fn default() -> Self {
+        SessionPolicy {
+            refresh: Refresh::Ops(1 << 4),
+            complete_pending_every: 1 << 10,
+            max_pending: 1 << 10,
+            report_size_every: 1 << 20,
+        }
+    }
+}
+
+impl BackendConfig {
+    /// Parses and validates a TOML configuration. Missing keys keep their defaults.
+    pub fn from_toml(toml: &str) -> Result<Self, ConfigError> {
+        let value = toml
+            .parse::<Value>()
+            .map_err(|error| ConfigError::Parse(error.to_string()))?;
+        let mut config = BackendConfig::default();
+        if let Some(table) = value.as_table() {
+            config.apply_table(table)?;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let toml = fs::read_to_string(path)
+            .map_err(|error| ConfigError::Io(path.to_owned(), error))?;
+        BackendConfig::from_toml(&toml)
+    }
+
+    /// Loads the file named by `TIMELY_STATE_CONFIG`, or the defaults if it is
+    /// unset, and applies any `TIMELY_STATE_<SECTION>_<KEY>` overrides.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let mut config = match env::var_os(CONFIG_FILE_VARIABLE) {
+            Some(path) => BackendConfig::from_file(path)?,
+            None => BackendConfig::default(),
+        };
+        for (variable, value) in env::vars() {
+            if variable == CONFIG_FILE_VARIABLE || !variable.starts_with(ENV_PREFIX) {
+                continue;
+            }
+            config.apply_override(&variable[ENV_PREFIX.len()..].to_lowercase(), &value)?;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks that every backend could be created with this configuration.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for (section, faster) in self.faster_sections() {
+            faster.validate(section)?;
+        }
+        for (section, rocksdb) in self.rocksdb_sections() {
+            rocksdb.validate(section)?;
+        }
+        self.sled.validate("sled")?;
+        self.cache.validate("cache")?;
+        Ok(())
+    }
+
+    fn faster_sections(&self) -> Vec<(&'static str, &FasterConfig)> {
+        vec![
+            ("faster", &self.faster),
+            ("faster_in_memory", &self.faster_in_memory),
+            ("faster_node", &self.faster_node),
+        ]
+    }
+
+    fn rocksdb_sections(&self) -> Vec<(&'static str, &RocksDBConfig)> {
+        vec![
+            ("rocksdb", &self.rocksdb),
+            ("rocksdb_merge", &self.rocksdb_merge),
+            ("rocksdb_merge2", &self.rocksdb_merge2),
+        ]
+    }
+
+    fn apply_table(&mut self, table: &Table) -> Result<(), ConfigError> {
+        for (key, value) in table {
+            match (key.as_str(), value) {
+                ("directory", value) => self.directory = PathBuf::from(string(key, value)?),
+                ("checkpoint_directory", value) => {
+                    self.checkpoint_directory = PathBuf::from(string(key, value)?)
+                }
+                (section, Value::Table(entries)) => {
+                    for (name, value) in entries {
+                        self.set(section, name, value)?;
+                    }
+                }
+                _ => return Err(ConfigError::UnknownKey(key.clone())),
+            }
+        }
+        Ok(())
+    }
+
+    // Applies an override named '<section>_<key>', whose value is parsed as in TOML
+    fn apply_override(&mut self, name: &str, raw: &str) -> Result<(), ConfigError> {
+        if name == "directory" {
+            self.directory = PathBuf::from(raw);
+            return Ok(());
+        }
+        if name == "checkpoint_directory" {
+            self.checkpoint_directory = PathBuf::from(raw);
+            return Ok(());
+        }
+        let value = format!("value = {}", raw)
+            .parse::<Value>()
+            .ok()
+            .and_then(|table| table.get("value").cloned())
+            .unwrap_or_else(|| Value::String(raw.to_owned()));
+        // Section names contain underscores, so try the longest section first
+        let mut sections = [
+            "faster_in_memory",
+            "faster_node",
+            "faster",
+            "rocksdb_merge2",
+            "rocksdb_merge",
+            "rocksdb",
+            "sled",
+            "cache",
+        ]
+        .iter()
+        .filter(|section| name.starts_with(&format!("{}_", section)));
+        match sections.next() {
+            Some(section) => self.set(section, &name[section.len() + 1..], &value),
+            None => Err(ConfigError::UnknownKey(name.to_owned())),
+        }
+    }
+
+    fn set(&mut self, section: &str, key: &str, value: &Value) -> Result<(), ConfigError> {
+        let qualified = format!("{}.{}", section, key);
+        match section {
+            "faster" => self.faster.set(&qualified, key, value),
+            "faster_in_memory" => self.faster_in_memory.set(&qualified, key, value),
+            "faster_node" => self.faster_node.set(&qualified, key, value),
+            "rocksdb" => self.rocksdb.set(&qualified, key, value),
+            "rocksdb_merge" => self.rocksdb_merge.set(&qualified, key, value),
+            "rocksdb_merge2" => self.rocksdb_merge2.set(&qualified, key, value),
+            "sled" => self.sled.set(&qualified, key, value),
+            "cache" => self.cache.set(&qualified, key, value),
+            _ => Err(ConfigError::UnknownKey(qualified)),
+        }
+    }
+}
+
+impl FasterConfig {
+    fn set(&mut self, qualified: &str, key: &str, value: &Value) -> Result<(), ConfigError> {
+        match key {
+            "table_size" => self.table_size = integer(qualified, value)?,
+            "log_size" => self.log_size = integer(qualified, value)?,
+            "pre_allocate_log" => self.pre_allocate_log = boolean(qualified, value)?,
+            "refresh_every" => self.session.refresh = Refresh::Ops(integer(qualified, value)?),
+            "refresh_interval_ms" => {
+                let millis = integer(qualified, value)?;
+                self.session.refresh = Refresh::Interval(Duration::from_millis(millis));
+            }
+            "complete_pending_every" => {
+                self.session.complete_pending_every = integer(qualified, value)?
+            }
+            "max_pending" => self.session.max_pending = integer(qualified, value)?,
+            "report_size_every" => self.session.report_size_every = integer(qualified, value)?,
+            "compact_above" => {
+                self.compaction.trigger = CompactionTrigger::LogSize(integer(qualified, value)?)
+            }
+            "compact_tombstones" => {
+                self.compaction.trigger = CompactionTrigger::Tombstones(float(qualified, value)?)
+            }
+            "compact_every_epoch" => {
+                self.compaction.trigger = if boolean(qualified, value)? {
+                    CompactionTrigger::Epoch
+                } else {
+                    CompactionTrigger::Never
+                }
+            }
+            "compact_fraction" => self.compaction.fraction = float(qualified, value)?,
+            "mutable_budget" => self.mutable_budget = Some(integer(qualified, value)?),
+            "sharding" => {
+                self.sharding = match string(qualified, value)? {
+                    "process" => Sharding::Process,
+                    "node" => Sharding::Node,
+                    "worker" => Sharding::Worker,
+                    other => {
+                        return Err(ConfigError::Invalid {
+                            key: qualified.to_owned(),
+                            reason: format!(
+                                "expected \"process\", \"node\" or \"worker\", found \"{}\"",
+                                other
+                            ),
+                        })
+                    }
+                }
+            }
+            "numa_nodes" => self.numa_nodes = integer(qualified, value)?,
+            _ => return Err(ConfigError::UnknownKey(qualified.to_owned())),
+        }
+        Ok(())
+    }
+
+    fn validate(&self, section: &str) -> Result<(), ConfigError> {
+        if !self.table_size.is_power_of_two() {
+            return Err(invalid(section, "table_size", "must be a power of two"));
+        }
+        if self.log_size == 0 {
+            return Err(invalid(section, "log_size", "must not be zero"));
+        }
+        match self.session.refresh {
+            Refresh::Ops(0) => return Err(invalid(section, "refresh_every", "must not be zero")),
+            Refresh::Interval(interval) if interval == Duration::from_millis(0) => {
+                return Err(invalid(section, "refresh_interval_ms", "must not be zero"))
+            }
+            _ => {}
+        }
+        if self.session.complete_pending_every == 0 {
+            return Err(invalid(section, "complete_pending_every", "must not be zero"));
+        }
+        if self.session.max_pending == 0 {
+            return Err(invalid(section, "max_pending", "must not be zero"));
+        }
+        if let CompactionTrigger::Tombstones(fraction) = self.compaction.trigger {
+            if !(fraction > 0.0 && fraction <= 1.0) {
+                return Err(invalid(section, "compact_tombstones", "must be in (0, 1]"));
+            }
+        }
+        if !(self.compaction.fraction > 0.0 && self.compaction.fraction <= 1.0) {
+            return Err(invalid(section, "compact_fraction", "must be in (0, 1]"));
+        }
+        match self.mutable_budget {
+            Some(0) => return Err(invalid(section, "mutable_budget", "must not be zero")),
+            Some(budget) if budget > self.log_size => {
+                return Err(invalid(section, "mutable_budget", "must not exceed log_size"))
+            }
+            _ => {}
+        }
+        if self.numa_nodes == 0 {
+            return Err(invalid(section, "numa_nodes", "must not be zero"));
+        }
+        // Only the instances that timely::execute creates are sharded
+        if section != "faster_node" {
+            let only_node = "only applies to the faster_node section";
+            if self.sharding != Sharding::Process {
+                return Err(invalid(section, "sharding", only_node));
+            }
+            if self.numa_nodes != 1 {
+                return Err(invalid(section, "numa_nodes", only_node));
+            }
+        }
+        Ok(())
+    }
+
+    /// The number of instances shared between the `workers` of a process.
+    pub fn shards(&self, workers: usize) -> usize {
+        match self.sharding {
+            Sharding::Process => 1,
+            Sharding::Node => self.numa_nodes.min(workers).max(1),
+            Sharding::Worker => workers.max(1),
+        }
+    }
+
+    /// The instance that the process's `worker`th of `workers` uses, from contiguous
+    /// ranges of workers.
+    pub fn shard_of(&self, worker: usize, workers: usize) -> usize {
+        worker * self.shards(workers) / workers.max(1)
+    }
+}
+
+impl RocksDBConfig {
+    fn set(&mut self, qualified: &str, key: &str, value: &Value) -> Result<(), ConfigError> {
+        match key {
+            "block_size" => self.block_size = Some(integer(qualified, value)?),
+            "lru_size" => self.lru_size = Some(integer(qualified, value)?),
+            "write_buffer_size" => self.write_buffer_size = Some(integer(qualified, value)?),
+            "hash_index_size" => self.hash_index_size = Some(integer(qualified, value)?),
+            _ => return Err(ConfigError::UnknownKey(qualified.to_owned())),
+        }
+        Ok(())
+    }
+
+    fn validate(&self, section: &str) -> Result<(), ConfigError> {
+        if self.block_size == Some(0) {
+            return Err(invalid(section, "block_size", "must not be zero"));
+        }
+        if self.write_buffer_size == Some(0) {
+            return Err(invalid(section, "write_buffer_size", "must not be zero"));
+        }
+        Ok(())
+    }
+}
+
+impl SledConfig {
+    fn set(&mut self, qualified: &str, key: &str, value: &Value) -> Result<(), ConfigError> {
+        match key {
+            "cache_capacity" => self.cache_capacity = Some(integer(qualified, value)?),
+            "flush_every_ms" => self.flush_every_ms = Some(integer(qualified, value)?),
+            _ => return Err(ConfigError::UnknownKey(qualified.to_owned())),
+        }
+        Ok(())
+    }
+
+    fn validate(&self, section: &str) -> Result<(), ConfigError> {
+        if self.flush_every_ms == Some(0) {
+            return Err(invalid(section, "flush_every_ms", "must not be zero"));
+        }
+        Ok(())
+    }
+}
+
+impl CacheConfig {
+    fn set(&mut self, qualified: &str, key: &str, value: &Value) -> Result<(), ConfigError> {
+        match key {
+            "budget" => self.budget = integer(qualified, value)?,
+            "policy" => {
+                self.policy = match string(qualified, value)? {
+                    "lru" => CachePolicy::Lru,
+                    "epoch" => CachePolicy::Epoch,
+                    other => {
+                        return Err(ConfigError::Invalid {
+                            key: qualified.to_owned(),
+                            reason: format!("expected \"lru\" or \"epoch\", found \"{}\"", other),
+                        })
+                    }
+                }
+            }
+            _ => return Err(ConfigError::UnknownKey(qualified.to_owned())),
+        }
+        Ok(())
+    }
+
+    fn validate(&self, section: &str) -> Result<(), ConfigError> {
+        if self.budget == 0 {
+            return Err(invalid(section, "budget", "must not be zero"));
+        }
+        Ok(())
+    }
+}
+
+fn invalid(section: &str, key: &str, reason: &str) -> ConfigError {
+    ConfigError::Invalid {
+        key: format!("{}.{}", section, key),
+        reason: reason.to_owned(),
+    }
+}
+
+fn integer<T: std::convert::TryFrom<i64>>(key: &str, value: &Value) -> Result<T, ConfigError> {
+    let integer = value.as_integer().ok_or_else(|| ConfigError::Invalid {
+        key: key.to_owned(),
+        reason: format!("expected an integer, found {}", value.type_str()),
+    })?;
+    T::try_from(integer).map_err(|_| ConfigError::Invalid {
+        key: key.to_owned(),
+        reason: format!("{} is out of range", integer),
+    })
+}
+
+fn boolean(key: &str, value: &Value) -> Result<bool, ConfigError> {
+    value.as_bool().ok_or_else(|| ConfigError::Invalid {
+        key: key.to_owned(),
+        reason: format!("expected a boolean, found {}", value.type_str()),
+    })
+}
+
+fn float(key: &str, value: &Value) -> Result<f64, ConfigError> {
+    value.as_float().ok_or_else(|| ConfigError::Invalid {
+        key: key.to_owned(),
+        reason: format!("expected a float, found {}", value.type_str()),
+    })
+}
+
+fn string<'a>(key: &str, value: &'a Value) -> Result<&'a str, ConfigError> {
+    value.as_str().ok_or_else(|| ConfigError::Invalid {
+        key: key.to_owned(),
+        reason: format!("expected a string, found {}", value.type_str()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BackendConfig, CachePolicy, CompactionTrigger, ConfigError, Refresh, Sharding};
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    #[test]
+    fn toml_overrides_defaults() {
+        let config = BackendConfig::from_toml(
+            r#"
+            directory = "/tmp/state"
+            checkpoint_directory = "/tmp/checkpoints"
+
+            [faster]
+            table_size = 1024
+            pre_allocate_log = false
+            refresh_interval_ms = 5
+            max_pending = 64
+            compact_tombstones = 0.25
+            mutable_budget = 65536
+
+            [rocksdb_merge]
+            block_size = 131072
+
+            [sled]
+            cache_capacity = 1048576
+
+            [cache]
+            policy = "epoch"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.directory, PathBuf::from("/tmp/state"));
+        assert_eq!(config.checkpoint_directory, PathBuf::from("/tmp/checkpoints"));
+        assert_eq!(config.faster.table_size, 1024);
+        assert!(!config.faster.pre_allocate_log);
+        assert_eq!(config.faster.log_size, BackendConfig::default().faster.log_size);
+        assert_eq!(config.faster.session.refresh, Refresh::Interval(Duration::from_millis(5)));
+        assert_eq!(config.faster.session.max_pending, 64);
+        assert_eq!(config.faster.compaction.trigger, CompactionTrigger::Tombstones(0.25));
+        assert_eq!(config.faster.mutable_budget, Some(65536));
+        assert_eq!(config.faster_node.compaction, BackendConfig::default().faster_node.compaction);
+        assert_eq!(config.faster_node.session, BackendConfig::default().faster_node.session);
+        assert_eq!(config.rocksdb_merge.block_size, Some(131072));
+        assert_eq!(config.rocksdb.block_size, None);
+        assert_eq!(config.sled.cache_capacity, Some(1048576));
+        assert_eq!(config.sled.flush_every_ms, None);
+        assert_eq!(config.cache.policy, CachePolicy::Epoch);
+        assert_eq!(config.cache.budget, BackendConfig::default().cache.budget);
+    }
+
+    #[test]
+    fn overrides_name_section_and_key() {
+        let mut config = BackendConfig::default();
+        config.apply_override("faster_node_log_size", "4096").unwrap();
+        config.apply_override("rocksdb_merge_lru_size", "8").unwrap();
+        config.apply_override("rocksdb_merge2_lru_size", "16").unwrap();
+        config.apply_override("faster_pre_allocate_log", "false").unwrap();
+        config.apply_override("cache_budget", "1024").unwrap();
+        config.apply_override("sled_flush_every_ms", "500").unwrap();
+        config.apply_override("faster_in_memory_refresh_every", "8").unwrap();
+        config.apply_override("faster_node_compact_every_epoch", "true").unwrap();
+        config.apply_override("faster_node_compact_fraction", "0.75").unwrap();
+        config.apply_override("faster_node_sharding", "node").unwrap();
+        config.apply_override("faster_node_numa_nodes", "2").unwrap();
+        assert_eq!(config.faster_node.log_size, 4096);
+        assert_eq!(config.faster.log_size, BackendConfig::default().faster.log_size);
+        assert_eq!(config.rocksdb_merge.lru_size, Some(8));
+        assert_eq!(config.rocksdb_merge2.lru_size, Some(16));
+        assert!(!config.faster.pre_allocate_log);
+        assert_eq!(config.cache.budget, 1024);
+        assert_eq!(config.sled.flush_every_ms, Some(500));
+        assert_eq!(config.faster_in_memory.session.refresh, Refresh::Ops(8));
+        assert_eq!(config.faster_node.compaction.trigger, CompactionTrigger::Epoch);
+        assert_eq!(config.faster_node.compaction.fraction, 0.75);
+        assert_eq!(config.faster_node.sharding, Sharding::Node);
+        assert_eq!(config.faster_node.numa_nodes, 2);
+    }
+
+    #[test]
+    fn shards_contiguous_workers() {
+        let mut config = BackendConfig::default().faster_node;
+        assert_eq!(config.shards(8), 1);
+        assert!((0..8).all(|worker| config.shard_of(worker, 8) == 0));
+
+        config.sharding = Sharding::Node;
+        config.numa_nodes = 2;
+        assert_eq!(config.shards(8), 2);
+        let shards: Vec<usize> = (0..8).map(|worker| config.shard_of(worker, 8)).collect();
+        assert_eq!(shards, vec![0, 0, 0, 0, 1, 1, 1, 1]);
+        assert_eq!(config.shards(1), 1);
+        assert_eq!(config.shard_of(0, 1), 0);
+
+        config.sharding = Sharding::Worker;
+        assert_eq!(config.shards(3), 3);
+        let shards: Vec<usize> = (0..3).map(|worker| config.shard_of(worker, 3)).collect();
+        assert_eq!(shards, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn rejects_invalid_configuration() {
+        match BackendConfig::from_toml("[faster]\ntablesize = 1024") {
+            Err(ConfigError::UnknownKey(key)) => assert_eq!(key, "faster.tablesize"),
+            other => panic!("unexpected {:?}", other),
+        }
+        match BackendConfig::from_toml("[faster]\ntable_size = 1000") {
+            Err(ConfigError::Invalid { key, .. }) => assert_eq!(key, "faster.table_size"),
+            other => panic!("unexpected {:?}", other),
+        }
+        match BackendConfig::from_toml("[faster_node]\ncomplete_pending_every = 0") {
+            Err(ConfigError::Invalid { key, .. }) => {
+                assert_eq!(key, "faster_node.complete_pending_every")
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match BackendConfig::from_toml("[faster]\ncompact_fraction = 1.5") {
+            Err(ConfigError::Invalid { key, .. }) => assert_eq!(key, "faster.compact_fraction"),
+            other => panic!("unexpected {:?}", other),
+        }
+        match BackendConfig::from_toml("[faster]\nlog_size = 1024\nmutable_budget = 2048") {
+            Err(ConfigError::Invalid { key, .. }) => assert_eq!(key, "faster.mutable_budget"),
+            other => panic!("unexpected {:?}", other),
+        }
+        match BackendConfig::from_toml("[faster_node]\nsharding = \"thread\"") {
+            Err(ConfigError::Invalid { key, .. }) => assert_eq!(key, "faster_node.sharding"),
+            other => panic!("unexpected {:?}", other),
+        }
+        match BackendConfig::from_toml("[faster]\nsharding = \"worker\"") {
+            Err(ConfigError::Invalid { key, .. }) => assert_eq!(key, "faster.sharding"),
+            other => panic!("unexpected {:?}", other),
+        }
+        match BackendConfig::from_toml("[faster_in_memory]\nnuma_nodes = 2") {
+            Err(ConfigError::Invalid { key, .. }) => {
+                assert_eq!(key, "faster_in_memory.numa_nodes")
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match BackendConfig::from_toml("[rocksdb]\nblock_size = -1") {
+            Err(ConfigError::Invalid { key, .. }) => assert_eq!(key, "rocksdb.block_size"),
+            other => panic!("unexpected {:?}", other),
+        }
+        match BackendConfig::from_toml("[rocksdb]\nblock_size = \"large\"") {
+            Err(ConfigError::Invalid { key, .. }) => assert_eq!(key, "rocksdb.block_size"),
+            other => panic!("unexpected {:?}", other),
+        }
+        match BackendConfig::from_toml("[cache]\npolicy = \"fifo\"") {
+            Err(ConfigError::Invalid { key, .. }) => assert_eq!(key, "cache.policy"),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(BackendConfig::from_file("missing.toml").is_err());
+    }
+}
@@ -0,0 +1,61 @@
+//! The traits managed state requires of its keys and values.
+//!
+//! Any serde type can be stored. Only values updated with `rmw` need to say how
+//! a modification is merged into the existing value.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// A type usable as the key of a `ManagedMap`.
+pub trait StateKey: 'static + Serialize + DeserializeOwned + Hash + Eq + Debug {}
+
+impl<T: 'static + Serialize + DeserializeOwned + Hash + Eq + Debug> StateKey for T {}
+
+/// A type storable in a `ManagedValue` or as the value of a `ManagedMap`.
+pub trait StateValue: 'static + Serialize + DeserializeOwned {}
+
+impl<T: 'static + Serialize + DeserializeOwned> StateValue for T {}
+
+/// A value that can be updated in place by `rmw`.
+pub trait Rmw: StateValue {
+    /// Combines `self` with `modification` into the value to store.
+    fn rmw(&self, modification: Self) -> Self;
+}
+
+macro_rules! impl_rmw_add {
+    ($($t:ty),*) => {
+        $(
+            impl Rmw for $t {
+                fn rmw(&self, modification: Self) -> Self {
+                    self + modification
+                }
+            }
+        )*
+    };
+}
+
+impl_rmw_add!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64);
+
+impl<T: StateValue + Clone> Rmw for Vec<T> {
+    fn rmw(&self, modification: Self) -> Self {
+        let mut merged = self.clone();
+        merged.extend(modification);
+        merged
+    }
+}
+
+impl Rmw for String {
+    fn rmw(&self, modification: Self) -> Self {
+        let mut merged = self.clone();
+        merged.push_str(&modification);
+        merged
+    }
+}
+
+impl Rmw for bool {
+    fn rmw(&self, modification: Self) -> Self {
+        modification
+    }
+}
@@ -0,0 +1,224 @@
+//! Maps whose entries expire once an operator's input frontier passes them.
+//!
+//! `StateHandle::get_expiring_map` returns an `ExpiringMap`, which is a
+//! `ManagedMap` whose entries can be inserted with `insert_until`. An entry
+//! tagged with time `t` expires once the frontier given to `advance` holds no
+//! time at or before `t`, the point from which the operator will not be asked
+//! about `t` again. Windowed operators can thus tag the state of a slide with
+//! the end of the last window that reads it, instead of removing it by hand.
+//!
+//! Expired entries disappear from reads at once, and are deleted from the
+//! backend by a sweep that `advance` and every later write continue a batch at
+//! a time, so that a frontier passing many entries does not stall the operator.
+//!
+//! The map keeps its bookkeeping in state of its own, next to the entries:
+//! the expiry of each tagged key, the tagged keys of each expiry time, and the
+//! set of expiry times still to sweep. All of it is checkpointed with the map.
+
+use crate::codec::{Bincode, Codec};
+use crate::primitives::{IterDirection, IterationError, KeyRange, ManagedMap, ManagedMapIter, ManagedValue};
+use crate::{earliest, Rmw, StateKey, StateValue};
+use std::collections::BTreeSet;
+use std::convert::TryInto;
+use std::rc::Rc;
+
+/// How many expired entries each step of the sweep deletes, at least.
+pub const SWEEP_BATCH: usize = 1024;
+
+/// A map whose entries may be tagged with the time they expire at.
+pub struct ExpiringMap<K, V> {
+    entries: Box<ManagedMap<K, V>>,
+    // The expiry of each tagged entry, by its encoded key
+    expiries: Box<ManagedMap<Vec<u8>, u64>>,
+    // The encoded keys tagged with each expiry time, including keys since re-tagged
+    keys: Box<ManagedMap<u64, Vec<Vec<u8>>>>,
+    // The expiry times whose keys are not swept yet, mirrored to 'times'
+    pending: BTreeSet<u64>,
+    times: Box<ManagedValue<Vec<u64>>>,
+    // Entries expiring before this time have expired
+    frontier: u64,
+}
+
+impl<K: StateKey, V: StateValue> ExpiringMap<K, V> {
+    pub(crate) fn new(
+        entries: Box<ManagedMap<K, V>>,
+        expiries: Box<ManagedMap<Vec<u8>, u64>>,
+        keys: Box<ManagedMap<u64, Vec<Vec<u8>>>>,
+        times: Box<ManagedValue<Vec<u64>>>,
+    ) -> Self {
+        let pending = times
+            .get()
+            .map_or_else(BTreeSet::new, |times| times.iter().cloned().collect());
+        ExpiringMap {
+            entries,
+            expiries,
+            keys,
+            pending,
+            times,
+            frontier: 0,
+        }
+    }
+
+    /// Inserts an entry that expires once the frontier passes `expiry`.
+    pub fn insert_until(&mut self, key: K, value: V, expiry: u64) {
+        self.tag(Bincode::encoded(&key), expiry);
+        self.entries.insert(key, value);
+        self.sweep_backlog();
+    }
+
+    /// Like `rmw`, and tags the entry to expire once the frontier passes `expiry`.
+    pub fn rmw_until(&mut self, key: K, modification: V, expiry: u64)
+    where
+        V: Rmw,
+    {
+        if self.expired(&key) {
+            self.entries.remove(&key);
+        }
+        self.tag(Bincode::encoded(&key), expiry);
+        self.entries.rmw(key, modification);
+        self.sweep_backlog();
+    }
+
+    /// The time that the entry of `key` expires at, if it is tagged and has not expired.
+    pub fn expiry(&self, key: &K) -> Option<u64> {
+        self.expiries
+            .get(&Bincode::encoded(key))
+            .map(|expiry| *expiry)
+            .filter(|expiry| *expiry >= self.frontier)
+            .filter(|_| self.entries.contains(key))
+    }
+
+    /// Expires the entries that `frontier` has passed and starts sweeping them.
+    ///
+    /// An empty frontier passes every time.
+    pub fn advance<T: Copy + TryInto<u64>>(&mut self, frontier: &[T]) {
+        let earliest = earliest(frontier).unwrap_or(u64::max_value());
+        if earliest > self.frontier {
+            self.frontier = earliest;
+        }
+        self.sweep_backlog();
+    }
+
+    /// Deletes expired entries until at least `batch` are deleted or none remain,
+    /// and returns how many were deleted.
+    pub fn sweep(&mut self, batch: usize) -> usize {
+        let mut swept = 0;
+        let mut changed = false;
+        while swept < batch {
+            let time = match self.pending.iter().next() {
+                Some(&time) if time < self.frontier => time,
+                _ => break,
+            };
+            for encoded in self.keys.remove(&time).unwrap_or_default() {
+                // Keys re-tagged since belong to a later time
+                if self.expiries.get(&encoded).map_or(false, |expiry| *expiry == time) {
+                    self.expiries.remove(&encoded);
+                    if self.entries.remove(&Bincode::decode(&encoded)).is_some() {
+                        swept += 1;
+                    }
+                }
+            }
+            self.pending.remove(&time);
+            changed = true;
+        }
+        if changed {
+            self.times.set(self.pending.iter().cloned().collect());
+        }
+        swept
+    }
+
+    // Whether expired entries remain to be swept
+    fn backlog(&self) -> bool {
+        self.pending.iter().next().map_or(false, |time| *time < self.frontier)
+    }
+
+    fn sweep_backlog(&mut self) {
+        if self.backlog() {
+            self.sweep(SWEEP_BATCH);
+        }
+    }
+
+    // Only entries awaiting the sweep can have expired
+    fn expired(&self, key: &K) -> bool {
+        self.backlog()
+            && self
+                .expiries
+                .get(&Bincode::encoded(key))
+                .map_or(false, |expiry| *expiry < self.frontier)
+    }
+
+    fn tag(&mut self, encoded: Vec<u8>, expiry: u64) {
+        if self.expiries.get(&encoded).map(|tagged| *tagged) == Some(expiry) {
+            return;
+        }
+        if self.pending.insert(expiry) {
+            self.times.set(self.pending.iter().cloned().collect());
+        }
+        self.keys.rmw(expiry, vec![encoded.clone()]);
+        self.expiries.insert(encoded, expiry);
+    }
+
+    // Entries inserted without an expiry, or removed, are no longer tagged
+    fn untag(&mut self, key: &K) {
+        if !self.pending.is_empty() {
+            self.expiries.remove(&Bincode::encoded(key));
+        }
+    }
+}
+
+impl<K: StateKey, V: StateValue> ManagedMap<K, V> for ExpiringMap<K, V> {
+    fn get_key_prefix_length(&self) -> usize {
+        self.entries.get_key_prefix_length()
+    }
+
+    /// Inserts an entry that never expires.
+    fn insert(&mut self, key: K, value: V) {
+        self.untag(&key);
+        self.entries.insert(key, value);
+        self.sweep_backlog();
+    }
+
+    fn get(&self, key: &K) -> Option<Rc<V>> {
+        if self.expired(key) {
+            return None;
+        }
+        self.entries.get(key)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        let expired = self.expired(key);
+        self.untag(key);
+        let value = self.entries.remove(key);
+        self.sweep_backlog();
+        value.filter(|_| !expired)
+    }
+
+    /// Updates the entry of `key`, keeping its expiry unless it has expired.
+    fn rmw(&mut self, key: K, modification: V)
+    where
+        V: Rmw,
+    {
+        if self.expired(&key) {
+            self.expiries.remove(&Bincode::encoded(&key));
+            self.entries.remove(&key);
+        }
+        self.entries.rmw(key, modification);
+        self.sweep_backlog();
+    }
+
+    fn contains(&self, key: &K) -> bool {
+        !self.expired(key) && self.entries.contains(key)
+    }
+
+    fn iter(
+        &self,
+        range: KeyRange<K>,
+        direction: IterDirection,
+    ) -> Result<ManagedMapIter<'_, K, V>, IterationError> {
+        let entries = self.entries.iter(range, direction)?;
+        if !self.backlog() {
+            return Ok(entries);
+        }
+        Ok(Box::new(entries.filter(move |(key, _)| !self.expired(key))))
+    }
+}
@@ -1,25 +1,142 @@
+#[cfg(feature = "faster")]
 extern crate faster_rs;
 
-use crate::primitives::{ManagedCount, ManagedMap, ManagedValue};
-use faster_rs::{FasterKey, FasterRmw, FasterValue};
-use std::hash::Hash;
+use crate::cache::CacheStats;
+use crate::compaction::CompactionStats;
+use crate::checkpoint::{CheckpointError, Snapshot};
+use crate::codec::{Bincode, Codec, Ordered, SortKey};
+use crate::column_family::ColumnFamilyOptions;
+use crate::config::BackendConfig;
+use crate::expiry::ExpiringMap;
+use crate::merge::{MergeOperator, MergingMap};
+use crate::primitives::{
+    BagList, ChunkedBag, ManagedBag, ManagedCount, ManagedList, ManagedMap,
+    ManagedPriorityQueue, ManagedSortedMap, ManagedValue, OrderedSortedMap, SortedQueue,
+};
+use crate::session::SessionStats;
+use std::convert::TryInto;
+use std::path::Path;
 use std::rc::Rc;
 
+pub use crate::data::{Rmw, StateKey, StateValue};
+
 pub mod backends;
+pub mod cache;
+pub mod checkpoint;
+pub mod codec;
+pub mod column_family;
+pub mod compaction;
+pub mod config;
+mod data;
+pub mod expiry;
+pub mod merge;
 pub mod primitives;
+pub mod rescale;
+pub mod session;
 
 pub trait StateBackend: 'static {
-    fn new() -> Self;
+    fn new(config: &BackendConfig) -> Self;
 
     fn get_managed_count(&self, name: &str) -> Box<ManagedCount>;
-    fn get_managed_value<V: 'static + FasterValue + FasterRmw>(
+    fn get_managed_value<V: StateValue>(&self, name: &str) -> Box<ManagedValue<V>>;
+    fn get_managed_map<K, V>(&self, name: &str) -> Box<ManagedMap<K, V>>
+    where
+        K: StateKey,
+        V: StateValue,
+    {
+        self.get_managed_map_with_codecs::<K, V, Bincode, Bincode>(name)
+    }
+    /// Creates a map whose keys are encoded with `KC` and values with `VC`.
+    fn get_managed_map_with_codecs<K, V, KC, VC>(&self, name: &str) -> Box<ManagedMap<K, V>>
+    where
+        K: StateKey,
+        V: StateValue,
+        KC: Codec<K>,
+        VC: Codec<V>;
+    /// Creates a map whose `rmw` combines values with `merge` instead of `Rmw::rmw`.
+    fn get_managed_map_with_merge<K, V, KC, VC>(
         &self,
         name: &str,
-    ) -> Box<ManagedValue<V>>;
-    fn get_managed_map<K, V>(&self, name: &str) -> Box<ManagedMap<K, V>>
+        merge: MergeOperator<V>,
+    ) -> Box<ManagedMap<K, V>>
+    where
+        K: StateKey,
+        V: StateValue,
+        KC: Codec<K>,
+        VC: Codec<V>,
+    {
+        let map = self.get_managed_map_with_codecs::<K, V, KC, VC>(name);
+        Box::new(MergingMap::new(map, merge))
+    }
+    /// Creates a bag whose items are kept in chunks of a map of their own, which
+    /// backends that merge in place append to without reading.
+    fn get_managed_bag<K, T>(&self, name: &str) -> Box<ManagedBag<K, T>>
     where
-        K: 'static + FasterKey + Hash + Eq + std::fmt::Debug,
-        V: 'static + FasterValue + FasterRmw;
+        K: StateKey,
+        T: StateValue + Clone,
+    {
+        Box::new(ChunkedBag::new(
+            self.get_managed_map(&format!("{}.lengths", name)),
+            self.get_managed_map_with_merge::<_, _, Bincode, Bincode>(
+                &format!("{}.chunks", name),
+                MergeOperator::append(),
+            ),
+        ))
+    }
+    /// Creates a sorted map kept in a map whose keys are encoded with `Ordered`.
+    ///
+    /// Backends whose maps cannot iterate must keep sorted maps some other way.
+    fn get_managed_sorted_map<K, V>(&self, name: &str) -> Box<ManagedSortedMap<K, V>>
+    where
+        K: SortKey,
+        V: StateValue,
+    {
+        Box::new(OrderedSortedMap::new(
+            self.get_managed_map_with_codecs::<K, V, Ordered, Bincode>(name),
+        ))
+    }
+    /// Keeps state created with `name` from now on in its own column family.
+    ///
+    /// Backends without column families ignore this.
+    fn create_column_family(&self, _name: &str, _options: &ColumnFamilyOptions) {}
+    /// Discards all state kept in the column family `name`.
+    fn drop_column_family(&self, _name: &str) {}
+    /// Writes a snapshot of all state to `directory`, from which it can be recovered.
+    ///
+    /// Backends that cannot take checkpoints return `CheckpointError::Unsupported`.
+    fn checkpoint(&self, _directory: &Path) -> Result<Snapshot, CheckpointError> {
+        Err(CheckpointError::Unsupported)
+    }
+    /// Opens a backend holding the state of a snapshot that `checkpoint` wrote.
+    ///
+    /// Backends that cannot take checkpoints return `CheckpointError::Unsupported`.
+    fn restore(_config: &BackendConfig, _snapshot: &Snapshot) -> Result<Self, CheckpointError>
+    where
+        Self: Sized,
+    {
+        Err(CheckpointError::Unsupported)
+    }
+    /// Tells the backend that its operator will not see times before `frontier` again.
+    ///
+    /// Backends that cache state write it back, and FASTER backends may compact their
+    /// log; others ignore this.
+    fn advance(&self, _frontier: u64) {}
+    /// How often the backend's caches were used, if it has any.
+    fn cache_stats(&self) -> Option<CacheStats> {
+        None
+    }
+    /// How much of its log the backend has compacted, if it compacts.
+    fn compaction_stats(&self) -> Option<CompactionStats> {
+        None
+    }
+    /// How often the backend's session had to wait for its store, if it has one.
+    fn session_stats(&self) -> Option<SessionStats> {
+        None
+    }
+    /// Roughly how many bytes of memory the backend's state takes, if it keeps count.
+    fn memory_usage(&self) -> Option<u64> {
+        None
+    }
 }
 
 pub struct StateHandle<S: StateBackend> {
@@ -42,36 +159,171 @@ impl<S: StateBackend> StateHandle<S> {
         }
     }
 
-    pub fn spawn_new_backend(&self) -> Self {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn spawn_new_backend(&self, config: &BackendConfig) -> Self {
         StateHandle {
-            backend: Rc::new(S::new()),
+            backend: Rc::new(S::new(config)),
             name: self.name.clone()
         }
     }
 
+    // Separates the handle's name from the state's name so that sibling handles
+    // such as "a" and "ab" cannot address each other's state
+    fn physical_name(&self, name: &str) -> String {
+        [&self.name, name].join(".")
+    }
+
     pub fn get_managed_count(&self, name: &str) -> Box<ManagedCount> {
-        let mut physical_name = self.name.clone();
-        physical_name.push_str(name);
-        self.backend.get_managed_count(&physical_name)
+        self.backend.get_managed_count(&self.physical_name(name))
     }
 
     pub fn get_managed_map<K, V>(&self, name: &str) -> Box<ManagedMap<K, V>>
     where
-        K: 'static + FasterKey + Hash + Eq + std::fmt::Debug,
-        V: 'static + FasterValue + FasterRmw,
+        K: StateKey,
+        V: StateValue,
+    {
+        self.backend.get_managed_map(&self.physical_name(name))
+    }
+
+    pub fn get_managed_map_with_codecs<K, V, KC, VC>(&self, name: &str) -> Box<ManagedMap<K, V>>
+    where
+        K: StateKey,
+        V: StateValue,
+        KC: Codec<K>,
+        VC: Codec<V>,
+    {
+        self.backend
+            .get_managed_map_with_codecs::<K, V, KC, VC>(&self.physical_name(name))
+    }
+
+    pub fn get_managed_map_with_merge<K, V>(
+        &self,
+        name: &str,
+        merge: MergeOperator<V>,
+    ) -> Box<ManagedMap<K, V>>
+    where
+        K: StateKey,
+        V: StateValue,
     {
-        let mut physical_name = self.name.clone();
-        physical_name.push_str(name);
-        self.backend.get_managed_map(&physical_name)
+        self.get_managed_map_with_codecs_and_merge::<K, V, Bincode, Bincode>(name, merge)
     }
 
-    pub fn get_managed_value<V: 'static + FasterValue + FasterRmw>(
+    pub fn get_managed_map_with_codecs_and_merge<K, V, KC, VC>(
         &self,
         name: &str,
-    ) -> Box<ManagedValue<V>> {
-        let mut physical_name = self.name.clone();
-        physical_name.push_str(name);
-        self.backend.get_managed_value(&physical_name)
+        merge: MergeOperator<V>,
+    ) -> Box<ManagedMap<K, V>>
+    where
+        K: StateKey,
+        V: StateValue,
+        KC: Codec<K>,
+        VC: Codec<V>,
+    {
+        self.backend
+            .get_managed_map_with_merge::<K, V, KC, VC>(&self.physical_name(name), merge)
+    }
+
+    /// Creates a map whose entries can be tagged with the time they expire at.
+    pub fn get_expiring_map<K, V>(&self, name: &str) -> ExpiringMap<K, V>
+    where
+        K: StateKey,
+        V: StateValue,
+    {
+        ExpiringMap::new(
+            self.get_managed_map(name),
+            self.get_managed_map(&format!("{}.expiry", name)),
+            self.get_managed_map(&format!("{}.expiring", name)),
+            self.get_managed_value(&format!("{}.expiry_times", name)),
+        )
+    }
+
+    /// Creates a bag of items under each key, appended to without rewriting the items before.
+    pub fn get_managed_bag<K, T>(&self, name: &str) -> Box<ManagedBag<K, T>>
+    where
+        K: StateKey,
+        T: StateValue + Clone,
+    {
+        self.backend.get_managed_bag(&self.physical_name(name))
+    }
+
+    /// Creates a single list of items, appended to without rewriting the items before.
+    pub fn get_managed_list<T>(&self, name: &str) -> Box<ManagedList<T>>
+    where
+        T: StateValue + Clone,
+    {
+        Box::new(BagList::new(self.get_managed_bag(name)))
+    }
+
+    /// Creates a map that hands out its entries in key order.
+    pub fn get_managed_sorted_map<K, V>(&self, name: &str) -> Box<ManagedSortedMap<K, V>>
+    where
+        K: SortKey,
+        V: StateValue,
+    {
+        self.backend.get_managed_sorted_map(&self.physical_name(name))
+    }
+
+    /// Creates a queue that pops items in order of their priority, smallest first.
+    pub fn get_managed_priority_queue<P, T>(&self, name: &str) -> Box<ManagedPriorityQueue<P, T>>
+    where
+        P: SortKey,
+        T: StateValue,
+    {
+        Box::new(SortedQueue::new(
+            self.get_managed_sorted_map(name),
+            self.get_managed_count(&format!("{}.pushed", name)),
+        ))
+    }
+
+    /// Keeps the state called `name` apart from all other state, tuned by `options`.
+    ///
+    /// Must be called before the state is first created.
+    pub fn create_column_family(&self, name: &str, options: &ColumnFamilyOptions) {
+        self.backend
+            .create_column_family(&self.physical_name(name), options)
+    }
+
+    /// Discards the state called `name` that was kept in its own column family.
+    pub fn drop_column_family(&self, name: &str) {
+        self.backend.drop_column_family(&self.physical_name(name))
+    }
+
+    pub fn get_managed_value<V: StateValue>(&self, name: &str) -> Box<ManagedValue<V>> {
+        self.backend.get_managed_value(&self.physical_name(name))
+    }
+
+    /// Writes a snapshot of the backend's state, including that of other handles
+    /// sharing the backend, to `directory`.
+    pub fn checkpoint(&self, directory: &Path) -> Result<Snapshot, CheckpointError> {
+        self.backend.checkpoint(directory)
+    }
+
+    /// Tells the backend that the operator's input frontier is now `frontier`.
+    pub fn advance<T: Copy + TryInto<u64>>(&self, frontier: &[T]) {
+        self.backend.advance(earliest(frontier).unwrap_or(u64::max_value()))
+    }
+
+    /// How often the backend's caches were used, if it has any.
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.backend.cache_stats()
+    }
+
+    /// How much of its log the backend has compacted, if it compacts.
+    pub fn compaction_stats(&self) -> Option<CompactionStats> {
+        self.backend.compaction_stats()
+    }
+
+    /// How often the backend's session had to wait for its store, if it has one.
+    pub fn session_stats(&self) -> Option<SessionStats> {
+        self.backend.session_stats()
+    }
+
+    /// Roughly how many bytes of memory the backend's state takes, if it keeps count.
+    pub fn memory_usage(&self) -> Option<u64> {
+        self.backend.memory_usage()
     }
 }
 
@@ -83,3 +335,13 @@ impl<S: StateBackend> Clone for StateHandle<S> {
         }
     }
 }
+
+/// The earliest time of `frontier` as a `u64`, or `None` if it is empty.
+///
+/// Times that do not convert to `u64` are later than every time that does.
+pub(crate) fn earliest<T: Copy + TryInto<u64>>(frontier: &[T]) -> Option<u64> {
+    frontier
+        .iter()
+        .map(|time| (*time).try_into().unwrap_or(u64::max_value()))
+        .min()
+}
@@ -0,0 +1,180 @@
+//! Merge operators that decide how `rmw` combines a map's values.
+//!
+//! By default `rmw` uses the value type's `Rmw` implementation. A map created
+//! with `get_managed_map_with_merge` uses the given `MergeOperator` instead, so
+//! that an operator can, say, keep the maximum of `u64`s rather than their sum.
+//! Such a map's `merge` applies the operator to values of any type, whether or
+//! not it implements `Rmw`.
+//! RocksDB backends apply the operator inside RocksDB as a true merge; other
+//! backends read the existing value, merge and write it back.
+//!
+//! RocksDB may merge operands before it has seen the value they apply to, so
+//! operators must be associative.
+
+use crate::primitives::{
+    IterDirection, IterationError, KeyRange, ManagedMap, ManagedMapIter, ReadStatus,
+};
+use crate::{Rmw, StateKey, StateValue};
+use std::cmp;
+use std::ops::Add;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Combines a map's existing value with a modification passed to `rmw`.
+pub struct MergeOperator<V> {
+    merge: Arc<Fn(V, V) -> V + Send + Sync>,
+}
+
+impl<V: StateValue> MergeOperator<V> {
+    /// Merges with `merge(existing, modification)`.
+    pub fn new<F>(merge: F) -> Self
+    where
+        F: Fn(V, V) -> V + Send + Sync + 'static,
+    {
+        MergeOperator {
+            merge: Arc::new(merge),
+        }
+    }
+
+    /// Merges with the value type's own `Rmw` implementation.
+    pub fn rmw() -> Self
+    where
+        V: Rmw,
+    {
+        MergeOperator::new(|existing: V, modification| existing.rmw(modification))
+    }
+
+    /// Adds modifications to the existing value.
+    pub fn sum() -> Self
+    where
+        V: Add<Output = V>,
+    {
+        MergeOperator::new(|existing, modification| existing + modification)
+    }
+
+    /// Keeps the larger of the existing value and the modification.
+    pub fn max() -> Self
+    where
+        V: Ord,
+    {
+        MergeOperator::new(cmp::max)
+    }
+
+    /// Keeps the smaller of the existing value and the modification.
+    pub fn min() -> Self
+    where
+        V: Ord,
+    {
+        MergeOperator::new(cmp::min)
+    }
+
+    pub fn merge(&self, existing: V, modification: V) -> V {
+        (self.merge)(existing, modification)
+    }
+}
+
+impl<T: StateValue> MergeOperator<Vec<T>> {
+    /// Appends the elements of modifications to the existing vector.
+    pub fn append() -> Self {
+        MergeOperator::new(|mut existing: Vec<T>, modification| {
+            existing.extend(modification);
+            existing
+        })
+    }
+}
+
+impl<V> Clone for MergeOperator<V> {
+    fn clone(&self) -> Self {
+        MergeOperator {
+            merge: Arc::clone(&self.merge),
+        }
+    }
+}
+
+// Gives any map a merge operator by replacing its 'rmw' with get+merge+put
+pub(crate) struct MergingMap<K, V> {
+    map: Box<ManagedMap<K, V>>,
+    merge: MergeOperator<V>,
+}
+
+impl<K: StateKey, V: StateValue> MergingMap<K, V> {
+    pub fn new(map: Box<ManagedMap<K, V>>, merge: MergeOperator<V>) -> Self {
+        MergingMap { map, merge }
+    }
+}
+
+impl<K: StateKey, V: StateValue> ManagedMap<K, V> for MergingMap<K, V> {
+    fn get_key_prefix_length(&self) -> usize {
+        self.map.get_key_prefix_length()
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        self.map.insert(key, value)
+    }
+
+    fn get(&self, key: &K) -> Option<Rc<V>> {
+        self.map.get(key)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.map.remove(key)
+    }
+
+    fn rmw(&mut self, key: K, modification: V)
+    where
+        V: Rmw,
+    {
+        ManagedMap::merge(self, key, modification);
+    }
+
+    fn merge(&mut self, key: K, modification: V) {
+        let merged = match self.map.remove(&key) {
+            Some(existing) => self.merge.merge(existing, modification),
+            None => modification,
+        };
+        self.map.insert(key, merged);
+    }
+
+    fn contains(&self, key: &K) -> bool {
+        self.map.contains(key)
+    }
+
+    fn multi_get(&self, keys: &[K]) -> Vec<Option<Rc<V>>> {
+        self.map.multi_get(keys)
+    }
+
+    fn insert_batch(&mut self, entries: Vec<(K, V)>) {
+        self.map.insert_batch(entries)
+    }
+
+    fn get_async(&self, key: &K, ticket: u64) -> ReadStatus<V> {
+        self.map.get_async(key, ticket)
+    }
+
+    fn complete_reads(&self, wait: bool) -> Vec<(u64, Option<Rc<V>>)> {
+        self.map.complete_reads(wait)
+    }
+
+    fn iter(
+        &self,
+        range: KeyRange<K>,
+        direction: IterDirection,
+    ) -> Result<ManagedMapIter<'_, K, V>, IterationError> {
+        self.map.iter(range, direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MergeOperator;
+
+    #[test]
+    fn operators_merge() {
+        assert_eq!(MergeOperator::<u64>::sum().merge(2, 3), 5);
+        assert_eq!(MergeOperator::<u64>::max().merge(2, 3), 3);
+        assert_eq!(MergeOperator::<u64>::min().merge(2, 3), 2);
+        assert_eq!(MergeOperator::append().merge(vec![1], vec![2, 3]), vec![1, 2, 3]);
+        assert_eq!(MergeOperator::<String>::rmw().merge("a".into(), "b".into()), "ab");
+        assert_eq!(MergeOperator::new(|a: u64, b| a * b).merge(2, 3), 6);
+    }
+}
@@ -0,0 +1,151 @@
+use super::{ManagedBag, ManagedList, ManagedMap};
+use crate::codec::{Bincode, Codec};
+use crate::{StateKey, StateValue};
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::vec;
+
+/// An iterator over the items of a `ManagedBag` or `ManagedList`.
+pub type ManagedListIter<'a, T> = Box<Iterator<Item = T> + 'a>;
+
+/// How many items a chunk of a bag kept in a `ManagedMap` holds.
+///
+/// Appending rewrites at most one chunk, and reading loads one chunk at a time.
+pub const BAG_CHUNK_LENGTH: usize = 256;
+
+/// A bag kept in two maps: the number of items of each key, and the items
+/// themselves in chunks of `BAG_CHUNK_LENGTH` under the key and chunk index.
+///
+/// Appends `rmw` the last chunk, so backends that merge in place never read it.
+pub(crate) struct ChunkedBag<K, T> {
+    lengths: Box<ManagedMap<Vec<u8>, u64>>,
+    chunks: Box<ManagedMap<(Vec<u8>, u64), Vec<T>>>,
+    key: PhantomData<K>,
+}
+
+impl<K: StateKey, T: StateValue + Clone> ChunkedBag<K, T> {
+    pub fn new(
+        lengths: Box<ManagedMap<Vec<u8>, u64>>,
+        chunks: Box<ManagedMap<(Vec<u8>, u64), Vec<T>>>,
+    ) -> Self {
+        ChunkedBag {
+            lengths,
+            chunks,
+            key: PhantomData,
+        }
+    }
+
+    fn length(&self, encoded: &Vec<u8>) -> u64 {
+        self.lengths.get(encoded).map_or(0, |length| *length)
+    }
+}
+
+// The number of chunks holding 'length' items
+fn chunk_count(length: u64) -> u64 {
+    let chunk_length = BAG_CHUNK_LENGTH as u64;
+    (length + chunk_length - 1) / chunk_length
+}
+
+impl<K: StateKey, T: StateValue + Clone> ManagedBag<K, T> for ChunkedBag<K, T> {
+    fn append(&mut self, key: K, item: T) {
+        let encoded = Bincode::encoded(&key);
+        let length = self.length(&encoded);
+        let chunk = length / BAG_CHUNK_LENGTH as u64;
+        self.chunks.rmw((encoded.clone(), chunk), vec![item]);
+        self.lengths.insert(encoded, length + 1);
+    }
+
+    fn len(&self, key: &K) -> usize {
+        self.length(&Bincode::encoded(key)) as usize
+    }
+
+    fn iter(&self, key: &K) -> ManagedListIter<'_, T> {
+        let encoded = Bincode::encoded(key);
+        let chunks = &self.chunks;
+        Box::new(
+            (0..chunk_count(self.length(&encoded))).flat_map(move |index| {
+                let chunk = chunks
+                    .get(&(encoded.clone(), index))
+                    .unwrap_or_else(|| Rc::new(Vec::new()));
+                (0..chunk.len()).map(move |item| chunk[item].clone())
+            }),
+        )
+    }
+
+    fn drain(&mut self, key: &K) -> ManagedListIter<'_, T> {
+        let encoded = Bincode::encoded(key);
+        let length = self.lengths.remove(&encoded).unwrap_or(0);
+        Box::new(Drain {
+            chunks: &mut self.chunks,
+            key: encoded,
+            next: 0,
+            end: chunk_count(length),
+            items: Vec::new().into_iter(),
+        })
+    }
+}
+
+// Removes one chunk at a time as its items are visited, and the rest when dropped
+struct Drain<'a, T: StateValue> {
+    chunks: &'a mut Box<ManagedMap<(Vec<u8>, u64), Vec<T>>>,
+    key: Vec<u8>,
+    next: u64,
+    end: u64,
+    items: vec::IntoIter<T>,
+}
+
+impl<'a, T: StateValue> Iterator for Drain<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        loop {
+            if let Some(item) = self.items.next() {
+                return Some(item);
+            }
+            if self.next == self.end {
+                return None;
+            }
+            let chunk = self.chunks.remove(&(self.key.clone(), self.next));
+            self.items = chunk.unwrap_or_default().into_iter();
+            self.next += 1;
+        }
+    }
+}
+
+impl<'a, T: StateValue> Drop for Drain<'a, T> {
+    fn drop(&mut self) {
+        while self.next < self.end {
+            self.chunks.remove(&(self.key.clone(), self.next));
+            self.next += 1;
+        }
+    }
+}
+
+/// A list kept as the only key of a bag.
+pub(crate) struct BagList<T> {
+    bag: Box<ManagedBag<(), T>>,
+}
+
+impl<T: StateValue> BagList<T> {
+    pub fn new(bag: Box<ManagedBag<(), T>>) -> Self {
+        BagList { bag }
+    }
+}
+
+impl<T: StateValue> ManagedList<T> for BagList<T> {
+    fn push(&mut self, item: T) {
+        self.bag.append((), item)
+    }
+
+    fn len(&self) -> usize {
+        self.bag.len(&())
+    }
+
+    fn iter(&self) -> ManagedListIter<'_, T> {
+        self.bag.iter(&())
+    }
+
+    fn drain(&mut self) -> ManagedListIter<'_, T> {
+        self.bag.drain(&())
+    }
+}
@@ -0,0 +1,170 @@
+//! Sorted maps kept on the heap, for backends whose maps cannot iterate in
+//! key order. FASTER checkpoints them to a file of their own next to its
+//! snapshot, as its store does not hold them.
+
+use super::{unshared, ManagedMapIter, ManagedSortedMap};
+#[cfg(feature = "faster")]
+use crate::checkpoint::CheckpointError;
+use crate::codec::SortKey;
+use crate::StateValue;
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "faster")]
+use std::fs;
+use std::ops::Bound;
+#[cfg(feature = "faster")]
+use std::path::Path;
+use std::rc::Rc;
+
+// The file of a checkpoint that holds the serialised sorted maps
+#[cfg(feature = "faster")]
+const SORTED_MAPS_FILE: &str = "sorted_maps.bin";
+
+type Serialiser = fn(&Any) -> Vec<u8>;
+
+/// A sorted map kept on the heap, for backends without ordered keys.
+pub(crate) struct BTreeSortedMap<K, V> {
+    map: Rc<RefCell<BTreeMap<K, Rc<V>>>>,
+}
+
+impl<K: SortKey, V: StateValue> ManagedSortedMap<K, V> for BTreeSortedMap<K, V> {
+    fn insert(&mut self, key: K, value: V) {
+        self.map.borrow_mut().insert(key, Rc::new(value));
+    }
+
+    fn get(&self, key: &K) -> Option<Rc<V>> {
+        self.map.borrow().get(key).map(Rc::clone)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.map.borrow_mut().remove(key).map(unshared)
+    }
+
+    fn first(&self) -> Option<(Rc<K>, Rc<V>)> {
+        self.map
+            .borrow()
+            .iter()
+            .next()
+            .map(|(key, value)| (Rc::new(key.clone()), Rc::clone(value)))
+    }
+
+    fn last(&self) -> Option<(Rc<K>, Rc<V>)> {
+        self.map
+            .borrow()
+            .iter()
+            .next_back()
+            .map(|(key, value)| (Rc::new(key.clone()), Rc::clone(value)))
+    }
+
+    fn pop_first(&mut self) -> Option<(K, V)> {
+        let mut map = self.map.borrow_mut();
+        let key = map.keys().next()?.clone();
+        let value = unshared(map.remove(&key)?);
+        Some((key, value))
+    }
+
+    // The entries are collected, as the map may change while they are iterated
+    fn range(&self, lower: Bound<K>, upper: Bound<K>) -> ManagedMapIter<'_, K, V> {
+        let entries: Vec<_> = self
+            .map
+            .borrow()
+            .range((lower, upper))
+            .map(|(key, value)| (Rc::new(key.clone()), Rc::clone(value)))
+            .collect();
+        Box::new(entries.into_iter())
+    }
+
+    fn split_off(&mut self, key: &K) -> Vec<(K, V)> {
+        self.map
+            .borrow_mut()
+            .split_off(key)
+            .into_iter()
+            .map(|(key, value)| (key, unshared(value)))
+            .collect()
+    }
+}
+
+fn serialise<K: SortKey, V: StateValue>(map: &Any) -> Vec<u8> {
+    let map = map
+        .downcast_ref::<RefCell<BTreeMap<K, Rc<V>>>>()
+        .expect("Sorted map registered with other types")
+        .borrow();
+    let entries: Vec<(&K, &V)> = map.iter().map(|(key, value)| (key, &**value)).collect();
+    bincode::serialize(&entries).unwrap()
+}
+
+fn deserialise<K: SortKey, V: StateValue>(serialised: &[u8]) -> BTreeMap<K, Rc<V>> {
+    let entries: Vec<(K, V)> = bincode::deserialize(serialised).unwrap();
+    entries
+        .into_iter()
+        .map(|(key, value)| (key, Rc::new(value)))
+        .collect()
+}
+
+/// The sorted maps that a backend keeps on the heap, by name, so that state
+/// created twice under one name is the same map.
+#[derive(Default)]
+pub(crate) struct SortedMaps {
+    // Maps are stored untyped, so each records how to serialise it when created
+    maps: RefCell<HashMap<String, (Rc<Any>, Serialiser)>>,
+    // Restored maps stay serialised until they are created again, when their types are known
+    restored: RefCell<HashMap<String, Vec<u8>>>,
+}
+
+impl SortedMaps {
+    pub fn get<K: SortKey, V: StateValue>(&self, name: &str) -> BTreeSortedMap<K, V> {
+        let map = Rc::clone(
+            &self
+                .maps
+                .borrow_mut()
+                .entry(name.to_owned())
+                .or_insert_with(|| {
+                    let map = match self.restored.borrow_mut().remove(name) {
+                        Some(serialised) => deserialise::<K, V>(&serialised),
+                        None => BTreeMap::new(),
+                    };
+                    (Rc::new(RefCell::new(map)), serialise::<K, V>)
+                })
+                .0,
+        );
+        BTreeSortedMap {
+            map: map
+                .downcast()
+                .unwrap_or_else(|_| panic!("Sorted map {} was created with other types", name)),
+        }
+    }
+
+    /// Writes every map, including restored maps that were not created again since,
+    /// to `directory`.
+    #[cfg(feature = "faster")]
+    pub fn checkpoint(&self, directory: &Path) -> Result<(), CheckpointError> {
+        let mut dump: Vec<(String, Vec<u8>)> = self
+            .maps
+            .borrow()
+            .iter()
+            .map(|(name, (map, serialise))| (name.clone(), serialise(&**map)))
+            .collect();
+        for (name, serialised) in self.restored.borrow().iter() {
+            dump.push((name.clone(), serialised.clone()));
+        }
+        dump.sort();
+        let path = directory.join(SORTED_MAPS_FILE);
+        fs::write(&path, bincode::serialize(&dump).unwrap())
+            .map_err(|error| CheckpointError::Io(path, error))
+    }
+
+    /// Reads the maps that `checkpoint` wrote to `directory`.
+    #[cfg(feature = "faster")]
+    pub fn restore(directory: &Path) -> Result<Self, CheckpointError> {
+        let path = directory.join(SORTED_MAPS_FILE);
+        let dump = fs::read(&path).map_err(|error| CheckpointError::Io(path.clone(), error))?;
+        let dump: Vec<(String, Vec<u8>)> = bincode::deserialize(&dump).map_err(|error| {
+            CheckpointError::Backend(format!("cannot read {}: {}", path.display(), error))
+        })?;
+        Ok(SortedMaps {
+            maps: RefCell::new(HashMap::new()),
+            restored: RefCell::new(dump.into_iter().collect()),
+        })
+    }
+}
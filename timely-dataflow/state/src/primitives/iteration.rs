@@ -0,0 +1,258 @@
+#[cfg(any(feature = "in-memory", feature = "rocksdb", feature = "sled"))]
+use crate::codec::Codec;
+use serde::Serialize;
+use std::fmt;
+use std::ops::Bound;
+use std::rc::Rc;
+
+/// An iterator over the decoded entries of a `ManagedMap`.
+pub type ManagedMapIter<'a, K, V> = Box<Iterator<Item = (Rc<K>, Rc<V>)> + 'a>;
+
+/// The order in which a `ManagedMap` iterator visits its keys.
+///
+/// Keys are ordered by their encoded representation, which for integer keys
+/// under the default bincode codec means callers should store them big-endian
+/// (e.g. `key.to_be()`) or use the `U64` codec if they want numeric order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IterDirection {
+    Forward,
+    Reverse,
+}
+
+/// The set of keys visited by a `ManagedMap` iterator.
+///
+/// Iteration never leaves the map it was started on, whatever the bounds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyRange<K> {
+    /// Every entry in the map.
+    All,
+    /// Every entry whose encoded key starts with the given bytes.
+    Prefix(Vec<u8>),
+    /// Every entry whose key lies between the two bounds.
+    Bounded(Bound<K>, Bound<K>),
+}
+
+impl<K> KeyRange<K> {
+    /// Every entry whose key starts with `prefix`, e.g. the first element of a tuple key.
+    ///
+    /// The prefix is serialised with bincode, so this only suits maps using the default key codec.
+    pub fn prefix<P: Serialize + ?Sized>(prefix: &P) -> Self {
+        KeyRange::Prefix(bincode::serialize(prefix).unwrap())
+    }
+
+    /// Every entry from `key` (inclusive) to the end of the map.
+    pub fn from(key: K) -> Self {
+        KeyRange::Bounded(Bound::Included(key), Bound::Unbounded)
+    }
+}
+
+/// The reasons a `ManagedMap` may be unable to iterate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IterationError {
+    /// The backend keeps its keys in a hash index and cannot produce them in order.
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for IterationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IterationError::Unsupported(backend) => {
+                write!(f, "{} does not support ordered iteration", backend)
+            }
+        }
+    }
+}
+
+impl std::error::Error for IterationError {}
+
+/// A `KeyRange` translated into bounds over physical (name-prefixed, encoded) keys.
+#[cfg(any(feature = "in-memory", feature = "rocksdb", feature = "sled"))]
+pub(crate) struct SerialisedRange {
+    lower: Bound<Vec<u8>>,
+    upper: Bound<Vec<u8>>,
+}
+
+#[cfg(any(feature = "in-memory", feature = "rocksdb", feature = "sled"))]
+impl SerialisedRange {
+    pub fn new<K, KC: Codec<K>>(name: &[u8], range: &KeyRange<K>) -> Self {
+        let prefixed = |key: &K| {
+            let mut bytes = name.to_vec();
+            KC::encode(key, &mut bytes);
+            bytes
+        };
+        let map_start = Bound::Included(name.to_vec());
+        let map_end = match successor(name) {
+            Some(end) => Bound::Excluded(end),
+            None => Bound::Unbounded,
+        };
+        match range {
+            KeyRange::All => SerialisedRange {
+                lower: map_start,
+                upper: map_end,
+            },
+            KeyRange::Prefix(prefix) => {
+                let mut start = name.to_vec();
+                start.extend_from_slice(prefix);
+                let upper = match successor(&start) {
+                    Some(end) => Bound::Excluded(end),
+                    None => Bound::Unbounded,
+                };
+                SerialisedRange {
+                    lower: Bound::Included(start),
+                    upper,
+                }
+            }
+            KeyRange::Bounded(lower, upper) => SerialisedRange {
+                lower: match lower {
+                    Bound::Included(key) => Bound::Included(prefixed(key)),
+                    Bound::Excluded(key) => Bound::Excluded(prefixed(key)),
+                    Bound::Unbounded => map_start,
+                },
+                upper: match upper {
+                    Bound::Included(key) => Bound::Included(prefixed(key)),
+                    Bound::Excluded(key) => Bound::Excluded(prefixed(key)),
+                    Bound::Unbounded => map_end,
+                },
+            },
+        }
+    }
+
+    /// The key to seek to before iterating in `direction`, if any.
+    #[cfg(feature = "rocksdb")]
+    pub fn seek_key(&self, direction: IterDirection) -> Option<&[u8]> {
+        let bound = match direction {
+            IterDirection::Forward => &self.lower,
+            IterDirection::Reverse => &self.upper,
+        };
+        match bound {
+            Bound::Included(key) | Bound::Excluded(key) => Some(key),
+            Bound::Unbounded => None,
+        }
+    }
+
+    /// The bounds to pass to a store that iterates over ranges natively, or `None` if
+    /// no key lies between them.
+    #[cfg(any(feature = "in-memory", feature = "sled"))]
+    pub fn bounds(&self) -> Option<(Bound<Vec<u8>>, Bound<Vec<u8>>)> {
+        let empty = match (&self.lower, &self.upper) {
+            (Bound::Included(lower), Bound::Included(upper)) => lower > upper,
+            (Bound::Included(lower), Bound::Excluded(upper))
+            | (Bound::Excluded(lower), Bound::Included(upper))
+            | (Bound::Excluded(lower), Bound::Excluded(upper)) => lower >= upper,
+            _ => false,
+        };
+        if empty {
+            return None;
+        }
+        Some((self.lower.clone(), self.upper.clone()))
+    }
+
+    /// Leaves out `key` and every key before it in `direction`, once an iterator has
+    /// visited them.
+    #[cfg(feature = "in-memory")]
+    pub fn advance_past(&mut self, key: Vec<u8>, direction: IterDirection) {
+        match direction {
+            IterDirection::Forward => self.lower = Bound::Excluded(key),
+            IterDirection::Reverse => self.upper = Bound::Excluded(key),
+        }
+    }
+
+    #[cfg(any(feature = "in-memory", feature = "rocksdb"))]
+    pub fn above_lower(&self, key: &[u8]) -> bool {
+        match &self.lower {
+            Bound::Included(lower) => key >= &lower[..],
+            Bound::Excluded(lower) => key > &lower[..],
+            Bound::Unbounded => true,
+        }
+    }
+
+    #[cfg(any(feature = "in-memory", feature = "rocksdb"))]
+    pub fn below_upper(&self, key: &[u8]) -> bool {
+        match &self.upper {
+            Bound::Included(upper) => key <= &upper[..],
+            Bound::Excluded(upper) => key < &upper[..],
+            Bound::Unbounded => true,
+        }
+    }
+
+    #[cfg(any(feature = "in-memory", feature = "rocksdb"))]
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.above_lower(key) && self.below_upper(key)
+    }
+}
+
+/// The smallest byte string greater than every string starting with `prefix`.
+#[cfg(any(feature = "in-memory", feature = "rocksdb", feature = "sled"))]
+fn successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < u8::max_value() {
+            end.push(last + 1);
+            return Some(end);
+        }
+    }
+    None
+}
+
+/// Collects entries held in a hash index into the order a `ManagedMap` iterator would visit them.
+#[cfg(feature = "in-memory")]
+pub(crate) fn sort_entries<'a, K, V, KC, I>(
+    entries: I,
+    range: &KeyRange<K>,
+    direction: IterDirection,
+) -> ManagedMapIter<'static, K, V>
+where
+    K: 'static,
+    V: 'static,
+    KC: Codec<K>,
+    I: Iterator<Item = (&'a K, &'a Rc<V>)>,
+{
+    let range = SerialisedRange::new::<K, KC>(&[], range);
+    let mut selected: Vec<(Vec<u8>, Rc<V>)> = entries
+        .map(|(key, value)| (KC::encoded(key), Rc::clone(value)))
+        .filter(|(key, _)| range.contains(key))
+        .collect();
+    selected.sort_by(|a, b| a.0.cmp(&b.0));
+    if direction == IterDirection::Reverse {
+        selected.reverse();
+    }
+    Box::new(
+        selected
+            .into_iter()
+            .map(|(key, value)| (Rc::new(KC::decode(&key)), value)),
+    )
+}
+
+#[cfg(all(test, any(feature = "in-memory", feature = "rocksdb")))]
+mod tests {
+    use super::{KeyRange, SerialisedRange};
+    use crate::codec::Bincode;
+    use std::ops::Bound;
+
+    #[test]
+    fn range_stays_within_map() {
+        let name = bincode::serialize("map").unwrap();
+        let range = SerialisedRange::new::<_, Bincode>(&name, &KeyRange::<u64>::All);
+
+        let mut inside = name.clone();
+        inside.extend(bincode::serialize(&42u64).unwrap());
+        let neighbour = bincode::serialize("mbp").unwrap();
+
+        assert!(range.contains(&inside));
+        assert!(!range.contains(&neighbour));
+    }
+
+    #[test]
+    fn bounded_range_respects_inclusion() {
+        let range = SerialisedRange::new::<_, Bincode>(
+            &[],
+            &KeyRange::Bounded(Bound::Excluded(1u64.to_be()), Bound::Included(3u64.to_be())),
+        );
+        let key = |k: u64| bincode::serialize(&k.to_be()).unwrap();
+
+        assert!(!range.contains(&key(1)));
+        assert!(range.contains(&key(2)));
+        assert!(range.contains(&key(3)));
+        assert!(!range.contains(&key(4)));
+    }
+}
@@ -1,7 +1,32 @@
-use faster_rs::{FasterKey, FasterRmw, FasterValue};
-use std::hash::Hash;
+use crate::codec::{Bincode, Codec, SortKey};
+use crate::{Rmw, StateKey, StateValue};
+use std::ops::Bound;
 use std::rc::Rc;
-use rocksdb::DBIterator;
+
+pub use self::bag::{ManagedListIter, BAG_CHUNK_LENGTH};
+pub use self::iteration::{IterDirection, IterationError, KeyRange, ManagedMapIter};
+pub use self::reads::{PendingReads, ReadStatus, Wake};
+pub(crate) use self::bag::{BagList, ChunkedBag};
+#[cfg(any(feature = "faster", feature = "in-memory"))]
+pub(crate) use self::heap_sorted::SortedMaps;
+pub(crate) use self::sorted::{OrderedSortedMap, SortedQueue};
+#[cfg(feature = "in-memory")]
+pub(crate) use self::iteration::sort_entries;
+#[cfg(any(feature = "in-memory", feature = "rocksdb", feature = "sled"))]
+pub(crate) use self::iteration::SerialisedRange;
+
+mod bag;
+#[cfg(any(feature = "faster", feature = "in-memory"))]
+mod heap_sorted;
+mod iteration;
+mod reads;
+mod sorted;
+
+/// Takes the value out of `value`. Values that `get` handed out may still be
+/// shared, in which case a copy decoded from the value's encoding is returned.
+pub(crate) fn unshared<V: StateValue>(value: Rc<V>) -> V {
+    Rc::try_unwrap(value).unwrap_or_else(|shared| Bincode::decode(&Bincode::encoded(&*shared)))
+}
 
 pub trait ManagedCount {
     fn decrease(&mut self, amount: i64);
@@ -10,25 +35,150 @@ pub trait ManagedCount {
     fn set(&mut self, value: i64);
 }
 
-pub trait ManagedValue<V: 'static + FasterValue + FasterRmw> {
+pub trait ManagedValue<V: StateValue> {
     fn set(&mut self, value: V);
     fn get(&self) -> Option<Rc<V>>;
     fn take(&mut self) -> Option<V>;
-    fn rmw(&mut self, modification: V);
+    /// Merges `modification` into the current value, or stores it if there is none.
+    fn rmw(&mut self, modification: V)
+    where
+        V: Rmw;
 }
 
 pub trait ManagedMap<K, V>
 where
-    K: FasterKey + Hash + Eq + std::fmt::Debug,
-    V: 'static + FasterValue + FasterRmw,
+    K: StateKey,
+    V: StateValue,
 {
     fn get_key_prefix_length(&self) -> usize;
     fn insert(&mut self, key: K, value: V);
     fn get(&self, key: &K) -> Option<Rc<V>>;
     fn remove(&mut self, key: &K) -> Option<V>;
-    fn rmw(&mut self, key: K, modification: V);
+    /// Merges `modification` into the value at `key`, or stores it if there is none.
+    fn rmw(&mut self, key: K, modification: V)
+    where
+        V: Rmw;
+    /// Merges `modification` into the value at `key` with the merge operator the map was
+    /// created with, or stores it if there is none.
+    ///
+    /// Unlike `rmw`, this needs no `Rmw` implementation of the value type. Maps created
+    /// without a merge operator panic.
+    fn merge(&mut self, _key: K, _modification: V) {
+        panic!("Only maps created with a merge operator can merge");
+    }
     fn contains(&self, key: &K) -> bool;
-    // Implemented only for RocksDB
-    fn iter(&mut self, key: K) -> DBIterator;
-    fn next(&mut self, iter: DBIterator) -> Option<(Rc<K>,Rc<V>)>;
+    /// Looks up every key of `keys`, returning their values in the same order.
+    ///
+    /// Backends that can serve several lookups at once override this.
+    fn multi_get(&self, keys: &[K]) -> Vec<Option<Rc<V>>> {
+        keys.iter().map(|key| self.get(key)).collect()
+    }
+    /// Inserts every entry of `entries`, later entries replacing earlier ones of the same key.
+    fn insert_batch(&mut self, entries: Vec<(K, V)>) {
+        for (key, value) in entries {
+            self.insert(key, value);
+        }
+    }
+    /// Merges every modification of `modifications` into its key's value, in order.
+    fn rmw_batch(&mut self, modifications: Vec<(K, V)>)
+    where
+        V: Rmw,
+    {
+        for (key, modification) in modifications {
+            self.rmw(key, modification);
+        }
+    }
+    /// Starts looking up `key`, returning the value at once if the backend has it at hand.
+    ///
+    /// Otherwise the lookup is pending, and a later `complete_reads` returns it with
+    /// `ticket`. Operators usually go through a `PendingReads` rather than call this.
+    fn get_async(&self, key: &K, _ticket: u64) -> ReadStatus<V> {
+        ReadStatus::Ready(self.get(key))
+    }
+    /// The tickets and values of pending lookups that have completed since last called.
+    ///
+    /// With `wait`, returns only once every pending lookup has completed.
+    fn complete_reads(&self, _wait: bool) -> Vec<(u64, Option<Rc<V>>)> {
+        Vec::new()
+    }
+    /// Iterates over the entries of this map whose keys fall in `range`.
+    ///
+    /// Backends that keep their keys in a hash index return `IterationError::Unsupported`.
+    fn iter(
+        &self,
+        range: KeyRange<K>,
+        direction: IterDirection,
+    ) -> Result<ManagedMapIter<'_, K, V>, IterationError>;
+}
+
+/// Lists of items kept under each key, which are only ever appended to and read or drained whole.
+///
+/// Unlike a `ManagedMap` of `Vec`s, appending does not read or rewrite the items
+/// already stored, and reading streams the items instead of loading them at once.
+pub trait ManagedBag<K, T>
+where
+    K: StateKey,
+    T: StateValue,
+{
+    /// Adds `item` to the end of the items of `key`.
+    fn append(&mut self, key: K, item: T);
+    /// The number of items of `key`.
+    fn len(&self, key: &K) -> usize;
+    /// Visits the items of `key` in the order they were appended.
+    fn iter(&self, key: &K) -> ManagedListIter<'_, T>;
+    /// Removes the items of `key`, visiting them in the order they were appended.
+    ///
+    /// Items that the iterator does not visit are removed all the same.
+    fn drain(&mut self, key: &K) -> ManagedListIter<'_, T>;
+}
+
+/// A single list of items, which is only ever appended to and read or drained whole.
+pub trait ManagedList<T: StateValue> {
+    /// Adds `item` to the end of the list.
+    fn push(&mut self, item: T);
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Visits the items in the order they were pushed.
+    fn iter(&self) -> ManagedListIter<'_, T>;
+    /// Empties the list, visiting its items in the order they were pushed.
+    fn drain(&mut self) -> ManagedListIter<'_, T>;
+}
+
+/// A map that hands out its entries in key order, such as the earliest of a set of deadlines.
+pub trait ManagedSortedMap<K, V>
+where
+    K: SortKey,
+    V: StateValue,
+{
+    fn insert(&mut self, key: K, value: V);
+    fn get(&self, key: &K) -> Option<Rc<V>>;
+    fn remove(&mut self, key: &K) -> Option<V>;
+    /// The entry with the smallest key.
+    fn first(&self) -> Option<(Rc<K>, Rc<V>)>;
+    /// The entry with the largest key.
+    fn last(&self) -> Option<(Rc<K>, Rc<V>)>;
+    /// Removes the entry with the smallest key.
+    fn pop_first(&mut self) -> Option<(K, V)>;
+    /// Visits the entries whose keys lie between `lower` and `upper`, in key order.
+    fn range(&self, lower: Bound<K>, upper: Bound<K>) -> ManagedMapIter<'_, K, V>;
+    /// Removes the entries whose keys are at least `key`, returning them in key order.
+    fn split_off(&mut self, key: &K) -> Vec<(K, V)>;
+}
+
+/// Items that pop in order of their priority, smallest first.
+///
+/// Items of equal priority pop in the order they were pushed.
+pub trait ManagedPriorityQueue<P, T>
+where
+    P: SortKey,
+    T: StateValue,
+{
+    fn push(&mut self, priority: P, item: T);
+    /// The item to pop next, along with its priority.
+    fn peek(&self) -> Option<(P, Rc<T>)>;
+    fn pop(&mut self) -> Option<(P, T)>;
+    /// Pops every item whose priority is smaller than `priority`.
+    fn pop_until(&mut self, priority: &P) -> Vec<(P, T)>;
 }
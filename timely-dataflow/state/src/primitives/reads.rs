@@ -0,0 +1,145 @@
+use super::ManagedMap;
+use crate::{StateKey, StateValue};
+use std::cmp;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+// Drains in a row that may complete nothing before `drain` starts backing off
+const SPINS: u32 = 16;
+// The longest that `drain` puts off waking the operator again
+const MAX_BACKOFF: Duration = Duration::from_millis(1);
+
+/// The outcome of starting a lookup with `ManagedMap::get_async`.
+#[derive(Debug, PartialEq)]
+pub enum ReadStatus<V> {
+    /// The backend had the entry at hand.
+    Ready(Option<Rc<V>>),
+    /// The backend is still reading the entry, which `complete_reads` will return.
+    Pending,
+}
+
+/// Something to tell when lookups are still pending, such as timely's `Activator`,
+/// so that the operator is scheduled again to collect them.
+pub trait Wake {
+    fn wake(&self);
+
+    /// Wakes once `delay` has passed. Wakers that cannot delay wake at once.
+    fn wake_after(&self, _delay: Duration) {
+        self.wake()
+    }
+}
+
+impl<F: Fn()> Wake for F {
+    fn wake(&self) {
+        self()
+    }
+}
+
+/// Lookups of a `ManagedMap` that may complete after the operator has yielded,
+/// each handed back along with the context it was submitted with.
+///
+/// Operators submit the lookups of a batch of input and `drain` those that have
+/// completed on every activation, instead of waiting for each lookup in turn.
+/// While lookups are pending, `drain` wakes the operator again, so that a
+/// backend reading from disk does not keep other operators from running.
+/// FASTER only completes reads when polled, so once drains keep completing
+/// nothing, `drain` asks to be woken exponentially later, up to a millisecond,
+/// rather than polling in a busy loop. The worker runs other operators, or
+/// parks, in the meantime.
+///
+/// A map's lookups must all be submitted through the same `PendingReads`.
+pub struct PendingReads<V, T> {
+    contexts: HashMap<u64, T>,
+    next_ticket: u64,
+    ready: Vec<(T, Option<Rc<V>>)>,
+    wake: Option<Box<Wake>>,
+    // Drains in a row that completed no lookup
+    idle: u32,
+}
+
+impl<V: StateValue, T> Default for PendingReads<V, T> {
+    fn default() -> Self {
+        PendingReads::new()
+    }
+}
+
+impl<V: StateValue, T> PendingReads<V, T> {
+    pub fn new() -> Self {
+        PendingReads {
+            contexts: HashMap::new(),
+            next_ticket: 0,
+            ready: Vec::new(),
+            wake: None,
+            idle: 0,
+        }
+    }
+
+    /// Woken by `drain` whenever lookups remain pending.
+    pub fn set_waker<W: Wake + 'static>(&mut self, wake: W) {
+        self.wake = Some(Box::new(wake));
+    }
+
+    /// Starts looking up `key` in `map`, to be returned by `drain` along with `context`.
+    pub fn submit<K: StateKey>(&mut self, map: &ManagedMap<K, V>, key: &K, context: T) {
+        let ticket = self.next_ticket;
+        self.next_ticket += 1;
+        match map.get_async(key, ticket) {
+            ReadStatus::Ready(value) => self.ready.push((context, value)),
+            ReadStatus::Pending => {
+                self.contexts.insert(ticket, context);
+            }
+        }
+    }
+
+    /// The lookups that have completed, in no particular order, without waiting for the rest.
+    pub fn drain<K: StateKey>(&mut self, map: &ManagedMap<K, V>) -> Vec<(T, Option<Rc<V>>)> {
+        self.collect(map, false)
+    }
+
+    /// Every lookup, waiting for those still pending.
+    pub fn wait<K: StateKey>(&mut self, map: &ManagedMap<K, V>) -> Vec<(T, Option<Rc<V>>)> {
+        self.collect(map, true)
+    }
+
+    /// The number of lookups that have not completed yet.
+    pub fn pending(&self) -> usize {
+        self.contexts.len()
+    }
+
+    /// Whether every submitted lookup has been handed back.
+    pub fn is_empty(&self) -> bool {
+        self.contexts.is_empty() && self.ready.is_empty()
+    }
+
+    fn collect<K: StateKey>(
+        &mut self,
+        map: &ManagedMap<K, V>,
+        wait: bool,
+    ) -> Vec<(T, Option<Rc<V>>)> {
+        let mut completed = std::mem::replace(&mut self.ready, Vec::new());
+        if !self.contexts.is_empty() {
+            for (ticket, value) in map.complete_reads(wait) {
+                if let Some(context) = self.contexts.remove(&ticket) {
+                    completed.push((context, value));
+                }
+            }
+        }
+        if self.contexts.is_empty() || !completed.is_empty() {
+            self.idle = 0;
+        } else {
+            self.idle += 1;
+        }
+        if !self.contexts.is_empty() {
+            if let Some(wake) = &self.wake {
+                if self.idle > SPINS {
+                    let shift = cmp::min(self.idle - SPINS, 10);
+                    wake.wake_after(cmp::min(Duration::from_micros(1 << shift), MAX_BACKOFF));
+                } else {
+                    wake.wake();
+                }
+            }
+        }
+        completed
+    }
+}
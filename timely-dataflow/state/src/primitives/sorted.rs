@@ -0,0 +1,109 @@
+use super::{
+    IterDirection, KeyRange, ManagedCount, ManagedMap, ManagedMapIter, ManagedPriorityQueue,
+    ManagedSortedMap,
+};
+use crate::codec::SortKey;
+use crate::StateValue;
+use std::ops::Bound;
+use std::rc::Rc;
+
+/// A sorted map kept in a `ManagedMap` whose keys are encoded with `Ordered`,
+/// so that the backend's own iteration visits them in order.
+pub(crate) struct OrderedSortedMap<K, V> {
+    map: Box<ManagedMap<K, V>>,
+}
+
+impl<K: SortKey, V: StateValue> OrderedSortedMap<K, V> {
+    pub fn new(map: Box<ManagedMap<K, V>>) -> Self {
+        OrderedSortedMap { map }
+    }
+
+    fn iter(&self, range: KeyRange<K>, direction: IterDirection) -> ManagedMapIter<'_, K, V> {
+        self.map
+            .iter(range, direction)
+            .expect("Sorted maps need a backend that iterates")
+    }
+}
+
+impl<K: SortKey, V: StateValue> ManagedSortedMap<K, V> for OrderedSortedMap<K, V> {
+    fn insert(&mut self, key: K, value: V) {
+        self.map.insert(key, value)
+    }
+
+    fn get(&self, key: &K) -> Option<Rc<V>> {
+        self.map.get(key)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.map.remove(key)
+    }
+
+    fn first(&self) -> Option<(Rc<K>, Rc<V>)> {
+        self.iter(KeyRange::All, IterDirection::Forward).next()
+    }
+
+    fn last(&self) -> Option<(Rc<K>, Rc<V>)> {
+        self.iter(KeyRange::All, IterDirection::Reverse).next()
+    }
+
+    fn pop_first(&mut self) -> Option<(K, V)> {
+        let (key, _) = self.first()?;
+        let value = self.map.remove(&key)?;
+        Some(((*key).clone(), value))
+    }
+
+    fn range(&self, lower: Bound<K>, upper: Bound<K>) -> ManagedMapIter<'_, K, V> {
+        self.iter(KeyRange::Bounded(lower, upper), IterDirection::Forward)
+    }
+
+    fn split_off(&mut self, key: &K) -> Vec<(K, V)> {
+        let keys: Vec<Rc<K>> = self
+            .iter(KeyRange::from(key.clone()), IterDirection::Forward)
+            .map(|(key, _)| key)
+            .collect();
+        keys.into_iter()
+            .filter_map(|key| self.map.remove(&key).map(|value| ((*key).clone(), value)))
+            .collect()
+    }
+}
+
+/// A priority queue kept in a sorted map under each item's priority and the
+/// order it was pushed in, so that items of equal priority pop first in, first out.
+pub(crate) struct SortedQueue<P, T> {
+    entries: Box<ManagedSortedMap<(P, u64), T>>,
+    pushed: Box<ManagedCount>,
+}
+
+impl<P: SortKey, T: StateValue> SortedQueue<P, T> {
+    pub fn new(entries: Box<ManagedSortedMap<(P, u64), T>>, pushed: Box<ManagedCount>) -> Self {
+        SortedQueue { entries, pushed }
+    }
+}
+
+impl<P: SortKey, T: StateValue> ManagedPriorityQueue<P, T> for SortedQueue<P, T> {
+    fn push(&mut self, priority: P, item: T) {
+        let sequence = self.pushed.get() as u64;
+        self.pushed.increase(1);
+        self.entries.insert((priority, sequence), item);
+    }
+
+    fn peek(&self) -> Option<(P, Rc<T>)> {
+        self.entries
+            .first()
+            .map(|(key, item)| (key.0.clone(), item))
+    }
+
+    fn pop(&mut self) -> Option<(P, T)> {
+        self.entries
+            .pop_first()
+            .map(|((priority, _), item)| (priority, item))
+    }
+
+    fn pop_until(&mut self, priority: &P) -> Vec<(P, T)> {
+        let mut popped = Vec::new();
+        while self.peek().map_or(false, |(first, _)| first < *priority) {
+            popped.extend(self.pop());
+        }
+        popped
+    }
+}
@@ -0,0 +1,261 @@
+//! Moving checkpointed state between different numbers of workers.
+//!
+//! Managed state is private to the worker that an operator's `Exchange` pact
+//! routed each key to, so a dataflow resumed with a different number of
+//! workers would look for most keys in the wrong place. A `Rescaler` reads a
+//! checkpoint written by `N` workers and, map by map, moves every entry to the
+//! worker that a routing function picks for it out of `M`, before writing a
+//! checkpoint of the same epoch for the `M` workers.
+//!
+//! Entries are only found again if they move to the worker that the operator's
+//! pact routes their key to, so both route through the same `KeyGroups`.
+//! `KeyGroups` hashes keys into a fixed number of virtual partitions and gives
+//! each worker a contiguous range of them, so that changing the number of
+//! workers moves whole key groups and leaves most keys where they are.
+//!
+//! Only maps are moved, as they are the only state whose entries say which key
+//! they belong to. Other state of the first `M` workers is kept as it was, and
+//! that of the remaining workers is lost. Backends must support iteration.
+
+use crate::checkpoint::{CheckpointError, WorkerCheckpoints};
+use crate::config::BackendConfig;
+use crate::primitives::{IterDirection, IterationError, KeyRange};
+use crate::{StateBackend, StateHandle, StateKey, StateValue};
+use std::ops::Range;
+use std::path::Path;
+use std::rc::Rc;
+
+/// A fixed number of virtual partitions of a key space.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyGroups {
+    count: usize,
+}
+
+impl KeyGroups {
+    /// Hashes keys into `count` groups, which bounds the number of workers they can be spread over.
+    pub fn new(count: usize) -> Self {
+        assert!(count > 0, "There must be at least one key group");
+        KeyGroups { count }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The group of a key with hash `hash`.
+    pub fn group(&self, hash: u64) -> usize {
+        (hash % self.count as u64) as usize
+    }
+
+    /// The worker, out of `peers`, that owns `group`.
+    pub fn worker(&self, group: usize, peers: usize) -> usize {
+        assert!(
+            peers > 0 && peers <= self.count,
+            "Cannot spread {} key groups over {} workers",
+            self.count,
+            peers
+        );
+        group * peers / self.count
+    }
+
+    /// The groups that `worker` owns out of `peers`.
+    pub fn groups(&self, worker: usize, peers: usize) -> Range<usize> {
+        let first = |worker: usize| (worker * self.count + peers - 1) / peers;
+        first(worker)..first(worker + 1)
+    }
+
+    /// The worker, out of `peers`, that a key with hash `hash` belongs to, as
+    /// an `Exchange` pact expects it.
+    pub fn route(&self, hash: u64, peers: usize) -> u64 {
+        self.worker(self.group(hash), peers) as u64
+    }
+}
+
+/// Redistributes the state of one handle in a checkpoint between workers.
+pub struct Rescaler<S: StateBackend> {
+    epoch: u64,
+    // The state of the old workers as checkpointed, one handle each
+    sources: Vec<StateHandle<S>>,
+    // The state of the new workers, starting from that of the old worker of the same index
+    targets: Vec<StateHandle<S>>,
+}
+
+impl<S: StateBackend> Rescaler<S> {
+    /// Opens the state that the handle `name` of each of `old_peers` workers
+    /// checkpointed under `directory` as of `epoch`, to be spread over `new_peers`.
+    pub fn open(
+        config: &BackendConfig,
+        directory: &Path,
+        epoch: u64,
+        name: &str,
+        old_peers: usize,
+        new_peers: usize,
+    ) -> Result<Self, CheckpointError> {
+        assert!(old_peers > 0 && new_peers > 0, "Cannot rescale from or to no workers");
+        let mut sources = Vec::with_capacity(old_peers);
+        let mut targets = Vec::with_capacity(new_peers);
+        for worker in 0..old_peers {
+            let checkpoints = WorkerCheckpoints::new(directory, worker);
+            let manifest = checkpoints.manifest(epoch)?.ok_or_else(|| {
+                CheckpointError::Manifest(
+                    checkpoints.epoch_directory(epoch),
+                    "no checkpoint was written".to_owned(),
+                )
+            })?;
+            let snapshot = manifest.states.get(name).ok_or_else(|| {
+                CheckpointError::Manifest(
+                    checkpoints.epoch_directory(epoch),
+                    format!("no state was checkpointed as {}", name),
+                )
+            })?;
+            sources.push(StateHandle::new(Rc::new(S::restore(config, snapshot)?), name));
+            if worker < new_peers {
+                targets.push(StateHandle::new(Rc::new(S::restore(config, snapshot)?), name));
+            }
+        }
+        for _ in old_peers..new_peers {
+            targets.push(StateHandle::new(Rc::new(S::new(config)), name));
+        }
+        Ok(Rescaler {
+            epoch,
+            sources,
+            targets,
+        })
+    }
+
+    /// Moves each entry of the map `name` to the worker that `groups` routes the
+    /// key's `hash` to out of the new workers, and returns how many entries moved.
+    pub fn map<K, V, H>(&self, name: &str, groups: KeyGroups, hash: H) -> Result<usize, IterationError>
+    where
+        K: StateKey + Clone,
+        V: StateValue,
+        H: Fn(&K) -> u64,
+    {
+        let peers = self.targets.len();
+        let mut targets: Vec<_> = self
+            .targets
+            .iter()
+            .map(|target| target.get_managed_map::<K, V>(name))
+            .collect();
+        let mut moved = 0;
+        for (worker, source) in self.sources.iter().enumerate() {
+            // A worker that remains takes entries out of its own copy of the map
+            let mut source = if worker < peers {
+                self.targets[worker].get_managed_map::<K, V>(name)
+            } else {
+                source.get_managed_map::<K, V>(name)
+            };
+            let keys: Vec<Rc<K>> = source
+                .iter(KeyRange::All, IterDirection::Forward)?
+                .map(|(key, _)| key)
+                .collect();
+            for key in keys {
+                let key = (*key).clone();
+                let target = groups.route(hash(&key), peers) as usize;
+                if worker == target {
+                    continue;
+                }
+                let value = source.remove(&key).expect("Iterated key has no value");
+                targets[target].insert(key, value);
+                moved += 1;
+            }
+        }
+        Ok(moved)
+    }
+
+    /// Writes a checkpoint of the epoch for each of the new workers under `directory`,
+    /// which must not be the directory the state was read from.
+    pub fn checkpoint(&self, directory: &Path) -> Result<(), CheckpointError> {
+        for (worker, target) in self.targets.iter().enumerate() {
+            WorkerCheckpoints::new(directory, worker).checkpoint(self.epoch, target)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KeyGroups;
+    #[cfg(feature = "in-memory")]
+    use super::Rescaler;
+    #[cfg(feature = "in-memory")]
+    use crate::checkpoint::WorkerCheckpoints;
+    #[cfg(feature = "in-memory")]
+    use crate::{backends::InMemoryBackend, config::BackendConfig, StateBackend, StateHandle};
+    #[cfg(feature = "in-memory")]
+    use std::rc::Rc;
+    #[cfg(feature = "in-memory")]
+    use tempfile::TempDir;
+
+    #[test]
+    fn workers_own_contiguous_key_groups() {
+        let groups = KeyGroups::new(10);
+        for &peers in &[1, 3, 4, 10] {
+            let mut owned = Vec::new();
+            for worker in 0..peers {
+                for group in groups.groups(worker, peers) {
+                    assert_eq!(groups.worker(group, peers), worker);
+                    owned.push(group);
+                }
+            }
+            assert_eq!(owned, (0..10).collect::<Vec<_>>());
+        }
+        assert_eq!(groups.route(13, 2), 0);
+        assert_eq!(groups.route(17, 2), 1);
+    }
+
+    #[cfg(feature = "in-memory")]
+    fn rescales(old_peers: usize, new_peers: usize) {
+        let config = BackendConfig::default();
+        let groups = KeyGroups::new(10);
+        let old = TempDir::new().unwrap();
+        let new = TempDir::new().unwrap();
+        for worker in 0..old_peers {
+            let handle = StateHandle::new(Rc::new(InMemoryBackend::new(&config)), "dataflow");
+            let mut map = handle.get_managed_map::<u64, String>("map");
+            for key in (0..30).filter(|key| groups.route(*key, old_peers) as usize == worker) {
+                map.insert(key, key.to_string());
+            }
+            handle.get_managed_value::<u64>("value").set(worker as u64);
+            WorkerCheckpoints::new(old.path(), worker).checkpoint(5, &handle).unwrap();
+        }
+
+        let rescaler =
+            Rescaler::<InMemoryBackend>::open(&config, old.path(), 5, "dataflow", old_peers, new_peers)
+                .unwrap();
+        let moved = rescaler
+            .map::<u64, String, _>("map", groups, |key| *key)
+            .unwrap();
+        assert!(moved > 0);
+        rescaler.checkpoint(new.path()).unwrap();
+
+        for worker in 0..new_peers {
+            let checkpoints = WorkerCheckpoints::new(new.path(), worker);
+            let manifest = checkpoints.manifest(5).unwrap().unwrap();
+            let backend = InMemoryBackend::restore(&config, &manifest.states["dataflow"]).unwrap();
+            let handle = StateHandle::new(Rc::new(backend), "dataflow");
+            let map = handle.get_managed_map::<u64, String>("map");
+            for key in 0..30 {
+                let expected = Some(key.to_string())
+                    .filter(|_| groups.route(key, new_peers) as usize == worker);
+                assert_eq!(map.get(&key).map(|value| (*value).clone()), expected);
+            }
+            let value = handle.get_managed_value::<u64>("value").get();
+            let expected = Some(worker as u64).filter(|_| worker < old_peers);
+            assert_eq!(value.map(|value| *value), expected);
+        }
+        assert_eq!(WorkerCheckpoints::new(new.path(), 0).latest_epoch(new_peers).unwrap(), Some(5));
+    }
+
+    #[test]
+    #[cfg(feature = "in-memory")]
+    fn rescales_up() {
+        rescales(2, 3);
+    }
+
+    #[test]
+    #[cfg(feature = "in-memory")]
+    fn rescales_down() {
+        rescales(3, 2);
+    }
+}
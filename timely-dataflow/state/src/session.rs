@@ -0,0 +1,357 @@
+//! Maintenance of a worker's session with a FASTER instance.
+//!
+//! Each session must `refresh` regularly, so that FASTER's epochs move on, and
+//! `complete_pending` the operations that went to disk. A `Session` numbers the
+//! operations of one session and tells its caller which of these are due after
+//! each, following a `SessionPolicy`. It does not hold the instance itself, so
+//! that queries using their own FASTER stores can share it with the backends.
+//!
+//! A session also decides when to compact the log, following a `CompactionPolicy`.
+//!
+//! Serial numbers belong to one session of one instance: FASTER's checkpoints
+//! record the last serial number each session persisted, by the identifier that
+//! `start_session` returned, and a recovered session continues after it. Each
+//! session counts its operations that went pending, so that the cost of sharing
+//! an instance between workers shows in `StateHandle::session_stats`, and over
+//! the whole process in `process_stats`.
+
+use crate::compaction::CompactionStats;
+use crate::config::{CompactionPolicy, CompactionTrigger, FasterConfig, Refresh, SessionPolicy};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+/// How often a session's operations had to wait for FASTER.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SessionStats {
+    pub operations: u64,
+    /// Operations that returned `PENDING`, because they went to disk or met another
+    /// session's operation on the same record.
+    pub pending: u64,
+    /// Times the session waited for its pending operations to be retried to completion.
+    pub retries: u64,
+}
+
+static PROCESS_OPERATIONS: AtomicU64 = AtomicU64::new(0);
+static PROCESS_PENDING: AtomicU64 = AtomicU64::new(0);
+static PROCESS_RETRIES: AtomicU64 = AtomicU64::new(0);
+
+/// The stats of every session of this process that has ended, summed over all workers.
+pub fn process_stats() -> SessionStats {
+    SessionStats {
+        operations: PROCESS_OPERATIONS.load(Ordering::Relaxed),
+        pending: PROCESS_PENDING.load(Ordering::Relaxed),
+        retries: PROCESS_RETRIES.load(Ordering::Relaxed),
+    }
+}
+
+/// The maintenance due after an operation, in the order it should be done.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Maintenance {
+    pub refresh: bool,
+    /// Wait for every pending operation to complete.
+    pub complete_pending: bool,
+    pub report_size: bool,
+    /// Check whether the log has grown enough to compact it.
+    pub check_compaction: bool,
+}
+
+/// The serial numbers and maintenance of one session.
+pub struct Session {
+    policy: SessionPolicy,
+    serial: u64,
+    since_refresh: u64,
+    since_completion: u64,
+    pending: u64,
+    refreshed_at: Instant,
+    compaction: CompactionPolicy,
+    // Writes and deletions since the last compaction
+    written: u64,
+    deleted: u64,
+    frontier: u64,
+    stats: CompactionStats,
+    guid: Option<String>,
+    counts: SessionStats,
+}
+
+impl Session {
+    pub fn new(policy: SessionPolicy) -> Self {
+        Session {
+            policy,
+            serial: 1,
+            since_refresh: 0,
+            since_completion: 0,
+            pending: 0,
+            refreshed_at: Instant::now(),
+            compaction: CompactionPolicy::default(),
+            written: 0,
+            deleted: 0,
+            frontier: 0,
+            stats: CompactionStats::default(),
+            guid: None,
+            counts: SessionStats::default(),
+        }
+    }
+
+    /// A session maintained and compacting as `config` sets out.
+    pub fn from_config(config: &FasterConfig) -> Self {
+        let mut session = Session::new(config.session.clone());
+        session.set_compaction(config.compaction.clone());
+        session
+    }
+
+    /// Follows `policy` from the next operation on, keeping the serial numbers.
+    pub fn set_policy(&mut self, policy: SessionPolicy) {
+        self.policy = policy;
+    }
+
+    /// Compacts the log following `policy`, which by default never happens.
+    pub fn set_compaction(&mut self, policy: CompactionPolicy) {
+        self.compaction = policy;
+    }
+
+    /// Records the identifier that FASTER's `start_session` returned for this session.
+    pub fn set_guid(&mut self, guid: String) {
+        self.guid = Some(guid);
+    }
+
+    /// The identifier under which FASTER's checkpoints record this session's progress.
+    pub fn guid(&self) -> Option<&str> {
+        self.guid.as_ref().map(|guid| guid.as_str())
+    }
+
+    /// Continues a recovered session, whose operations up to serial number `persisted`
+    /// are in the checkpoint.
+    pub fn resume(&mut self, persisted: u64) {
+        self.serial = persisted + 1;
+    }
+
+    /// The serial number of the next operation.
+    pub fn serial(&self) -> u64 {
+        self.serial
+    }
+
+    /// Counts an operation that returned `PENDING`, towards the policy's `max_pending`.
+    pub fn note_pending(&mut self) {
+        self.pending += 1;
+        self.counts.pending += 1;
+    }
+
+    /// How often this session's operations had to wait for FASTER.
+    pub fn stats(&self) -> SessionStats {
+        self.counts
+    }
+
+    /// Counts a write to the log, towards the policy's fraction of tombstones.
+    pub fn note_write(&mut self, deletion: bool) {
+        self.written += 1;
+        if deletion {
+            self.deleted += 1;
+        }
+    }
+
+    /// Ends the current operation, moving on to the next serial number.
+    pub fn after_op(&mut self) -> Maintenance {
+        self.serial += 1;
+        self.since_refresh += 1;
+        self.since_completion += 1;
+        self.counts.operations += 1;
+
+        let refresh = match self.policy.refresh {
+            Refresh::Ops(every) => self.since_refresh >= every,
+            Refresh::Interval(interval) => self.refreshed_at.elapsed() >= interval,
+        };
+        if refresh {
+            self.since_refresh = 0;
+            if let Refresh::Interval(_) = self.policy.refresh {
+                self.refreshed_at = Instant::now();
+            }
+        }
+
+        let complete_pending = self.since_completion >= self.policy.complete_pending_every
+            || self.pending >= self.policy.max_pending;
+        if complete_pending {
+            if self.pending > 0 {
+                self.counts.retries += 1;
+            }
+            self.since_completion = 0;
+            self.pending = 0;
+        }
+
+        let every = self.policy.report_size_every;
+        let check_compaction = complete_pending
+            && match self.compaction.trigger {
+                CompactionTrigger::LogSize(_) | CompactionTrigger::Tombstones(_) => true,
+                CompactionTrigger::Never | CompactionTrigger::Epoch => false,
+            };
+        Maintenance {
+            refresh,
+            complete_pending,
+            report_size: every != 0 && self.serial % every == 0,
+            check_compaction,
+        }
+    }
+
+    /// Whether the policy compacts a log of `log_size` bytes now.
+    pub fn compaction_due(&self, log_size: u64) -> bool {
+        match self.compaction.trigger {
+            CompactionTrigger::LogSize(threshold) => log_size > threshold,
+            CompactionTrigger::Tombstones(fraction) => {
+                self.written > 0 && self.deleted as f64 >= fraction * self.written as f64
+            }
+            CompactionTrigger::Never | CompactionTrigger::Epoch => false,
+        }
+    }
+
+    /// Moves the operator's frontier to `frontier`, returning whether the policy
+    /// compacts at this new epoch.
+    pub fn advance(&mut self, frontier: u64) -> bool {
+        let advanced = frontier > self.frontier;
+        if advanced {
+            self.frontier = frontier;
+        }
+        advanced && self.compaction.trigger == CompactionTrigger::Epoch
+    }
+
+    /// How much of the log this session's compactions have reclaimed.
+    pub fn compaction_stats(&self) -> CompactionStats {
+        self.stats
+    }
+
+    /// Ends an operation on `faster` that returned `status`, doing the maintenance due.
+    #[cfg(feature = "faster")]
+    pub fn maintain(&mut self, faster: &faster_rs::FasterKv, status: u8) {
+        if status == faster_rs::status::PENDING {
+            self.note_pending();
+        }
+        let maintenance = self.after_op();
+        if maintenance.refresh {
+            faster.refresh();
+        }
+        if maintenance.complete_pending {
+            faster.complete_pending(true);
+        }
+        if maintenance.report_size {
+            log::info!("FASTER size after {} operations: {}", self.serial, faster.size());
+        }
+        if maintenance.check_compaction && self.compaction_due(faster.size()) {
+            self.compact(faster);
+        }
+    }
+
+    /// Compacts the log of `faster` as far as the policy's fraction.
+    #[cfg(feature = "faster")]
+    pub fn compact(&mut self, faster: &faster_rs::FasterKv) {
+        if let Some(reclaimed) = crate::compaction::compact(faster, self.compaction.fraction) {
+            self.written = 0;
+            self.deleted = 0;
+            self.stats.compactions += 1;
+            self.stats.reclaimed_bytes += reclaimed;
+        }
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        PROCESS_OPERATIONS.fetch_add(self.counts.operations, Ordering::Relaxed);
+        PROCESS_PENDING.fetch_add(self.counts.pending, Ordering::Relaxed);
+        PROCESS_RETRIES.fetch_add(self.counts.retries, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Maintenance, Session, SessionStats};
+    use crate::config::{CompactionPolicy, CompactionTrigger, Refresh, SessionPolicy};
+    use std::thread;
+    use std::time::Duration;
+
+    fn policy(refresh: Refresh) -> SessionPolicy {
+        SessionPolicy {
+            refresh,
+            complete_pending_every: 8,
+            max_pending: 3,
+            report_size_every: 5,
+        }
+    }
+
+    #[test]
+    fn counts_operations() {
+        let mut session = Session::new(policy(Refresh::Ops(2)));
+        let due: Vec<Maintenance> = (0..8).map(|_| session.after_op()).collect();
+        assert_eq!(session.serial(), 9);
+        let refreshes: Vec<bool> = due.iter().map(|due| due.refresh).collect();
+        assert_eq!(refreshes, vec![false, true, false, true, false, true, false, true]);
+        assert_eq!(due.iter().position(|due| due.complete_pending), Some(7));
+        assert_eq!(due.iter().position(|due| due.report_size), Some(3));
+    }
+
+    #[test]
+    fn completes_once_enough_are_pending() {
+        let mut session = Session::new(policy(Refresh::Ops(100)));
+        session.note_pending();
+        session.note_pending();
+        assert!(!session.after_op().complete_pending);
+        session.note_pending();
+        assert!(session.after_op().complete_pending);
+        session.note_pending();
+        assert!(!session.after_op().complete_pending);
+        let stats = SessionStats { operations: 3, pending: 4, retries: 1 };
+        assert_eq!(session.stats(), stats);
+    }
+
+    #[test]
+    fn resumes_after_persisted_serial() {
+        let mut session = Session::new(policy(Refresh::Ops(100)));
+        assert_eq!(session.guid(), None);
+        session.set_guid("a-session".to_owned());
+        session.resume(41);
+        assert_eq!(session.guid(), Some("a-session"));
+        assert_eq!(session.serial(), 42);
+        session.after_op();
+        assert_eq!(session.serial(), 43);
+    }
+
+    #[test]
+    fn refreshes_on_an_interval() {
+        let mut session = Session::new(policy(Refresh::Interval(Duration::from_millis(20))));
+        assert!(!session.after_op().refresh);
+        thread::sleep(Duration::from_millis(25));
+        assert!(session.after_op().refresh);
+        assert!(!session.after_op().refresh);
+    }
+
+    #[test]
+    fn decides_when_to_compact() {
+        let mut session = Session::new(policy(Refresh::Ops(100)));
+        assert!(!session.compaction_due(u64::max_value()));
+        session.set_compaction(CompactionPolicy {
+            trigger: CompactionTrigger::LogSize(1024),
+            fraction: 0.5,
+        });
+        assert!(!session.compaction_due(1024));
+        assert!(session.compaction_due(1025));
+        let due: Vec<Maintenance> = (0..8).map(|_| session.after_op()).collect();
+        assert_eq!(due.iter().position(|due| due.check_compaction), Some(7));
+
+        session.set_compaction(CompactionPolicy {
+            trigger: CompactionTrigger::Tombstones(0.5),
+            fraction: 0.5,
+        });
+        assert!(!session.compaction_due(0));
+        session.note_write(false);
+        session.note_write(true);
+        session.note_write(false);
+        assert!(!session.compaction_due(0));
+        session.note_write(true);
+        assert!(session.compaction_due(0));
+
+        assert!(!session.advance(1));
+        session.set_compaction(CompactionPolicy {
+            trigger: CompactionTrigger::Epoch,
+            fraction: 0.5,
+        });
+        assert!(!session.advance(1));
+        assert!(session.advance(2));
+        assert!(!session.after_op().check_compaction);
+    }
+}
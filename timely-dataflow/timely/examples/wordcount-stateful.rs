@@ -5,7 +5,7 @@ use std::collections::HashMap;
 use timely::dataflow::{InputHandle, ProbeHandle};
 use timely::dataflow::operators::{Map, Operator, Inspect, Probe};
 use timely::dataflow::channels::pact::Exchange;
-use timely::state::backends::{InMemoryBackend, FASTERBackend};
+use timely::state::backends::InMemoryBackend;
 
 fn main() {
     // initializes and runs a timely dataflow.
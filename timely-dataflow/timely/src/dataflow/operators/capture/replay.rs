@@ -37,6 +37,10 @@
 //! This means that each timely dataflow replay operator can replay any number of streams,
 //! allowing the replay to occur in a timely dataflow computation with more or fewer workers
 //! than that in which the stream was captured.
+//!
+//! A computation resumed from a checkpoint of epoch `e` replays with `replay_from`, which
+//! keeps the progress messages but drops the data at times up to `e`, as the checkpointed
+//! state already reflects it.
 
 use crate::Data;
 use crate::dataflow::{Scope, Stream};
@@ -52,59 +56,76 @@ use super::event::EventIterator;
 pub trait Replay<T: Timestamp, D: Data> {
     /// Replays `self` into the provided scope, as a `Stream<S, D>`.
     fn replay_into<S: Scope<Timestamp=T>>(self, scope: &mut S) -> Stream<S, D>;
+    /// Replays `self` into the provided scope, dropping the data at times less or equal to `epoch`.
+    fn replay_from<S: Scope<Timestamp=T>>(self, scope: &mut S, epoch: T) -> Stream<S, D>;
 }
 
 impl<T: Timestamp, D: Data, I> Replay<T, D> for I
 where I : IntoIterator,
       <I as IntoIterator>::Item: EventIterator<T, D>+'static {
     fn replay_into<S: Scope<Timestamp=T>>(self, scope: &mut S) -> Stream<S, D>{
+        replay(self, scope, None)
+    }
+
+    fn replay_from<S: Scope<Timestamp=T>>(self, scope: &mut S, epoch: T) -> Stream<S, D>{
+        replay(self, scope, Some(epoch))
+    }
+}
 
-        let mut builder = OperatorBuilder::new("Replay".to_owned(), scope.clone());
+fn replay<T, D, I, S>(events: I, scope: &mut S, resume: Option<T>) -> Stream<S, D>
+where T: Timestamp,
+      D: Data,
+      I: IntoIterator,
+      <I as IntoIterator>::Item: EventIterator<T, D>+'static,
+      S: Scope<Timestamp=T> {
 
-        let address = builder.operator_info().address;
-        let activator = scope.activator_for(&address[..]);
+    let mut builder = OperatorBuilder::new("Replay".to_owned(), scope.clone());
 
-        let (targets, stream) = builder.new_output();
+    let address = builder.operator_info().address;
+    let activator = scope.activator_for(&address[..]);
 
-        let mut output = PushBuffer::new(PushCounter::new(targets));
-        let mut event_streams = self.into_iter().collect::<Vec<_>>();
-        let mut started = false;
+    let (targets, stream) = builder.new_output();
 
-        builder.build(
-            move |_frontier| { },
-            move |_consumed, internal, produced| {
+    let mut output = PushBuffer::new(PushCounter::new(targets));
+    let mut event_streams = events.into_iter().collect::<Vec<_>>();
+    let mut started = false;
 
-                if !started {
-                    // The first thing we do is modify our capabilities to match the number of streams we manage.
-                    // This should be a simple change of `self.event_streams.len() - 1`. We only do this once, as
-                    // our very first action.
-                    internal[0].update(Default::default(), (event_streams.len() as i64) - 1);
-                    started = true;
-                }
+    builder.build(
+        move |_frontier| { },
+        move |_consumed, internal, produced| {
 
-                for event_stream in event_streams.iter_mut() {
-                    while let Some(event) = event_stream.next() {
-                        match *event {
-                            Event::Progress(ref vec) => {
-                                internal[0].extend(vec.iter().cloned());
-                            },
-                            Event::Messages(ref time, ref data) => {
+            if !started {
+                // The first thing we do is modify our capabilities to match the number of streams we manage.
+                // This should be a simple change of `self.event_streams.len() - 1`. We only do this once, as
+                // our very first action.
+                internal[0].update(Default::default(), (event_streams.len() as i64) - 1);
+                started = true;
+            }
+
+            for event_stream in event_streams.iter_mut() {
+                while let Some(event) = event_stream.next() {
+                    match *event {
+                        Event::Progress(ref vec) => {
+                            internal[0].extend(vec.iter().cloned());
+                        },
+                        Event::Messages(ref time, ref data) => {
+                            if resume.as_ref().map_or(true, |epoch| !time.less_equal(epoch)) {
                                 output.session(time).give_iterator(data.iter().cloned());
                             }
                         }
                     }
                 }
+            }
 
-                // Always reschedule `replay`.
-                activator.activate();
+            // Always reschedule `replay`.
+            activator.activate();
 
-                output.cease();
-                output.inner().produced().borrow_mut().drain_into(&mut produced[0]);
+            output.cease();
+            output.inner().produced().borrow_mut().drain_into(&mut produced[0]);
 
-                false
-            }
-        );
+            false
+        }
+    );
 
-        stream
-    }
+    stream
 }
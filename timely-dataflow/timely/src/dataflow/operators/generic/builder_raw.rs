@@ -20,6 +20,7 @@ use crate::dataflow::{Stream, Scope};
 use crate::dataflow::channels::pushers::Tee;
 use crate::dataflow::channels::pact::ParallelizationContract;
 use crate::dataflow::operators::generic::operator_info::OperatorInfo;
+use crate::state::{StateBackend, StateHandle};
 
 /// Contains type-free information about the operator properties.
 pub struct OperatorShape {
@@ -61,6 +62,7 @@ pub struct OperatorBuilder<G: Scope> {
     address: Vec<usize>,    // path to the operator (ending with index).
     shape: OperatorShape,
     summary: Vec<Vec<Antichain<<G::Timestamp as Timestamp>::Summary>>>,
+    state_name: Option<String>,
 }
 
 impl<G: Scope> OperatorBuilder<G> {
@@ -81,6 +83,7 @@ impl<G: Scope> OperatorBuilder<G> {
             address,
             shape: OperatorShape::new(name, peers),
             summary: vec![],
+            state_name: None,
         }
     }
 
@@ -183,6 +186,34 @@ impl<G: Scope> OperatorBuilder<G> {
     pub fn operator_info(&self) -> OperatorInfo {
         OperatorInfo::new(self.index, self.global, &self.address[..])
     }
+
+    /// Names the operator's state, which is otherwise named by the operator's worker-unique identifier.
+    ///
+    /// Named state keeps its keys when operators are added to or removed from the dataflow,
+    /// which restoring the dataflow from a checkpoint relies on.
+    pub fn set_state_name(&mut self, name: &str) {
+        self.state_name = Some(name.to_owned());
+    }
+
+    /// A handle to the operator's state, kept in the backend of its scope.
+    ///
+    /// Panics if another operator of the scope has the same state name.
+    pub fn state_handle(&self) -> StateHandle<G::StateBackend> {
+        let handle = self.scope.get_state_handle().create_sub_handle(&self.state_name());
+        self.scope.worker_state().claim(handle.name());
+        handle
+    }
+
+    /// A handle to the operator's state, kept in a backend of its own.
+    ///
+    /// Panics if another operator of the worker has the same state name.
+    pub fn state_handle_with_backend<S: StateBackend>(&self) -> StateHandle<S> {
+        self.scope.state_handle(&self.state_name())
+    }
+
+    fn state_name(&self) -> String {
+        self.state_name.clone().unwrap_or_else(|| self.global.to_string())
+    }
 }
 
 struct OperatorCore<T, PEP, PIP>
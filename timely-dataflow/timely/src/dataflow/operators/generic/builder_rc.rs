@@ -21,6 +21,7 @@ use crate::dataflow::operators::generic::handles::{InputHandle, new_input_handle
 use crate::dataflow::operators::generic::operator_info::OperatorInfo;
 
 use crate::logging::TimelyLogger as Logger;
+use crate::state::{StateBackend, StateHandle};
 
 use super::builder_raw::OperatorBuilder as OperatorBuilderRaw;
 
@@ -172,6 +173,21 @@ impl<G: Scope> OperatorBuilder<G> {
     pub fn operator_info(&self) -> OperatorInfo {
         self.builder.operator_info()
     }
+
+    /// Names the operator's state, which is otherwise named by the operator's worker-unique identifier.
+    pub fn set_state_name(&mut self, name: &str) {
+        self.builder.set_state_name(name);
+    }
+
+    /// A handle to the operator's state, kept in the backend of its scope.
+    pub fn state_handle(&self) -> StateHandle<G::StateBackend> {
+        self.builder.state_handle()
+    }
+
+    /// A handle to the operator's state, kept in a backend of its own.
+    pub fn state_handle_with_backend<S: StateBackend>(&self) -> StateHandle<S> {
+        self.builder.state_handle_with_backend()
+    }
 }
 
 
@@ -15,7 +15,6 @@ use super::builder_rc::OperatorBuilder;
 use crate::dataflow::operators::generic::OperatorInfo;
 use crate::dataflow::operators::generic::notificator::{Notificator, FrontierNotificator};
 use crate::state::{StateBackend, StateHandle};
-use std::rc::Rc;
 
 /// Methods to construct generic streaming and blocking operators.
 pub trait Operator<G: Scope, D1: Data> {
@@ -66,6 +65,44 @@ pub trait Operator<G: Scope, D1: Data> {
             &mut OutputHandle<G::Timestamp, D2, Tee<G::Timestamp, D2>>)+'static,
         P: ParallelizationContract<G::Timestamp, D1>;
 
+    /// Like `unary_frontier`, but names the operator's state `state_name` rather than by the
+    /// operator's worker-unique identifier.
+    ///
+    /// Named state keeps its keys when operators are added to or removed from the dataflow,
+    /// which restoring the dataflow from a checkpoint relies on. The name must be unique among
+    /// the operators of the worker, but other operators may read the same stream.
+    ///
+    /// # Examples
+    /// ```
+    /// use timely::dataflow::operators::{ToStream, Inspect};
+    /// use timely::dataflow::operators::generic::Operator;
+    /// use timely::dataflow::channels::pact::Pipeline;
+    ///
+    /// timely::example(|scope| {
+    ///     let stream = (0u64..10).to_stream(scope);
+    ///     stream.inspect(|x| println!("seen: {:?}", x));
+    ///     stream
+    ///         .unary_frontier_named(Pipeline, "example", "example-counts", |_cap, _info, state_handle| {
+    ///             let mut counts = state_handle.get_managed_count("counts");
+    ///             let mut vector = Vec::new();
+    ///             move |input, output| {
+    ///                 while let Some((time, data)) = input.next() {
+    ///                     data.swap(&mut vector);
+    ///                     counts.increase(vector.len() as i64);
+    ///                     output.session(&time).give_vec(&mut vector);
+    ///                 }
+    ///             }
+    ///         });
+    /// });
+    /// ```
+    fn unary_frontier_named<D2, B, L, P>(&self, pact: P, name: &str, state_name: &str, constructor: B) -> Stream<G, D2>
+    where
+        D2: Data,
+        B: FnOnce(Capability<G::Timestamp>, OperatorInfo, StateHandle<G::StateBackend>) -> L,
+        L: FnMut(&mut FrontieredInputHandle<G::Timestamp, D1, P::Puller>,
+            &mut OutputHandle<G::Timestamp, D2, Tee<G::Timestamp, D2>>)+'static,
+        P: ParallelizationContract<G::Timestamp, D1>;
+
     /// Creates a new dataflow operator that partitions its input stream by a parallelization
     /// strategy `pact`, and repeatedly invokes `logic`, the function returned by the function passed as `constructor`.
     /// `logic` can read from the input stream, write to the output stream, and inspect the frontier at the input.
@@ -154,6 +191,15 @@ pub trait Operator<G: Scope, D1: Data> {
             P: ParallelizationContract<G::Timestamp, D1>>
              (&self, pact: P, name: &str, init: impl IntoIterator<Item=G::Timestamp>, logic: L) -> Stream<G, D2>;
 
+    /// Like `unary_notify`, but names the operator's state `state_name`, as `unary_frontier_named` does.
+    fn unary_notify_named<D2: Data,
+            L: FnMut(&mut InputHandle<G::Timestamp, D1, P::Puller>,
+                     &mut OutputHandle<G::Timestamp, D2, Tee<G::Timestamp, D2>>,
+                     &mut Notificator<G::Timestamp>,
+                     &StateHandle<G::StateBackend>)+'static,
+            P: ParallelizationContract<G::Timestamp, D1>>
+             (&self, pact: P, name: &str, state_name: &str, init: impl IntoIterator<Item=G::Timestamp>, logic: L) -> Stream<G, D2>;
+
     /// Creates a new dataflow operator that partitions its input stream by a parallelization
     /// strategy `pact`, and repeatedly invokes `logic`, the function returned by the function passed as `constructor`.
     /// `logic` can read from the input stream, write to the output stream, and inspect the frontier at the input.
@@ -231,6 +277,15 @@ pub trait Operator<G: Scope, D1: Data> {
                  &mut OutputHandle<G::Timestamp, D2, Tee<G::Timestamp, D2>>)+'static,
         P: ParallelizationContract<G::Timestamp, D1>;
 
+    /// Like `unary`, but names the operator's state `state_name`, as `unary_frontier_named` does.
+    fn unary_named<D2, B, L, P>(&self, pact: P, name: &str, state_name: &str, constructor: B) -> Stream<G, D2>
+    where
+        D2: Data,
+        B: FnOnce(Capability<G::Timestamp>, OperatorInfo, StateHandle<G::StateBackend>) -> L,
+        L: FnMut(&mut InputHandle<G::Timestamp, D1, P::Puller>,
+                 &mut OutputHandle<G::Timestamp, D2, Tee<G::Timestamp, D2>>)+'static,
+        P: ParallelizationContract<G::Timestamp, D1>;
+
     /// Creates a new dataflow operator that partitions its input stream by a parallelization
     /// strategy `pact`, and repeatedly invokes `logic`, the function returned by the function passed as `constructor`.
     /// `logic` can read from the input stream, and write to the output stream.
@@ -333,6 +388,18 @@ pub trait Operator<G: Scope, D1: Data> {
         P1: ParallelizationContract<G::Timestamp, D1>,
         P2: ParallelizationContract<G::Timestamp, D2>;
 
+    /// Like `binary_frontier`, but names the operator's state `state_name`, as `unary_frontier_named` does.
+    fn binary_frontier_named<D2, D3, B, L, P1, P2>(&self, other: &Stream<G, D2>, pact1: P1, pact2: P2, name: &str, state_name: &str, constructor: B) -> Stream<G, D3>
+    where
+        D2: Data,
+        D3: Data,
+        B: FnOnce(Capability<G::Timestamp>, OperatorInfo, StateHandle<G::StateBackend>) -> L,
+        L: FnMut(&mut FrontieredInputHandle<G::Timestamp, D1, P1::Puller>,
+                 &mut FrontieredInputHandle<G::Timestamp, D2, P2::Puller>,
+                 &mut OutputHandle<G::Timestamp, D3, Tee<G::Timestamp, D3>>)+'static,
+        P1: ParallelizationContract<G::Timestamp, D1>,
+        P2: ParallelizationContract<G::Timestamp, D2>;
+
     /// Creates a new dataflow operator that partitions its input streams by a parallelization
     /// strategy `pact`, and repeatedly invokes `logic`, the function returned by the function passed as `constructor`.
     /// `logic` can read from the input streams, write to the output stream, and inspect the frontier at the inputs.
@@ -455,6 +522,18 @@ pub trait Operator<G: Scope, D1: Data> {
               P2: ParallelizationContract<G::Timestamp, D2>>
             (&self, other: &Stream<G, D2>, pact1: P1, pact2: P2, name: &str, init: impl IntoIterator<Item=G::Timestamp>, logic: L) -> Stream<G, D3>;
 
+    /// Like `binary_notify`, but names the operator's state `state_name`, as `unary_frontier_named` does.
+    fn binary_notify_named<D2: Data,
+              D3: Data,
+              L: FnMut(&mut InputHandle<G::Timestamp, D1, P1::Puller>,
+                       &mut InputHandle<G::Timestamp, D2, P2::Puller>,
+                       &mut OutputHandle<G::Timestamp, D3, Tee<G::Timestamp, D3>>,
+                       &mut Notificator<G::Timestamp>,
+                       &StateHandle<G::StateBackend>)+'static,
+              P1: ParallelizationContract<G::Timestamp, D1>,
+              P2: ParallelizationContract<G::Timestamp, D2>>
+            (&self, other: &Stream<G, D2>, pact1: P1, pact2: P2, name: &str, state_name: &str, init: impl IntoIterator<Item=G::Timestamp>, logic: L) -> Stream<G, D3>;
+
     /// Creates a new dataflow operator that partitions its input streams by a parallelization
     /// strategy `pact`, and repeatedly invokes `logic`, the function returned by the function passed as `constructor`.
     /// `logic` can read from the input streams, write to the output stream, and inspect the frontier at the inputs.
@@ -561,6 +640,18 @@ pub trait Operator<G: Scope, D1: Data> {
         P1: ParallelizationContract<G::Timestamp, D1>,
         P2: ParallelizationContract<G::Timestamp, D2>;
 
+    /// Like `binary`, but names the operator's state `state_name`, as `unary_frontier_named` does.
+    fn binary_named<D2, D3, B, L, P1, P2>(&self, other: &Stream<G, D2>, pact1: P1, pact2: P2, name: &str, state_name: &str, constructor: B) -> Stream<G, D3>
+    where
+        D2: Data,
+        D3: Data,
+        B: FnOnce(Capability<G::Timestamp>, OperatorInfo, StateHandle<G::StateBackend>) -> L,
+        L: FnMut(&mut InputHandle<G::Timestamp, D1, P1::Puller>,
+                 &mut InputHandle<G::Timestamp, D2, P2::Puller>,
+                 &mut OutputHandle<G::Timestamp, D3, Tee<G::Timestamp, D3>>)+'static,
+        P1: ParallelizationContract<G::Timestamp, D1>,
+        P2: ParallelizationContract<G::Timestamp, D2>;
+
     /// Creates a new dataflow operator that partitions its input streams by a parallelization
     /// strategy `pact`, and repeatedly invokes `logic`, the function returned by the function passed as `constructor`.
     /// `logic` can read from the input streams, write to the output stream, and inspect the frontier at the inputs.
@@ -649,25 +740,18 @@ impl<G: Scope, D1: Data> Operator<G, D1> for Stream<G, D1> {
             &mut OutputHandle<G::Timestamp, D2, Tee<G::Timestamp, D2>>)+'static,
         P: ParallelizationContract<G::Timestamp, D1>
     {
-        let mut builder = OperatorBuilder::new(name.to_owned(), self.scope());
-        let operator_info = builder.operator_info();
-        let state_handle = self.scope().get_state_handle().create_sub_handle(&operator_info.global_id.to_string());
-
-        let mut input = builder.new_input(self, pact);
-        let (mut output, stream) = builder.new_output();
-
-        builder.build(move |mut capabilities| {
-            // `capabilities` should be a single-element vector.
-            let capability = capabilities.pop().unwrap();
-            let mut logic = constructor(capability, operator_info, state_handle);
-            move |frontiers| {
-                let mut input_handle = FrontieredInputHandle::new(&mut input, &frontiers[0]);
-                let mut output_handle = output.activate();
-                logic(&mut input_handle, &mut output_handle);
-            }
-        });
+        self.unary_frontier_state(pact, name, None, constructor)
+    }
 
-        stream
+    fn unary_frontier_named<D2, B, L, P>(&self, pact: P, name: &str, state_name: &str, constructor: B) -> Stream<G, D2>
+    where
+        D2: Data,
+        B: FnOnce(Capability<G::Timestamp>, OperatorInfo, StateHandle<G::StateBackend>) -> L,
+        L: FnMut(&mut FrontieredInputHandle<G::Timestamp, D1, P::Puller>,
+            &mut OutputHandle<G::Timestamp, D2, Tee<G::Timestamp, D2>>)+'static,
+        P: ParallelizationContract<G::Timestamp, D1>
+    {
+        self.unary_frontier_state(pact, name, Some(state_name), constructor)
     }
 
     fn unary_frontier_core<D2, B, L, P, S>(&self, pact: P, name: &str, constructor: B) -> Stream<G, D2>
@@ -682,7 +766,7 @@ impl<G: Scope, D1: Data> Operator<G, D1> for Stream<G, D1> {
 
         let mut builder = OperatorBuilder::new(name.to_owned(), self.scope());
         let operator_info = builder.operator_info();
-        let state_handle = StateHandle::new(Rc::new(S::new()), &operator_info.global_id.to_string());
+        let state_handle = builder.state_handle_with_backend::<S>();
 
         let mut input = builder.new_input(self, pact);
         let (mut output, stream) = builder.new_output();
@@ -702,25 +786,23 @@ impl<G: Scope, D1: Data> Operator<G, D1> for Stream<G, D1> {
     }
 
     fn unary_notify<D2: Data,
-        L: FnMut(&mut InputHandle<G::Timestamp, D1, P::Puller>,
-            &mut OutputHandle<G::Timestamp, D2, Tee<G::Timestamp, D2>>,
-            &mut Notificator<G::Timestamp>,
-            &StateHandle<G::StateBackend>)+'static,
-        P: ParallelizationContract<G::Timestamp, D1>>
-    (&self, pact: P, name: &str, init: impl IntoIterator<Item=G::Timestamp>, mut logic: L) -> Stream<G, D2> {
-        self.unary_frontier(pact, name, move |capability, _info, state_handle| {
-            let mut notificator = FrontierNotificator::new();
-            for time in init {
-                notificator.notify_at(capability.delayed(&time));
-            }
+            L: FnMut(&mut InputHandle<G::Timestamp, D1, P::Puller>,
+                     &mut OutputHandle<G::Timestamp, D2, Tee<G::Timestamp, D2>>,
+                     &mut Notificator<G::Timestamp>,
+                     &StateHandle<G::StateBackend>)+'static,
+            P: ParallelizationContract<G::Timestamp, D1>>
+             (&self, pact: P, name: &str, init: impl IntoIterator<Item=G::Timestamp>, logic: L) -> Stream<G, D2> {
+        self.unary_notify_state(pact, name, None, init, logic)
+    }
 
-            let logging = self.scope().logging();
-            move |input, output| {
-                let frontier = &[input.frontier()];
-                let notificator = &mut Notificator::new(frontier, &mut notificator, &logging);
-                logic(&mut input.handle, output, notificator, &state_handle);
-            }
-        })
+    fn unary_notify_named<D2: Data,
+            L: FnMut(&mut InputHandle<G::Timestamp, D1, P::Puller>,
+                     &mut OutputHandle<G::Timestamp, D2, Tee<G::Timestamp, D2>>,
+                     &mut Notificator<G::Timestamp>,
+                     &StateHandle<G::StateBackend>)+'static,
+            P: ParallelizationContract<G::Timestamp, D1>>
+             (&self, pact: P, name: &str, state_name: &str, init: impl IntoIterator<Item=G::Timestamp>, logic: L) -> Stream<G, D2> {
+        self.unary_notify_state(pact, name, Some(state_name), init, logic)
     }
 
     fn unary_notify_core<D2: Data,
@@ -752,28 +834,21 @@ impl<G: Scope, D1: Data> Operator<G, D1> for Stream<G, D1> {
         D2: Data,
         B: FnOnce(Capability<G::Timestamp>, OperatorInfo, StateHandle<G::StateBackend>) -> L,
         L: FnMut(&mut InputHandle<G::Timestamp, D1, P::Puller>,
-            &mut OutputHandle<G::Timestamp, D2, Tee<G::Timestamp, D2>>)+'static,
+                 &mut OutputHandle<G::Timestamp, D2, Tee<G::Timestamp, D2>>)+'static,
         P: ParallelizationContract<G::Timestamp, D1>
     {
-        let mut builder = OperatorBuilder::new(name.to_owned(), self.scope());
-        let operator_info = builder.operator_info();
-        let state_handle = self.scope().get_state_handle().create_sub_handle(&operator_info.global_id.to_string());
-
-        let mut input = builder.new_input(self, pact);
-        let (mut output, stream) = builder.new_output();
-        builder.set_notify(false);
-
-        builder.build(move |mut capabilities| {
-            // `capabilities` should be a single-element vector.
-            let capability = capabilities.pop().unwrap();
-            let mut logic = constructor(capability, operator_info, state_handle);
-            move |_frontiers| {
-                let mut output_handle = output.activate();
-                logic(&mut input, &mut output_handle);
-            }
-        });
+        self.unary_state(pact, name, None, constructor)
+    }
 
-        stream
+    fn unary_named<D2, B, L, P>(&self, pact: P, name: &str, state_name: &str, constructor: B) -> Stream<G, D2>
+    where
+        D2: Data,
+        B: FnOnce(Capability<G::Timestamp>, OperatorInfo, StateHandle<G::StateBackend>) -> L,
+        L: FnMut(&mut InputHandle<G::Timestamp, D1, P::Puller>,
+                 &mut OutputHandle<G::Timestamp, D2, Tee<G::Timestamp, D2>>)+'static,
+        P: ParallelizationContract<G::Timestamp, D1>
+    {
+        self.unary_state(pact, name, Some(state_name), constructor)
     }
 
     fn unary_core<D2, B, L, P, S>(&self, pact: P, name: &str, constructor: B) -> Stream<G, D2>
@@ -788,7 +863,7 @@ impl<G: Scope, D1: Data> Operator<G, D1> for Stream<G, D1> {
 
         let mut builder = OperatorBuilder::new(name.to_owned(), self.scope());
         let operator_info = builder.operator_info();
-        let state_handle = StateHandle::new(Rc::new(S::new()), &operator_info.global_id.to_string());
+        let state_handle = builder.state_handle_with_backend::<S>();
 
         let mut input = builder.new_input(self, pact);
         let (mut output, stream) = builder.new_output();
@@ -813,32 +888,26 @@ impl<G: Scope, D1: Data> Operator<G, D1> for Stream<G, D1> {
         D3: Data,
         B: FnOnce(Capability<G::Timestamp>, OperatorInfo, StateHandle<G::StateBackend>) -> L,
         L: FnMut(&mut FrontieredInputHandle<G::Timestamp, D1, P1::Puller>,
-            &mut FrontieredInputHandle<G::Timestamp, D2, P2::Puller>,
-            &mut OutputHandle<G::Timestamp, D3, Tee<G::Timestamp, D3>>)+'static,
+                 &mut FrontieredInputHandle<G::Timestamp, D2, P2::Puller>,
+                 &mut OutputHandle<G::Timestamp, D3, Tee<G::Timestamp, D3>>)+'static,
         P1: ParallelizationContract<G::Timestamp, D1>,
         P2: ParallelizationContract<G::Timestamp, D2>
     {
-        let mut builder = OperatorBuilder::new(name.to_owned(), self.scope());
-        let operator_info = builder.operator_info();
-        let state_handle = self.scope().get_state_handle().create_sub_handle(&operator_info.global_id.to_string());
-
-        let mut input1 = builder.new_input(self, pact1);
-        let mut input2 = builder.new_input(other, pact2);
-        let (mut output, stream) = builder.new_output();
-
-        builder.build(move |mut capabilities| {
-            // `capabilities` should be a single-element vector.
-            let capability = capabilities.pop().unwrap();
-            let mut logic = constructor(capability, operator_info, state_handle);
-            move |frontiers| {
-                let mut input1_handle = FrontieredInputHandle::new(&mut input1, &frontiers[0]);
-                let mut input2_handle = FrontieredInputHandle::new(&mut input2, &frontiers[1]);
-                let mut output_handle = output.activate();
-                logic(&mut input1_handle, &mut input2_handle, &mut output_handle);
-            }
-        });
+        self.binary_frontier_state(other, pact1, pact2, name, None, constructor)
+    }
 
-        stream
+    fn binary_frontier_named<D2, D3, B, L, P1, P2>(&self, other: &Stream<G, D2>, pact1: P1, pact2: P2, name: &str, state_name: &str, constructor: B) -> Stream<G, D3>
+    where
+        D2: Data,
+        D3: Data,
+        B: FnOnce(Capability<G::Timestamp>, OperatorInfo, StateHandle<G::StateBackend>) -> L,
+        L: FnMut(&mut FrontieredInputHandle<G::Timestamp, D1, P1::Puller>,
+                 &mut FrontieredInputHandle<G::Timestamp, D2, P2::Puller>,
+                 &mut OutputHandle<G::Timestamp, D3, Tee<G::Timestamp, D3>>)+'static,
+        P1: ParallelizationContract<G::Timestamp, D1>,
+        P2: ParallelizationContract<G::Timestamp, D2>
+    {
+        self.binary_frontier_state(other, pact1, pact2, name, Some(state_name), constructor)
     }
 
     fn binary_frontier_core<D2, D3, B, L, P1, P2, S>(&self, other: &Stream<G, D2>, pact1: P1, pact2: P2, name: &str, constructor: B) -> Stream<G, D3>
@@ -856,7 +925,7 @@ impl<G: Scope, D1: Data> Operator<G, D1> for Stream<G, D1> {
 
         let mut builder = OperatorBuilder::new(name.to_owned(), self.scope());
         let operator_info = builder.operator_info();
-        let state_handle = StateHandle::new(Rc::new(S::new()), &operator_info.global_id.to_string());
+        let state_handle = builder.state_handle_with_backend::<S>();
 
         let mut input1 = builder.new_input(self, pact1);
         let mut input2 = builder.new_input(other, pact2);
@@ -878,28 +947,29 @@ impl<G: Scope, D1: Data> Operator<G, D1> for Stream<G, D1> {
     }
 
     fn binary_notify<D2: Data,
-        D3: Data,
-        L: FnMut(&mut InputHandle<G::Timestamp, D1, P1::Puller>,
-            &mut InputHandle<G::Timestamp, D2, P2::Puller>,
-            &mut OutputHandle<G::Timestamp, D3, Tee<G::Timestamp, D3>>,
-            &mut Notificator<G::Timestamp>,
-            &StateHandle<G::StateBackend>)+'static,
-        P1: ParallelizationContract<G::Timestamp, D1>,
-        P2: ParallelizationContract<G::Timestamp, D2>>
-    (&self, other: &Stream<G, D2>, pact1: P1, pact2: P2, name: &str, init: impl IntoIterator<Item=G::Timestamp>, mut logic: L) -> Stream<G, D3> {
-        self.binary_frontier(other, pact1, pact2, name, |capability, _info, state_handle| {
-            let mut notificator = FrontierNotificator::new();
-            for time in init {
-                notificator.notify_at(capability.delayed(&time));
-            }
+              D3: Data,
+              L: FnMut(&mut InputHandle<G::Timestamp, D1, P1::Puller>,
+                       &mut InputHandle<G::Timestamp, D2, P2::Puller>,
+                       &mut OutputHandle<G::Timestamp, D3, Tee<G::Timestamp, D3>>,
+                       &mut Notificator<G::Timestamp>,
+                       &StateHandle<G::StateBackend>)+'static,
+              P1: ParallelizationContract<G::Timestamp, D1>,
+              P2: ParallelizationContract<G::Timestamp, D2>>
+            (&self, other: &Stream<G, D2>, pact1: P1, pact2: P2, name: &str, init: impl IntoIterator<Item=G::Timestamp>, logic: L) -> Stream<G, D3> {
+        self.binary_notify_state(other, pact1, pact2, name, None, init, logic)
+    }
 
-            let logging = self.scope().logging();
-            move |input1, input2, output| {
-                let frontiers = &[input1.frontier(), input2.frontier()];
-                let notificator = &mut Notificator::new(frontiers, &mut notificator, &logging);
-                logic(&mut input1.handle, &mut input2.handle, output, notificator, &state_handle);
-            }
-        })
+    fn binary_notify_named<D2: Data,
+              D3: Data,
+              L: FnMut(&mut InputHandle<G::Timestamp, D1, P1::Puller>,
+                       &mut InputHandle<G::Timestamp, D2, P2::Puller>,
+                       &mut OutputHandle<G::Timestamp, D3, Tee<G::Timestamp, D3>>,
+                       &mut Notificator<G::Timestamp>,
+                       &StateHandle<G::StateBackend>)+'static,
+              P1: ParallelizationContract<G::Timestamp, D1>,
+              P2: ParallelizationContract<G::Timestamp, D2>>
+            (&self, other: &Stream<G, D2>, pact1: P1, pact2: P2, name: &str, state_name: &str, init: impl IntoIterator<Item=G::Timestamp>, logic: L) -> Stream<G, D3> {
+        self.binary_notify_state(other, pact1, pact2, name, Some(state_name), init, logic)
     }
 
     fn binary_notify_core<D2: Data,
@@ -936,31 +1006,26 @@ impl<G: Scope, D1: Data> Operator<G, D1> for Stream<G, D1> {
         D3: Data,
         B: FnOnce(Capability<G::Timestamp>, OperatorInfo, StateHandle<G::StateBackend>) -> L,
         L: FnMut(&mut InputHandle<G::Timestamp, D1, P1::Puller>,
-            &mut InputHandle<G::Timestamp, D2, P2::Puller>,
-            &mut OutputHandle<G::Timestamp, D3, Tee<G::Timestamp, D3>>)+'static,
+                 &mut InputHandle<G::Timestamp, D2, P2::Puller>,
+                 &mut OutputHandle<G::Timestamp, D3, Tee<G::Timestamp, D3>>)+'static,
         P1: ParallelizationContract<G::Timestamp, D1>,
         P2: ParallelizationContract<G::Timestamp, D2>
     {
-        let mut builder = OperatorBuilder::new(name.to_owned(), self.scope());
-        let operator_info = builder.operator_info();
-        let state_handle = self.scope().get_state_handle().create_sub_handle(&operator_info.global_id.to_string());
-
-        let mut input1 = builder.new_input(self, pact1);
-        let mut input2 = builder.new_input(other, pact2);
-        let (mut output, stream) = builder.new_output();
-        builder.set_notify(false);
-
-        builder.build(move |mut capabilities| {
-            // `capabilities` should be a single-element vector.
-            let capability = capabilities.pop().unwrap();
-            let mut logic = constructor(capability, operator_info, state_handle);
-            move |_frontiers| {
-                let mut output_handle = output.activate();
-                logic(&mut input1, &mut input2, &mut output_handle);
-            }
-        });
+        self.binary_state(other, pact1, pact2, name, None, constructor)
+    }
 
-        stream
+    fn binary_named<D2, D3, B, L, P1, P2>(&self, other: &Stream<G, D2>, pact1: P1, pact2: P2, name: &str, state_name: &str, constructor: B) -> Stream<G, D3>
+    where
+        D2: Data,
+        D3: Data,
+        B: FnOnce(Capability<G::Timestamp>, OperatorInfo, StateHandle<G::StateBackend>) -> L,
+        L: FnMut(&mut InputHandle<G::Timestamp, D1, P1::Puller>,
+                 &mut InputHandle<G::Timestamp, D2, P2::Puller>,
+                 &mut OutputHandle<G::Timestamp, D3, Tee<G::Timestamp, D3>>)+'static,
+        P1: ParallelizationContract<G::Timestamp, D1>,
+        P2: ParallelizationContract<G::Timestamp, D2>
+    {
+        self.binary_state(other, pact1, pact2, name, Some(state_name), constructor)
     }
 
     fn binary_core<D2, D3, B, L, P1, P2, S>(&self, other: &Stream<G, D2>, pact1: P1, pact2: P2, name: &str, constructor: B) -> Stream<G, D3>
@@ -978,7 +1043,7 @@ impl<G: Scope, D1: Data> Operator<G, D1> for Stream<G, D1> {
 
         let mut builder = OperatorBuilder::new(name.to_owned(), self.scope());
         let operator_info = builder.operator_info();
-        let state_handle = StateHandle::new(Rc::new(S::new()), &operator_info.global_id.to_string());
+        let state_handle = builder.state_handle_with_backend::<S>();
 
         let mut input1 = builder.new_input(self, pact1);
         let mut input2 = builder.new_input(other, pact2);
@@ -1015,6 +1080,193 @@ impl<G: Scope, D1: Data> Operator<G, D1> for Stream<G, D1> {
     }
 }
 
+impl<G: Scope, D1: Data> Stream<G, D1> {
+
+    fn unary_frontier_state<D2, B, L, P>(&self, pact: P, name: &str, state_name: Option<&str>, constructor: B) -> Stream<G, D2>
+    where
+        D2: Data,
+        B: FnOnce(Capability<G::Timestamp>, OperatorInfo, StateHandle<G::StateBackend>) -> L,
+        L: FnMut(&mut FrontieredInputHandle<G::Timestamp, D1, P::Puller>,
+            &mut OutputHandle<G::Timestamp, D2, Tee<G::Timestamp, D2>>)+'static,
+        P: ParallelizationContract<G::Timestamp, D1>
+    {
+        let mut builder = OperatorBuilder::new(name.to_owned(), self.scope());
+        if let Some(state_name) = state_name {
+            builder.set_state_name(state_name);
+        }
+        let operator_info = builder.operator_info();
+        let state_handle = builder.state_handle();
+
+        let mut input = builder.new_input(self, pact);
+        let (mut output, stream) = builder.new_output();
+
+        builder.build(move |mut capabilities| {
+            // `capabilities` should be a single-element vector.
+            let capability = capabilities.pop().unwrap();
+            let mut logic = constructor(capability, operator_info, state_handle);
+            move |frontiers| {
+                let mut input_handle = FrontieredInputHandle::new(&mut input, &frontiers[0]);
+                let mut output_handle = output.activate();
+                logic(&mut input_handle, &mut output_handle);
+            }
+        });
+
+        stream
+    }
+
+    fn unary_notify_state<D2: Data,
+        L: FnMut(&mut InputHandle<G::Timestamp, D1, P::Puller>,
+            &mut OutputHandle<G::Timestamp, D2, Tee<G::Timestamp, D2>>,
+            &mut Notificator<G::Timestamp>,
+            &StateHandle<G::StateBackend>)+'static,
+        P: ParallelizationContract<G::Timestamp, D1>>
+    (&self, pact: P, name: &str, state_name: Option<&str>, init: impl IntoIterator<Item=G::Timestamp>, mut logic: L) -> Stream<G, D2> {
+        self.unary_frontier_state(pact, name, state_name, move |capability, _info, state_handle| {
+            let mut notificator = FrontierNotificator::new();
+            for time in init {
+                notificator.notify_at(capability.delayed(&time));
+            }
+
+            let logging = self.scope().logging();
+            move |input, output| {
+                let frontier = &[input.frontier()];
+                let notificator = &mut Notificator::new(frontier, &mut notificator, &logging);
+                logic(&mut input.handle, output, notificator, &state_handle);
+            }
+        })
+    }
+
+    fn unary_state<D2, B, L, P>(&self, pact: P, name: &str, state_name: Option<&str>, constructor: B) -> Stream<G, D2>
+    where
+        D2: Data,
+        B: FnOnce(Capability<G::Timestamp>, OperatorInfo, StateHandle<G::StateBackend>) -> L,
+        L: FnMut(&mut InputHandle<G::Timestamp, D1, P::Puller>,
+            &mut OutputHandle<G::Timestamp, D2, Tee<G::Timestamp, D2>>)+'static,
+        P: ParallelizationContract<G::Timestamp, D1>
+    {
+        let mut builder = OperatorBuilder::new(name.to_owned(), self.scope());
+        if let Some(state_name) = state_name {
+            builder.set_state_name(state_name);
+        }
+        let operator_info = builder.operator_info();
+        let state_handle = builder.state_handle();
+
+        let mut input = builder.new_input(self, pact);
+        let (mut output, stream) = builder.new_output();
+        builder.set_notify(false);
+
+        builder.build(move |mut capabilities| {
+            // `capabilities` should be a single-element vector.
+            let capability = capabilities.pop().unwrap();
+            let mut logic = constructor(capability, operator_info, state_handle);
+            move |_frontiers| {
+                let mut output_handle = output.activate();
+                logic(&mut input, &mut output_handle);
+            }
+        });
+
+        stream
+    }
+
+    fn binary_frontier_state<D2, D3, B, L, P1, P2>(&self, other: &Stream<G, D2>, pact1: P1, pact2: P2, name: &str, state_name: Option<&str>, constructor: B) -> Stream<G, D3>
+    where
+        D2: Data,
+        D3: Data,
+        B: FnOnce(Capability<G::Timestamp>, OperatorInfo, StateHandle<G::StateBackend>) -> L,
+        L: FnMut(&mut FrontieredInputHandle<G::Timestamp, D1, P1::Puller>,
+            &mut FrontieredInputHandle<G::Timestamp, D2, P2::Puller>,
+            &mut OutputHandle<G::Timestamp, D3, Tee<G::Timestamp, D3>>)+'static,
+        P1: ParallelizationContract<G::Timestamp, D1>,
+        P2: ParallelizationContract<G::Timestamp, D2>
+    {
+        let mut builder = OperatorBuilder::new(name.to_owned(), self.scope());
+        if let Some(state_name) = state_name {
+            builder.set_state_name(state_name);
+        }
+        let operator_info = builder.operator_info();
+        let state_handle = builder.state_handle();
+
+        let mut input1 = builder.new_input(self, pact1);
+        let mut input2 = builder.new_input(other, pact2);
+        let (mut output, stream) = builder.new_output();
+
+        builder.build(move |mut capabilities| {
+            // `capabilities` should be a single-element vector.
+            let capability = capabilities.pop().unwrap();
+            let mut logic = constructor(capability, operator_info, state_handle);
+            move |frontiers| {
+                let mut input1_handle = FrontieredInputHandle::new(&mut input1, &frontiers[0]);
+                let mut input2_handle = FrontieredInputHandle::new(&mut input2, &frontiers[1]);
+                let mut output_handle = output.activate();
+                logic(&mut input1_handle, &mut input2_handle, &mut output_handle);
+            }
+        });
+
+        stream
+    }
+
+    fn binary_notify_state<D2: Data,
+        D3: Data,
+        L: FnMut(&mut InputHandle<G::Timestamp, D1, P1::Puller>,
+            &mut InputHandle<G::Timestamp, D2, P2::Puller>,
+            &mut OutputHandle<G::Timestamp, D3, Tee<G::Timestamp, D3>>,
+            &mut Notificator<G::Timestamp>,
+            &StateHandle<G::StateBackend>)+'static,
+        P1: ParallelizationContract<G::Timestamp, D1>,
+        P2: ParallelizationContract<G::Timestamp, D2>>
+    (&self, other: &Stream<G, D2>, pact1: P1, pact2: P2, name: &str, state_name: Option<&str>, init: impl IntoIterator<Item=G::Timestamp>, mut logic: L) -> Stream<G, D3> {
+        self.binary_frontier_state(other, pact1, pact2, name, state_name, |capability, _info, state_handle| {
+            let mut notificator = FrontierNotificator::new();
+            for time in init {
+                notificator.notify_at(capability.delayed(&time));
+            }
+
+            let logging = self.scope().logging();
+            move |input1, input2, output| {
+                let frontiers = &[input1.frontier(), input2.frontier()];
+                let notificator = &mut Notificator::new(frontiers, &mut notificator, &logging);
+                logic(&mut input1.handle, &mut input2.handle, output, notificator, &state_handle);
+            }
+        })
+    }
+
+    fn binary_state<D2, D3, B, L, P1, P2>(&self, other: &Stream<G, D2>, pact1: P1, pact2: P2, name: &str, state_name: Option<&str>, constructor: B) -> Stream<G, D3>
+    where
+        D2: Data,
+        D3: Data,
+        B: FnOnce(Capability<G::Timestamp>, OperatorInfo, StateHandle<G::StateBackend>) -> L,
+        L: FnMut(&mut InputHandle<G::Timestamp, D1, P1::Puller>,
+            &mut InputHandle<G::Timestamp, D2, P2::Puller>,
+            &mut OutputHandle<G::Timestamp, D3, Tee<G::Timestamp, D3>>)+'static,
+        P1: ParallelizationContract<G::Timestamp, D1>,
+        P2: ParallelizationContract<G::Timestamp, D2>
+    {
+        let mut builder = OperatorBuilder::new(name.to_owned(), self.scope());
+        if let Some(state_name) = state_name {
+            builder.set_state_name(state_name);
+        }
+        let operator_info = builder.operator_info();
+        let state_handle = builder.state_handle();
+
+        let mut input1 = builder.new_input(self, pact1);
+        let mut input2 = builder.new_input(other, pact2);
+        let (mut output, stream) = builder.new_output();
+        builder.set_notify(false);
+
+        builder.build(move |mut capabilities| {
+            // `capabilities` should be a single-element vector.
+            let capability = capabilities.pop().unwrap();
+            let mut logic = constructor(capability, operator_info, state_handle);
+            move |_frontiers| {
+                let mut output_handle = output.activate();
+                logic(&mut input1, &mut input2, &mut output_handle);
+            }
+        });
+
+        stream
+    }
+}
+
 /// Creates a new data stream source for a scope.
 ///
 /// The source is defined by a name, and a constructor which takes a default capability to
@@ -14,6 +14,7 @@ use crate::order::Product;
 use crate::logging::TimelyLogger as Logger;
 use crate::worker::AsWorker;
 use crate::state::{StateBackend, StateHandle};
+use crate::state::checkpoint::WorkerState;
 
 use super::{ScopeParent, Scope};
 
@@ -84,6 +85,9 @@ where
     fn log_register(&self) -> ::std::cell::RefMut<crate::logging_core::Registry<crate::logging::WorkerIdentifier>> {
         self.parent.log_register()
     }
+    fn worker_state(&self) -> Rc<WorkerState> {
+        self.parent.worker_state()
+    }
 }
 
 impl<'a, G, T, S> Scheduler for Child<'a, G, T, S>
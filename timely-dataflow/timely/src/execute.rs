@@ -1,16 +1,119 @@
 //! Starts a timely dataflow execution from configuration information and per-worker logic.
 use crate::communication::{initialize_from, Configuration, Allocator, allocator::AllocateBuilder, WorkerGuards};
+#[cfg(feature = "in-memory")]
 use crate::dataflow::scopes::Child;
 use crate::worker::Worker;
-use crate::state::backends::{InMemoryBackend, FASTERBackend};
+#[cfg(feature = "in-memory")]
+use crate::state::backends::{InMemoryBackend, InMemoryNativeBackend, InMemoryOrderedBackend};
+#[cfg(feature = "faster")]
+use crate::state::backends::{FASTERBackend, FASTERInMemoryBackend, FASTERNodeBackend};
+#[cfg(feature = "rocksdb")]
+use crate::state::backends::{RocksDBBackend, RocksDBMergeBackend};
+#[cfg(feature = "sled")]
+use crate::state::backends::SledBackend;
+use crate::state::StateBackend;
 use crate::state::StateHandle;
+use crate::state::config::BackendConfig;
+#[cfg(feature = "faster")]
+use crate::state::config::FasterConfig;
 
 use std::rc::Rc;
-use faster_rs::{FasterKv, FasterKvBuilder};
+#[cfg(feature = "faster")]
+use faster_rs::FasterKv;
+#[cfg(feature = "faster")]
 use tempfile::TempDir;
+#[cfg(feature = "faster")]
 use std::sync::Arc;
-use timely_state::backends::FASTERNodeBackend;
-use std::time::Duration;
+
+/// A state backend that `execute` can create for each worker it starts.
+///
+/// `execute` prepares a backend's `Process` once per process, creates each worker's backend
+/// from it on the worker's thread, and calls `worker_finished` once the worker's dataflows
+/// have run to completion.
+pub trait WorkerBackend: StateBackend + 'static {
+    /// What the workers of a process share, from which each worker's backend is created.
+    type Process: Send + Sync + 'static;
+
+    /// Prepares what the `workers` workers of a process share.
+    fn process(config: &BackendConfig, workers: usize) -> Self::Process;
+
+    /// Creates the backend of the worker with global index `index`.
+    fn worker(process: &Self::Process, index: usize) -> Self;
+
+    /// Called once the worker with global index `index` has finished.
+    fn worker_finished(_process: &Self::Process, _index: usize) { }
+}
+
+// Backends that each worker opens for itself from the process's configuration
+macro_rules! worker_backend {
+    ($($backend:ty),*) => {
+        $(
+            impl WorkerBackend for $backend {
+                type Process = BackendConfig;
+
+                fn process(config: &BackendConfig, _workers: usize) -> BackendConfig {
+                    config.clone()
+                }
+
+                fn worker(config: &BackendConfig, _index: usize) -> Self {
+                    <$backend as StateBackend>::new(config)
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "in-memory")]
+worker_backend!(InMemoryBackend, InMemoryNativeBackend, InMemoryOrderedBackend);
+#[cfg(feature = "faster")]
+worker_backend!(FASTERBackend, FASTERInMemoryBackend);
+#[cfg(feature = "rocksdb")]
+worker_backend!(RocksDBBackend, RocksDBMergeBackend);
+#[cfg(feature = "sled")]
+worker_backend!(SledBackend);
+
+/// The FASTER instances that the workers of a process share, as the `faster_node`
+/// section's `sharding` sets out.
+#[cfg(feature = "faster")]
+pub struct FasterShards {
+    // Each shard's instance is shared by a contiguous range of the process's workers
+    shards: Vec<(Arc<FasterKv>, Arc<TempDir>)>,
+    workers: usize,
+    faster_config: FasterConfig,
+}
+
+#[cfg(feature = "faster")]
+impl FasterShards {
+    // The instance of the process's worker with global index `index`
+    fn shard(&self, index: usize) -> &(Arc<FasterKv>, Arc<TempDir>) {
+        let worker = index % self.workers;
+        &self.shards[self.faster_config.shard_of(worker, self.workers)]
+    }
+}
+
+#[cfg(feature = "faster")]
+impl WorkerBackend for FASTERNodeBackend {
+    type Process = FasterShards;
+
+    fn process(backend_config: &BackendConfig, workers: usize) -> FasterShards {
+        let faster_config = backend_config.faster_node.clone();
+        let shards = (0 .. faster_config.shards(workers))
+            .map(|_| FASTERNodeBackend::new_shared_faster(backend_config))
+            .collect();
+        FasterShards { shards, workers, faster_config }
+    }
+
+    fn worker(process: &FasterShards, index: usize) -> Self {
+        let (faster_kv, faster_directory) = process.shard(index);
+        FASTERNodeBackend::start_session(faster_kv, faster_directory, &process.faster_config)
+    }
+
+    fn worker_finished(process: &FasterShards, index: usize) {
+        let (faster_kv, _) = process.shard(index);
+        faster_kv.complete_pending(true);
+        faster_kv.stop_session();
+    }
+}
 
 /// Executes a single-threaded timely dataflow computation.
 ///
@@ -55,6 +158,7 @@ use std::time::Duration;
 /// // the extracted data should have data (0..10) at timestamp 0.
 /// assert_eq!(data.extract()[0].1, (0..10).collect::<Vec<_>>());
 /// ```
+#[cfg(feature = "in-memory")]
 pub fn example<T, F>(func: F) -> T
 where
     T: Send+'static,
@@ -85,13 +189,16 @@ where
 ///     })
 /// });
 /// ```
+///
+/// State backends are configured from the environment, as for [`execute`](fn.execute.html).
 pub fn execute_directly<T, F>(func: F) -> T
 where
     T: Send+'static,
     F: FnOnce(&mut Worker<crate::communication::allocator::thread::Thread>)->T+Send+Sync+'static
 {
     let alloc = crate::communication::allocator::thread::Thread::new();
-    let mut worker = crate::worker::Worker::new(alloc);
+    let backend_config = BackendConfig::from_env().expect("Invalid state backend configuration");
+    let mut worker = crate::worker::Worker::with_backend_config(alloc, backend_config);
     let result = func(&mut worker);
     while worker.step_or_park(None) { }
     result
@@ -148,10 +255,45 @@ where
 /// // the extracted data should have data (0..10) thrice at timestamp 0.
 /// assert_eq!(recv.extract()[0].1, (0..30).map(|x| x / 3).collect::<Vec<_>>());
 /// ```
-pub fn execute<T, F>(mut config: Configuration, func: F) -> Result<WorkerGuards<T>,String>
+///
+/// Each worker receives a `StateHandle` over its own backend of type `B`, created as `B`'s
+/// [`WorkerBackend`](trait.WorkerBackend.html) implementation sets out. State backends are
+/// configured by `BackendConfig::from_env`, which reads the TOML file named by
+/// `TIMELY_STATE_CONFIG` and overrides from `TIMELY_STATE_*` variables.
+pub fn execute<B, T, F>(config: Configuration, func: F) -> Result<WorkerGuards<T>,String>
+where
+    B: WorkerBackend,
+    T:Send+'static,
+    F: Fn(&mut Worker<Allocator>, StateHandle<B>)->T+Send+Sync+'static {
+    let backend_config = BackendConfig::from_env().map_err(|error| error.to_string())?;
+    execute_with_backend_config(config, backend_config, func)
+}
+
+/// Executes a timely dataflow whose state backends are created with `backend_config`.
+///
+/// Refer to [`execute`](fn.execute.html) for more details.
+///
+/// ```rust
+/// use timely::dataflow::operators::{ToStream, Inspect};
+/// use timely::state::StateHandle;
+/// use timely::state::backends::InMemoryBackend;
+/// use timely::state::config::BackendConfig;
+///
+/// let mut backend_config = BackendConfig::default();
+/// backend_config.directory = std::env::temp_dir();
+///
+/// timely::execute_with_backend_config(timely::Configuration::Process(3), backend_config, |worker, _: StateHandle<InMemoryBackend>| {
+///     worker.dataflow::<(),_,_,InMemoryBackend>(|scope, _| {
+///         (0..10).to_stream(scope)
+///                .inspect(|x| println!("seen: {:?}", x));
+///     })
+/// }).unwrap();
+/// ```
+pub fn execute_with_backend_config<B, T, F>(mut config: Configuration, backend_config: BackendConfig, func: F) -> Result<WorkerGuards<T>,String>
 where
+    B: WorkerBackend,
     T:Send+'static,
-    F: Fn(&mut Worker<Allocator>, StateHandle<FASTERNodeBackend>)->T+Send+Sync+'static {
+    F: Fn(&mut Worker<Allocator>, StateHandle<B>)->T+Send+Sync+'static {
 
     if let Configuration::Cluster { ref mut log_fn, .. } = config {
 
@@ -185,17 +327,11 @@ where
 
     let (allocators, other) = config.try_build()?;
 
-    let faster_directory = Arc::new(TempDir::new_in(".").expect("Unable to create directory for FASTER"));
-    let faster_directory_string = faster_directory.path().to_str().unwrap();
-
-    let mut builder = FasterKvBuilder::new(1 << 24, 12 * 1024 * 1024 * 1024);
-    builder.with_disk(faster_directory_string)
-            .set_pre_allocate_log(false);
-    let faster_kv = Arc::new(builder.build().unwrap());
+    let process_state = B::process(&backend_config, allocators.len());
 
     initialize_from(allocators, other, move |allocator| {
 
-        let mut worker = Worker::new(allocator);
+        let mut worker = Worker::with_backend_config(allocator, backend_config.clone());
 
         // If an environment variable is set, use it as the default timely logging.
         if let Ok(addr) = ::std::env::var("TIMELY_WORKER_LOG_ADDR") {
@@ -217,13 +353,12 @@ where
             }
         }
 
-        faster_kv.start_session();
-        let faster_backend = Rc::new(FASTERNodeBackend::new_from_existing(&faster_kv, &faster_directory));
-        let state_handle = StateHandle::new(faster_backend, "");
+        let state_handle = StateHandle::new(Rc::new(B::worker(&process_state, worker.index())), "");
 
         let result = func(&mut worker, state_handle);
         while worker.step_or_park(None) { }
 
+        B::worker_finished(&process_state, worker.index());
         result
     })
 }
@@ -277,17 +412,19 @@ where
 /// host2:port
 /// host3:port
 /// ```
-pub fn execute_from_args<I, T, F>(iter: I, func: F) -> Result<WorkerGuards<T>,String>
+pub fn execute_from_args<I, B, T, F>(iter: I, func: F) -> Result<WorkerGuards<T>,String>
     where I: Iterator<Item=String>,
+          B: WorkerBackend,
           T:Send+'static,
-          F: Fn(&mut Worker<Allocator>, StateHandle<FASTERNodeBackend>)->T+Send+Sync+'static, {
+          F: Fn(&mut Worker<Allocator>, StateHandle<B>)->T+Send+Sync+'static, {
     let configuration = Configuration::from_args(iter)?;
     execute(configuration, func)
 }
 
 /// Executes a timely dataflow from supplied allocators and logging.
 ///
-/// Refer to [`execute`](fn.execute.html) for more details.
+/// Refer to [`execute`](fn.execute.html) for more details, including how state backends
+/// are configured.
 ///
 /// ```rust
 /// use timely::dataflow::operators::{ToStream, Inspect};
@@ -302,30 +439,23 @@ pub fn execute_from_args<I, T, F>(iter: I, func: F) -> Result<WorkerGuards<T>,St
 ///     })
 /// }).unwrap();
 /// ```
-pub fn execute_from<A, T, F>(builders: Vec<A>, others: Box<::std::any::Any>, func: F) -> Result<WorkerGuards<T>,String>
+pub fn execute_from<A, B, T, F>(builders: Vec<A>, others: Box<::std::any::Any>, func: F) -> Result<WorkerGuards<T>,String>
 where
     A: AllocateBuilder+'static,
+    B: WorkerBackend,
     T: Send+'static,
-    F: Fn(&mut Worker<<A as AllocateBuilder>::Allocator>, StateHandle<FASTERNodeBackend>)->T+Send+Sync+'static {
-    let faster_directory = Arc::new(TempDir::new_in(".").expect("Unable to create directory for FASTER"));
-    let faster_directory_string = faster_directory.path().to_str().unwrap();
-
-    let mut builder = FasterKvBuilder::new(1 << 24, 12 * 1024 * 1024 * 1024);
-    builder.with_disk(faster_directory_string)
-        .set_pre_allocate_log(false);
-    let faster_kv = Arc::new(builder.build().unwrap());
+    F: Fn(&mut Worker<<A as AllocateBuilder>::Allocator>, StateHandle<B>)->T+Send+Sync+'static {
+    let backend_config = BackendConfig::from_env().map_err(|error| error.to_string())?;
+    let process_state = B::process(&backend_config, builders.len());
 
     initialize_from(builders, others, move |allocator| {
-        let mut worker = Worker::new(allocator);
-        faster_kv.start_session();
-        let faster_backend = Rc::new(FASTERNodeBackend::new_from_existing(&faster_kv, &faster_directory));
-        let state_handle = StateHandle::new(faster_backend, &worker.index().to_string());
+        let mut worker = Worker::with_backend_config(allocator, backend_config.clone());
+        let state_handle = StateHandle::new(Rc::new(B::worker(&process_state, worker.index())), &worker.index().to_string());
 
         let result = func(&mut worker, state_handle);
         while worker.step_or_park(None) { }
 
-        faster_kv.complete_pending(true);
-        faster_kv.stop_session();
+        B::worker_finished(&process_state, worker.index());
         result
     })
 }
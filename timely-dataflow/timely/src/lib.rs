@@ -70,7 +70,9 @@ extern crate timely_logging;
 extern crate timely_state;
 extern crate tempfile;
 
-pub use execute::{execute, execute_directly, execute_from_args, example};
+pub use execute::{execute, execute_directly, execute_from_args, execute_with_backend_config, WorkerBackend};
+#[cfg(feature = "in-memory")]
+pub use execute::example;
 pub use order::PartialOrder;
 
 pub use timely_communication::Configuration;
@@ -98,6 +100,7 @@ pub mod state {
 pub mod worker;
 pub mod progress;
 pub mod dataflow;
+#[cfg(feature = "in-memory")]
 pub mod synchronization;
 pub mod execute;
 pub mod order;
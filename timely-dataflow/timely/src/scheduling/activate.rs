@@ -2,6 +2,9 @@
 
 use std::rc::Rc;
 use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::time::{Duration, Instant};
 
 /// Allocation-free activation tracker.
 pub struct Activations {
@@ -10,6 +13,9 @@ pub struct Activations {
     bounds: Vec<(usize, usize)>,
     slices: Vec<usize>,
     buffer: Vec<usize>,
+    // Delayed activations, by when they are due since `timer`
+    timer: Instant,
+    queue: BinaryHeap<Reverse<(Duration, Vec<usize>)>>,
 }
 
 impl Activations {
@@ -21,6 +27,8 @@ impl Activations {
             bounds: Vec::new(),
             slices: Vec::new(),
             buffer: Vec::new(),
+            timer: Instant::now(),
+            queue: BinaryHeap::new(),
         }
     }
 
@@ -35,9 +43,37 @@ impl Activations {
         self.slices.extend(path);
     }
 
+    /// Unparks task addressed by `path` once `delay` has passed.
+    pub fn activate_after(&mut self, path: &[usize], delay: Duration) {
+        if delay == Duration::from_secs(0) {
+            self.activate(path);
+        }
+        else {
+            let moment = self.timer.elapsed() + delay;
+            self.queue.push(Reverse((moment, path.to_vec())));
+        }
+    }
+
+    /// The time until the next delayed activation is due, if there is one.
+    pub fn empty_for(&self) -> Option<Duration> {
+        self.queue.peek().map(|Reverse((moment, _))| {
+            let elapsed = self.timer.elapsed();
+            if *moment > elapsed { *moment - elapsed } else { Duration::from_secs(0) }
+        })
+    }
+
     /// Discards the current active set and presents the next active set.
     pub fn advance(&mut self) {
 
+        // Unpark delayed activations that are due.
+        if !self.queue.is_empty() {
+            let now = self.timer.elapsed();
+            while self.queue.peek().map_or(false, |Reverse((moment, _))| *moment <= now) {
+                let Reverse((_moment, path)) = self.queue.pop().unwrap();
+                self.activate(&path[..]);
+            }
+        }
+
         self.bounds.drain(.. self.clean);
 
         {   // Scoped, to allow borrow to drop.
@@ -114,6 +150,22 @@ impl Activator {
             .borrow_mut()
             .activate(&self.path[..]);
     }
+    /// Activates the associated path once `delay` has passed.
+    pub fn activate_after(&self, delay: Duration) {
+        self.queue
+            .borrow_mut()
+            .activate_after(&self.path[..], delay);
+    }
+}
+
+/// Lets an operator waiting on state lookups schedule itself to collect them.
+impl crate::state::primitives::Wake for Activator {
+    fn wake(&self) {
+        self.activate()
+    }
+    fn wake_after(&self, delay: Duration) {
+        self.activate_after(delay)
+    }
 }
 
 /// A wrapper that unparks on drop.
@@ -149,4 +201,4 @@ impl<T> Drop for ActivateOnDrop<T> {
     fn drop(&mut self) {
         self.activator.borrow_mut().activate(&self.address[..]);
     }
-}
\ No newline at end of file
+}
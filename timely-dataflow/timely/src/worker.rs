@@ -15,6 +15,8 @@ use crate::progress::operate::Operate;
 use crate::dataflow::scopes::{Child, Scope};
 use crate::logging::TimelyLogger;
 use crate::state::{StateBackend, StateHandle};
+use crate::state::checkpoint::{CheckpointError, WorkerCheckpoints, WorkerState};
+use crate::state::config::BackendConfig;
 
 /// Methods provided by the root Worker.
 ///
@@ -48,6 +50,20 @@ pub trait AsWorker : Scheduler {
     fn log_register(&self) -> ::std::cell::RefMut<crate::logging_core::Registry<crate::logging::WorkerIdentifier>>;
     /// Provides access to the timely logging stream.
     fn logging(&self) -> Option<crate::logging::TimelyLogger> { self.log_register().get("timely") }
+    /// The managed state of the worker's dataflows.
+    fn worker_state(&self) -> Rc<WorkerState>;
+    /// Configuration for the state backends of the worker's operators.
+    fn backend_config(&self) -> Rc<BackendConfig> { self.worker_state().config() }
+    /// The worker's checkpoints of managed state, kept in the configured checkpoint directory.
+    fn checkpoints(&self) -> WorkerCheckpoints { self.worker_state().checkpoints().clone() }
+    /// Opens the state handle `name` on a new backend, restored from the worker's checkpoint if it has one.
+    ///
+    /// Panics if the checkpointed state cannot be restored.
+    fn state_handle<S: StateBackend>(&self, name: &str) -> StateHandle<S> {
+        self.worker_state()
+            .open(name)
+            .unwrap_or_else(|error| panic!("Unable to open state {}: {}", name, error))
+    }
 }
 
 /// A `Worker` is the entry point to a timely dataflow computation. It wraps a `Allocate`,
@@ -69,6 +85,7 @@ pub struct Worker<A: Allocate> {
     // These are then associated with a dataflow once constructed.
     temp_channel_ids: Rc<RefCell<Vec<usize>>>,
 
+    state: Rc<WorkerState>,
 }
 
 impl<A: Allocate> AsWorker for Worker<A> {
@@ -93,6 +110,7 @@ impl<A: Allocate> AsWorker for Worker<A> {
     fn log_register(&self) -> RefMut<crate::logging_core::Registry<crate::logging::WorkerIdentifier>> {
         self.log_register()
     }
+    fn worker_state(&self) -> Rc<WorkerState> { self.state.clone() }
 }
 
 impl<A: Allocate> Scheduler for Worker<A> {
@@ -103,7 +121,14 @@ impl<A: Allocate> Scheduler for Worker<A> {
 
 impl<A: Allocate> Worker<A> {
     /// Allocates a new `Worker` bound to a channel allocator.
+    ///
+    /// State backends of the worker use the default `BackendConfig`.
     pub fn new(c: A) -> Worker<A> {
+        Worker::with_backend_config(c, BackendConfig::default())
+    }
+
+    /// Allocates a new `Worker` whose state backends are created with `backend_config`.
+    pub fn with_backend_config(c: A, backend_config: BackendConfig) -> Worker<A> {
         let now = Instant::now();
         let index = c.index();
         Worker {
@@ -117,9 +142,26 @@ impl<A: Allocate> Worker<A> {
             activations: Rc::new(RefCell::new(Activations::new())),
             active_dataflows: Vec::new(),
             temp_channel_ids: Rc::new(RefCell::new(Vec::new())),
+            state: Rc::new(WorkerState::new(Rc::new(backend_config), index)),
         }
     }
 
+    /// Restores the state of dataflows built from now on from the worker's checkpoint of `epoch`.
+    ///
+    /// Dataflows must be built as they were when the checkpoint was taken, so that
+    /// their state handles have the same names.
+    pub fn restore_from(&mut self, epoch: u64) -> Result<(), CheckpointError> {
+        self.state.restore_from(epoch)
+    }
+
+    /// Checkpoints the state of every dataflow the worker built as of `epoch`.
+    ///
+    /// Call this once the worker's probes have passed `epoch`, so that the state
+    /// reflects every update up to `epoch` and none after it.
+    pub fn checkpoint(&self, epoch: u64) -> Result<(), CheckpointError> {
+        self.state.checkpoint(epoch)
+    }
+
     /// Performs one step of the computation.
     ///
     /// A step gives each dataflow operator a chance to run, and is the
@@ -200,6 +242,12 @@ impl<A: Allocate> Worker<A> {
             .advance();
 
         if self.activations.borrow().is_empty() {
+            // Park no longer than until the next delayed activation is due.
+            let duration = match (duration, self.activations.borrow().empty_for()) {
+                (Some(duration), Some(delay)) => Some(::std::cmp::min(duration, delay)),
+                (duration, None) => duration,
+                (None, delay) => delay,
+            };
             self.allocator
                 .borrow()
                 .await_events(duration);
@@ -398,8 +446,8 @@ impl<A: Allocate> Worker<A> {
         let subscope = RefCell::new(subscope);
 
 
-        let state_backend = Rc::new(S::new());
-        let state_handle = StateHandle::new(state_backend, &self.index().to_string());
+        // Named by the dataflow's index, which is the same whenever dataflows are built in the same order
+        let state_handle = self.state_handle::<S>(&format!("dataflow-{}", dataflow_index));
 
         let result = {
             let mut builder = Child::new(&subscope, self.clone(), logging.clone(), state_handle);
@@ -458,6 +506,7 @@ impl<A: Allocate> Clone for Worker<A> {
             activations: self.activations.clone(),
             active_dataflows: Vec::new(),
             temp_channel_ids: self.temp_channel_ids.clone(),
+            state: self.state.clone(),
         }
     }
 }
@@ -0,0 +1,21 @@
+extern crate timely;
+
+use std::time::Duration;
+use timely::scheduling::Activations;
+
+#[test]
+fn delayed_activations_wait_until_due() {
+    let mut activations = Activations::new();
+    activations.activate_after(&[0, 1], Duration::from_millis(20));
+    activations.advance();
+    assert!(activations.is_empty());
+    assert!(activations.empty_for().unwrap() <= Duration::from_millis(20));
+
+    ::std::thread::sleep(Duration::from_millis(20));
+    assert_eq!(activations.empty_for(), Some(Duration::from_secs(0)));
+    activations.advance();
+    let mut active = Vec::new();
+    activations.for_extensions(&[0], |index| active.push(index));
+    assert_eq!(active, vec![1]);
+    assert_eq!(activations.empty_for(), None);
+}
@@ -0,0 +1,69 @@
+extern crate timely;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use timely::Configuration;
+use timely::execute::WorkerBackend;
+use timely::state::{StateBackend, StateHandle, StateKey, StateValue};
+use timely::state::backends::InMemoryBackend;
+use timely::state::codec::Codec;
+use timely::state::config::BackendConfig;
+use timely::state::primitives::{ManagedCount, ManagedMap, ManagedValue};
+
+static FINISHED: AtomicUsize = AtomicUsize::new(0);
+
+// An in-memory backend that counts the workers that finished with it
+struct CountingBackend(InMemoryBackend);
+
+impl StateBackend for CountingBackend {
+    fn new(config: &BackendConfig) -> Self {
+        CountingBackend(InMemoryBackend::new(config))
+    }
+
+    fn get_managed_count(&self, name: &str) -> Box<ManagedCount> {
+        self.0.get_managed_count(name)
+    }
+
+    fn get_managed_value<V: StateValue>(&self, name: &str) -> Box<ManagedValue<V>> {
+        self.0.get_managed_value(name)
+    }
+
+    fn get_managed_map_with_codecs<K, V, KC, VC>(&self, name: &str) -> Box<ManagedMap<K, V>>
+    where
+        K: StateKey,
+        V: StateValue,
+        KC: Codec<K>,
+        VC: Codec<V>,
+    {
+        self.0.get_managed_map_with_codecs::<K, V, KC, VC>(name)
+    }
+}
+
+impl WorkerBackend for CountingBackend {
+    type Process = BackendConfig;
+
+    fn process(config: &BackendConfig, _workers: usize) -> BackendConfig {
+        config.clone()
+    }
+
+    fn worker(config: &BackendConfig, _index: usize) -> Self {
+        CountingBackend::new(config)
+    }
+
+    fn worker_finished(_config: &BackendConfig, _index: usize) {
+        FINISHED.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn execute_finishes_every_worker_backend() {
+    let mut backend_config = BackendConfig::default();
+    backend_config.directory = std::env::temp_dir();
+    let guards = timely::execute_with_backend_config(
+        Configuration::Process(3),
+        backend_config,
+        |_worker, _: StateHandle<CountingBackend>| { },
+    ).unwrap();
+    drop(guards);
+    assert_eq!(FINISHED.load(Ordering::SeqCst), 3);
+}